@@ -0,0 +1,147 @@
+//! Timings for loading a WAD and decoding its map lumps, as a baseline for
+//! performance work. Run with `cargo bench --features testutil`.
+//!
+//! The WAD is generated with `WadBuilder` rather than shipped: a few maps,
+//! each a grid of separate square rooms with a thing in every room, so the
+//! numbers don't depend on having a commercial IWAD around.
+
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use doom_map_stats::builder::MapBuilder;
+use doom_map_stats::builder::WadBuilder;
+use doom_map_stats::map::Linedef;
+use doom_map_stats::map::MapThing;
+use doom_map_stats::map::Sector;
+use doom_map_stats::map::Sidedef;
+use doom_map_stats::map::LINEDEF_FLAG_IMPASSABLE;
+use doom_map_stats::map::NO_SIDEDEF;
+use doom_map_stats::wad::Wad;
+
+const MAPS: usize = 8;
+const ROOMS_PER_SIDE: usize = 32;
+const ROOM_SIZE: i16 = 64;
+
+/// Runs `f` repeatedly for about a second after a short warmup, then prints
+/// the fastest and mean time per run.
+fn bench(name: &str, mut f: impl FnMut()) {
+    let warmup = Instant::now();
+    while warmup.elapsed() < Duration::from_millis(200) {
+        f();
+    }
+    let mut runs = vec![];
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        let run = Instant::now();
+        f();
+        runs.push(run.elapsed());
+    }
+    let mean = start.elapsed() / runs.len() as u32;
+    let fastest = runs.iter().min().unwrap();
+    println!(
+        "{:<24} {:>10.1?} fastest {:>10.1?} mean ({} runs)",
+        name,
+        fastest,
+        mean,
+        runs.len()
+    );
+}
+
+/// One map: a grid of one-sided square rooms, each its own sector with an imp
+/// in the middle. The node lumps are left empty since loading doesn't need
+/// them.
+fn map(name: &str) -> MapBuilder {
+    let mut map = MapBuilder::new(name);
+    for row in 0..ROOMS_PER_SIDE {
+        for col in 0..ROOMS_PER_SIDE {
+            let room = (row * ROOMS_PER_SIDE + col) as u16;
+            let (x, y) = (col as i16 * ROOM_SIZE * 2, row as i16 * ROOM_SIZE * 2);
+            map = map
+                .vertex(x, y)
+                .vertex(x, y + ROOM_SIZE)
+                .vertex(x + ROOM_SIZE, y + ROOM_SIZE)
+                .vertex(x + ROOM_SIZE, y);
+            for side in 0..4u16 {
+                let first = room * 4;
+                map = map
+                    .linedef(Linedef {
+                        start_vertex: first + side,
+                        end_vertex: first + (side + 1) % 4,
+                        flags: LINEDEF_FLAG_IMPASSABLE,
+                        special: 0,
+                        tag: 0,
+                        right_sidedef: first + side,
+                        left_sidedef: NO_SIDEDEF,
+                    })
+                    .sidedef(Sidedef {
+                        x_offset: 0,
+                        y_offset: 0,
+                        upper_texture: "-".to_string(),
+                        lower_texture: "-".to_string(),
+                        middle_texture: "STARTAN3".to_string(),
+                        sector: room,
+                    });
+            }
+            let middle = ROOM_SIZE / 2;
+            map = map
+                .sector(Sector {
+                    floor_height: 0,
+                    ceiling_height: 128,
+                    floor_flat: "FLOOR4_8".to_string(),
+                    ceiling_flat: "CEIL3_5".to_string(),
+                    light_level: 160,
+                    special: 0,
+                    tag: 0,
+                })
+                .thing(MapThing {
+                    x: x + middle,
+                    y: y + middle,
+                    angle: 0,
+                    doomednum: 3001,
+                    flags: 7,
+                });
+        }
+    }
+    map
+}
+
+fn synthetic_wad() -> Vec<u8> {
+    (1..=MAPS)
+        .fold(WadBuilder::new(), |wad, n| {
+            wad.map(map(&format!("MAP{:02}", n)))
+        })
+        .build()
+}
+
+fn main() {
+    let path =
+        std::env::temp_dir().join(format!("doom-map-stats-bench-{}.wad", std::process::id()));
+    std::fs::write(&path, synthetic_wad()).expect("can't write the benchmark WAD");
+    let _cleanup = RemoveOnDrop(path.clone());
+
+    bench("Wad::from_file", || {
+        black_box(Wad::from_file(&path).unwrap());
+    });
+
+    let wad = Wad::from_file(&path).unwrap();
+    let map = &wad.maps()[0];
+    bench("Map::things", || {
+        black_box(map.things().unwrap());
+    });
+    bench("Map::linedefs", || {
+        black_box(map.linedefs().unwrap());
+    });
+    bench("Map::sectors", || {
+        black_box(map.sectors().unwrap());
+    });
+}
+
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
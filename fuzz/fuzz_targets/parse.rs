@@ -0,0 +1,9 @@
+#![no_main]
+
+use doom_map_stats::wad::Wad;
+use libfuzzer_sys::fuzz_target;
+
+// run with `cargo fuzz run parse`; any panic is a bug
+fuzz_target!(|data: &[u8]| {
+    let _ = Wad::try_parse(data);
+});
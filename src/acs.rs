@@ -0,0 +1,84 @@
+use byteorder::ByteOrder;
+use byteorder::LE;
+
+use std::ops::Range;
+
+use crate::map::Map;
+
+/// The bytecode format of a `BEHAVIOR` lump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AcsFormat {
+    /// Hexen's original `ACS\0` format.
+    Old,
+    /// ZDoom's `ACSE` format.
+    Enhanced,
+    /// ZDoom's `ACSe` format, which stores function and array names
+    /// compactly.
+    LittleEnhanced,
+}
+
+/// What a `BEHAVIOR` lump's header says about its scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AcsInfo {
+    pub format: AcsFormat,
+    /// The number of scripts defined, open and closed alike.
+    pub scripts: usize,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<usize> {
+    Some(LE::read_u32(data.get(offset..offset + 4)?) as usize)
+}
+
+/// Finds a chunk of an enhanced-format lump among those in `chunks`.
+fn find_chunk<'a>(data: &'a [u8], chunks: Range<usize>, id: &[u8; 4]) -> Option<&'a [u8]> {
+    let data = data.get(..chunks.end.min(data.len()))?;
+    let mut pos = chunks.start;
+    while let Some(header) = data.get(pos..pos + 8) {
+        let length = LE::read_u32(&header[4..]) as usize;
+        if &header[..4] == id {
+            return data.get(pos + 8..pos + 8 + length);
+        }
+        pos += 8 + length;
+    }
+    None
+}
+
+impl Map {
+    /// The format and script count of the map's `BEHAVIOR` lump, or `None` if
+    /// the map has no ACS or its header can't be read.
+    ///
+    /// The old format's directory, which the header's second word points at,
+    /// starts with the script count. Enhanced lumps list their scripts in an
+    /// `SPTR` chunk instead. Current ACC writes them behind an `ACS\0` header
+    /// so that Hexen can still load them: the real format tag and the offset
+    /// of the chunks then sit just before the old directory, and scripts take
+    /// 8 bytes each. Lumps from older ACC versions start with the `ACSE` or
+    /// `ACSe` tag, point straight at the chunks, and use 12 bytes per script.
+    pub fn acs_info(&self) -> Option<AcsInfo> {
+        let data = &self.behavior.as_ref()?.data;
+        let directory = read_u32(data, 4)?;
+        let (format, chunks, entry_size) = match data.get(..4)? {
+            b"ACSE" => (AcsFormat::Enhanced, directory..data.len(), 12),
+            b"ACSe" => (AcsFormat::LittleEnhanced, directory..data.len(), 12),
+            b"ACS\0" => match data.get(directory.wrapping_sub(8)..directory) {
+                Some(pretag) if directory >= 24 && &pretag[4..] == b"ACSE" => {
+                    let start = LE::read_u32(pretag) as usize;
+                    (AcsFormat::Enhanced, start..directory - 8, 8)
+                }
+                Some(pretag) if directory >= 24 && &pretag[4..] == b"ACSe" => {
+                    let start = LE::read_u32(pretag) as usize;
+                    (AcsFormat::LittleEnhanced, start..directory - 8, 8)
+                }
+                _ => {
+                    return Some(AcsInfo {
+                        format: AcsFormat::Old,
+                        scripts: read_u32(data, directory)?,
+                    })
+                }
+            },
+            _ => return None,
+        };
+        let scripts = find_chunk(data, chunks, b"SPTR").map_or(0, |c| c.len() / entry_size);
+        Some(AcsInfo { format, scripts })
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt::Display;
+
+use crate::compat::Port;
+use crate::wad::Lump;
+use crate::wad::Wad;
+
+/// The languages ZDoom-family ports define actors in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActorLanguage {
+    Decorate,
+    ZScript,
+}
+
+impl ActorLanguage {
+    /// The lump name a WAD's definitions in the language start from.
+    pub fn lump_name(self) -> &'static str {
+        match self {
+            ActorLanguage::Decorate => "DECORATE",
+            ActorLanguage::ZScript => "ZSCRIPT",
+        }
+    }
+
+    /// The least capable port that reads the language: ZDoom for DECORATE,
+    /// GZDoom for ZScript. This is what the compatibility matrix grades
+    /// these lumps by.
+    pub fn port(self) -> Port {
+        match self {
+            ActorLanguage::Decorate => Port::ZDoom,
+            ActorLanguage::ZScript => Port::GZDoom,
+        }
+    }
+
+    // the keyword starting a top-level actor definition
+    fn keyword(self) -> &'static str {
+        match self {
+            ActorLanguage::Decorate => "actor",
+            ActorLanguage::ZScript => "class",
+        }
+    }
+}
+
+impl Display for ActorLanguage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ActorLanguage::Decorate => "DECORATE",
+            ActorLanguage::ZScript => "ZScript",
+        })
+    }
+}
+
+/// One lump of actor definitions, read with a light scan rather than parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActorLump {
+    pub name: String,
+    pub language: ActorLanguage,
+    pub size: usize,
+    /// How many actors (in DECORATE) or classes (in ZScript) the lump
+    /// declares at the top level. ZScript's `extend class` adds to an
+    /// existing class and `mixin class` isn't one, so neither is counted.
+    pub declarations: usize,
+    /// The files named by its `#include` lines, as written.
+    pub includes: Vec<String>,
+}
+
+/// What a WAD defines in DECORATE and ZScript, which only ZDoom-family ports
+/// read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActorDefsInfo {
+    /// The `DECORATE` and `ZSCRIPT` lumps in directory order, each followed
+    /// by the lumps it includes that the WAD has. No lump is listed twice.
+    pub lumps: Vec<ActorLump>,
+    /// Included names that aren't a lump in the WAD, such as paths into a
+    /// PK3 loaded alongside it.
+    pub missing_includes: Vec<String>,
+}
+
+impl ActorDefsInfo {
+    pub fn defines_actors(&self) -> bool {
+        self.lumps.iter().any(|l| l.declarations > 0)
+    }
+
+    /// The size of every definition lump in bytes.
+    pub fn total_size(&self) -> usize {
+        self.lumps.iter().map(|l| l.size).sum()
+    }
+
+    pub fn declarations(&self) -> usize {
+        self.lumps.iter().map(|l| l.declarations).sum()
+    }
+
+    /// The least capable port that reads every definition lump, or `None`
+    /// without any.
+    pub fn minimum_port(&self) -> Option<Port> {
+        self.lumps.iter().map(|l| l.language.port()).max()
+    }
+}
+
+// the text with comments and string contents blanked out, so keywords and
+// braces inside them aren't counted
+fn code_only(text: &str) -> String {
+    let mut code = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        code.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                code.push(' ');
+            }
+            '"' => {
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    if c == '"' && !escaped {
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+                code.push_str("\"\"");
+            }
+            _ => code.push(c),
+        }
+    }
+    code
+}
+
+fn scan(lump: &Lump, language: ActorLanguage) -> ActorLump {
+    let text = String::from_utf8_lossy(lump.as_bytes());
+    let includes = text
+        .lines()
+        .filter_map(|line| {
+            let directive = line.trim_start().strip_prefix('#')?;
+            if !directive.get(..7)?.eq_ignore_ascii_case("include") {
+                return None;
+            }
+            Some(directive.split('"').nth(1)?.to_string())
+        })
+        .collect();
+
+    let code = code_only(&text);
+    let mut depth = 0usize;
+    let mut declarations = 0;
+    let mut previous = "";
+    for token in code.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '{' && c != '}') {
+        for part in token.split_inclusive(['{', '}']) {
+            let word = part.trim_end_matches(['{', '}']);
+            if depth == 0
+                && word.eq_ignore_ascii_case(language.keyword())
+                && !["extend", "mixin"]
+                    .iter()
+                    .any(|p| previous.eq_ignore_ascii_case(p))
+            {
+                declarations += 1;
+            }
+            if !word.is_empty() {
+                previous = word;
+            }
+            match part.chars().last() {
+                Some('{') => depth += 1,
+                Some('}') => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+    ActorLump {
+        name: lump.name().to_string(),
+        language,
+        size: lump.size(),
+        declarations,
+        includes,
+    }
+}
+
+impl Wad {
+    /// Finds the WAD's DECORATE and ZScript definitions: every `DECORATE`
+    /// and `ZSCRIPT` lump, and the lumps their `#include` lines name,
+    /// matched case-insensitively. Each is scanned for its size and its
+    /// top-level declarations, skipping comments and strings; nothing is
+    /// parsed further.
+    pub fn actor_definitions(&self) -> ActorDefsInfo {
+        let mut info = ActorDefsInfo::default();
+        let mut pending = self
+            .iter_lumps()
+            .filter_map(|lump| {
+                [ActorLanguage::Decorate, ActorLanguage::ZScript]
+                    .iter()
+                    .copied()
+                    .find(|language| lump.name() == language.lump_name())
+                    .map(|language| (lump, language))
+            })
+            .collect::<Vec<_>>();
+        pending.reverse();
+        while let Some((lump, language)) = pending.pop() {
+            let scanned = scan(lump, language);
+            for include in scanned.includes.iter().rev() {
+                let seen = |name: &str| {
+                    info.lumps.iter().any(|l| l.name == name)
+                        || pending.iter().any(|(l, _)| l.name() == name)
+                        || scanned.name == name
+                };
+                match self.lump_by_name(include) {
+                    Some(included) if !seen(included.name()) => pending.push((included, language)),
+                    Some(_) => {}
+                    None if !info.missing_includes.contains(include) => {
+                        info.missing_includes.push(include.clone())
+                    }
+                    None => {}
+                }
+            }
+            info.lumps.push(scanned);
+        }
+        info
+    }
+}
@@ -0,0 +1,261 @@
+use byteorder::ByteOrder;
+use byteorder::LE;
+
+use crate::game::Game;
+use crate::wad::name_from_bytes;
+use crate::wad::Wad;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimationKind {
+    Texture,
+    Flat,
+}
+
+/// A sequence of textures or flats that the engine cycles through, in frame order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationGroup {
+    pub kind: AnimationKind,
+    pub frames: Vec<String>,
+}
+
+impl AnimationGroup {
+    pub fn new(kind: AnimationKind, frames: &[&str]) -> Self {
+        Self {
+            kind,
+            frames: frames.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.frames.iter().any(|f| f.eq_ignore_ascii_case(name))
+    }
+}
+
+// the hardcoded animdefs table from the vanilla engine, expanded to the
+// frames that lie between each start and end name in the IWADs
+const VANILLA_FLATS: [&[&str]; 9] = [
+    &["NUKAGE1", "NUKAGE2", "NUKAGE3"],
+    &["FWATER1", "FWATER2", "FWATER3", "FWATER4"],
+    &["SWATER1", "SWATER2", "SWATER3", "SWATER4"],
+    &["LAVA1", "LAVA2", "LAVA3", "LAVA4"],
+    &["BLOOD1", "BLOOD2", "BLOOD3"],
+    &["RROCK05", "RROCK06", "RROCK07", "RROCK08"],
+    &["SLIME01", "SLIME02", "SLIME03", "SLIME04"],
+    &["SLIME05", "SLIME06", "SLIME07", "SLIME08"],
+    &["SLIME09", "SLIME10", "SLIME11", "SLIME12"],
+];
+
+const VANILLA_TEXTURES: [&[&str]; 13] = [
+    &["BLODGR1", "BLODGR2", "BLODGR3", "BLODGR4"],
+    &["SLADRIP1", "SLADRIP2", "SLADRIP3"],
+    &["BLODRIP1", "BLODRIP2", "BLODRIP3", "BLODRIP4"],
+    &["FIREWALA", "FIREWALB", "FIREWALL"],
+    &["GSTFONT1", "GSTFONT2", "GSTFONT3"],
+    &["FIRELAV3", "FIRELAVA"],
+    &["FIREMAG1", "FIREMAG2", "FIREMAG3"],
+    &["FIREBLU1", "FIREBLU2"],
+    &["ROCKRED1", "ROCKRED2", "ROCKRED3"],
+    &["BFALL1", "BFALL2", "BFALL3", "BFALL4"],
+    &["SFALL1", "SFALL2", "SFALL3", "SFALL4"],
+    &["WFALL1", "WFALL2", "WFALL3", "WFALL4"],
+    &["DBRAIN1", "DBRAIN2", "DBRAIN3", "DBRAIN4"],
+];
+
+/// The animation groups built into the vanilla engine. Groups defined by an
+/// `ANIMATED` lump can be appended to this list.
+pub fn vanilla_animations() -> Vec<AnimationGroup> {
+    groups(&VANILLA_FLATS, &VANILLA_TEXTURES)
+}
+
+// Heretic's table, expanded the same way
+const HERETIC_FLATS: [&[&str]; 6] = [
+    &["FLTWAWA1", "FLTWAWA2", "FLTWAWA3"],
+    &["FLTSLUD1", "FLTSLUD2", "FLTSLUD3"],
+    &["FLTTELE1", "FLTTELE2", "FLTTELE3", "FLTTELE4"],
+    &["FLTFLWW1", "FLTFLWW2", "FLTFLWW3"],
+    &["FLTLAVA1", "FLTLAVA2", "FLTLAVA3", "FLTLAVA4"],
+    &["FLATHUH1", "FLATHUH2", "FLATHUH3", "FLATHUH4"],
+];
+
+const HERETIC_TEXTURES: [&[&str]; 2] = [
+    &["LAVAFL1", "LAVAFL2", "LAVAFL3"],
+    &["WATRWAL1", "WATRWAL2", "WATRWAL3"],
+];
+
+fn groups(flats: &[&[&str]], textures: &[&[&str]]) -> Vec<AnimationGroup> {
+    flats
+        .iter()
+        .map(|frames| AnimationGroup::new(AnimationKind::Flat, frames))
+        .chain(
+            textures
+                .iter()
+                .map(|frames| AnimationGroup::new(AnimationKind::Texture, frames)),
+        )
+        .collect()
+}
+
+/// The animation groups hardcoded in a game's engine. Hexen defines all of
+/// its animations in the `ANIMDEFS` lump, so it has none; Strife's table isn't
+/// included yet.
+pub fn stock_animations(game: Game) -> Vec<AnimationGroup> {
+    match game {
+        Game::Heretic => groups(&HERETIC_FLATS, &HERETIC_TEXTURES),
+        Game::Hexen | Game::Strife => vec![],
+        Game::Doom | Game::Doom2 | Game::Unknown => vanilla_animations(),
+    }
+}
+
+/// How much of a stock animation group a WAD provides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationCoverage {
+    pub group: AnimationGroup,
+    /// The group's frames that the WAD defines, in frame order.
+    pub present: Vec<String>,
+}
+
+impl AnimationCoverage {
+    pub fn is_complete(&self) -> bool {
+        self.present.len() == self.group.frames.len()
+    }
+
+    pub fn missing(&self) -> Vec<&str> {
+        self.group
+            .frames
+            .iter()
+            .filter(|f| !self.present.contains(f))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// A record from a Boom `ANIMATED` lump. The frames are every texture or flat
+/// between `first` and `last` in WAD order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimDef {
+    pub kind: AnimationKind,
+    pub last: String,
+    pub first: String,
+    pub speed: u32,
+}
+
+/// A record from a Boom `SWITCHES` lump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchDef {
+    pub off: String,
+    pub on: String,
+    pub episode: u16,
+}
+
+const ANIMDEF_SIZE: usize = 23;
+const SWITCHDEF_SIZE: usize = 20;
+
+pub fn parse_animated(data: &[u8]) -> Vec<AnimDef> {
+    let mut defs = vec![];
+    for r in data.chunks_exact(ANIMDEF_SIZE) {
+        if r[0] == 0xFF {
+            break;
+        }
+        // bit 0 picks textures over flats; bit 1 is the ZDoom "allow decals"
+        // flag, which doesn't change the animation
+        let kind = if r[0] & 1 == 0 {
+            AnimationKind::Flat
+        } else {
+            AnimationKind::Texture
+        };
+        defs.push(AnimDef {
+            kind,
+            last: name_from_bytes(&r[1..]),
+            first: name_from_bytes(&r[10..]),
+            speed: LE::read_u32(&r[19..]),
+        });
+    }
+    defs
+}
+
+pub fn parse_switches(data: &[u8]) -> Vec<SwitchDef> {
+    let mut defs = vec![];
+    for r in data.chunks_exact(SWITCHDEF_SIZE) {
+        let episode = LE::read_u16(&r[18..]);
+        if episode == 0 {
+            break;
+        }
+        defs.push(SwitchDef {
+            off: name_from_bytes(&r[0..]),
+            on: name_from_bytes(&r[9..]),
+            episode,
+        });
+    }
+    defs
+}
+
+impl Wad {
+    /// The detected game's stock animation groups that this WAD defines at
+    /// least one frame of, from its flats and `TEXTURE1`/`TEXTURE2` entries.
+    /// A WAD that replaces only some frames of a group makes the animation
+    /// flicker between its own art and the IWAD's.
+    pub fn texture_animations(&self) -> Vec<AnimationCoverage> {
+        let flats = self.flat_names();
+        let textures = self.texture_names();
+        stock_animations(self.detect_game())
+            .into_iter()
+            .filter_map(|group| {
+                let defined = match group.kind {
+                    AnimationKind::Flat => &flats,
+                    AnimationKind::Texture => &textures,
+                };
+                let present = group
+                    .frames
+                    .iter()
+                    .filter(|f| defined.iter().any(|d| d.eq_ignore_ascii_case(f)))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if present.is_empty() {
+                    None
+                } else {
+                    Some(AnimationCoverage { group, present })
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(kind: u8, last: &str, first: &str) -> Vec<u8> {
+        let mut r = vec![kind];
+        for name in [last, first] {
+            let mut bytes = [0u8; 9];
+            bytes[..name.len()].copy_from_slice(name.as_bytes());
+            r.extend_from_slice(&bytes);
+        }
+        r.extend_from_slice(&8u32.to_le_bytes());
+        r
+    }
+
+    #[test]
+    fn animated_kind_comes_from_bit_zero() {
+        let mut data = [
+            record(0, "NUKAGE3", "NUKAGE1"),
+            record(1, "SLADRIP3", "SLADRIP1"),
+            record(2, "BLOOD3", "BLOOD1"),
+            record(3, "FIREBLU2", "FIREBLU1"),
+        ]
+        .concat();
+        data.push(0xFF);
+        let kinds = parse_animated(&data)
+            .iter()
+            .map(|d| d.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                AnimationKind::Flat,
+                AnimationKind::Texture,
+                AnimationKind::Flat,
+                AnimationKind::Texture
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeSet;
+
+use crate::geometry::contact;
+use crate::geometry::Contact;
+use crate::map::Map;
+use crate::Error;
+
+pub const BLOCK_SIZE: i32 = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blockmap {
+    pub origin_x: i16,
+    pub origin_y: i16,
+    pub columns: u16,
+    pub rows: u16,
+    /// The linedefs listed in each cell, row by row from the origin.
+    pub cells: Vec<Vec<u16>>,
+}
+
+/// A blockmap cell whose stored linedef list differs from a rebuild.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellMismatch {
+    pub column: u16,
+    pub row: u16,
+    /// Linedefs crossing the cell that the stored list leaves out.
+    pub missing: Vec<u16>,
+    /// Linedefs in the stored list that never touch the cell.
+    pub extra: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockmapReport {
+    pub origin_x: i16,
+    pub origin_y: i16,
+    pub columns: u16,
+    pub rows: u16,
+    pub mismatches: Vec<CellMismatch>,
+}
+
+impl BlockmapReport {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+fn invalid(map: &Map) -> Error {
+    Error::InvalidLumpSize(map.blockmap.name.clone(), map.blockmap.data.len())
+}
+
+impl Map {
+    pub fn blockmap(&self) -> Result<Blockmap, Error> {
+        let data = self.component(&self.blockmap)?;
+        let e = self.endianness;
+        if data.len() < 8 {
+            return Err(invalid(self));
+        }
+        let columns = e.read_u16(&data[4..]);
+        let rows = e.read_u16(&data[6..]);
+        let count = columns as usize * rows as usize;
+        if data.len() < 8 + count * 2 {
+            return Err(invalid(self));
+        }
+
+        let mut cells = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut pos = e.read_u16(&data[8 + i * 2..]) as usize * 2;
+            let mut lines = vec![];
+            // every list starts with a 0 word that the engine treats as linedef 0;
+            // builders emit it as a header, so it is skipped here
+            if data.get(pos..pos + 2).map(|w| e.read_u16(w)) == Some(0) {
+                pos += 2;
+            }
+            loop {
+                match data.get(pos..pos + 2).map(|w| e.read_u16(w)) {
+                    Some(0xFFFF) => break,
+                    Some(line) => lines.push(line),
+                    None => return Err(invalid(self)),
+                }
+                pos += 2;
+            }
+            cells.push(lines);
+        }
+
+        Ok(Blockmap {
+            origin_x: e.read_i16(&data[0..]),
+            origin_y: e.read_i16(&data[2..]),
+            columns,
+            rows,
+            cells,
+        })
+    }
+
+    /// Rebuilds the blockmap from the linedefs, using the stored origin and
+    /// dimensions, and compares each cell with the stored list. A linedef that
+    /// only runs along a cell's border may be listed or not, as node builders
+    /// disagree on that case.
+    pub fn blockmap_consistency(&self) -> Result<BlockmapReport, Error> {
+        let stored = self.blockmap()?;
+        let vertexes = self.vertexes()?;
+        let linedefs = self.linedefs()?;
+
+        let cell_count = stored.cells.len();
+        if cell_count == 0 {
+            return Ok(BlockmapReport {
+                origin_x: stored.origin_x,
+                origin_y: stored.origin_y,
+                columns: stored.columns,
+                rows: stored.rows,
+                mismatches: vec![],
+            });
+        }
+        let mut crossing = vec![BTreeSet::new(); cell_count];
+        let mut touching = vec![BTreeSet::new(); cell_count];
+        let block = BLOCK_SIZE as f64;
+        for (i, line) in linedefs.iter().enumerate() {
+            let (v1, v2) = match (
+                vertexes.get(line.start_vertex as usize),
+                vertexes.get(line.end_vertex as usize),
+            ) {
+                (Some(v1), Some(v2)) => (v1, v2),
+                _ => continue,
+            };
+            let p1 = (
+                (v1.x as i32 - stored.origin_x as i32) as f64,
+                (v1.y as i32 - stored.origin_y as i32) as f64,
+            );
+            let p2 = (
+                (v2.x as i32 - stored.origin_x as i32) as f64,
+                (v2.y as i32 - stored.origin_y as i32) as f64,
+            );
+            let clamp_col =
+                |x: f64| ((x / block).floor() as i64).clamp(0, stored.columns as i64 - 1);
+            let clamp_row = |y: f64| ((y / block).floor() as i64).clamp(0, stored.rows as i64 - 1);
+            // include the neighbouring cell on each side so lines on a border are seen
+            let (c0, c1) = (
+                clamp_col(p1.0.min(p2.0) - 1.0),
+                clamp_col(p1.0.max(p2.0) + 1.0),
+            );
+            let (r0, r1) = (
+                clamp_row(p1.1.min(p2.1) - 1.0),
+                clamp_row(p1.1.max(p2.1) + 1.0),
+            );
+            for row in r0..=r1 {
+                for column in c0..=c1 {
+                    let min = (column as f64 * block, row as f64 * block);
+                    let max = (min.0 + block, min.1 + block);
+                    let cell = row as usize * stored.columns as usize + column as usize;
+                    match contact(p1, p2, min, max) {
+                        Contact::Interior => {
+                            crossing[cell].insert(i as u16);
+                            touching[cell].insert(i as u16);
+                        }
+                        Contact::Edge => {
+                            touching[cell].insert(i as u16);
+                        }
+                        Contact::None => {}
+                    }
+                }
+            }
+        }
+
+        let mut mismatches = vec![];
+        for (cell, lines) in stored.cells.iter().enumerate() {
+            let lines = lines.iter().cloned().collect::<BTreeSet<_>>();
+            let missing = crossing[cell]
+                .difference(&lines)
+                .cloned()
+                .collect::<Vec<_>>();
+            let extra = lines
+                .difference(&touching[cell])
+                .cloned()
+                .collect::<Vec<_>>();
+            if !missing.is_empty() || !extra.is_empty() {
+                mismatches.push(CellMismatch {
+                    column: (cell % stored.columns as usize) as u16,
+                    row: (cell / stored.columns as usize) as u16,
+                    missing,
+                    extra,
+                });
+            }
+        }
+
+        Ok(BlockmapReport {
+            origin_x: stored.origin_x,
+            origin_y: stored.origin_y,
+            columns: stored.columns,
+            rows: stored.rows,
+            mismatches,
+        })
+    }
+}
@@ -0,0 +1,87 @@
+use crate::map::Linedef;
+use crate::map::Map;
+use crate::map::Node;
+use crate::map::Seg;
+use crate::map::Sidedef;
+use crate::map::Subsector;
+use crate::map::NODE_SUBSECTOR;
+use crate::map::NO_SIDEDEF;
+use crate::Error;
+
+/// Walks the node tree to the subsector containing a point, the way the engine
+/// does it. A map without nodes is a single subsector. Returns `None` if the tree
+/// points at a node that doesn't exist.
+pub(crate) fn subsector_at(nodes: &[Node], x: f64, y: f64) -> Option<u16> {
+    if nodes.is_empty() {
+        return Some(0);
+    }
+    let mut child = nodes.len() as u16 - 1;
+    // a well-formed tree is never deeper than it has nodes
+    for _ in 0..=nodes.len() {
+        if child & NODE_SUBSECTOR != 0 {
+            return Some(child & !NODE_SUBSECTOR);
+        }
+        let node = nodes.get(child as usize)?;
+        let left = (y - node.y as f64) * node.dx as f64;
+        let right = (x - node.x as f64) * node.dy as f64;
+        child = if right < left {
+            node.left_child
+        } else {
+            node.right_child
+        };
+    }
+    None
+}
+
+/// The sector a subsector belongs to, taken from its first seg.
+pub(crate) fn subsector_sector(
+    subsector: &Subsector,
+    segs: &[Seg],
+    linedefs: &[Linedef],
+    sidedefs: &[Sidedef],
+) -> Option<u16> {
+    let seg = segs.get(subsector.first_seg as usize)?;
+    let line = linedefs.get(seg.linedef as usize)?;
+    let side = if seg.direction == 0 {
+        line.right_sidedef
+    } else {
+        line.left_sidedef
+    };
+    if side == NO_SIDEDEF {
+        return None;
+    }
+    sidedefs.get(side as usize).map(|s| s.sector)
+}
+
+/// `NODES` signatures of the ZDoom extended formats: plain and compressed,
+/// for normal and GL nodes.
+const ZDOOM_NODE_SIGNATURES: [&[u8; 4]; 8] = [
+    b"XNOD", b"ZNOD", b"XGLN", b"ZGLN", b"XGL2", b"ZGL2", b"XGL3", b"ZGL3",
+];
+
+impl Map {
+    /// Guesses which node builder produced the map from the signature at the
+    /// start of its `NODES` lump:
+    ///
+    /// - `xNd4` is DeePBSP's V4 extended format, which only DeePBSP writes.
+    /// - `XNOD`, `ZNOD` and their GL counterparts are ZDoom's extended formats,
+    ///   defined by ZDBSP. Other builders that write them, such as ZokumBSP,
+    ///   mostly came later, so they're reported as ZDBSP.
+    ///
+    /// Vanilla-format nodes look the same whichever tool built them, so those
+    /// give `None`, as do maps saved without nodes.
+    pub fn node_builder_hint(&self) -> Option<&'static str> {
+        let nodes = &self.nodes.data;
+        if nodes.starts_with(b"xNd4") {
+            Some("DeePBSP")
+        } else if ZDOOM_NODE_SIGNATURES.iter().any(|s| nodes.starts_with(*s)) {
+            Some("ZDBSP")
+        } else {
+            None
+        }
+    }
+
+    pub fn subsector_at(&self, x: i16, y: i16) -> Result<Option<u16>, Error> {
+        Ok(subsector_at(&self.nodes()?, x as f64, y as f64))
+    }
+}
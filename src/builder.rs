@@ -0,0 +1,253 @@
+//! In-memory WADs for tests and benchmarks, built from the same structures the
+//! parser returns. Enabled by the `testutil` feature, and always in the
+//! crate's own tests.
+
+use crate::map::Linedef;
+use crate::map::MapThing;
+use crate::map::Sector;
+use crate::map::Sidedef;
+use crate::map::Vertex;
+#[cfg(test)]
+use crate::map::NO_SIDEDEF;
+use crate::wad::name_to_bytes;
+
+fn push_name(out: &mut Vec<u8>, name: &str) {
+    out.extend_from_slice(&name_to_bytes(name));
+}
+
+/// One map's geometry and things. The node lumps (`SEGS`, `SSECTORS`, `NODES`)
+/// and `REJECT` and `BLOCKMAP` are written empty unless given with `lump`.
+#[derive(Debug, Clone, Default)]
+pub struct MapBuilder {
+    name: String,
+    things: Vec<MapThing>,
+    vertexes: Vec<Vertex>,
+    linedefs: Vec<Linedef>,
+    sidedefs: Vec<Sidedef>,
+    sectors: Vec<Sector>,
+    lumps: Vec<(String, Vec<u8>)>,
+}
+
+impl MapBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn thing(mut self, thing: MapThing) -> Self {
+        self.things.push(thing);
+        self
+    }
+
+    pub fn vertex(mut self, x: i16, y: i16) -> Self {
+        self.vertexes.push(Vertex { x, y });
+        self
+    }
+
+    pub fn linedef(mut self, linedef: Linedef) -> Self {
+        self.linedefs.push(linedef);
+        self
+    }
+
+    pub fn sidedef(mut self, sidedef: Sidedef) -> Self {
+        self.sidedefs.push(sidedef);
+        self
+    }
+
+    pub fn sector(mut self, sector: Sector) -> Self {
+        self.sectors.push(sector);
+        self
+    }
+
+    /// Replaces one of the lumps written empty, such as `BLOCKMAP`, or adds
+    /// `BEHAVIOR` or `SCRIPTS`.
+    pub fn lump(mut self, name: impl Into<String>, data: Vec<u8>) -> Self {
+        self.lumps.push((name.into(), data));
+        self
+    }
+
+    /// Writes the things and linedefs as Hexen records, with the tag as the
+    /// special's first argument, and adds an empty `BEHAVIOR` lump. Things
+    /// get no thing ID, height or special.
+    #[cfg(test)]
+    pub(crate) fn hexen(self) -> Self {
+        let mut things = vec![];
+        for t in &self.things {
+            for field in [0, t.x, t.y, 0, t.angle] {
+                things.extend_from_slice(&field.to_le_bytes());
+            }
+            things.extend_from_slice(&t.doomednum.to_le_bytes());
+            things.extend_from_slice(&t.flags.to_le_bytes());
+            things.extend_from_slice(&[0; 6]);
+        }
+        let mut linedefs = vec![];
+        for l in &self.linedefs {
+            for field in [l.start_vertex, l.end_vertex, l.flags] {
+                linedefs.extend_from_slice(&field.to_le_bytes());
+            }
+            linedefs.extend_from_slice(&[l.special as u8, l.tag as u8, 0, 0, 0, 0]);
+            linedefs.extend_from_slice(&l.right_sidedef.to_le_bytes());
+            linedefs.extend_from_slice(&l.left_sidedef.to_le_bytes());
+        }
+        self.lump("THINGS", things)
+            .lump("LINEDEFS", linedefs)
+            .lump("BEHAVIOR", b"ACS\0\x08\0\0\0\0\0\0\0".to_vec())
+    }
+
+    /// The map's marker and components, in the order the engine requires.
+    fn into_lumps(self) -> Vec<(String, Vec<u8>)> {
+        let things = self.things.iter().flat_map(|t| t.to_bytes()).collect();
+        let linedefs = self.linedefs.iter().flat_map(|l| l.to_bytes()).collect();
+        let sidedefs = self.sidedefs.iter().flat_map(|s| s.to_bytes()).collect();
+        let vertexes = self.vertexes.iter().flat_map(|v| v.to_bytes()).collect();
+        let sectors = self.sectors.iter().flat_map(|s| s.to_bytes()).collect();
+
+        let mut lumps = vec![
+            (self.name, vec![]),
+            ("THINGS".to_string(), things),
+            ("LINEDEFS".to_string(), linedefs),
+            ("SIDEDEFS".to_string(), sidedefs),
+            ("VERTEXES".to_string(), vertexes),
+            ("SEGS".to_string(), vec![]),
+            ("SSECTORS".to_string(), vec![]),
+            ("NODES".to_string(), vec![]),
+            ("SECTORS".to_string(), sectors),
+            ("REJECT".to_string(), vec![]),
+            ("BLOCKMAP".to_string(), vec![]),
+        ];
+        for (name, data) in self.lumps {
+            match lumps.iter_mut().skip(1).find(|(n, _)| *n == name) {
+                Some((_, old)) => *old = data,
+                None => lumps.push((name, data)),
+            }
+        }
+        lumps
+    }
+}
+
+/// A WAD assembled lump by lump, serialized with `build` into bytes that
+/// `Wad::from_reader` accepts. Everything is little-endian.
+#[derive(Debug, Clone, Default)]
+pub struct WadBuilder {
+    iwad: bool,
+    lumps: Vec<(String, Vec<u8>)>,
+}
+
+impl WadBuilder {
+    /// An empty PWAD.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the WAD as an IWAD.
+    pub fn iwad(mut self) -> Self {
+        self.iwad = true;
+        self
+    }
+
+    /// Appends a lump. Names longer than 8 bytes are cut short.
+    pub fn lump(mut self, name: impl Into<String>, data: Vec<u8>) -> Self {
+        self.lumps.push((name.into(), data));
+        self
+    }
+
+    /// Appends a map's marker and lumps.
+    pub fn map(mut self, map: MapBuilder) -> Self {
+        self.lumps.extend(map.into_lumps());
+        self
+    }
+
+    /// The WAD file: the header, each lump's data in order, then the directory.
+    pub fn build(&self) -> Vec<u8> {
+        let mut data = vec![];
+        let mut directory = vec![];
+        for (name, lump) in &self.lumps {
+            directory.extend_from_slice(&(12 + data.len() as i32).to_le_bytes());
+            directory.extend_from_slice(&(lump.len() as i32).to_le_bytes());
+            push_name(&mut directory, name);
+            data.extend_from_slice(lump);
+        }
+        let mut wad = if self.iwad { b"IWAD" } else { b"PWAD" }.to_vec();
+        wad.extend_from_slice(&(self.lumps.len() as i32).to_le_bytes());
+        wad.extend_from_slice(&(12 + data.len() as i32).to_le_bytes());
+        wad.extend_from_slice(&data);
+        wad.extend_from_slice(&directory);
+        wad
+    }
+}
+
+/// A 256-unit square room with a player start, the smallest map every
+/// statistic works on.
+#[cfg(test)]
+pub(crate) fn square_room(name: &str) -> MapBuilder {
+    let corners = [(0, 0), (0, 256), (256, 256), (256, 0)];
+    let mut map = corners
+        .iter()
+        .fold(MapBuilder::new(name), |map, &(x, y)| map.vertex(x, y));
+    for i in 0..4u16 {
+        map = map
+            .linedef(Linedef {
+                start_vertex: i,
+                end_vertex: (i + 1) % 4,
+                flags: 1,
+                special: 0,
+                tag: 0,
+                right_sidedef: i,
+                left_sidedef: NO_SIDEDEF,
+            })
+            .sidedef(Sidedef {
+                x_offset: 0,
+                y_offset: 0,
+                upper_texture: "-".to_string(),
+                lower_texture: "-".to_string(),
+                middle_texture: "STARTAN3".to_string(),
+                sector: 0,
+            });
+    }
+    map.sector(Sector {
+        floor_height: 0,
+        ceiling_height: 128,
+        floor_flat: "FLOOR4_8".to_string(),
+        ceiling_flat: "CEIL3_5".to_string(),
+        light_level: 160,
+        special: 0,
+        tag: 0,
+    })
+    .thing(MapThing {
+        x: 64,
+        y: 64,
+        angle: 90,
+        doomednum: 1,
+        flags: 7,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::wad::Wad;
+
+    #[test]
+    fn built_wad_parses_back() {
+        let bytes = WadBuilder::new()
+            .map(square_room("MAP01"))
+            .map(square_room("MAP02").lump("BLOCKMAP", vec![0; 8]))
+            .lump("DEHACKED", b"Patch File for DeHackEd".to_vec())
+            .build();
+        let wad = Wad::from_reader(Cursor::new(bytes)).unwrap();
+        let names = wad.maps().iter().map(|m| m.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["MAP01", "MAP02"]);
+        let map = &wad.maps()[0];
+        assert_eq!(map.things().unwrap(), square_room("MAP01").things);
+        assert_eq!(map.vertexes().unwrap().len(), 4);
+        assert_eq!(map.linedefs().unwrap().len(), 4);
+        assert_eq!(map.sectors().unwrap()[0].floor_flat, "FLOOR4_8");
+        assert_eq!(wad.maps()[1].component_sizes().blockmap, 8);
+        assert!(wad.lump_by_name("DEHACKED").is_some());
+        assert!(!wad.is_iwad());
+    }
+}
@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use crate::compat::Port;
+use crate::json::Json;
+use crate::stats::MapStats;
+use crate::stats::StatSet;
+use crate::Error;
+
+/// The name of the cache file inside the cache directory.
+pub const CACHE_FILE: &str = "doom-map-stats-cache.json";
+
+const CACHE_VERSION: f64 = 3.0;
+
+/// Enough of a file's metadata to tell whether it changed since it was cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    pub mtime_secs: u64,
+    pub mtime_nanos: u32,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Result<Self, Error> {
+        let metadata = fs::metadata(path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Self {
+            size: metadata.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        })
+    }
+}
+
+/// One map's remembered stats, and the hash of the lumps they came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedMap {
+    /// The map's `Map::lumps_hash` when the stats were computed.
+    pub hash: String,
+    pub stats: MapStats,
+    /// `Map::is_hexen_format`.
+    pub hexen_format: bool,
+    /// The least capable port the map's own data needs, its
+    /// `CompatRow::minimum_port`.
+    pub port: Port,
+}
+
+/// What's remembered about one WAD: its lump count and the stats of every map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedWad {
+    pub lumps: usize,
+    pub maps: Vec<CachedMap>,
+}
+
+impl CachedWad {
+    /// The stats remembered for a map with this name and `Map::lumps_hash`,
+    /// if they cover `stats`, so a changed WAD only needs its changed maps
+    /// recomputed.
+    pub fn reusable(&self, name: &str, hash: &str, stats: StatSet) -> Option<&MapStats> {
+        self.maps
+            .iter()
+            .find(|m| m.stats.name == name && m.hash == hash && m.stats.computed() == stats)
+            .map(|m| &m.stats)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    path: PathBuf,
+    stamp: FileStamp,
+    wad: CachedWad,
+}
+
+/// Per-map statistics remembered between runs, keyed by each WAD's canonical
+/// path, size and modification time.
+///
+/// An entry is invalidated in two steps. While the path, size and
+/// modification time all still match the file on disk, `get` returns the
+/// whole entry and the WAD needn't even be parsed. Once any of them differs,
+/// the WAD is parsed again, but `previous` still returns the old entry, and
+/// a map's stats are only recomputed when `CachedWad::reusable` finds no
+/// remembered map with the same name and `Map::lumps_hash`. Editing one map
+/// of a megawad then recomputes just that map, while a node rebuild, which
+/// rewrites every map's node lumps, recomputes them all.
+///
+/// The cache is a single JSON document:
+///
+/// ```text
+/// {"version":3,"entries":[{"path":"/wads/a.wad","size":1234,"mtime_secs":1700000000,
+///   "mtime_nanos":0,"lumps":11,"maps":[{"hash":"9f86...","hexen_format":false,
+///   "port":"vanilla","stats":{"map":"MAP01",...}}]}]}
+/// ```
+///
+/// Each `stats` object has the same fields as the `--format json` output. A
+/// file that can't be read or has another version, such as one written
+/// before maps had hashes or ports, is treated as an empty cache, so
+/// everything is recomputed once and the file rewritten in the current
+/// version.
+#[derive(Debug)]
+pub struct StatsCache {
+    file: PathBuf,
+    entries: Vec<Entry>,
+    dirty: bool,
+}
+
+fn key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+fn entry_from_json(json: &Json) -> Option<Entry> {
+    let number = |key: &str| json.get(key)?.as_f64();
+    Some(Entry {
+        path: PathBuf::from(json.get("path")?.as_str()?),
+        stamp: FileStamp {
+            size: number("size")? as u64,
+            mtime_secs: number("mtime_secs")? as u64,
+            mtime_nanos: number("mtime_nanos")? as u32,
+        },
+        wad: CachedWad {
+            lumps: number("lumps")? as usize,
+            maps: json
+                .get("maps")?
+                .as_array()?
+                .iter()
+                .map(|map| {
+                    Some(CachedMap {
+                        hash: map.get("hash")?.as_str()?.to_string(),
+                        stats: MapStats::from_json(map.get("stats")?)?,
+                        hexen_format: map.get("hexen_format")?.as_bool()?,
+                        port: Port::from_name(map.get("port")?.as_str()?)?,
+                    })
+                })
+                .collect::<Option<_>>()?,
+        },
+    })
+}
+
+fn entry_to_json(entry: &Entry) -> Json {
+    Json::object(vec![
+        ("path", entry.path.to_string_lossy().into_owned().into()),
+        ("size", entry.stamp.size.into()),
+        ("mtime_secs", entry.stamp.mtime_secs.into()),
+        ("mtime_nanos", entry.stamp.mtime_nanos.into()),
+        ("lumps", entry.wad.lumps.into()),
+        (
+            "maps",
+            Json::Array(
+                entry
+                    .wad
+                    .maps
+                    .iter()
+                    .map(|map| {
+                        Json::object(vec![
+                            ("hash", map.hash.as_str().into()),
+                            ("hexen_format", map.hexen_format.into()),
+                            ("port", map.port.to_string().into()),
+                            ("stats", map.stats.to_json()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+impl StatsCache {
+    /// Opens the cache in `dir`, creating the directory if needed.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        fs::create_dir_all(dir.as_ref())?;
+        let file = dir.as_ref().join(CACHE_FILE);
+        let entries = fs::read_to_string(&file)
+            .ok()
+            .and_then(|text| Json::parse(&text))
+            .filter(|json| json.get("version").and_then(Json::as_f64) == Some(CACHE_VERSION))
+            .and_then(|json| {
+                json.get("entries")?
+                    .as_array()?
+                    .iter()
+                    .map(entry_from_json)
+                    .collect::<Option<Vec<_>>>()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            file,
+            entries,
+            dirty: false,
+        })
+    }
+
+    /// The cached stats for a WAD, if the file hasn't changed since.
+    pub fn get(&self, path: &Path) -> Option<&CachedWad> {
+        let stamp = FileStamp::of(path).ok()?;
+        let path = key(path);
+        self.entries
+            .iter()
+            .find(|e| e.path == path && e.stamp == stamp)
+            .map(|e| &e.wad)
+    }
+
+    /// The entry for a WAD whether or not the file has changed since, for
+    /// reusing the stats of the maps that haven't.
+    pub fn previous(&self, path: &Path) -> Option<&CachedWad> {
+        let path = key(path);
+        self.entries.iter().find(|e| e.path == path).map(|e| &e.wad)
+    }
+
+    pub fn insert(&mut self, path: &Path, wad: CachedWad) -> Result<(), Error> {
+        let stamp = FileStamp::of(path)?;
+        let path = key(path);
+        self.entries.retain(|e| e.path != path);
+        self.entries.push(Entry { path, stamp, wad });
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Writes the cache back to disk if anything was added.
+    pub fn save(&mut self) -> Result<(), Error> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let document = Json::object(vec![
+            ("version", CACHE_VERSION.into()),
+            (
+                "entries",
+                Json::Array(self.entries.iter().map(entry_to_json).collect()),
+            ),
+        ]);
+        fs::write(&self.file, format!("{}\n", document))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
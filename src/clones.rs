@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use crate::map::Map;
+use crate::sha256::Sha256;
+use crate::wad::Wad;
+
+fn same_geometry(a: &Map, b: &Map) -> bool {
+    a.vertexes.data == b.vertexes.data
+        && a.linedefs.data == b.linedefs.data
+        && a.sidedefs.data == b.sidedefs.data
+        && a.sectors.data == b.sectors.data
+}
+
+impl Map {
+    /// A SHA-256 of the map's authored geometry, as lowercase hex: the raw
+    /// bytes of `VERTEXES`, `LINEDEFS`, `SIDEDEFS` and `SECTORS`, hashed one
+    /// after another in that order with nothing between them. The same as
+    /// `cat VERTEXES LINEDEFS SIDEDEFS SECTORS | sha256sum` on the extracted
+    /// lumps. `THINGS` and the lumps a node builder writes, `SEGS`,
+    /// `SSECTORS`, `NODES`, `REJECT` and `BLOCKMAP`, are left out, so the hash
+    /// survives a node rebuild or a resave but not a change to the
+    /// architecture.
+    pub fn geometry_hash(&self) -> String {
+        let mut hash = Sha256::new();
+        for lump in [
+            &self.vertexes,
+            &self.linedefs,
+            &self.sidedefs,
+            &self.sectors,
+        ] {
+            hash.update(&lump.data);
+        }
+        hash.finish_hex()
+    }
+}
+
+impl Map {
+    /// A SHA-256 of every lump the map has, GL nodes included, for telling
+    /// whether anything its statistics are computed from changed. Each lump
+    /// is hashed as its name, its size as 8 little-endian bytes and then its
+    /// data, so moving bytes from one lump to the next changes the hash.
+    /// Missing optional lumps are left out.
+    pub fn lumps_hash(&self) -> String {
+        let mut hash = Sha256::new();
+        let lumps = [
+            &self.things,
+            &self.linedefs,
+            &self.sidedefs,
+            &self.vertexes,
+            &self.segs,
+            &self.subsectors,
+            &self.nodes,
+            &self.sectors,
+            &self.blockmap,
+        ];
+        let optional = [&self.reject, &self.behavior, &self.scripts];
+        for lump in lumps
+            .iter()
+            .copied()
+            .chain(optional.iter().filter_map(|l| l.as_ref()))
+            .chain(&self.gl)
+        {
+            hash.update(lump.name.as_bytes());
+            hash.update(&(lump.data.len() as u64).to_le_bytes());
+            hash.update(&lump.data);
+        }
+        hash.finish_hex()
+    }
+}
+
+impl Wad {
+    /// Groups of maps with exactly the same things, ignoring their order in the
+    /// lump, but not all the same geometry: typically a map and its minor-edit
+    /// re-release or deathmatch variant. Maps without things and maps whose
+    /// things can't be decoded are left out.
+    pub fn thing_layout_clones(&self) -> Vec<Vec<&Map>> {
+        let mut layouts = HashMap::<Vec<_>, Vec<&Map>>::new();
+        for map in self.maps() {
+            let mut things = match map.things() {
+                Ok(things) if !things.is_empty() => things
+                    .iter()
+                    .map(|t| (t.x, t.y, t.angle, t.doomednum, t.flags))
+                    .collect::<Vec<_>>(),
+                _ => continue,
+            };
+            things.sort_unstable();
+            layouts.entry(things).or_default().push(map);
+        }
+
+        let mut groups = layouts
+            .into_values()
+            .filter(|maps| maps.iter().any(|m| !same_geometry(m, maps[0])))
+            .collect::<Vec<_>>();
+        // keep the output in WAD order
+        let position = |map: &Map| self.maps().iter().position(|m| std::ptr::eq(m, map));
+        groups.sort_by_key(|maps| position(maps[0]));
+        groups
+    }
+}
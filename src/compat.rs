@@ -0,0 +1,653 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use crate::demo::DemoHeader;
+use crate::game::Game;
+use crate::json::Json;
+use crate::map::Map;
+use crate::map::THING_FLAG_FRIENDLY;
+use crate::map::THING_FLAG_NOT_COOP;
+use crate::map::THING_FLAG_NOT_DEATHMATCH;
+use crate::wad::Wad;
+use crate::Error;
+
+/// Source ports in roughly increasing order of the features they support.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Port {
+    #[default]
+    Vanilla,
+    LimitRemoving,
+    Boom,
+    Mbf,
+    ZDoom,
+    GZDoom,
+}
+
+impl Port {
+    pub const ALL: [Port; 6] = [
+        Port::Vanilla,
+        Port::LimitRemoving,
+        Port::Boom,
+        Port::Mbf,
+        Port::ZDoom,
+        Port::GZDoom,
+    ];
+
+    /// The port a name as displayed stands for, such as `Boom` or
+    /// `limit-removing`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.to_string() == name)
+    }
+}
+
+impl Display for Port {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Port::Vanilla => "vanilla",
+            Port::LimitRemoving => "limit-removing",
+            Port::Boom => "Boom",
+            Port::Mbf => "MBF",
+            Port::ZDoom => "ZDoom",
+            Port::GZDoom => "GZDoom",
+        })
+    }
+}
+
+/// The evidence behind `Wad::engine_compatibility`'s guess. Each field is one
+/// kind of signal, so the guess can be explained.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EngineCompat {
+    /// Version bytes of the built-in demos that have one.
+    pub demo_versions: Vec<u8>,
+    /// Maps with more than 32767 vertexes, linedefs, sidedefs, segs or
+    /// subsectors, or a blockmap over 64 KiB, which overflow vanilla's signed
+    /// 16-bit indexes.
+    pub limit_removing_maps: Vec<String>,
+    /// Maps using Boom's extended or generalized linedef or sector specials.
+    pub boom_special_maps: Vec<String>,
+    /// Maps using MBF things, thing flags, sky transfers or MBF21 line flags.
+    pub mbf_maps: Vec<String>,
+    /// Maps with monsters flagged friendly, which are also in `mbf_maps`.
+    pub friendly_maps: Vec<String>,
+    /// Whether the WAD carries a `DEHACKED` lump, which only ports load.
+    pub dehacked: bool,
+    /// Maps whose nodes are in the DeePBSP or uncompressed ZDoom format.
+    pub extended_node_maps: Vec<String>,
+    /// Maps whose nodes are in the compressed ZDoom format.
+    pub compressed_node_maps: Vec<String>,
+    /// Whether the WAD has text-format (UDMF) maps.
+    pub udmf: bool,
+    /// Whether a Doom WAD has maps in Hexen format.
+    pub hexen_format: bool,
+    /// ZDoom definition lumps such as `DECORATE` or `ZMAPINFO`.
+    pub zdoom_lumps: Vec<String>,
+    /// Lumps only GZDoom reads, such as `ZSCRIPT` or `GLDEFS`.
+    pub gzdoom_lumps: Vec<String>,
+}
+
+const ZDOOM_LUMPS: [&str; 9] = [
+    "DECORATE", "ZMAPINFO", "MAPINFO", "SNDINFO", "LANGUAGE", "KEYCONF", "SBARINFO", "TEXTURES",
+    "LOADACS",
+];
+
+const GZDOOM_LUMPS: [&str; 4] = ["ZSCRIPT", "GLDEFS", "MODELDEF", "MATERIAL"];
+
+/// The most vertexes, linedefs, sidedefs, segs or subsectors vanilla can
+/// index, since it stores indexes as signed 16-bit numbers.
+pub const VANILLA_INDEX_LIMIT: usize = 32767;
+const VANILLA_BLOCKMAP_LIMIT: usize = 0x10000;
+
+const HELPER_DOG: u16 = 888;
+
+const LINEDEF_FLAG_MBF21: u16 = 0x1000 | 0x2000;
+
+fn is_boom_linedef_special(special: u16) -> bool {
+    (142..=270).contains(&special) || special >= 0x2F80
+}
+
+fn is_mbf_linedef_special(special: u16) -> bool {
+    matches!(special, 271 | 272)
+}
+
+/// How many of some map structure there are against vanilla's limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexUsage {
+    pub count: usize,
+    pub limit: usize,
+}
+
+impl IndexUsage {
+    /// How many more would still fit, negative once the limit is passed.
+    pub fn headroom(&self) -> i64 {
+        self.limit as i64 - self.count as i64
+    }
+
+    pub fn overflows(&self) -> bool {
+        self.count > self.limit
+    }
+}
+
+/// What `Wad::engine_compatibility` notices about one map.
+struct MapSignals {
+    compressed_nodes: bool,
+    extended_nodes: bool,
+    over_limits: bool,
+    boom: bool,
+    mbf: bool,
+    friendly: bool,
+}
+
+impl MapSignals {
+    /// The specials and flags are only looked at in Doom-format maps of a
+    /// `doom` game; lumps that can't be read count as empty.
+    fn of(map: &Map, doom: bool) -> Self {
+        let nodes = &map.nodes.data;
+        let linedefs = map.linedefs().unwrap_or_default();
+        let counts = [
+            map.vertexes().map(|v| v.len()).unwrap_or(0),
+            linedefs.len(),
+            map.sidedefs().map(|s| s.len()).unwrap_or(0),
+            map.segs().map(|s| s.len()).unwrap_or(0),
+            map.subsectors().map(|s| s.len()).unwrap_or(0),
+        ];
+        let mut signals = MapSignals {
+            compressed_nodes: nodes.starts_with(b"ZNOD") || nodes.starts_with(b"ZGLN"),
+            extended_nodes: nodes.starts_with(b"xNd4") || nodes.starts_with(b"XNOD"),
+            over_limits: counts.iter().any(|&n| n > VANILLA_INDEX_LIMIT)
+                || map.blockmap.data.len() > VANILLA_BLOCKMAP_LIMIT,
+            boom: false,
+            mbf: false,
+            friendly: false,
+        };
+        if !doom || map.behavior.is_some() {
+            return signals;
+        }
+        let sectors = map.sectors().unwrap_or_default();
+        let things = map.things().unwrap_or_default();
+        signals.boom = linedefs.iter().any(|l| is_boom_linedef_special(l.special))
+            || sectors.iter().any(|s| s.special & !0x1F != 0)
+            || things
+                .iter()
+                .any(|t| t.port_flags() & (THING_FLAG_NOT_DEATHMATCH | THING_FLAG_NOT_COOP) != 0);
+        signals.friendly = things
+            .iter()
+            .any(|t| t.port_flags() & THING_FLAG_FRIENDLY != 0);
+        signals.mbf = signals.friendly
+            || linedefs
+                .iter()
+                .any(|l| is_mbf_linedef_special(l.special) || l.flags & LINEDEF_FLAG_MBF21 != 0)
+            || things.iter().any(|t| t.doomednum == HELPER_DOG);
+        signals
+    }
+}
+
+impl Map {
+    /// Whether vanilla Doom can run the map, going by the map's own lumps.
+    /// It can't when any of these hold:
+    ///
+    /// - its nodes are in the DeePBSP or a ZDoom format, which vanilla can't
+    ///   read;
+    /// - it has more than 32767 vertexes, linedefs, sidedefs, segs or
+    ///   subsectors, or a blockmap over 64 KiB;
+    /// - it's in Hexen format, with a `BEHAVIOR` lump;
+    /// - it uses Boom's extended or generalized specials, or the Boom thing
+    ///   flags for leaving things out of deathmatch or cooperative play;
+    /// - it uses MBF's specials, MBF21's line flags, the helper dog or
+    ///   friendly monsters.
+    ///
+    /// Specials are read as Doom's, so this is only meaningful for Doom
+    /// maps. UDMF maps aren't read as `Map`s; `Wad::engine_compatibility`
+    /// notes them, along with the WAD-wide signals such as `DEHACKED`.
+    pub fn is_vanilla_compatible(&self) -> bool {
+        let signals = MapSignals::of(self, true);
+        !(signals.compressed_nodes
+            || signals.extended_nodes
+            || signals.over_limits
+            || self.behavior.is_some()
+            || signals.boom
+            || signals.mbf)
+    }
+
+    /// The segs against vanilla's index limit. Past it, the renderer reads
+    /// segs through wrapped-around indexes and walls draw as garbage. Maps
+    /// with ZDoom nodes keep their segs in `NODES` and count zero here.
+    pub fn seg_overflow(&self) -> Result<IndexUsage, Error> {
+        Ok(IndexUsage {
+            count: self.segs()?.len(),
+            limit: VANILLA_INDEX_LIMIT,
+        })
+    }
+
+    /// The subsectors against vanilla's index limit. Node children mark
+    /// subsectors with the top bit, so the tree can't point at any past it.
+    pub fn ssector_overflow(&self) -> Result<IndexUsage, Error> {
+        Ok(IndexUsage {
+            count: self.subsectors()?.len(),
+            limit: VANILLA_INDEX_LIMIT,
+        })
+    }
+}
+
+impl EngineCompat {
+    /// The least capable port that handles every signal found.
+    pub fn minimum_port(&self) -> Port {
+        let mut port = Port::Vanilla;
+        let mut need = |condition: bool, p: Port| {
+            if condition {
+                port = port.max(p);
+            }
+        };
+        need(!self.limit_removing_maps.is_empty(), Port::LimitRemoving);
+        need(!self.extended_node_maps.is_empty(), Port::LimitRemoving);
+        need(
+            self.demo_versions.iter().any(|v| (200..=202).contains(v)),
+            Port::Boom,
+        );
+        need(!self.boom_special_maps.is_empty(), Port::Boom);
+        need(self.dehacked, Port::Boom);
+        need(self.demo_versions.contains(&203), Port::Mbf);
+        need(!self.mbf_maps.is_empty(), Port::Mbf);
+        need(!self.compressed_node_maps.is_empty(), Port::ZDoom);
+        need(self.udmf, Port::ZDoom);
+        need(self.hexen_format, Port::ZDoom);
+        need(!self.zdoom_lumps.is_empty(), Port::ZDoom);
+        need(!self.gzdoom_lumps.is_empty(), Port::GZDoom);
+        port
+    }
+}
+
+impl Wad {
+    /// Collects the signals that hint at which source port a WAD needs. The
+    /// map checks assume Doom's specials and are skipped for other games,
+    /// whose own `MAPINFO`, `SNDINFO` and ACS lumps are also not signals.
+    pub fn engine_compatibility(&self) -> EngineCompat {
+        let game = self.detect_game();
+        let doom = !matches!(game, Game::Heretic | Game::Hexen | Game::Strife);
+        let mut compat = EngineCompat {
+            demo_versions: (1..=3)
+                .filter_map(|n| DemoHeader::parse(self.demo(n)?).ok()?.version)
+                .collect(),
+            dehacked: self.lump_by_name("DEHACKED").is_some(),
+            udmf: self.lump_by_name("TEXTMAP").is_some(),
+            hexen_format: doom
+                && self.maps().iter().any(|m| m.behavior.is_some())
+                && !self.is_iwad(),
+            zdoom_lumps: ZDOOM_LUMPS
+                .iter()
+                .filter(|name| doom || !matches!(**name, "MAPINFO" | "SNDINFO"))
+                .filter(|name| self.lump_by_name(name).is_some())
+                .map(|name| name.to_string())
+                .collect(),
+            gzdoom_lumps: GZDOOM_LUMPS
+                .iter()
+                .filter(|name| self.lump_by_name(name).is_some())
+                .map(|name| name.to_string())
+                .collect(),
+            ..EngineCompat::default()
+        };
+
+        for map in self.maps() {
+            let name = map.name().to_string();
+            let signals = MapSignals::of(map, doom);
+            let lists = [
+                (signals.compressed_nodes, &mut compat.compressed_node_maps),
+                (signals.extended_nodes, &mut compat.extended_node_maps),
+                (signals.over_limits, &mut compat.limit_removing_maps),
+                (signals.boom, &mut compat.boom_special_maps),
+                (signals.friendly, &mut compat.friendly_maps),
+                (signals.mbf, &mut compat.mbf_maps),
+            ];
+            for (found, maps) in lists {
+                if found {
+                    maps.push(name.clone());
+                }
+            }
+        }
+        compat
+    }
+
+    /// Every distinct linedef special and sector special the WAD's maps use,
+    /// in that order, for judging a whole megawad at once. Zero, no special,
+    /// is included when some line or sector has none. Maps whose lumps can't
+    /// be decoded are left out.
+    pub fn used_specials(&self) -> (BTreeSet<u16>, BTreeSet<u16>) {
+        let (mut line_specials, mut sector_specials) = (BTreeSet::new(), BTreeSet::new());
+        for map in self.maps() {
+            if let Ok(lines) = map.iter_linedefs() {
+                line_specials.extend(lines.map(|l| l.special));
+            }
+            if let Ok(sectors) = map.iter_sectors() {
+                sector_specials.extend(sectors.map(|s| s.special));
+            }
+        }
+        (line_specials, sector_specials)
+    }
+}
+
+/// How well a map is expected to run in a port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CompatLevel {
+    Pass,
+    /// The map's own data is fine, but the WAD has lumps or demos the port
+    /// doesn't read, such as `DEHACKED` in vanilla or `DECORATE` in Boom. The
+    /// map may still play, with something missing.
+    Warn,
+    /// The map uses limits, specials, nodes or a format the port can't
+    /// handle.
+    Fail,
+}
+
+impl Display for CompatLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CompatLevel::Pass => "pass",
+            CompatLevel::Warn => "warn",
+            CompatLevel::Fail => "fail",
+        })
+    }
+}
+
+/// The ports `Wad::compatibility_matrix` checks against. ZDoom stands for
+/// GZDoom as well, which only warns for GZDoom's own lumps.
+pub const MATRIX_PORTS: [Port; 4] = [Port::Vanilla, Port::Boom, Port::Mbf, Port::ZDoom];
+
+/// A map's row of a `CompatMatrix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatRow {
+    pub map: String,
+    /// The least capable port the map's own data needs.
+    pub minimum_port: Port,
+    /// One level for each of the matrix's ports, in order.
+    pub levels: Vec<CompatLevel>,
+}
+
+/// Each map's expected compatibility with a few common ports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatMatrix {
+    pub ports: Vec<Port>,
+    /// Binary maps in WAD order, followed by the UDMF maps.
+    pub rows: Vec<CompatRow>,
+}
+
+impl CompatMatrix {
+    /// The rows, each map's levels keyed by port name.
+    pub fn to_json(&self) -> Json {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let levels = self
+                    .ports
+                    .iter()
+                    .zip(&row.levels)
+                    .map(|(port, level)| (port.to_string(), level.to_string().into()));
+                Json::object(vec![
+                    ("map", row.map.as_str().into()),
+                    ("minimum_port", row.minimum_port.to_string().into()),
+                    ("ports", Json::object(levels)),
+                ])
+            })
+            .collect();
+        Json::object(vec![
+            (
+                "ports",
+                Json::Array(self.ports.iter().map(|p| p.to_string().into()).collect()),
+            ),
+            ("maps", Json::Array(rows)),
+        ])
+    }
+}
+
+impl EngineCompat {
+    /// The least capable port a map's own signals need, leaving out the
+    /// WAD-wide ones.
+    fn map_port(&self, map: &Map) -> Port {
+        let name = map.name().to_string();
+        let listed = |maps: &[String]| maps.contains(&name);
+        if listed(&self.compressed_node_maps) || (self.hexen_format && map.behavior.is_some()) {
+            Port::ZDoom
+        } else if listed(&self.mbf_maps) {
+            Port::Mbf
+        } else if listed(&self.boom_special_maps) {
+            Port::Boom
+        } else if listed(&self.limit_removing_maps) || listed(&self.extended_node_maps) {
+            Port::LimitRemoving
+        } else {
+            Port::Vanilla
+        }
+    }
+
+    /// The least capable port the WAD-wide signals need: demos, `DEHACKED`
+    /// and port-specific lumps.
+    fn wad_port(&self) -> Port {
+        EngineCompat {
+            demo_versions: self.demo_versions.clone(),
+            dehacked: self.dehacked,
+            zdoom_lumps: self.zdoom_lumps.clone(),
+            gzdoom_lumps: self.gzdoom_lumps.clone(),
+            ..EngineCompat::default()
+        }
+        .minimum_port()
+    }
+}
+
+impl EngineCompat {
+    /// Why a binary map the matrix rates at `port` needs it, checking the
+    /// signals in `map_port`'s order.
+    fn map_reason(&self, map: &str, port: Port) -> &'static str {
+        let listed = |maps: &[String]| maps.iter().any(|m| m == map);
+        match port {
+            Port::ZDoom if listed(&self.compressed_node_maps) => "compressed nodes",
+            Port::ZDoom | Port::GZDoom => "Hexen format",
+            Port::Mbf => "MBF features",
+            Port::Boom => "Boom specials",
+            Port::LimitRemoving if listed(&self.limit_removing_maps) => "over vanilla limits",
+            Port::LimitRemoving => "extended nodes",
+            Port::Vanilla => "nothing",
+        }
+    }
+
+    /// The WAD-wide signals that need `port` itself, as `wad_port` weighs
+    /// them.
+    fn wad_reasons(&self, port: Port) -> Vec<String> {
+        let mut reasons = vec![];
+        match port {
+            Port::GZDoom => reasons.push(self.gzdoom_lumps.join(", ")),
+            Port::ZDoom => reasons.push(self.zdoom_lumps.join(", ")),
+            Port::Mbf => reasons.push("an MBF demo".to_string()),
+            Port::Boom => {
+                if self.dehacked {
+                    reasons.push("DEHACKED lump".to_string());
+                }
+                if self.demo_versions.iter().any(|v| (200..=202).contains(v)) {
+                    reasons.push("a Boom demo".to_string());
+                }
+            }
+            Port::Vanilla | Port::LimitRemoving => {}
+        }
+        reasons
+    }
+}
+
+// how a verdict names a port: "a Boom-compatible port"
+fn port_phrase(port: Port) -> String {
+    match port {
+        Port::Vanilla => "vanilla".to_string(),
+        Port::LimitRemoving => "a limit-removing port".to_string(),
+        Port::Mbf => "an MBF-compatible port".to_string(),
+        Port::GZDoom => "GZDoom".to_string(),
+        port => format!("a {}-compatible port", port),
+    }
+}
+
+// "Boom specials in MAP01, MAP02, MAP03 and 2 more", for each reason in the
+// order its first map comes
+fn describe_maps(reasons: &[(&str, &str)]) -> String {
+    let mut groups: Vec<(&str, Vec<&str>)> = vec![];
+    for &(reason, map) in reasons {
+        match groups.iter_mut().find(|(r, _)| *r == reason) {
+            Some((_, maps)) => maps.push(map),
+            None => groups.push((reason, vec![map])),
+        }
+    }
+    groups
+        .iter()
+        .map(|(reason, maps)| {
+            let mut list = maps[..maps.len().min(3)].join(", ");
+            if maps.len() > 3 {
+                list += &format!(" and {} more", maps.len() - 3);
+            }
+            format!("{} in {}", reason, list)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn compat_row(map: String, minimum_port: Port, wad_port: Port) -> CompatRow {
+    let levels = MATRIX_PORTS
+        .iter()
+        .map(|&port| {
+            if minimum_port > port {
+                CompatLevel::Fail
+            } else if wad_port > port {
+                CompatLevel::Warn
+            } else {
+                CompatLevel::Pass
+            }
+        })
+        .collect();
+    CompatRow {
+        map,
+        minimum_port,
+        levels,
+    }
+}
+
+impl Wad {
+    /// Grades every map against `MATRIX_PORTS`, from the signals
+    /// `engine_compatibility` collects. A map fails a port less capable than
+    /// its own limits, specials, nodes or format need, with Hexen-format and
+    /// UDMF maps needing ZDoom. It passes with a warning when only the WAD's
+    /// demos, `DEHACKED` or port-specific lumps need more.
+    pub fn compatibility_matrix(&self) -> CompatMatrix {
+        self.matrix_from(&self.engine_compatibility())
+    }
+
+    fn matrix_from(&self, compat: &EngineCompat) -> CompatMatrix {
+        let wad_port = compat.wad_port();
+        let mut rows: Vec<CompatRow> = self
+            .maps()
+            .iter()
+            .map(|map| compat_row(map.name().to_string(), compat.map_port(map), wad_port))
+            .collect();
+        rows.extend(
+            self.udmf_maps()
+                .iter()
+                .map(|map| compat_row(map.name().to_string(), Port::ZDoom, wad_port)),
+        );
+        CompatMatrix {
+            ports: MATRIX_PORTS.to_vec(),
+            rows,
+        }
+    }
+}
+
+impl Wad {
+    /// A one-sentence summary of `compatibility_matrix`, for release notes:
+    /// the most capable port any map or WAD-wide lump needs, and what needs
+    /// it, such as "Requires a Boom-compatible port (Boom specials in MAP12)".
+    /// The maps' part is the highest `CompatRow::minimum_port`, so it always
+    /// agrees with the matrix. When only the WAD's demos, `DEHACKED` or port
+    /// lumps, which the matrix shows as warnings, need more than the maps,
+    /// the sentence names those and says what the maps alone need.
+    pub fn port_verdict(&self) -> String {
+        let compat = self.engine_compatibility();
+        let matrix = self.matrix_from(&compat);
+        let wad_port = compat.wad_port();
+        let map_port = matrix
+            .rows
+            .iter()
+            .map(|r| r.minimum_port)
+            .max()
+            .unwrap_or_default();
+        // the binary maps come first, then the UDMF ones
+        let reasons = matrix
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.minimum_port == map_port)
+            .map(|(i, row)| {
+                let reason = if i < self.maps().len() {
+                    compat.map_reason(&row.map, map_port)
+                } else {
+                    "UDMF"
+                };
+                (reason, row.map.as_str())
+            })
+            .collect::<Vec<_>>();
+
+        if wad_port > map_port {
+            let maps = match map_port {
+                Port::Vanilla => "the maps alone run in vanilla".to_string(),
+                port => format!(
+                    "the maps alone need {} ({})",
+                    port_phrase(port),
+                    describe_maps(&reasons)
+                ),
+            };
+            format!(
+                "Requires {} ({}); {}",
+                port_phrase(wad_port),
+                compat.wad_reasons(wad_port).join(", "),
+                maps
+            )
+        } else if map_port == Port::Vanilla {
+            "Runs in vanilla; nothing needs a source port".to_string()
+        } else {
+            format!(
+                "Requires {} ({})",
+                port_phrase(map_port),
+                describe_maps(&reasons)
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::builder::square_room;
+    use crate::builder::WadBuilder;
+    use crate::map::MapThing;
+
+    fn hexen_format_wad(doomednum: u16) -> Wad {
+        let thing = MapThing {
+            x: 128,
+            y: 128,
+            angle: 0,
+            doomednum,
+            flags: 7,
+        };
+        let map = square_room("MAP01").thing(thing).hexen();
+        let bytes = WadBuilder::new().map(map).build();
+        Wad::from_reader(Cursor::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn hexen_pwads_dont_need_zdoom_for_their_format() {
+        // an ettin
+        let compat = hexen_format_wad(10030).engine_compatibility();
+        assert!(!compat.hexen_format);
+        assert_eq!(compat.minimum_port(), Port::Vanilla);
+    }
+
+    #[test]
+    fn doom_maps_in_hexen_format_need_zdoom() {
+        // an imp
+        let compat = hexen_format_wad(3001).engine_compatibility();
+        assert!(compat.hexen_format);
+        assert_eq!(compat.minimum_port(), Port::ZDoom);
+    }
+}
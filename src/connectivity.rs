@@ -0,0 +1,368 @@
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::geometry::sector_at;
+use crate::json::Json;
+use crate::lint::LINE_TELEPORT_SPECIALS;
+use crate::lint::THING_TELEPORT_SPECIALS;
+use crate::map::Map;
+use crate::map::LINEDEF_FLAG_IMPASSABLE;
+use crate::map::NO_SIDEDEF;
+use crate::things::Thing;
+use crate::Error;
+
+/// How a `Graph` edge gets the player from one sector to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// A plain two-sided line, walked across.
+    Open,
+    /// A door the player opens from the line, vanilla or generalized.
+    Door,
+    /// A line that lowers a lift, vanilla or generalized.
+    Lift,
+    /// A teleporter line, taking the player from its right side's sector to
+    /// where it sends them. Unlike the other kinds it only goes one way.
+    Teleporter,
+}
+
+/// One way between two sectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    /// The linedef making the connection.
+    pub linedef: usize,
+    pub kind: EdgeKind,
+}
+
+/// A map's sectors joined by the lines the player can cross, as built by
+/// `Map::connectivity_graph`. Every edge but a teleporter's goes both ways.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph {
+    pub sectors: usize,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// The sectors reachable in one step from each sector, with the kind of
+    /// edge leading there, in edge order.
+    pub fn adjacency_list(&self) -> Vec<Vec<(usize, EdgeKind)>> {
+        let mut neighbors = vec![vec![]; self.sectors];
+        for edge in &self.edges {
+            neighbors[edge.from].push((edge.to, edge.kind));
+            if edge.kind != EdgeKind::Teleporter {
+                neighbors[edge.to].push((edge.from, edge.kind));
+            }
+        }
+        neighbors
+    }
+
+    /// The graph in Graphviz's DOT language. Two-way edges are drawn without
+    /// arrows, and doors, lifts and teleporters are labeled.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph sectors {\n");
+        for sector in 0..self.sectors {
+            writeln!(dot, "    {};", sector).unwrap();
+        }
+        for edge in &self.edges {
+            let attributes = match edge.kind {
+                EdgeKind::Open => "dir=none",
+                EdgeKind::Door => "dir=none, style=dashed, label=\"door\"",
+                EdgeKind::Lift => "dir=none, style=dashed, label=\"lift\"",
+                EdgeKind::Teleporter => "style=dotted, label=\"teleporter\"",
+            };
+            writeln!(dot, "    {} -> {} [{}];", edge.from, edge.to, attributes).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// How many sectors a sector has to lead to for `Map::topology` to count it
+/// as a hub.
+pub const HUB_DEGREE: usize = 8;
+
+/// How a map's sectors are laid out, from `Map::topology`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TopologyKind {
+    /// Mostly one route through: the longest shortest path between two
+    /// sectors passes at least two thirds of them.
+    Linear,
+    /// Routes that fork, without any single sector they meet at.
+    Branching,
+    /// At least one hub sector, from which much of the map leads off.
+    HubAndSpoke,
+}
+
+impl TopologyKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TopologyKind::Linear => "linear",
+            TopologyKind::Branching => "branching",
+            TopologyKind::HubAndSpoke => "hub-and-spoke",
+        }
+    }
+}
+
+/// A map's level flow: its classification and the graph measures it's made
+/// from. The measures are taken over the largest group of sectors joined to
+/// each other, so control sectors and other unconnected scraps don't count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Topology {
+    pub kind: TopologyKind,
+    /// How many sectors the measures cover.
+    pub sectors: usize,
+    /// The most steps the shortest route between two sectors takes.
+    pub diameter: usize,
+    /// How many other sectors a sector leads to, on average.
+    pub average_degree: f64,
+    pub max_degree: usize,
+    /// How many sectors lead to at least `HUB_DEGREE` others.
+    pub hubs: usize,
+}
+
+impl Topology {
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("kind", self.kind.name().into()),
+            ("sectors", self.sectors.into()),
+            ("diameter", self.diameter.into()),
+            ("average_degree", self.average_degree.into()),
+            ("max_degree", self.max_degree.into()),
+            ("hubs", self.hubs.into()),
+        ])
+    }
+}
+
+// each sector's distance from `start`, or `None` where it can't be reached
+fn distances(neighbors: &[BTreeSet<usize>], start: usize) -> Vec<Option<usize>> {
+    let mut distance = vec![None; neighbors.len()];
+    distance[start] = Some(0);
+    let mut queue = VecDeque::from([start]);
+    while let Some(sector) = queue.pop_front() {
+        let next = distance[sector].map(|d| d + 1);
+        for &n in &neighbors[sector] {
+            if distance[n].is_none() {
+                distance[n] = next;
+                queue.push_back(n);
+            }
+        }
+    }
+    distance
+}
+
+// doors opened from the line itself, as opposed to remotely through a tag
+const DOOR_SPECIALS: [u16; 10] = [1, 26, 27, 28, 31, 32, 33, 34, 117, 118];
+const LIFT_SPECIALS: [u16; 8] = [10, 21, 62, 88, 120, 121, 122, 123];
+const GENERALIZED_LIFTS: std::ops::Range<u16> = 0x3400..0x3800;
+// generalized locked doors and doors
+const GENERALIZED_DOORS: std::ops::Range<u16> = 0x3800..0x4000;
+
+fn edge_kind(special: u16) -> EdgeKind {
+    if DOOR_SPECIALS.contains(&special) || GENERALIZED_DOORS.contains(&special) {
+        EdgeKind::Door
+    } else if LIFT_SPECIALS.contains(&special) || GENERALIZED_LIFTS.contains(&special) {
+        EdgeKind::Lift
+    } else {
+        EdgeKind::Open
+    }
+}
+
+impl Map {
+    /// Builds the graph of sectors joined by two-sided lines without the
+    /// impassable flag, with teleporters leading to the sectors they send the
+    /// player to: a tagged sector with a teleport landing, or the sector in
+    /// front of another line with the same tag. Height differences aren't
+    /// considered, so an edge may be a ledge that only goes one way in
+    /// practice. Lines pointing at missing sidedefs or sectors are left out.
+    pub fn connectivity_graph(&self) -> Result<Graph, Error> {
+        let vertexes = self.vertexes()?;
+        let linedefs = self.linedefs()?;
+        let sidedefs = self.sidedefs()?;
+        let sectors = self.sectors()?;
+        let side_sector = |side: u16| {
+            sidedefs
+                .get(side as usize)
+                .map(|s| s.sector as usize)
+                .filter(|&s| s < sectors.len())
+        };
+        let landings = self
+            .things()?
+            .iter()
+            .filter(|t| t.doomednum == Thing::TeleportLanding.doomednum())
+            .filter_map(|t| sector_at(&vertexes, &linedefs, &sidedefs, t.x as f64, t.y as f64))
+            .map(|s| s as usize)
+            .filter(|&s| s < sectors.len())
+            .collect::<Vec<_>>();
+
+        let mut edges = vec![];
+        for (i, line) in linedefs.iter().enumerate() {
+            let right = side_sector(line.right_sidedef);
+            if line.flags & LINEDEF_FLAG_IMPASSABLE == 0 && line.left_sidedef != NO_SIDEDEF {
+                if let (Some(from), Some(to)) = (right, side_sector(line.left_sidedef)) {
+                    edges.push(Edge {
+                        from,
+                        to,
+                        linedef: i,
+                        kind: edge_kind(line.special),
+                    });
+                }
+            }
+
+            let from = match right {
+                Some(from) if line.tag != 0 => from,
+                _ => continue,
+            };
+            let mut destinations = vec![];
+            if THING_TELEPORT_SPECIALS.contains(&line.special) {
+                destinations.extend(
+                    landings
+                        .iter()
+                        .copied()
+                        .filter(|&s| sectors[s].tag == line.tag),
+                );
+            } else if LINE_TELEPORT_SPECIALS.contains(&line.special) {
+                destinations.extend(
+                    linedefs
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, l)| j != i && l.tag == line.tag)
+                        .filter_map(|(_, l)| side_sector(l.right_sidedef)),
+                );
+            }
+            destinations.sort_unstable();
+            destinations.dedup();
+            edges.extend(destinations.into_iter().map(|to| Edge {
+                from,
+                to,
+                linedef: i,
+                kind: EdgeKind::Teleporter,
+            }));
+        }
+        Ok(Graph {
+            sectors: sectors.len(),
+            edges,
+        })
+    }
+
+    /// Classifies the map's level flow from `connectivity_graph`, taken as
+    /// undirected with each pair of sectors joined at most once. A map with
+    /// a hub is `HubAndSpoke`; otherwise it's `Linear` when the route its
+    /// diameter measures passes at least two thirds of the sectors it covers,
+    /// and `Branching` when it doesn't. This is only a heuristic: a big room
+    /// with many small sectors around it for lighting or detail counts as a
+    /// hub too. Fails with `Error::EmptyMap` for a map without sectors.
+    pub fn topology(&self) -> Result<Topology, Error> {
+        let graph = self.connectivity_graph()?;
+        if graph.sectors == 0 {
+            return Err(Error::EmptyMap(self.name.clone()));
+        }
+        let mut neighbors = vec![BTreeSet::new(); graph.sectors];
+        for edge in graph.edges.iter().filter(|e| e.from != e.to) {
+            neighbors[edge.from].insert(edge.to);
+            neighbors[edge.to].insert(edge.from);
+        }
+
+        let mut seen = vec![false; graph.sectors];
+        let mut largest = vec![];
+        for start in 0..graph.sectors {
+            if seen[start] {
+                continue;
+            }
+            let component = distances(&neighbors, start)
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| d.is_some())
+                .map(|(s, _)| s)
+                .collect::<Vec<_>>();
+            for &s in &component {
+                seen[s] = true;
+            }
+            if component.len() > largest.len() {
+                largest = component;
+            }
+        }
+
+        let diameter = largest
+            .iter()
+            .filter_map(|&s| distances(&neighbors, s).into_iter().flatten().max())
+            .max()
+            .unwrap_or(0);
+        let degrees = largest.iter().map(|&s| neighbors[s].len());
+        let average_degree = degrees.clone().sum::<usize>() as f64 / largest.len() as f64;
+        let max_degree = degrees.clone().max().unwrap_or(0);
+        let hubs = degrees.filter(|&d| d >= HUB_DEGREE).count();
+        let kind = if hubs > 0 {
+            TopologyKind::HubAndSpoke
+        } else if (diameter + 1) * 3 >= largest.len() * 2 {
+            TopologyKind::Linear
+        } else {
+            TopologyKind::Branching
+        };
+        Ok(Topology {
+            kind,
+            sectors: largest.len(),
+            diameter,
+            average_degree,
+            max_degree,
+            hubs,
+        })
+    }
+
+    /// Secret sectors that can't be reached by walking from the player 1 start.
+    ///
+    /// This is a conservative connectivity check, not a full reachability
+    /// analysis: any two-sided line without the impassable flag joins its two
+    /// sectors, regardless of height differences, locked doors or lifts, and
+    /// teleporters are not followed. A secret reported here is walled off from
+    /// the start entirely; a secret not reported may still be unreachable.
+    pub fn unreachable_secrets(&self) -> Result<Vec<u16>, Error> {
+        let vertexes = self.vertexes()?;
+        let linedefs = self.linedefs()?;
+        let sidedefs = self.sidedefs()?;
+        let sectors = self.sectors()?;
+        if sectors.is_empty() {
+            return Err(Error::EmptyMap(self.name.clone()));
+        }
+
+        let start = self
+            .things()?
+            .into_iter()
+            .find(|t| t.doomednum == Thing::Player1Start.doomednum())
+            .ok_or_else(|| Error::NoPlayerStart(self.name.clone()))?;
+        let start_sector = sector_at(
+            &vertexes,
+            &linedefs,
+            &sidedefs,
+            start.x as f64,
+            start.y as f64,
+        )
+        .ok_or_else(|| Error::NoPlayerStart(self.name.clone()))?;
+
+        // teleporters aren't followed, keeping to what can be walked
+        let neighbors = self.connectivity_graph()?.adjacency_list();
+
+        let mut reached = vec![false; sectors.len()];
+        let mut queue = VecDeque::new();
+        if (start_sector as usize) < sectors.len() {
+            reached[start_sector as usize] = true;
+            queue.push_back(start_sector as usize);
+        }
+        while let Some(sector) = queue.pop_front() {
+            for &(next, kind) in &neighbors[sector] {
+                if kind != EdgeKind::Teleporter && !reached[next] {
+                    reached[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Ok(sectors
+            .iter()
+            .enumerate()
+            .filter(|(i, s)| s.is_secret() && !reached[*i])
+            .map(|(i, _)| i as u16)
+            .collect())
+    }
+}
@@ -0,0 +1,89 @@
+use crate::wad::Lump;
+use crate::Error;
+
+/// The settings a demo was recorded with, from the start of its lump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DemoHeader {
+    /// The engine version byte, such as 109 for Doom 1.9 or 202 for Boom 2.02.
+    /// Demos from before Doom 1.4 have no version byte.
+    pub version: Option<u8>,
+    /// The skill level from 0 (I'm too young to die) to 4 (Nightmare!).
+    pub skill: u8,
+    pub episode: u8,
+    pub map: u8,
+    pub deathmatch: bool,
+    pub players: [bool; 4],
+}
+
+impl DemoHeader {
+    pub fn parse(lump: &Lump) -> Result<Self, Error> {
+        let data = &lump.data;
+        let truncated = || Error::InvalidLumpSize(lump.name.clone(), data.len());
+        let first = *data.first().ok_or_else(truncated)?;
+        let players = |at: usize| -> Result<[bool; 4], Error> {
+            let bytes = data.get(at..at + 4).ok_or_else(truncated)?;
+            Ok([bytes[0] != 0, bytes[1] != 0, bytes[2] != 0, bytes[3] != 0])
+        };
+        match first {
+            // pre-1.4 demos start straight with the skill
+            0..=4 => {
+                let h = data.get(..3).ok_or_else(truncated)?;
+                Ok(Self {
+                    version: None,
+                    skill: h[0],
+                    episode: h[1],
+                    map: h[2],
+                    deathmatch: false,
+                    players: players(3)?,
+                })
+            }
+            // Boom and MBF: a 6-byte signature and a compatibility level come
+            // before the settings, then 64 bytes of game options
+            200..=203 => {
+                let h = data.get(..13).ok_or_else(truncated)?;
+                Ok(Self {
+                    version: Some(first),
+                    skill: h[8],
+                    episode: h[9],
+                    map: h[10],
+                    deathmatch: h[11] != 0,
+                    players: players(13 + 64)?,
+                })
+            }
+            _ => {
+                let h = data.get(..9).ok_or_else(truncated)?;
+                Ok(Self {
+                    version: Some(first),
+                    skill: h[1],
+                    episode: h[2],
+                    map: h[3],
+                    deathmatch: h[4] != 0,
+                    players: players(9)?,
+                })
+            }
+        }
+    }
+
+    /// The version as the engine displays it, e.g. `1.9` or `2.02`.
+    pub fn version_name(&self) -> String {
+        match self.version {
+            None => "pre-1.4".to_string(),
+            Some(v) if v >= 200 => format!("{}.{:02}", v / 100, v % 100),
+            Some(v) => format!("{}.{}", v / 100, v % 100),
+        }
+    }
+
+    /// The map the demo plays, in the naming scheme of episodic or
+    /// commercial (Doom 2) games.
+    pub fn map_name(&self, commercial: bool) -> String {
+        if commercial {
+            format!("MAP{:02}", self.map)
+        } else {
+            format!("E{}M{}", self.episode, self.map)
+        }
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players.iter().filter(|&&p| p).count()
+    }
+}
@@ -0,0 +1,904 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::map::Map;
+use crate::map::LINEDEF_FLAG_TWO_SIDED;
+use crate::map::NO_SIDEDEF;
+use crate::map::SECTOR_SIZE;
+use crate::stats::DETAILED_SECTOR_EDGES;
+use crate::things::ThingTable;
+use crate::wad::Lump;
+use crate::wad::LumpKind;
+use crate::wad::Wad;
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A problem found while parsing or validating a WAD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub map: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, map: Option<&str>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            map: map.map(str::to_string),
+            message: message.into(),
+        }
+    }
+
+    /// The diagnostic as a GitHub Actions workflow command, which the
+    /// Actions log turns into an annotation: `::error` for errors,
+    /// `::warning` for warnings and `::notice` for the rest. `file` is the
+    /// WAD's path and the map, if any, becomes the annotation's title. WADs
+    /// have no lines, so none is given.
+    pub fn to_github_annotation(&self, file: &str) -> String {
+        // workflow commands escape these in values, and ':' and ',' in
+        // properties too
+        let escape = |text: &str, property: bool| {
+            let mut escaped = String::with_capacity(text.len());
+            for c in text.chars() {
+                match c {
+                    '%' => escaped.push_str("%25"),
+                    '\r' => escaped.push_str("%0D"),
+                    '\n' => escaped.push_str("%0A"),
+                    ':' if property => escaped.push_str("%3A"),
+                    ',' if property => escaped.push_str("%2C"),
+                    c => escaped.push(c),
+                }
+            }
+            escaped
+        };
+        let command = match self.severity {
+            Severity::Info => "notice",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let mut properties = format!("file={}", escape(file, true));
+        if let Some(map) = &self.map {
+            properties += &format!(",title={}", escape(map, true));
+        }
+        format!(
+            "::{} {}::{}",
+            command,
+            properties,
+            escape(&self.message, false)
+        )
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.map {
+            Some(map) => write!(f, "{}: {}: {}", self.severity, map, self.message),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Two lumps whose data regions share the bytes `start..end`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LumpOverlap {
+    pub first: String,
+    pub second: String,
+    pub start: u64,
+    pub end: u64,
+    /// Whether the lumps have the same name, offset and size. WAD optimizers
+    /// store identical lumps such as two maps' `REJECT` once and point both
+    /// directory entries at it, so this is deliberate rather than a broken
+    /// directory.
+    pub shared: bool,
+}
+
+/// What's wrong with a lump named like a marker or map component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LumpNameProblem {
+    /// A `_START` marker with no `_END` after it.
+    UnclosedSection,
+    /// An `_END` marker with no `_START` before it.
+    UnopenedSection,
+    /// A map component such as `THINGS` that isn't part of a loaded map.
+    StrayMapLump,
+}
+
+/// A lump whose name is reserved for a place it isn't in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MisplacedLump {
+    /// The lump's position in the directory.
+    pub index: usize,
+    pub name: String,
+    pub problem: LumpNameProblem,
+}
+
+/// A lump no engine reads, which editors sometimes leave behind: an empty
+/// lump that marks nothing, or one still carrying a new lump's placeholder
+/// name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLump {
+    /// The lump's position in the directory.
+    pub index: usize,
+    pub name: String,
+    pub size: usize,
+}
+
+// the names editors give a lump until it's renamed
+const PLACEHOLDER_LUMP_NAMES: [&str; 3] = ["LUMP", "NEWLUMP", "UNNAMED"];
+
+// The section a `_START` or `_END` marker belongs to, with the doubled
+// `FF_`, `SS_` and `PP_` forms joining their single ones, and whether it
+// starts the section.
+fn marker_section(name: &str) -> Option<(&str, bool)> {
+    let (section, start) = match name.strip_suffix("_START") {
+        Some(section) => (section, true),
+        None => (name.strip_suffix("_END")?, false),
+    };
+    let bytes = section.as_bytes();
+    let section = if bytes.len() == 2 && bytes[0] == bytes[1] {
+        &section[..1]
+    } else {
+        section
+    };
+    Some((section, start)).filter(|(section, _)| !section.is_empty())
+}
+
+/// The field of a seg that points past the end of its target lump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SegField {
+    StartVertex,
+    EndVertex,
+    Linedef,
+}
+
+impl Display for SegField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SegField::StartVertex => "start vertex",
+            SegField::EndVertex => "end vertex",
+            SegField::Linedef => "linedef",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadSegReference {
+    pub seg: usize,
+    pub field: SegField,
+    pub value: u16,
+}
+
+/// A linedef with both sides in the same sector, from
+/// `Map::self_referencing_lines`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfReferencingLine {
+    pub linedef: usize,
+    pub sector: u16,
+    /// Whether the line is flagged two-sided, as the self-referencing sector
+    /// trick behind deep water and invisible bridges needs. Without the flag
+    /// the engine draws the line as a solid one-sided wall, which is usually
+    /// a line given a second side by mistake.
+    pub two_sided: bool,
+}
+
+/// A linedef or sidedef index that points past the end of its target lump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefError {
+    /// A linedef's right (`left == false`) or left sidedef doesn't exist. A
+    /// missing right sidedef is reported even as `NO_SIDEDEF`, since every
+    /// line needs one.
+    Sidedef {
+        linedef: usize,
+        left: bool,
+        value: u16,
+    },
+    /// A sidedef's sector doesn't exist.
+    Sector { sidedef: usize, value: u16 },
+}
+
+impl Display for RefError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            RefError::Sidedef {
+                linedef,
+                left,
+                value,
+            } => write!(
+                f,
+                "linedef {} references missing {} sidedef {}",
+                linedef,
+                if left { "left" } else { "right" },
+                value
+            ),
+            RefError::Sector { sidedef, value } => {
+                write!(f, "sidedef {} references missing sector {}", sidedef, value)
+            }
+        }
+    }
+}
+
+/// Which way a linedef's two-sided flag disagrees with its sidedefs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlagMismatch {
+    /// The line has a left sidedef but isn't flagged two-sided, so the
+    /// engine treats it as a solid wall.
+    MissingFlag,
+    /// The line is flagged two-sided but has no left sidedef, which crashes
+    /// vanilla when the renderer reads the missing side.
+    MissingSidedef,
+}
+
+impl Display for FlagMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FlagMismatch::MissingFlag => "has two sidedefs but isn't flagged two-sided",
+            FlagMismatch::MissingSidedef => "is flagged two-sided but has one sidedef",
+        })
+    }
+}
+
+impl Map {
+    /// Finds linedefs pointing at sidedefs that don't exist, and sidedefs
+    /// pointing at sectors that don't exist. Either crashes the renderer.
+    pub fn reference_errors(&self) -> Result<Vec<RefError>, Error> {
+        let sidedefs = self.sidedefs()?;
+        let sectors = self.sectors()?.len();
+        let mut errors = vec![];
+        for (i, line) in self.linedefs()?.iter().enumerate() {
+            if line.right_sidedef as usize >= sidedefs.len() {
+                errors.push(RefError::Sidedef {
+                    linedef: i,
+                    left: false,
+                    value: line.right_sidedef,
+                });
+            }
+            if line.left_sidedef != NO_SIDEDEF && line.left_sidedef as usize >= sidedefs.len() {
+                errors.push(RefError::Sidedef {
+                    linedef: i,
+                    left: true,
+                    value: line.left_sidedef,
+                });
+            }
+        }
+        for (i, side) in sidedefs.iter().enumerate() {
+            if side.sector as usize >= sectors {
+                errors.push(RefError::Sector {
+                    sidedef: i,
+                    value: side.sector,
+                });
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Finds linedefs whose two-sided flag doesn't match whether they have a
+    /// left sidedef, in linedef order.
+    pub fn sidedef_flag_mismatches(&self) -> Result<Vec<(usize, FlagMismatch)>, Error> {
+        Ok(self
+            .linedefs()?
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let flagged = line.flags & LINEDEF_FLAG_TWO_SIDED != 0;
+                match (line.left_sidedef != NO_SIDEDEF, flagged) {
+                    (true, false) => Some((i, FlagMismatch::MissingFlag)),
+                    (false, true) => Some((i, FlagMismatch::MissingSidedef)),
+                    _ => None,
+                }
+            })
+            .collect())
+    }
+
+    /// Finds segs whose vertex or linedef indexes are out of range, which
+    /// crash most ports. Vertexes that uncompressed ZDoom nodes append to the
+    /// map's own count as valid; with compressed nodes the extra vertex count
+    /// can't be read, so vertex indexes aren't checked.
+    pub fn validate_segs(&self) -> Result<Vec<BadSegReference>, Error> {
+        let segs = self.segs()?;
+        let linedefs = self.linedefs()?.len();
+        let nodes = &self.nodes.data;
+        let vertexes = if nodes.starts_with(b"ZNOD") || nodes.starts_with(b"ZGLN") {
+            None
+        } else if nodes.starts_with(b"XNOD") && nodes.len() >= 12 {
+            let extra = u32::from_le_bytes([nodes[8], nodes[9], nodes[10], nodes[11]]);
+            Some(self.vertexes()?.len() + extra as usize)
+        } else {
+            Some(self.vertexes()?.len())
+        };
+
+        let mut bad = vec![];
+        for (i, seg) in segs.iter().enumerate() {
+            let mut check = |field, value: u16, limit: Option<usize>| {
+                if limit.is_some_and(|limit| value as usize >= limit) {
+                    bad.push(BadSegReference {
+                        seg: i,
+                        field,
+                        value,
+                    });
+                }
+            };
+            check(SegField::StartVertex, seg.start_vertex, vertexes);
+            check(SegField::EndVertex, seg.end_vertex, vertexes);
+            check(SegField::Linedef, seg.linedef, Some(linedefs));
+        }
+        Ok(bad)
+    }
+
+    /// Linedefs whose right and left sidedefs face the same sector, in
+    /// linedef order. Sidedefs that don't exist are skipped.
+    pub fn self_referencing_lines(&self) -> Result<Vec<SelfReferencingLine>, Error> {
+        let sidedefs = self.sidedefs()?;
+        let sector = |side: u16| sidedefs.get(side as usize).map(|s| s.sector);
+        Ok(self
+            .iter_linedefs()?
+            .enumerate()
+            .filter(|(_, line)| line.left_sidedef != NO_SIDEDEF)
+            .filter_map(|(i, line)| {
+                let right = sector(line.right_sidedef)?;
+                Some(SelfReferencingLine {
+                    linedef: i,
+                    sector: right,
+                    two_sided: line.flags & LINEDEF_FLAG_TWO_SIDED != 0,
+                })
+                .filter(|_| sector(line.left_sidedef) == Some(right))
+            })
+            .collect())
+    }
+
+    /// Groups of vertexes at exactly the same position, each group in index
+    /// order and the groups by their first vertex. Some editors leave one
+    /// behind on every line split, and they confuse node builders. Every
+    /// vertex past the first of its group is a duplicate.
+    pub fn duplicate_vertices(&self) -> Result<Vec<Vec<u16>>, Error> {
+        let mut positions = BTreeMap::<(i16, i16), Vec<u16>>::new();
+        for (i, v) in self.vertexes()?.iter().enumerate() {
+            positions.entry((v.x, v.y)).or_default().push(i as u16);
+        }
+        let mut groups = positions
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|group| group[0]);
+        Ok(groups)
+    }
+
+    /// Sectors no sidedef references, usually left behind by a deleted room.
+    /// Each takes `SECTOR_SIZE` bytes of the SECTORS lump for nothing.
+    pub fn orphan_sectors(&self) -> Result<Vec<u16>, Error> {
+        let mut used = vec![false; self.sectors()?.len()];
+        for side in self.sidedefs()? {
+            if let Some(u) = used.get_mut(side.sector as usize) {
+                *u = true;
+            }
+        }
+        Ok((0..used.len() as u16)
+            .filter(|&i| !used[i as usize])
+            .collect())
+    }
+}
+
+impl Wad {
+    /// Finds lumps whose `[offset, offset + size)` data regions overlap.
+    /// Zero-size lumps such as markers are ignored.
+    pub fn lump_overlaps(&self) -> Vec<LumpOverlap> {
+        let mut regions = self
+            .lumps
+            .iter()
+            .filter(|l| !l.data.is_empty())
+            .map(|l| (l.offset as u64, l.offset as u64 + l.data.len() as u64, l))
+            .collect::<Vec<_>>();
+        regions.sort_by_key(|&(start, end, _)| (start, end));
+
+        let mut overlaps = vec![];
+        let mut active: Vec<(u64, u64, &str)> = vec![];
+        for (start, end, lump) in regions {
+            active.retain(|&(_, active_end, _)| active_end > start);
+            for &(active_start, active_end, name) in &active {
+                overlaps.push(LumpOverlap {
+                    first: name.to_string(),
+                    second: lump.name.clone(),
+                    start,
+                    end: end.min(active_end),
+                    shared: name == lump.name && (active_start, active_end) == (start, end),
+                });
+            }
+            active.push((start, end, &lump.name));
+        }
+        overlaps
+    }
+
+    /// Finds marker and map component names out of place. Sections are
+    /// matched by their letters, so `FF_START` may be closed by `F_END` and
+    /// nested sections such as `F_START`, `FF_START`, `FF_END`, `F_END` are
+    /// balanced. A map component belongs to a map when the lump before its
+    /// run of components names a map that was loaded, or when it's between
+    /// a UDMF map's `TEXTMAP` and `ENDMAP`.
+    pub fn misplaced_lumps(&self) -> Vec<MisplacedLump> {
+        let mut misplaced = vec![];
+        let mut problem = |index: usize, problem| {
+            misplaced.push(MisplacedLump {
+                index,
+                name: self.lumps[index].name.clone(),
+                problem,
+            })
+        };
+        let mut open = BTreeMap::<&str, Vec<usize>>::new();
+        let mut in_map = false;
+        let mut in_udmf_map = false;
+        for (i, lump) in self.lumps.iter().enumerate() {
+            // a UDMF map's REJECT and BEHAVIOR come after its TEXTMAP; the
+            // names keep their case with `ParseOptions::preserve_case`
+            if lump.name.eq_ignore_ascii_case("TEXTMAP") {
+                in_udmf_map = true;
+            } else if lump.name.eq_ignore_ascii_case("ENDMAP") {
+                in_udmf_map = false;
+            } else if in_udmf_map {
+                continue;
+            }
+            if lump.kind != LumpKind::Other {
+                if i == 0 || self.lumps[i - 1].kind == LumpKind::Other {
+                    let marker = i.checked_sub(1).map_or("", |m| self.lumps[m].name.as_str());
+                    in_map = self.maps().iter().any(|m| m.name() == marker);
+                }
+                if !in_map {
+                    problem(i, LumpNameProblem::StrayMapLump);
+                }
+                continue;
+            }
+            match marker_section(&lump.name) {
+                Some((section, true)) => open.entry(section).or_default().push(i),
+                Some((section, false)) => {
+                    let start = open.get_mut(section).and_then(|starts| starts.pop());
+                    if start.is_none() {
+                        problem(i, LumpNameProblem::UnopenedSection);
+                    }
+                }
+                None => {}
+            }
+        }
+        for i in open.into_values().flatten() {
+            problem(i, LumpNameProblem::UnclosedSection);
+        }
+        misplaced.sort_by_key(|m| m.index);
+        misplaced
+    }
+
+    /// Finds lumps left over in the directory: any named `LUMP`, `NEWLUMP` or
+    /// `UNNAMED`, and empty lumps that aren't markers. Map markers, which
+    /// are followed by map components or UDMF's `TEXTMAP`, UDMF's `ENDMAP`,
+    /// `_START` and `_END` markers and `GL_` markers are kept, as are map
+    /// components, whose being empty `Map::empty_components` reports.
+    pub fn dead_lumps(&self) -> Vec<DeadLump> {
+        self.lumps
+            .iter()
+            .enumerate()
+            .filter(|&(i, lump)| {
+                let placeholder = PLACEHOLDER_LUMP_NAMES.contains(&lump.name.as_str());
+                let marks_map = |next: &Lump| {
+                    next.kind != LumpKind::Other || next.name.eq_ignore_ascii_case("TEXTMAP")
+                };
+                let marker = lump.kind != LumpKind::Other
+                    || self.lumps.get(i + 1).is_some_and(marks_map)
+                    || lump.name.eq_ignore_ascii_case("ENDMAP")
+                    || lump.name.ends_with("_START")
+                    || lump.name.ends_with("_END")
+                    || lump.name.starts_with("GL_");
+                placeholder || (lump.data.is_empty() && !marker)
+            })
+            .map(|(index, lump)| DeadLump {
+                index,
+                name: lump.name.clone(),
+                size: lump.data.len(),
+            })
+            .collect()
+    }
+
+    /// Runs every WAD-level consistency check, after anything lenient parsing
+    /// recorded.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = self.parse_diagnostics().to_vec();
+        for overlap in self.lump_overlaps() {
+            let diagnostic = if overlap.shared {
+                Diagnostic::new(
+                    Severity::Info,
+                    None,
+                    format!(
+                        "two '{}' lumps share bytes {}..{}",
+                        overlap.first, overlap.start, overlap.end
+                    ),
+                )
+            } else {
+                Diagnostic::new(
+                    Severity::Warning,
+                    None,
+                    format!(
+                        "lumps '{}' and '{}' overlap at bytes {}..{}",
+                        overlap.first, overlap.second, overlap.start, overlap.end
+                    ),
+                )
+            };
+            diagnostics.push(diagnostic);
+        }
+        for lump in self.misplaced_lumps() {
+            let (severity, problem) = match lump.problem {
+                LumpNameProblem::UnclosedSection => (Severity::Error, "has no matching end marker"),
+                LumpNameProblem::UnopenedSection => {
+                    (Severity::Error, "has no matching start marker")
+                }
+                LumpNameProblem::StrayMapLump => (Severity::Warning, "isn't part of any map"),
+            };
+            diagnostics.push(Diagnostic::new(
+                severity,
+                None,
+                format!("lump {} '{}' {}", lump.index, lump.name, problem),
+            ));
+        }
+        let dead = self.dead_lumps();
+        if !dead.is_empty() {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                None,
+                format!(
+                    "{} lumps look left over and could be removed: {}",
+                    dead.len(),
+                    dead.iter()
+                        .map(|l| format!("{} '{}'", l.index, l.name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ));
+        }
+        diagnostics.extend(self.palette_consistency());
+        let table = self.thing_table();
+        for map in self.maps() {
+            if let Err(e) = map_diagnostics(map, table, &mut diagnostics) {
+                diagnostics.push(Diagnostic::new(
+                    Severity::Error,
+                    Some(map.name()),
+                    e.to_string(),
+                ));
+            }
+        }
+        diagnostics
+    }
+}
+
+fn map_diagnostics(
+    map: &Map,
+    table: ThingTable,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), Error> {
+    for lump in map.empty_components() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some(map.name()),
+            format!("the {} lump is empty", lump),
+        ));
+    }
+    for error in map.reference_errors()? {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Some(map.name()),
+            error.to_string(),
+        ));
+    }
+    for (linedef, mismatch) in map.sidedef_flag_mismatches()? {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some(map.name()),
+            format!("linedef {} {}", linedef, mismatch),
+        ));
+    }
+    for bad in map.validate_segs()? {
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            Some(map.name()),
+            format!(
+                "seg {} references missing {} {}",
+                bad.seg, bad.field, bad.value
+            ),
+        ));
+    }
+    for (usage, what) in [
+        (map.seg_overflow()?, "segs"),
+        (map.ssector_overflow()?, "subsectors"),
+    ] {
+        if usage.overflows() {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                Some(map.name()),
+                format!(
+                    "{} {} overflow vanilla's limit of {} by {}",
+                    usage.count,
+                    what,
+                    usage.limit,
+                    -usage.headroom()
+                ),
+            ));
+        }
+    }
+    let duplicates = map.duplicate_vertices()?;
+    if let Some(largest) = duplicates.iter().rev().max_by_key(|group| group.len()) {
+        let vertex = map.vertexes()?[largest[0] as usize];
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some(map.name()),
+            format!(
+                "{} vertexes duplicate another's position; the largest group is {} at ({}, {})",
+                duplicates
+                    .iter()
+                    .map(|group| group.len() - 1)
+                    .sum::<usize>(),
+                largest.len(),
+                vertex.x,
+                vertex.y
+            ),
+        ));
+    }
+    for sector in map.unclosable_sectors()? {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some(map.name()),
+            format!("sector {} has too few linedefs to enclose a floor", sector),
+        ));
+    }
+    let detailed = map.over_detailed_sectors()?;
+    if !detailed.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Info,
+            Some(map.name()),
+            format!(
+                "{} sectors have over {} linedefs each: {}",
+                detailed.len(),
+                DETAILED_SECTOR_EDGES,
+                detailed
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ));
+    }
+    let orphans = map.orphan_sectors()?;
+    if !orphans.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some(map.name()),
+            format!(
+                "{} sectors aren't used by any sidedef, wasting {} bytes: {}",
+                orphans.len(),
+                orphans.len() * SECTOR_SIZE,
+                orphans
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        ));
+    }
+    let things = map.things()?;
+    // placing things needs the geometry, and an empty lump was reported above
+    if map.require_geometry().is_ok() {
+        for i in map.out_of_bounds_things()? {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                Some(map.name()),
+                format!(
+                    "thing {} at ({}, {}) is outside the map",
+                    i, things[i].x, things[i].y
+                ),
+            ));
+        }
+        for i in map.embedded_things(table)? {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                Some(map.name()),
+                format!(
+                    "thing {} at ({}, {}) is in the void or doesn't fit where it stands",
+                    i, things[i].x, things[i].y
+                ),
+            ));
+        }
+        for i in map.floating_things(table)? {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                Some(map.name()),
+                format!(
+                    "thing {} ({}) at ({}, {}) spawns below the floor",
+                    i,
+                    table.thing_name(things[i].doomednum),
+                    things[i].x,
+                    things[i].y
+                ),
+            ));
+        }
+    }
+    for i in map.unspawnable_things()? {
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some(map.name()),
+            format!(
+                "thing {} ({}) at ({}, {}) has no skill or multiplayer flags and never spawns",
+                i,
+                table.thing_name(things[i].doomednum),
+                things[i].x,
+                things[i].y
+            ),
+        ));
+    }
+    for stack in map.stacked_things(table)? {
+        let first = &things[stack[0]];
+        diagnostics.push(Diagnostic::new(
+            Severity::Warning,
+            Some(map.name()),
+            format!(
+                "things {} share the position ({}, {})",
+                stack
+                    .iter()
+                    .map(|&i| format!("{} ({})", i, table.thing_name(things[i].doomednum)))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                first.x,
+                first.y
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::builder::square_room;
+    use crate::builder::WadBuilder;
+    use crate::map::Linedef;
+    use crate::map::LINEDEF_FLAG_IMPASSABLE;
+    use crate::wad::ParseOptions;
+
+    fn parse(bytes: Vec<u8>) -> Wad {
+        Wad::from_reader(Cursor::new(bytes)).unwrap()
+    }
+
+    // a WAD of empty lumps with these names, read leniently so stray map
+    // lumps are kept
+    fn misplaced(names: &[&str]) -> Vec<(usize, LumpNameProblem)> {
+        let builder = names
+            .iter()
+            .fold(WadBuilder::new(), |b, &name| b.lump(name, vec![]));
+        let options = ParseOptions {
+            strict: false,
+            ..ParseOptions::default()
+        };
+        let wad = Wad::from_reader_with_options(Cursor::new(builder.build()), options).unwrap();
+        wad.misplaced_lumps()
+            .into_iter()
+            .map(|m| (m.index, m.problem))
+            .collect()
+    }
+
+    #[test]
+    fn two_sided_flags_are_checked_against_the_sidedefs() {
+        let line = |flags, left_sidedef| Linedef {
+            start_vertex: 0,
+            end_vertex: 2,
+            flags,
+            special: 0,
+            tag: 0,
+            right_sidedef: 0,
+            left_sidedef,
+        };
+        let room = square_room("MAP01")
+            .linedef(line(LINEDEF_FLAG_TWO_SIDED, 1))
+            .linedef(line(0, 1))
+            .linedef(line(LINEDEF_FLAG_TWO_SIDED, NO_SIDEDEF))
+            .linedef(line(LINEDEF_FLAG_IMPASSABLE, NO_SIDEDEF));
+        let wad = parse(WadBuilder::new().map(room).build());
+
+        assert_eq!(
+            wad.maps()[0].sidedef_flag_mismatches().unwrap(),
+            [
+                (5, FlagMismatch::MissingFlag),
+                (6, FlagMismatch::MissingSidedef)
+            ]
+        );
+    }
+
+    #[test]
+    fn a_square_room_has_no_flag_mismatches() {
+        let wad = parse(WadBuilder::new().map(square_room("MAP01")).build());
+        assert!(wad.maps()[0].sidedef_flag_mismatches().unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_end_marker_without_a_start_is_unopened() {
+        assert_eq!(
+            misplaced(&["F_END", "S_START", "S_END"]),
+            [(0, LumpNameProblem::UnopenedSection)]
+        );
+        assert_eq!(
+            misplaced(&["P_START", "P_END", "PP_END"]),
+            [(2, LumpNameProblem::UnopenedSection)]
+        );
+    }
+
+    #[test]
+    fn a_start_marker_without_an_end_is_unclosed() {
+        assert_eq!(
+            misplaced(&["S_START", "F_START", "F_END"]),
+            [(0, LumpNameProblem::UnclosedSection)]
+        );
+        assert_eq!(
+            misplaced(&["FF_START", "F_START", "F_END"]),
+            [(0, LumpNameProblem::UnclosedSection)]
+        );
+    }
+
+    #[test]
+    fn nested_and_doubled_sections_balance() {
+        assert!(misplaced(&["F_START", "FF_START", "FF_END", "F_END"]).is_empty());
+        assert!(misplaced(&["FF_START", "F_END"]).is_empty());
+        assert!(misplaced(&["F_START", "FF_END"]).is_empty());
+        assert!(misplaced(&["S_START", "F_START", "F_END", "S_END"]).is_empty());
+    }
+
+    #[test]
+    fn map_lumps_outside_a_map_are_stray() {
+        assert_eq!(
+            misplaced(&["DEMO1", "THINGS", "SECTORS"]),
+            [
+                (1, LumpNameProblem::StrayMapLump),
+                (2, LumpNameProblem::StrayMapLump)
+            ]
+        );
+        let wad = parse(WadBuilder::new().map(square_room("MAP01")).build());
+        assert!(wad.misplaced_lumps().is_empty());
+    }
+
+    #[test]
+    fn misplaced_lumps_are_validation_errors() {
+        let wad = parse(WadBuilder::new().lump("F_END", vec![]).build());
+        let diagnostics = wad.validate();
+        assert!(diagnostics.iter().any(|d| d.severity == Severity::Error
+            && d.message == "lump 0 'F_END' has no matching start marker"));
+    }
+
+    #[test]
+    fn lowercase_udmf_maps_are_recognized_when_case_is_kept() {
+        let bytes = WadBuilder::new()
+            .lump("map01", vec![])
+            .lump("textmap", b"namespace = \"zdoom\";".to_vec())
+            .lump("reject", vec![])
+            .lump("behavior", vec![0; 8])
+            .lump("endmap", vec![])
+            .build();
+        let options = ParseOptions {
+            preserve_case: true,
+            ..ParseOptions::default()
+        };
+        let wad = Wad::from_reader_with_options(Cursor::new(bytes), options).unwrap();
+
+        assert_eq!(wad.udmf_maps().len(), 1);
+        assert_eq!(wad.udmf_maps()[0].name(), "map01");
+        assert!(wad.misplaced_lumps().is_empty());
+        assert!(wad.dead_lumps().is_empty());
+    }
+}
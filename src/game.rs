@@ -0,0 +1,371 @@
+use std::fmt::Display;
+
+use crate::things::ThingTable;
+use crate::wad::Wad;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Game {
+    Doom,
+    Doom2,
+    Heretic,
+    Hexen,
+    Strife,
+    Unknown,
+}
+
+impl Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Game::Doom => "Doom",
+            Game::Doom2 => "Doom2",
+            Game::Heretic => "Heretic",
+            Game::Hexen => "Hexen",
+            Game::Strife => "Strife",
+            Game::Unknown => "Unknown",
+        })
+    }
+}
+
+/// The games whose names `Game` tables cover, `Unknown` aside.
+const KNOWN_GAMES: [Game; 5] = [
+    Game::Doom,
+    Game::Doom2,
+    Game::Heretic,
+    Game::Hexen,
+    Game::Strife,
+];
+
+// full-screen graphics shown outside of play, in the order the game shows them
+const DOOM_FULL_SCREENS: [&str; 10] = [
+    "TITLEPIC", "INTERPIC", "CREDIT", "HELP", "HELP1", "HELP2", "BOSSBACK", "VICTORY2", "PFUB1",
+    "PFUB2",
+];
+const HERETIC_FULL_SCREENS: [&str; 7] = [
+    "TITLE", "CREDIT", "HELP1", "HELP2", "FINAL1", "FINAL2", "E2END",
+];
+const HEXEN_FULL_SCREENS: [&str; 8] = [
+    "TITLE", "CREDIT", "HELP1", "HELP2", "INTERPIC", "FINALE1", "FINALE2", "FINALE3",
+];
+const STRIFE_FULL_SCREENS: [&str; 6] = ["TITLEPIC", "CREDIT", "HELP0", "HELP1", "HELP2", "HELP3"];
+
+impl Game {
+    /// The ceiling flat that the engine draws the sky in place of.
+    pub fn sky_flat(self) -> &'static str {
+        match self {
+            Game::Hexen => "F_SKY",
+            Game::Strife => "F_SKY001",
+            Game::Doom | Game::Doom2 | Game::Heretic | Game::Unknown => "F_SKY1",
+        }
+    }
+
+    /// The full-screen graphics shown outside of play: title, credits, help
+    /// and finale screens.
+    pub fn full_screens(self) -> &'static [&'static str] {
+        match self {
+            Game::Heretic => &HERETIC_FULL_SCREENS,
+            Game::Hexen => &HEXEN_FULL_SCREENS,
+            Game::Strife => &STRIFE_FULL_SCREENS,
+            Game::Doom | Game::Doom2 | Game::Unknown => &DOOM_FULL_SCREENS,
+        }
+    }
+
+    /// The intermission screen's backgrounds. Doom has a generic one and a map
+    /// per episode, Heretic only the episode maps. Strife has no intermission.
+    pub fn intermission_backgrounds(self) -> &'static [&'static str] {
+        match self {
+            Game::Heretic => &["MAPE1", "MAPE2", "MAPE3"],
+            Game::Hexen => &["INTERPIC"],
+            Game::Strife => &[],
+            Game::Doom | Game::Doom2 | Game::Unknown => &["INTERPIC", "WIMAP0", "WIMAP1", "WIMAP2"],
+        }
+    }
+}
+
+/// Whether a flat is some game's sky flat. Maps are read without knowing
+/// their game, and the names don't clash, so the map statistics accept any.
+pub fn is_sky_flat(name: &str) -> bool {
+    KNOWN_GAMES
+        .iter()
+        .any(|g| g.sky_flat().eq_ignore_ascii_case(name))
+}
+
+fn is_episode_map(name: &str) -> bool {
+    let b = name.as_bytes();
+    b.len() == 4 && b[0] == b'E' && b[1].is_ascii_digit() && b[2] == b'M' && b[3].is_ascii_digit()
+}
+
+/// A commercial IWAD release, identified by the MD5 of the whole file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KnownIwad {
+    pub game: Game,
+    /// The file name it shipped under, such as `DOOM2.WAD`.
+    pub file: &'static str,
+    /// The release, such as `1.9` or `1.9 (Ultimate Doom)`.
+    pub version: &'static str,
+    pub md5: &'static str,
+}
+
+impl Display for KnownIwad {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.file, self.version)
+    }
+}
+
+const fn iwad(
+    game: Game,
+    file: &'static str,
+    version: &'static str,
+    md5: &'static str,
+) -> KnownIwad {
+    KnownIwad {
+        game,
+        file,
+        version,
+        md5,
+    }
+}
+
+/// The IWADs `Wad::identify_iwad` recognizes, by their published checksums.
+pub const KNOWN_IWADS: [KnownIwad; 22] = [
+    iwad(
+        Game::Doom,
+        "DOOM1.WAD",
+        "1.9 (shareware)",
+        "f0cefca49926d00903cf57551d901abe",
+    ),
+    iwad(
+        Game::Doom,
+        "DOOM.WAD",
+        "1.666",
+        "54978d12de87f162b9bcc011676cb3c0",
+    ),
+    iwad(
+        Game::Doom,
+        "DOOM.WAD",
+        "1.8",
+        "11e1cd216801ea2657723abc86ecb01f",
+    ),
+    iwad(
+        Game::Doom,
+        "DOOM.WAD",
+        "1.9",
+        "1cd63c5ddff1bf8ce844237f580e9cf3",
+    ),
+    iwad(
+        Game::Doom,
+        "DOOM.WAD",
+        "1.9 (Ultimate Doom)",
+        "c4fe9fd920207691a9f493668e0a2083",
+    ),
+    iwad(
+        Game::Doom,
+        "DOOM.WAD",
+        "BFG Edition",
+        "fb35c4a5a9fd49ec29ab6e900572c524",
+    ),
+    iwad(
+        Game::Doom2,
+        "DOOM2.WAD",
+        "1.666",
+        "30e3c2d0350b67bfbf47271970b74b2f",
+    ),
+    iwad(
+        Game::Doom2,
+        "DOOM2.WAD",
+        "1.7",
+        "ea74a47a791fdef2e9f2ea8b8a9da13b",
+    ),
+    iwad(
+        Game::Doom2,
+        "DOOM2.WAD",
+        "1.8",
+        "c236745bb01d89bbb866c8fed81b6f8c",
+    ),
+    iwad(
+        Game::Doom2,
+        "DOOM2.WAD",
+        "1.9",
+        "25e1459ca71d321525f84628f45ca8cd",
+    ),
+    iwad(
+        Game::Doom2,
+        "DOOM2.WAD",
+        "BFG Edition",
+        "c3bea40570c23e511a7ed3ebcd9865f7",
+    ),
+    iwad(
+        Game::Doom2,
+        "TNT.WAD",
+        "1.9",
+        "4e158d9953c79ccf97bd0663244cc6b6",
+    ),
+    iwad(
+        Game::Doom2,
+        "TNT.WAD",
+        "1.9 (id Anthology)",
+        "1d39e405bf6ee3df69a8d2646c8d5c49",
+    ),
+    iwad(
+        Game::Doom2,
+        "PLUTONIA.WAD",
+        "1.9",
+        "75c8cf89566741fa9d22447604053bd7",
+    ),
+    iwad(
+        Game::Doom2,
+        "PLUTONIA.WAD",
+        "1.9 (id Anthology)",
+        "3493be7e1e2588bc9c8b31eab2587a04",
+    ),
+    iwad(
+        Game::Heretic,
+        "HERETIC1.WAD",
+        "1.2 (shareware)",
+        "ae779722390ec32fa37b0d361f7d82f8",
+    ),
+    iwad(
+        Game::Heretic,
+        "HERETIC.WAD",
+        "1.0",
+        "3117e399cdb4298eaa3941625f4b2923",
+    ),
+    iwad(
+        Game::Heretic,
+        "HERETIC.WAD",
+        "1.2",
+        "1e4cb4ef075ad344dd63971637307e04",
+    ),
+    iwad(
+        Game::Heretic,
+        "HERETIC.WAD",
+        "1.3",
+        "66d686b1ed6d35ff103f15dbd30e0341",
+    ),
+    iwad(
+        Game::Hexen,
+        "HEXEN.WAD",
+        "1.0",
+        "b2543a03521365261d0a0f74d5dd90f0",
+    ),
+    iwad(
+        Game::Hexen,
+        "HEXEN.WAD",
+        "1.1",
+        "abb033caf81e26f12a2103e1fa25453f",
+    ),
+    iwad(
+        Game::Strife,
+        "STRIFE1.WAD",
+        "1.2",
+        "2fed2031a5b03892106e0f117f17901f",
+    ),
+];
+
+impl Wad {
+    /// The IWAD release this file is, byte for byte, from the MD5 in
+    /// `Wad::md5` and `KNOWN_IWADS`, so the WAD has to be read with
+    /// `ParseOptions::hash_iwad`. A modified or unlisted IWAD, and every
+    /// PWAD, gives `None`; `detect_game` can still guess at their game.
+    pub fn identify_iwad(&self) -> Option<KnownIwad> {
+        let md5 = self.md5()?;
+        KNOWN_IWADS.iter().copied().find(|iwad| iwad.md5 == md5)
+    }
+
+    /// Guesses the game a WAD was made for. Hexen is recognized by its map
+    /// format in an IWAD, or in a PWAD whose things are mostly ones only
+    /// Hexen has, since ZDoom ports take Doom maps in Hexen's format too.
+    /// Strife is recognized by its conversation scripts or sound curve table,
+    /// Heretic by its menu graphics, and Doom versus Doom 2 by the map naming
+    /// scheme.
+    pub fn detect_game(&self) -> Game {
+        let hexen_format = self.maps().iter().any(|m| m.behavior.is_some());
+        if hexen_format && (self.is_iwad() || self.has_hexen_things()) {
+            Game::Hexen
+        } else if !self.strife_dialogs().is_empty() || self.lump_by_name("SNDCURVE").is_some() {
+            Game::Strife
+        } else if self.lump_by_name("M_HTIC").is_some() {
+            Game::Heretic
+        } else if self.maps().iter().any(|m| m.name().starts_with("MAP")) {
+            Game::Doom2
+        } else if self.maps().iter().any(|m| is_episode_map(m.name())) {
+            Game::Doom
+        } else {
+            Game::Unknown
+        }
+    }
+
+    // Whether the Hexen-format maps have more things whose numbers only
+    // Hexen's table knows than ones only Doom's knows. Numbers both share,
+    // such as player starts, say nothing either way.
+    fn has_hexen_things(&self) -> bool {
+        let (mut hexen, mut doom) = (0, 0);
+        let things = self
+            .maps()
+            .iter()
+            .filter(|m| m.is_hexen_format())
+            .filter_map(|m| m.things().ok())
+            .flatten();
+        for thing in things {
+            let n = thing.doomednum;
+            match (
+                ThingTable::Hexen.category(n).is_some(),
+                ThingTable::Doom.category(n).is_some(),
+            ) {
+                (true, false) => hexen += 1,
+                (false, true) => doom += 1,
+                _ => {}
+            }
+        }
+        hexen > doom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::builder::square_room;
+    use crate::builder::MapBuilder;
+    use crate::builder::WadBuilder;
+    use crate::map::MapThing;
+
+    fn with_things(map: MapBuilder, doomednums: &[u16]) -> MapBuilder {
+        doomednums.iter().fold(map, |map, &doomednum| {
+            map.thing(MapThing {
+                x: 128,
+                y: 128,
+                angle: 0,
+                doomednum,
+                flags: 7,
+            })
+        })
+    }
+
+    fn game(builder: WadBuilder) -> Game {
+        Wad::from_reader(Cursor::new(builder.build()))
+            .unwrap()
+            .detect_game()
+    }
+
+    #[test]
+    fn doom_maps_in_hexen_format_are_not_hexen() {
+        // imps, a shotgun guy and a shotgun, plus a map spot, which ZDoom
+        // took from Hexen
+        let map = with_things(square_room("MAP01"), &[3001, 3001, 9, 2001, 9001]).hexen();
+        assert_eq!(game(WadBuilder::new().map(map)), Game::Doom2);
+        let map = with_things(square_room("E1M1"), &[3004]).hexen();
+        assert_eq!(game(WadBuilder::new().map(map)), Game::Doom);
+    }
+
+    #[test]
+    fn hexen_maps_are_hexen() {
+        // ettins, an afrit and blue mana
+        let map = with_things(square_room("MAP01"), &[10030, 10030, 10060, 122]).hexen();
+        assert_eq!(game(WadBuilder::new().map(map)), Game::Hexen);
+        // an IWAD needs no things to tell
+        let map = square_room("MAP01").hexen();
+        assert_eq!(game(WadBuilder::new().iwad().map(map.clone())), Game::Hexen);
+        assert_eq!(game(WadBuilder::new().map(map)), Game::Doom2);
+    }
+}
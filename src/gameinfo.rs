@@ -0,0 +1,102 @@
+use crate::json::Json;
+use crate::wad::Wad;
+
+/// Which lump a `GameInfo` was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameInfoSource {
+    /// ZDoom's `GAMEINFO`, with `KEY = "value"` lines.
+    Gameinfo,
+    /// The JSON `GAMECONF` lump of ID24 and the ports following it.
+    Gameconf,
+}
+
+/// What a WAD declares about how it's meant to be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameInfo {
+    pub source: GameInfoSource,
+    /// The IWAD the mod is built for, as the lump names it, such as
+    /// `doom2.wad`.
+    pub iwad: Option<String>,
+    /// Files to load along with the WAD, in order.
+    pub load: Vec<String>,
+    /// The title shown while the game starts.
+    pub title: Option<String>,
+}
+
+// the quoted strings of a value such as `"a.wad", "b.pk3"`, or the bare word
+// if it isn't quoted
+fn gameinfo_values(value: &str) -> Vec<String> {
+    let value = value.trim();
+    if !value.starts_with('"') {
+        return Some(value.to_string())
+            .filter(|v| !v.is_empty())
+            .into_iter()
+            .collect();
+    }
+    value
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect()
+}
+
+fn parse_gameinfo(text: &str) -> GameInfo {
+    let mut info = GameInfo {
+        source: GameInfoSource::Gameinfo,
+        iwad: None,
+        load: vec![],
+        title: None,
+    };
+    for line in text.lines() {
+        let line = line.split("//").next().unwrap_or_default();
+        let (key, value) = match line.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let values = gameinfo_values(value);
+        match key.trim().to_ascii_uppercase().as_str() {
+            "IWAD" => info.iwad = values.into_iter().next(),
+            "LOAD" => info.load.extend(values),
+            "STARTUPTITLE" => info.title = values.into_iter().next(),
+            _ => {}
+        }
+    }
+    info
+}
+
+fn parse_gameconf(text: &str) -> Option<GameInfo> {
+    let json = Json::parse(text)?;
+    let data = json.get("data")?;
+    let string = |key: &str| data.get(key).and_then(Json::as_str).map(str::to_string);
+    let strings = |key: &str| {
+        data.get(key)
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect::<Vec<_>>()
+    };
+    Some(GameInfo {
+        source: GameInfoSource::Gameconf,
+        iwad: string("iwad"),
+        load: [strings("pwadfiles"), strings("dehfiles")].concat(),
+        title: string("title"),
+    })
+}
+
+impl Wad {
+    /// The loading requirements the WAD declares, from its `GAMEINFO` lump or,
+    /// failing that, its `GAMECONF`. From `GAMEINFO` the `IWAD`, `LOAD` and
+    /// `STARTUPTITLE` keys are read, in any case, and other keys are ignored.
+    /// From `GAMECONF` the `iwad` and `title` fields of its `data` object are
+    /// read, with `pwadfiles` followed by `dehfiles` as the files to load.
+    /// `None` if the WAD has neither lump or its `GAMECONF` isn't valid JSON.
+    pub fn gameinfo(&self) -> Option<GameInfo> {
+        if let Some(lump) = self.lump_by_name("GAMEINFO") {
+            return Some(parse_gameinfo(&String::from_utf8_lossy(lump.as_bytes())));
+        }
+        let lump = self.lump_by_name("GAMECONF")?;
+        parse_gameconf(&String::from_utf8_lossy(lump.as_bytes()))
+    }
+}
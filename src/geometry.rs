@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+
+use crate::map::Bounds;
+use crate::map::Linedef;
+use crate::map::Map;
+use crate::map::Sidedef;
+use crate::map::Vertex;
+use crate::map::LINEDEF_FLAG_IMPASSABLE;
+use crate::map::NO_SIDEDEF;
+use crate::stats::AreaSource;
+use crate::stats::DetailLineRule;
+use crate::stats::DetailRatio;
+use crate::stats::MapShape;
+use crate::stats::ShapeKind;
+use crate::stats::DETAILED_SECTOR_EDGES;
+use crate::stats::SPRAWLING_FILL;
+use crate::stats::SQUARE_ASPECT_LIMIT;
+use crate::Error;
+
+/// Finds the sector containing a point by casting a ray towards +x and taking
+/// the side of the nearest linedef it hits, the same way editors do without a
+/// BSP tree. Returns `None` for points in the void.
+pub(crate) fn sector_at(
+    vertexes: &[Vertex],
+    linedefs: &[Linedef],
+    sidedefs: &[Sidedef],
+    x: f64,
+    y: f64,
+) -> Option<u16> {
+    let mut nearest: Option<(f64, u16)> = None;
+    for line in linedefs {
+        let (v1, v2) = match (
+            vertexes.get(line.start_vertex as usize),
+            vertexes.get(line.end_vertex as usize),
+        ) {
+            (Some(v1), Some(v2)) => (v1, v2),
+            _ => continue,
+        };
+        let (x1, y1, x2, y2) = (v1.x as f64, v1.y as f64, v2.x as f64, v2.y as f64);
+        if (y1 <= y) == (y2 <= y) {
+            continue;
+        }
+        let hit_x = x1 + (y - y1) * (x2 - x1) / (y2 - y1);
+        if hit_x < x || nearest.is_some_and(|(d, _)| hit_x - x >= d) {
+            continue;
+        }
+        // a negative cross product puts the point on the right (front) side
+        let cross = (x2 - x1) * (y - y1) - (y2 - y1) * (x - x1);
+        let side = if cross < 0.0 {
+            line.right_sidedef
+        } else {
+            line.left_sidedef
+        };
+        nearest = Some((hit_x - x, side));
+    }
+
+    let (_, side) = nearest?;
+    if side == NO_SIDEDEF {
+        return None;
+    }
+    sidedefs.get(side as usize).map(|s| s.sector)
+}
+
+/// How a segment meets a closed box: not at all, only along its edges or
+/// corners, or through its interior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Contact {
+    None,
+    Edge,
+    Interior,
+}
+
+pub(crate) fn contact(p1: (f64, f64), p2: (f64, f64), min: (f64, f64), max: (f64, f64)) -> Contact {
+    // Liang-Barsky clipping against the closed box
+    let (dx, dy) = (p2.0 - p1.0, p2.1 - p1.1);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-dx, p1.0 - min.0),
+        (dx, max.0 - p1.0),
+        (-dy, p1.1 - min.1),
+        (dy, max.1 - p1.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return Contact::None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    if t0 > t1 {
+        return Contact::None;
+    }
+    let t = (t0 + t1) / 2.0;
+    let (mx, my) = (p1.0 + dx * t, p1.1 + dy * t);
+    if min.0 < mx && mx < max.0 && min.1 < my && my < max.1 {
+        Contact::Interior
+    } else {
+        Contact::Edge
+    }
+}
+
+impl Map {
+    pub fn sector_at(&self, x: i16, y: i16) -> Result<Option<u16>, Error> {
+        Ok(sector_at(
+            &self.vertexes()?,
+            &self.linedefs()?,
+            &self.sidedefs()?,
+            x as f64,
+            y as f64,
+        ))
+    }
+
+    /// Each linedef's start and end vertex, in linedef order.
+    pub fn linedef_segments(&self) -> Result<Vec<(Vertex, Vertex)>, Error> {
+        let vertexes = self.vertexes()?;
+        self.linedefs()?
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let vertex = |v: u16| {
+                    vertexes.get(v as usize).copied().ok_or_else(|| {
+                        Error::InvalidReference(
+                            self.name.clone(),
+                            format!("linedef {} references missing vertex {}", i, v),
+                        )
+                    })
+                };
+                Ok((vertex(line.start_vertex)?, vertex(line.end_vertex)?))
+            })
+            .collect()
+    }
+
+    /// The linedefs bounding each sector, indexed by sector number. Each list
+    /// is in ascending linedef order and names a line once, even when both of
+    /// its sides face the same sector. Sidedefs pointing at a sector that
+    /// doesn't exist are ignored.
+    pub fn sector_lines(&self) -> Result<Vec<Vec<usize>>, Error> {
+        let sidedefs = self.sidedefs()?;
+        let mut lines = vec![vec![]; self.sectors()?.len()];
+        for (i, line) in self.linedefs()?.iter().enumerate() {
+            for side in [line.right_sidedef, line.left_sidedef] {
+                let sector = match sidedefs.get(side as usize) {
+                    Some(s) => s.sector as usize,
+                    None => continue,
+                };
+                if let Some(list) = lines.get_mut(sector) {
+                    if list.last() != Some(&i) {
+                        list.push(i);
+                    }
+                }
+            }
+        }
+        Ok(lines)
+    }
+
+    /// How many linedefs bound each sector, indexed by sector number and
+    /// counted from `sector_lines`, so a line with both sides in a sector
+    /// counts once.
+    pub fn sector_edge_counts(&self) -> Result<Vec<usize>, Error> {
+        Ok(self.sector_lines()?.iter().map(Vec::len).collect())
+    }
+
+    /// Sectors bounded by more than `DETAILED_SECTOR_EDGES` linedefs, usually
+    /// microdetail such as curves and trim made of many short lines. They're
+    /// slow for node builders to split and a sign of a heavily detailed map,
+    /// not an error.
+    pub fn over_detailed_sectors(&self) -> Result<Vec<usize>, Error> {
+        Ok(self
+            .sector_edge_counts()?
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > DETAILED_SECTOR_EDGES)
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    /// Sectors bounded by one or two linedefs, too few to enclose any floor.
+    /// Sectors without lines are left out; they're usually unused, which
+    /// `orphan_sectors` reports.
+    pub fn unclosable_sectors(&self) -> Result<Vec<usize>, Error> {
+        Ok(self
+            .sector_edge_counts()?
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| (1..3).contains(&count))
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    /// The extent of the map's vertexes, or `None` for a map without any.
+    pub fn bounds(&self) -> Result<Option<Bounds>, Error> {
+        let vertexes = self.vertexes()?;
+        Ok(vertexes.iter().fold(None, |bounds, v| {
+            Some(match bounds {
+                None => Bounds {
+                    min_x: v.x,
+                    min_y: v.y,
+                    max_x: v.x,
+                    max_y: v.y,
+                },
+                Some(b) => Bounds {
+                    min_x: b.min_x.min(v.x),
+                    min_y: b.min_y.min(v.y),
+                    max_x: b.max_x.max(v.x),
+                    max_y: b.max_y.max(v.y),
+                },
+            })
+        }))
+    }
+
+    /// The floor area of each sector in square map units. Sectors whose outline
+    /// can't be traced, such as ones with an unclosed edge, a missing vertex or no
+    /// lines at all, are NaN rather than zero, so they aren't mistaken for
+    /// tiny slivers; filter them with `f64::is_nan`. A map with an empty
+    /// geometry lump fails with `Error::EmptyLump`.
+    pub fn sector_areas(&self) -> Result<Vec<f64>, Error> {
+        self.require_geometry()?;
+        Ok(sector_areas(
+            &self.vertexes()?,
+            &self.linedefs()?,
+            &self.sidedefs()?,
+            self.sectors()?.len(),
+        ))
+    }
+
+    /// The total floor area of every sector whose outline can be traced, in
+    /// square map units.
+    pub fn floor_area(&self) -> Result<f64, Error> {
+        Ok(self
+            .sector_areas()?
+            .iter()
+            .filter(|a| !a.is_nan())
+            .fold(0.0, |total, a| total + a))
+    }
+
+    /// Linedefs per square map unit of floor, a rough measure of how much
+    /// architectural detail a map has. When the sectors don't enclose any area,
+    /// which happens with unclosed outlines, the bounding box is used instead.
+    pub fn detail_ratio(&self) -> Result<DetailRatio, Error> {
+        let linedefs = self.linedefs()?.len() as f64;
+        let floor = self.floor_area()?;
+        let (area, source) = if floor > 0.0 {
+            (floor, AreaSource::Floor)
+        } else {
+            let area = self.bounds()?.map_or(0.0, |b| {
+                (b.max_x as f64 - b.min_x as f64) * (b.max_y as f64 - b.min_y as f64)
+            });
+            (area, AreaSource::BoundingBox)
+        };
+        Ok(DetailRatio {
+            ratio: if area > 0.0 { linedefs / area } else { 0.0 },
+            area,
+            source,
+        })
+    }
+
+    /// The bounding box's width over its height: above 1 for a map wider
+    /// east to west than north to south. Infinite for a box with no height,
+    /// and 1 for a single point. Fails with `Error::EmptyLump` for a map
+    /// without geometry.
+    pub fn bounding_box_aspect_ratio(&self) -> Result<f64, Error> {
+        self.require_geometry()?;
+        let b = self.bounds()?.expect("a map with geometry has vertexes");
+        let width = b.max_x as f64 - b.min_x as f64;
+        let height = b.max_y as f64 - b.min_y as f64;
+        Ok(if height > 0.0 {
+            width / height
+        } else if width > 0.0 {
+            f64::INFINITY
+        } else {
+            1.0
+        })
+    }
+
+    /// Classifies the map's footprint by how much of its bounding box the
+    /// floor fills and, when that's at least `SPRAWLING_FILL`, by the box's
+    /// aspect ratio against `SQUARE_ASPECT_LIMIT`.
+    pub fn shape(&self) -> Result<MapShape, Error> {
+        let aspect_ratio = self.bounding_box_aspect_ratio()?;
+        let floor = self.floor_area()?;
+        let box_area = self.bounds()?.map_or(0.0, |b| {
+            (b.max_x as f64 - b.min_x as f64) * (b.max_y as f64 - b.min_y as f64)
+        });
+        let fill = if box_area > 0.0 {
+            (floor / box_area).min(1.0)
+        } else {
+            0.0
+        };
+        let kind = if fill < SPRAWLING_FILL {
+            ShapeKind::Sprawling
+        } else if aspect_ratio > SQUARE_ASPECT_LIMIT {
+            ShapeKind::Wide
+        } else if aspect_ratio < 1.0 / SQUARE_ASPECT_LIMIT {
+            ShapeKind::Tall
+        } else {
+            ShapeKind::Square
+        };
+        Ok(MapShape {
+            kind,
+            aspect_ratio,
+            fill,
+        })
+    }
+
+    /// The fraction of two-sided linedefs that are detail by the default
+    /// `DetailLineRule`: impassable, with floors and ceilings within 8 units
+    /// of each other. A finer measure of style than `detail_ratio`.
+    pub fn detail_line_ratio(&self) -> Result<f64, Error> {
+        self.detail_line_ratio_with(DetailLineRule::default())
+    }
+
+    /// The fraction of two-sided linedefs that are detail by `rule`, or 0 for
+    /// a map without any. This is a heuristic, and lines whose sidedefs or
+    /// sectors are missing count as structural. Fails with `Error::EmptyLump`
+    /// for a map without geometry.
+    pub fn detail_line_ratio_with(&self, rule: DetailLineRule) -> Result<f64, Error> {
+        self.require_geometry()?;
+        let sidedefs = self.sidedefs()?;
+        let sectors = self.sectors()?;
+        let sector_of = |side: u16| sectors.get(sidedefs.get(side as usize)?.sector as usize);
+        let mut two_sided = 0;
+        let mut detail = 0;
+        for line in self.linedefs()? {
+            if line.left_sidedef == NO_SIDEDEF {
+                continue;
+            }
+            two_sided += 1;
+            let (front, back) = match (sector_of(line.right_sidedef), sector_of(line.left_sidedef))
+            {
+                (Some(front), Some(back)) => (front, back),
+                _ => continue,
+            };
+            let step = |a: i16, b: i16| (a as i32 - b as i32).unsigned_abs();
+            if (!rule.impassable_only || line.flags & LINEDEF_FLAG_IMPASSABLE != 0)
+                && step(front.floor_height, back.floor_height) <= rule.max_floor_step as u32
+                && step(front.ceiling_height, back.ceiling_height) <= rule.max_ceiling_step as u32
+            {
+                detail += 1;
+            }
+        }
+        Ok(if two_sided > 0 {
+            detail as f64 / two_sided as f64
+        } else {
+            0.0
+        })
+    }
+}
+
+/// The floor area of each sector, indexed by sector number. Each linedef adds its
+/// shoelace term to the sector on either side, so holes and nested sectors come
+/// out right as long as the sector outlines are closed. Sectors whose outline
+/// isn't closed, has no lines, or uses a missing vertex get NaN.
+pub(crate) fn sector_areas(
+    vertexes: &[Vertex],
+    linedefs: &[Linedef],
+    sidedefs: &[Sidedef],
+    sector_count: usize,
+) -> Vec<f64> {
+    let mut areas = vec![0.0; sector_count];
+    let mut closed = vec![false; sector_count];
+    let mut broken = vec![false; sector_count];
+    // a closed outline leaves every vertex with as many sector edges in as out
+    let mut balance = HashMap::<(usize, u16), i32>::new();
+    let sector_of = |side: u16| {
+        sidedefs
+            .get(side as usize)
+            .map(|s| s.sector as usize)
+            .filter(|&s| s < sector_count)
+    };
+    for line in linedefs {
+        let front = sector_of(line.right_sidedef);
+        let back = sector_of(line.left_sidedef);
+        let (v1, v2) = match (
+            vertexes.get(line.start_vertex as usize),
+            vertexes.get(line.end_vertex as usize),
+        ) {
+            (Some(v1), Some(v2)) => (v1, v2),
+            _ => {
+                for s in front.into_iter().chain(back) {
+                    broken[s] = true;
+                }
+                continue;
+            }
+        };
+        let term = v1.x as f64 * v2.y as f64 - v2.x as f64 * v1.y as f64;
+        // front sides run clockwise around their sector, back sides counterclockwise
+        if let Some(s) = front {
+            areas[s] -= term / 2.0;
+            closed[s] = true;
+            *balance.entry((s, line.start_vertex)).or_default() += 1;
+            *balance.entry((s, line.end_vertex)).or_default() -= 1;
+        }
+        if let Some(s) = back {
+            areas[s] += term / 2.0;
+            closed[s] = true;
+            *balance.entry((s, line.end_vertex)).or_default() += 1;
+            *balance.entry((s, line.start_vertex)).or_default() -= 1;
+        }
+    }
+    for (&(s, _), &b) in &balance {
+        if b != 0 {
+            closed[s] = false;
+        }
+    }
+    areas
+        .iter()
+        .enumerate()
+        .map(|(s, a)| {
+            if closed[s] && !broken[s] {
+                a.abs()
+            } else {
+                f64::NAN
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,307 @@
+use crate::png::crc32;
+
+const MAGIC: [u8; 2] = [0x1F, 0x8B];
+const METHOD_DEFLATE: u8 = 8;
+
+const FLAG_HCRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+
+pub(crate) fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Decompresses a gzip file, including one made of several concatenated
+/// members. Returns `None` if it's malformed, fails its checksum or would
+/// come to more than `max_size` bytes, which stops a small file from
+/// inflating to fill memory.
+pub(crate) fn decompress(data: &[u8], max_size: usize) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        let member = data.get(pos..)?;
+        if !is_gzip(member) || *member.get(2)? != METHOD_DEFLATE {
+            return None;
+        }
+        let flags = *member.get(3)?;
+        let mut header = 10;
+        if flags & FLAG_EXTRA != 0 {
+            let len = u16::from_le_bytes([*member.get(header)?, *member.get(header + 1)?]);
+            header += 2 + len as usize;
+        }
+        for flag in [FLAG_NAME, FLAG_COMMENT] {
+            if flags & flag != 0 {
+                header += member.get(header..)?.iter().position(|&b| b == 0)? + 1;
+            }
+        }
+        if flags & FLAG_HCRC != 0 {
+            header += 2;
+        }
+
+        let start = out.len();
+        let mut inflater = Inflater {
+            data: member.get(header..)?,
+            bit: 0,
+            out: &mut out,
+            max_size,
+        };
+        inflater.inflate()?;
+        let end = header + inflater.bit.div_ceil(8);
+        let trailer = member.get(end..end + 8)?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc != crc32(&out[start..]) || size != (out.len() - start) as u32 {
+            return None;
+        }
+        pos += end + 8;
+    }
+    Some(out)
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// the order code length code lengths are stored in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const MAX_BITS: usize = 15;
+
+/// A canonical Huffman code, stored as the number of codes of each length
+/// and the symbols in code order.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = (0..lengths.len() as u16)
+            .filter(|&s| lengths[s as usize] != 0)
+            .collect::<Vec<_>>();
+        // a stable sort keeps symbols of the same length in numeric order
+        symbols.sort_by_key(|&s| lengths[s as usize]);
+        Self { counts, symbols }
+    }
+}
+
+struct Inflater<'a> {
+    data: &'a [u8],
+    bit: usize,
+    out: &'a mut Vec<u8>,
+    max_size: usize,
+}
+
+impl Inflater<'_> {
+    // whether `n` more bytes fit under the size limit
+    fn room_for(&self, n: usize) -> Option<()> {
+        Some(()).filter(|_| n <= self.max_size - self.out.len())
+    }
+
+    fn bits(&mut self, n: u8) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.bit / 8)?;
+            value |= (((byte >> (self.bit % 8)) & 1) as u32) << i;
+            self.bit += 1;
+        }
+        Some(value)
+    }
+
+    // Huffman codes are packed most significant bit first, unlike everything
+    // else in the stream
+    fn decode(&mut self, code: &Huffman) -> Option<u16> {
+        let (mut value, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..=MAX_BITS {
+            value |= self.bits(1)? as i32;
+            let count = code.counts[len] as i32;
+            if value - first < count {
+                return code.symbols.get((index + value - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            value <<= 1;
+        }
+        None
+    }
+
+    fn inflate(&mut self) -> Option<()> {
+        loop {
+            let last = self.bits(1)? == 1;
+            match self.bits(2)? {
+                0 => self.stored()?,
+                1 => {
+                    let mut lengths = [8u8; 288];
+                    lengths[144..256].fill(9);
+                    lengths[256..280].fill(7);
+                    self.block(&Huffman::new(&lengths), &Huffman::new(&[5; 30]))?
+                }
+                2 => {
+                    let (literals, distances) = self.dynamic_codes()?;
+                    self.block(&literals, &distances)?
+                }
+                _ => return None,
+            }
+            if last {
+                return Some(());
+            }
+        }
+    }
+
+    fn stored(&mut self) -> Option<()> {
+        let start = self.bit.div_ceil(8);
+        let header = self.data.get(start..start + 4)?;
+        let len = u16::from_le_bytes([header[0], header[1]]);
+        if len != !u16::from_le_bytes([header[2], header[3]]) {
+            return None;
+        }
+        let bytes = self.data.get(start + 4..start + 4 + len as usize)?;
+        self.room_for(bytes.len())?;
+        self.out.extend_from_slice(bytes);
+        self.bit = (start + 4 + len as usize) * 8;
+        Some(())
+    }
+
+    fn dynamic_codes(&mut self) -> Option<(Huffman, Huffman)> {
+        let literal_count = self.bits(5)? as usize + 257;
+        let distance_count = self.bits(5)? as usize + 1;
+        let code_length_count = self.bits(4)? as usize + 4;
+        let mut code_lengths = [0u8; 19];
+        for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[i] = self.bits(3)? as u8;
+        }
+        let code_length_code = Huffman::new(&code_lengths);
+
+        let mut lengths = vec![];
+        while lengths.len() < literal_count + distance_count {
+            let (value, repeat) = match self.decode(&code_length_code)? {
+                len @ 0..=15 => (len as u8, 1),
+                16 => (*lengths.last()?, 3 + self.bits(2)?),
+                17 => (0, 3 + self.bits(3)?),
+                18 => (0, 11 + self.bits(7)?),
+                _ => return None,
+            };
+            lengths.extend(std::iter::repeat_n(value, repeat as usize));
+        }
+        if lengths.len() != literal_count + distance_count {
+            return None;
+        }
+        Some((
+            Huffman::new(&lengths[..literal_count]),
+            Huffman::new(&lengths[literal_count..]),
+        ))
+    }
+
+    fn block(&mut self, literals: &Huffman, distances: &Huffman) -> Option<()> {
+        loop {
+            let symbol = self.decode(literals)? as usize;
+            match symbol {
+                0..=255 => {
+                    self.room_for(1)?;
+                    self.out.push(symbol as u8);
+                }
+                256 => return Some(()),
+                _ => {
+                    let i = symbol - 257;
+                    let length =
+                        *LENGTH_BASE.get(i)? as usize + self.bits(*LENGTH_EXTRA.get(i)?)? as usize;
+                    let d = self.decode(distances)? as usize;
+                    let distance = *DISTANCE_BASE.get(d)? as usize
+                        + self.bits(*DISTANCE_EXTRA.get(d)?)? as usize;
+                    let from = self.out.len().checked_sub(distance)?;
+                    self.room_for(length)?;
+                    // the copy may overlap the bytes it produces
+                    for j in 0..length {
+                        let byte = self.out[from + j];
+                        self.out.push(byte);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // `IWAD` at level 0, in a stored block
+    const STORED: &str = "1f8b0800000000000403010400fbff49574144530b783604000000";
+    // `hello hello hello hello` with zlib's fixed-codes strategy
+    const FIXED: &str = "1f8b0800000000000203cb48cdc9c957c8402701e3513d8d17000000";
+    // three copies of a map's lump names, which zlib gives its own codes
+    const DYNAMIC: &str = "1f8b0800000000000203cd8cc10d804008045ba1153d56454f30408cfd5722f19af0373b936c6ea26b50170563090ae101373cf1a00caa47a0a579901a7f6e2cc75e4473b7769cd345f9e3b7174035c56fea000000";
+    const LUMP_NAMES: &str =
+        "THINGS LINEDEFS SIDEDEFS VERTEXES SEGS SSECTORS NODES SECTORS REJECT BLOCKMAP ";
+
+    #[test]
+    fn each_block_type_inflates() {
+        assert_eq!(decompress(&hex(STORED), 1 << 20).unwrap(), b"IWAD");
+        assert_eq!(
+            decompress(&hex(FIXED), 1 << 20).unwrap(),
+            b"hello hello hello hello"
+        );
+        assert_eq!(
+            decompress(&hex(DYNAMIC), 1 << 20).unwrap(),
+            LUMP_NAMES.repeat(3).as_bytes()
+        );
+    }
+
+    #[test]
+    fn concatenated_members_are_joined() {
+        let data = [hex(STORED), hex(FIXED)].concat();
+        assert_eq!(
+            decompress(&data, 1 << 20).unwrap(),
+            b"IWADhello hello hello hello"
+        );
+    }
+
+    #[test]
+    fn output_past_the_limit_is_refused() {
+        let cases = [(STORED, 4), (FIXED, 23), (DYNAMIC, LUMP_NAMES.len() * 3)];
+        for (data, size) in cases {
+            assert!(decompress(&hex(data), size).is_some());
+            assert!(decompress(&hex(data), size - 1).is_none());
+        }
+        let data = [hex(STORED), hex(STORED)].concat();
+        assert!(decompress(&data, 8).is_some());
+        assert!(decompress(&data, 7).is_none());
+    }
+
+    #[test]
+    fn damaged_files_are_refused() {
+        let mut data = hex(FIXED);
+        let crc = data.len() - 8;
+        data[crc] ^= 1;
+        assert!(decompress(&data, 1 << 20).is_none());
+        let data = hex(DYNAMIC);
+        assert!(decompress(&data[..data.len() - 12], 1 << 20).is_none());
+        assert!(decompress(b"PWAD", 1 << 20).is_none());
+    }
+}
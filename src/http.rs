@@ -0,0 +1,327 @@
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crate::Error;
+
+/// How many redirects `get` follows before giving up.
+pub const MAX_REDIRECTS: usize = 5;
+
+// how long the server may go quiet before the download is abandoned
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+struct Url {
+    /// The host and port as written, for the `Host` header and redirects.
+    authority: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+fn parse_url(url: &str) -> Result<Url, String> {
+    let rest = match url.strip_prefix("http://") {
+        Some(rest) => rest,
+        None if url.starts_with("https://") => {
+            return Err("only plain http:// URLs are supported, not HTTPS".to_string())
+        }
+        None => return Err("not an http:// URL".to_string()),
+    };
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| format!("'{}' isn't a port number", port))?,
+        ),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err("the URL has no host".to_string());
+    }
+    Ok(Url {
+        authority: authority.to_string(),
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+// a redirect's Location, which may be relative to the URL it came from
+fn resolve(base: &Url, location: &str) -> String {
+    if location.contains("://") {
+        location.to_string()
+    } else if location.starts_with('/') {
+        format!("http://{}{}", base.authority, location)
+    } else {
+        let directory = &base.path[..base.path.rfind('/').map_or(0, |i| i + 1)];
+        format!("http://{}{}{}", base.authority, directory, location)
+    }
+}
+
+enum Response {
+    Body(Vec<u8>),
+    Redirect(String),
+}
+
+fn read_line(reader: &mut impl BufRead) -> Result<String, String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+        return Err("the connection closed early".to_string());
+    }
+    Ok(line.trim_end().to_string())
+}
+
+fn too_large(max_size: u64) -> String {
+    format!("the file is larger than the {} byte limit", max_size)
+}
+
+// a body sent in chunks, each preceded by its size in hex
+fn read_chunked(reader: &mut impl BufRead, max_size: u64) -> Result<Vec<u8>, String> {
+    let mut body = vec![];
+    loop {
+        let line = read_line(reader)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = u64::from_str_radix(size, 16)
+            .map_err(|_| format!("malformed chunk size '{}'", size))?;
+        if size == 0 {
+            return Ok(body);
+        }
+        // the body never passes max_size, so this can't underflow, where
+        // adding a huge chunk size to the length could overflow
+        if size > max_size - body.len() as u64 {
+            return Err(too_large(max_size));
+        }
+        let read = reader
+            .take(size)
+            .read_to_end(&mut body)
+            .map_err(|e| e.to_string())?;
+        if (read as u64) < size {
+            return Err("the connection closed early".to_string());
+        }
+        read_line(reader)?;
+    }
+}
+
+fn request(url: &Url, max_size: u64) -> Result<Response, String> {
+    let mut stream =
+        TcpStream::connect((url.host.as_str(), url.port)).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: doom-map-stats\r\nAccept-Encoding: identity\r\nConnection: close\r\n\r\n",
+        url.path, url.authority
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut reader = BufReader::new(stream);
+    let status_line = read_line(&mut reader)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| format!("malformed status line '{}'", status_line))?;
+    let mut headers = vec![];
+    loop {
+        let line = read_line(&mut reader)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    };
+
+    if matches!(status, 301 | 302 | 303 | 307 | 308) {
+        return header("location")
+            .map(|location| Response::Redirect(resolve(url, location)))
+            .ok_or_else(|| format!("redirected ({}) without a location", status));
+    }
+    if status != 200 {
+        let reason = status_line.split_once(' ').map_or("", |(_, reason)| reason);
+        return Err(format!("the server answered {}", reason));
+    }
+
+    if header("transfer-encoding").is_some_and(|e| e.eq_ignore_ascii_case("chunked")) {
+        return read_chunked(&mut reader, max_size).map(Response::Body);
+    }
+    let length = header("content-length").and_then(|l| l.parse::<u64>().ok());
+    if length.is_some_and(|length| length > max_size) {
+        return Err(too_large(max_size));
+    }
+    let mut body = vec![];
+    reader
+        .take(max_size + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| e.to_string())?;
+    if body.len() as u64 > max_size {
+        return Err(too_large(max_size));
+    }
+    if length.is_some_and(|length| (body.len() as u64) < length) {
+        return Err("the connection closed early".to_string());
+    }
+    Ok(Response::Body(body))
+}
+
+/// Downloads a file over plain HTTP into memory, following up to
+/// `MAX_REDIRECTS` redirects. The body is read as it arrives and the
+/// download stops with an error once it passes `max_size` bytes, or before
+/// it starts if the server says it will. HTTPS isn't supported, since the
+/// standard library has no TLS. Fails with `Error::Download`.
+pub fn get(url: &str, max_size: u64) -> Result<Vec<u8>, Error> {
+    let fail = |reason: String| Error::Download(url.to_string(), reason);
+    let mut current = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let target = parse_url(&current).map_err(fail)?;
+        match request(&target, max_size).map_err(fail)? {
+            Response::Body(data) => return Ok(data),
+            Response::Redirect(location) => current = location,
+        }
+    }
+    Err(fail(format!("more than {} redirects", MAX_REDIRECTS)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn urls_are_split_into_host_port_and_path() {
+        let url = parse_url("http://example.com:8080/idgames/a.zip#top").unwrap();
+        assert_eq!(url.authority, "example.com:8080");
+        assert_eq!((url.host.as_str(), url.port), ("example.com", 8080));
+        assert_eq!(url.path, "/idgames/a.zip");
+
+        let url = parse_url("http://example.com").unwrap();
+        assert_eq!((url.port, url.path.as_str()), (80, "/"));
+
+        assert!(parse_url("https://example.com/").is_err());
+        assert!(parse_url("ftp://example.com/").is_err());
+        assert!(parse_url("http://:80/").is_err());
+        assert!(parse_url("http://example.com:http/").is_err());
+    }
+
+    #[test]
+    fn redirects_resolve_against_the_url() {
+        let base = parse_url("http://example.com/levels/doom2/a.zip").unwrap();
+        let cases = [
+            ("http://mirror.org/a.zip", "http://mirror.org/a.zip"),
+            ("/other/b.zip", "http://example.com/other/b.zip"),
+            ("b.zip", "http://example.com/levels/doom2/b.zip"),
+        ];
+        for (location, resolved) in cases {
+            assert_eq!(resolve(&base, location), resolved);
+        }
+    }
+
+    fn chunked(body: &str, max_size: u64) -> Result<Vec<u8>, String> {
+        read_chunked(&mut Cursor::new(body.as_bytes()), max_size)
+    }
+
+    #[test]
+    fn chunks_are_joined_until_the_last() {
+        let body = "4\r\nIWAD\r\n3;name=value\r\n ab\r\n0\r\n\r\n";
+        assert_eq!(chunked(body, 100).unwrap(), b"IWAD ab");
+        assert_eq!(chunked(body, 7).unwrap(), b"IWAD ab");
+    }
+
+    #[test]
+    fn oversized_chunks_are_refused() {
+        let body = "4\r\nIWAD\r\n4\r\nPWAD\r\n0\r\n\r\n";
+        assert_eq!(chunked(body, 7), Err(too_large(7)));
+        // a size that would overflow when added to the body so far
+        let body = "4\r\nIWAD\r\nffffffffffffffff\r\n";
+        assert_eq!(chunked(body, u64::MAX - 1), Err(too_large(u64::MAX - 1)));
+    }
+
+    #[test]
+    fn malformed_or_cut_short_chunks_fail() {
+        assert!(chunked("zz\r\n", 100).is_err());
+        assert!(chunked("10\r\nshort", 100).is_err());
+        assert!(chunked("4\r\nIWAD\r\n", 100).is_err());
+    }
+
+    // serves each of `responses` to one connection in turn, returning the
+    // server's URL
+    fn serve(responses: Vec<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                while !read_line(&mut reader).unwrap().is_empty() {}
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn get_reads_the_body() {
+        let url = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nPWAD".to_string()
+        ]);
+        assert_eq!(get(&format!("{}/a.wad", url), 100).unwrap(), b"PWAD");
+    }
+
+    #[test]
+    fn get_reads_a_chunked_body() {
+        let url = serve(vec![
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nPW\r\n2\r\nAD\r\n0\r\n\r\n"
+                .to_string(),
+        ]);
+        assert_eq!(get(&url, 100).unwrap(), b"PWAD");
+    }
+
+    #[test]
+    fn get_follows_redirects() {
+        let url = serve(vec![
+            "HTTP/1.1 302 Found\r\nLocation: /b.wad\r\n\r\n".to_string(),
+            "HTTP/1.1 200 OK\r\n\r\nIWAD".to_string(),
+        ]);
+        assert_eq!(get(&format!("{}/a.wad", url), 100).unwrap(), b"IWAD");
+    }
+
+    #[test]
+    fn get_stops_at_the_size_limit() {
+        let url = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 400\r\n\r\n".to_string(),
+            "HTTP/1.1 200 OK\r\n\r\nmore than ten bytes".to_string(),
+        ]);
+        assert!(matches!(get(&url, 10), Err(Error::Download(..))));
+        assert!(matches!(get(&url, 10), Err(Error::Download(..))));
+    }
+
+    #[test]
+    fn get_reports_error_statuses() {
+        let url = serve(vec!["HTTP/1.1 404 Not Found\r\n\r\n".to_string()]);
+        match get(&url, 100) {
+            Err(Error::Download(_, reason)) => {
+                assert_eq!(reason, "the server answered 404 Not Found")
+            }
+            other => panic!(
+                "expected a download error, got {:?}",
+                other.map(|b| b.len())
+            ),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+pub mod acs;
+pub mod actors;
+pub mod animations;
+pub mod blockmap;
+mod bsp;
+#[cfg(any(test, feature = "testutil"))]
+pub mod builder;
+pub mod cache;
+mod clones;
+pub mod compat;
+pub mod connectivity;
+pub mod demo;
+pub mod diagnostics;
+pub mod game;
+pub mod gameinfo;
+mod geometry;
+mod gzip;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod json;
+pub mod lint;
+pub mod lumps;
+pub mod map;
+mod md5;
+pub mod movers;
+pub mod openness;
+pub mod pattern;
+pub mod picture;
+mod placement;
+mod png;
+pub mod polygons;
+pub mod progression;
+pub mod render;
+mod sha256;
+pub mod stats;
+pub mod textures;
+pub mod theme;
+pub mod things;
+pub mod udmf;
+mod voodoo;
+pub mod wad;
+
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// Everything that can go wrong reading and analyzing a WAD. Every fallible
+/// function in the library returns this type.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Reading the file failed.
+    Io(#[from] std::io::Error),
+    /// The file doesn't start with an `IWAD` or `PWAD` header.
+    NotAWad(PathBuf),
+    /// A map lump at the given offset appears outside of a map, or twice in
+    /// one map.
+    InvalidLumpOrder(i32, String),
+    /// A lump's size in bytes doesn't fit its format.
+    InvalidLumpSize(String, usize),
+    /// No map has the requested name; the second field lists the ones there are.
+    MapNotFound(String, Vec<String>),
+    /// The map has no player 1 start inside a sector to walk from.
+    NoPlayerStart(String),
+    /// The map has no sectors, so there's nothing to walk through.
+    EmptyMap(String),
+    /// One of the map's geometry lumps is present but has no data; the second
+    /// field names it.
+    EmptyLump(String, String),
+    /// The header or directory can't be right; the field says why.
+    CorruptDirectory(String),
+    /// A directory entry's offset and size, in bytes, point outside the file.
+    LumpOutOfBounds(String, i32, i32),
+    /// The named lump can't be decoded as a picture in Doom's patch format.
+    InvalidPicture(String),
+    /// A map structure points at an index past the end of another lump; the
+    /// second field describes the reference.
+    InvalidReference(String, String),
+    /// The file starts like a gzip file but is damaged, or decompresses to
+    /// more than a WAD can hold.
+    InvalidGzip(PathBuf),
+    /// A lump index is past the end of the directory, which has the number of
+    /// entries in the second field.
+    LumpIndexOutOfRange(usize, usize),
+    /// The directory indexes `start..end` can't be read as a map; the third
+    /// field says why.
+    InvalidLumpRange(usize, usize, String),
+    /// A directory of lump files has no file for the required lump named in
+    /// the second field.
+    MissingLump(PathBuf, String),
+    /// A partial map, kept by lenient parsing, has no lump of the kind named
+    /// in the second field.
+    MissingMapLump(String, String),
+    /// Downloading the URL in the first field failed; the second says why.
+    Download(String, String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(i) => write!(f, "I/O error: {}", i),
+            Error::NotAWad(p) => write!(f, "not a WAD file: {}", p.to_string_lossy()),
+            Error::InvalidLumpOrder(w, n) => {
+                write!(f, "encountered out-of-order lump at offset {}: '{}'", w, n)
+            }
+            Error::InvalidLumpSize(n, s) => {
+                write!(f, "lump '{}' has an invalid size of {} bytes", n, s)
+            }
+            Error::MapNotFound(n, available) => write!(
+                f,
+                "map '{}' not found (available: {})",
+                n,
+                available.join(", ")
+            ),
+            Error::NoPlayerStart(m) => {
+                write!(f, "map '{}' has no player 1 start inside a sector", m)
+            }
+            Error::EmptyMap(m) => write!(f, "map '{}' has no sectors", m),
+            Error::EmptyLump(m, l) => write!(f, "map '{}' has an empty {} lump", m, l),
+            Error::CorruptDirectory(r) => write!(f, "corrupt WAD directory: {}", r),
+            Error::LumpOutOfBounds(n, offset, size) => write!(
+                f,
+                "lump '{}' ({} bytes at offset {}) lies outside the file",
+                n, size, offset
+            ),
+            Error::InvalidPicture(n) => write!(f, "lump '{}' is not a valid picture", n),
+            Error::InvalidReference(m, r) => write!(f, "map '{}': {}", m, r),
+            Error::InvalidGzip(p) => {
+                write!(f, "can't decompress gzip file: {}", p.to_string_lossy())
+            }
+            Error::LumpIndexOutOfRange(i, n) => {
+                write!(f, "there is no lump {}; the directory has {}", i, n)
+            }
+            Error::InvalidLumpRange(start, end, r) => {
+                write!(f, "lumps {}:{} aren't a map: {}", start, end, r)
+            }
+            Error::MissingLump(p, l) => {
+                write!(f, "{} has no {} lump", p.to_string_lossy(), l)
+            }
+            Error::MissingMapLump(m, l) => write!(f, "map '{}' has no {} lump", m, l),
+            Error::Download(url, r) => write!(f, "can't download {}: {}", url, r),
+        }
+    }
+}
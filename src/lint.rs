@@ -0,0 +1,240 @@
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::Severity;
+use crate::geometry::sector_at;
+use crate::map::Map;
+use crate::map::Sector;
+use crate::map::THING_FLAG_MULTIPLAYER;
+use crate::things::Thing;
+use crate::things::ThingTable;
+use crate::Error;
+
+/// Which checks `Map::lint` runs. Every check is on by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LintOptions {
+    /// Locked lines whose key isn't in the map.
+    pub keys: bool,
+    /// Maps with no way to end the level.
+    pub exits: bool,
+    /// Teleporters with nowhere to go.
+    pub teleports: bool,
+    /// A missing player start, and secrets walled off from it.
+    pub reachability: bool,
+    /// The game's thing types, which say what keys the map has. Doom's by
+    /// default.
+    pub table: ThingTable,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            keys: true,
+            exits: true,
+            teleports: true,
+            reachability: true,
+            table: ThingTable::Doom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyColor {
+    Red,
+    Blue,
+    Yellow,
+    /// Heretic's third key, which opens the doors Doom locks with red.
+    Green,
+}
+
+/// What a locked line asks the player to carry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lock {
+    AnyKey,
+    Key(KeyColor),
+    AllKeys,
+}
+
+const EXIT_SPECIALS: [u16; 6] = [11, 51, 52, 124, 197, 198];
+const SECTOR_EXIT_SPECIAL: u16 = 11;
+
+// teleporters that move the activator to a landing thing in a tagged sector
+pub(crate) const THING_TELEPORT_SPECIALS: [u16; 12] =
+    [39, 97, 125, 126, 174, 195, 207, 208, 209, 210, 268, 269];
+// Boom teleporters that move the activator to another line with the same tag
+pub(crate) const LINE_TELEPORT_SPECIALS: [u16; 8] = [243, 244, 262, 263, 264, 265, 266, 267];
+
+const GENERALIZED_LOCKED: std::ops::Range<u16> = 0x3800..0x3C00;
+
+// Heretic keeps Doom's locked door specials, with green for red, and has no
+// generalized ones. Hexen and Strife lock doors their own way, which isn't
+// read.
+fn lock(special: u16, table: ThingTable) -> Option<Lock> {
+    match table {
+        ThingTable::Doom => doom_lock(special),
+        ThingTable::Heretic => match special {
+            26 | 32 => Some(Lock::Key(KeyColor::Blue)),
+            28 | 33 => Some(Lock::Key(KeyColor::Green)),
+            27 | 34 => Some(Lock::Key(KeyColor::Yellow)),
+            _ => None,
+        },
+        ThingTable::Hexen | ThingTable::Strife => None,
+    }
+}
+
+fn doom_lock(special: u16) -> Option<Lock> {
+    Some(match special {
+        26 | 32 | 99 | 133 => Lock::Key(KeyColor::Blue),
+        28 | 33 | 134 | 135 => Lock::Key(KeyColor::Red),
+        27 | 34 | 136 | 137 => Lock::Key(KeyColor::Yellow),
+        s if GENERALIZED_LOCKED.contains(&s) => match (s >> 6) & 7 {
+            0 => Lock::AnyKey,
+            1 | 4 => Lock::Key(KeyColor::Red),
+            2 | 5 => Lock::Key(KeyColor::Blue),
+            3 | 6 => Lock::Key(KeyColor::Yellow),
+            _ => Lock::AllKeys,
+        },
+        _ => return None,
+    })
+}
+
+fn key_color(doomednum: u16, table: ThingTable) -> Option<KeyColor> {
+    if table == ThingTable::Heretic {
+        return match doomednum {
+            73 => Some(KeyColor::Green),
+            79 => Some(KeyColor::Blue),
+            80 => Some(KeyColor::Yellow),
+            _ => None,
+        };
+    }
+    match table.doom_thing(doomednum)? {
+        Thing::RedKeycard | Thing::RedSkullKey => Some(KeyColor::Red),
+        Thing::BlueKeycard | Thing::BlueSkullKey => Some(KeyColor::Blue),
+        Thing::YellowKeycard | Thing::YellowSkullKey => Some(KeyColor::Yellow),
+        _ => None,
+    }
+}
+
+// maps that the engine ends when their bosses die, without an exit line
+fn ends_on_boss_death(name: &str) -> bool {
+    let b = name.as_bytes();
+    b.len() == 4 && b[0] == b'E' && b[2] == b'M' && b[3] == b'8'
+}
+
+impl Map {
+    /// Runs the completability checks chosen in `options`, most severe
+    /// first. Errors mean the map can't be finished as built; warnings are
+    /// likely mistakes. The checks read Doom's specials, so Heretic, Hexen and Strife
+    /// maps give misleading results, apart from Heretic's locked doors and
+    /// keys. Hexen and Strife maps aren't checked for keys.
+    pub fn lint(&self, options: &LintOptions) -> Result<Vec<Diagnostic>, Error> {
+        let vertexes = self.vertexes()?;
+        let linedefs = self.linedefs()?;
+        let sidedefs = self.sidedefs()?;
+        let sectors = self.sectors()?;
+        let things = self.things()?;
+        let mut issues = vec![];
+        let mut issue = |severity, message: String| {
+            issues.push(Diagnostic::new(severity, Some(self.name()), message));
+        };
+
+        if options.keys {
+            let keys = things
+                .iter()
+                .filter(|t| t.flags & THING_FLAG_MULTIPLAYER == 0)
+                .filter_map(|t| key_color(t.doomednum, options.table))
+                .collect::<Vec<_>>();
+            for (i, line) in linedefs.iter().enumerate() {
+                let missing = match lock(line.special, options.table) {
+                    Some(Lock::AnyKey) if keys.is_empty() => "any key".to_string(),
+                    Some(Lock::Key(color)) if !keys.contains(&color) => {
+                        format!("the {:?} key", color).to_lowercase()
+                    }
+                    Some(Lock::AllKeys)
+                        if [KeyColor::Red, KeyColor::Blue, KeyColor::Yellow]
+                            .iter()
+                            .any(|c| !keys.contains(c)) =>
+                    {
+                        "all three keys".to_string()
+                    }
+                    _ => continue,
+                };
+                issue(
+                    Severity::Error,
+                    format!(
+                        "linedef {} needs {}, which the map doesn't have",
+                        i, missing
+                    ),
+                );
+            }
+        }
+
+        if options.exits {
+            let has_exit = linedefs.iter().any(|l| EXIT_SPECIALS.contains(&l.special))
+                || sectors.iter().any(|s| s.special == SECTOR_EXIT_SPECIAL)
+                || things
+                    .iter()
+                    .any(|t| t.doomednum == Thing::RomerosHead.doomednum())
+                || ends_on_boss_death(self.name());
+            if !has_exit {
+                issue(Severity::Error, "there is no way to exit".to_string());
+            }
+        }
+
+        if options.teleports {
+            let landing_sectors = things
+                .iter()
+                .filter(|t| t.doomednum == Thing::TeleportLanding.doomednum())
+                .filter_map(|t| sector_at(&vertexes, &linedefs, &sidedefs, t.x as f64, t.y as f64))
+                .filter_map(|s| sectors.get(s as usize))
+                .collect::<Vec<&Sector>>();
+            for (i, line) in linedefs.iter().enumerate() {
+                let to_thing = THING_TELEPORT_SPECIALS.contains(&line.special);
+                let to_line = LINE_TELEPORT_SPECIALS.contains(&line.special);
+                if !to_thing && !to_line {
+                    continue;
+                }
+                let message = if line.tag == 0 {
+                    format!("teleporter linedef {} has no tag", i)
+                } else if to_thing && !landing_sectors.iter().any(|s| s.tag == line.tag) {
+                    format!(
+                        "teleporter linedef {} has no landing spot in a sector tagged {}",
+                        i, line.tag
+                    )
+                } else if to_line
+                    && !linedefs
+                        .iter()
+                        .enumerate()
+                        .any(|(j, l)| j != i && l.tag == line.tag)
+                {
+                    format!(
+                        "teleporter linedef {} has no other linedef tagged {}",
+                        i, line.tag
+                    )
+                } else {
+                    continue;
+                };
+                issue(Severity::Warning, message);
+            }
+        }
+
+        if options.reachability {
+            match self.unreachable_secrets() {
+                Ok(secrets) => {
+                    for sector in secrets {
+                        issue(
+                            Severity::Warning,
+                            format!("secret sector {} is walled off from the start", sector),
+                        );
+                    }
+                }
+                Err(Error::NoPlayerStart(_) | Error::EmptyMap(_)) => issue(
+                    Severity::Error,
+                    "there is no player 1 start inside a sector".to_string(),
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+
+        issues.sort_by_key(|d| std::cmp::Reverse(d.severity));
+        Ok(issues)
+    }
+}
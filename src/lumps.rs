@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+use crate::wad::Lump;
+use crate::wad::LumpKind;
+use crate::wad::Wad;
+
+// lumps found in the IWADs or read by the engine under a fixed name
+const STANDARD_LUMPS: [&str; 22] = [
+    "PLAYPAL", "COLORMAP", "ENDOOM", "ENDTEXT", "TEXTURE1", "TEXTURE2", "PNAMES", "GENMIDI",
+    "DMXGUS", "DMXGUSC", "DEMO1", "DEMO2", "DEMO3", "DEMO4", "TITLEPIC", "INTERPIC", "CREDIT",
+    "HELP", "BOSSBACK", "VICTORY2", "ANIMATED", "SWITCHES",
+];
+
+// name prefixes of the stock menu, status bar, intermission, sound and music lumps
+const STANDARD_PREFIXES: [&str; 12] = [
+    "M_", "ST", "WI", "CWILV", "BRDR_", "AMMNUM", "END", "HELP", "PFUB", "DS", "DP", "D_",
+];
+
+/// Marker pairs enclosing sprites, flats and patches. Both the vanilla and the
+/// doubled forms used by PWADs are recognized.
+pub(crate) const MARKER_SECTIONS: [(&str, &str); 6] = [
+    ("S_START", "S_END"),
+    ("SS_START", "SS_END"),
+    ("F_START", "F_END"),
+    ("FF_START", "FF_END"),
+    ("P_START", "P_END"),
+    ("PP_START", "PP_END"),
+];
+
+fn is_marker(name: &str) -> bool {
+    name.ends_with("_START") || name.ends_with("_END")
+}
+
+/// Whether a lump name is one of Strife's `SCRIPT00` to `SCRIPT99`
+/// conversation lumps. `SCRIPT00` holds dialog shared by every map; the others
+/// belong to the map with the same number.
+pub fn is_strife_dialog(name: &str) -> bool {
+    name.len() == 8 && name.starts_with("SCRIPT") && name[6..].bytes().all(|b| b.is_ascii_digit())
+}
+
+impl Wad {
+    /// Strife's conversation lumps, in directory order.
+    pub fn strife_dialogs(&self) -> Vec<&Lump> {
+        self.iter_lumps()
+            .filter(|l| is_strife_dialog(l.name()))
+            .collect()
+    }
+
+    /// The detected game's title, intermission, help and finale screens present
+    /// in the WAD, in the order of `Game::full_screens`.
+    pub fn title_screens(&self) -> Vec<&Lump> {
+        self.detect_game()
+            .full_screens()
+            .iter()
+            .filter_map(|name| self.lump_by_name(name))
+            .collect()
+    }
+
+    /// Replacement intermission backgrounds for the detected game, and the level name graphics the
+    /// intermission shows (`WILV` and `CWILV` lumps).
+    pub fn intermission_graphics(&self) -> Vec<&Lump> {
+        let backgrounds = self.detect_game().intermission_backgrounds();
+        self.iter_lumps()
+            .filter(|l| {
+                let name = l.name();
+                backgrounds.contains(&name) || name.starts_with("WILV") || name.starts_with("CWILV")
+            })
+            .collect()
+    }
+
+    /// Lumps that aren't part of a map, inside a sprite, flat or patch section,
+    /// or one of the standard Doom lumps. What's left are a mod's own additions
+    /// such as `DECORATE`, `DEHACKED` or `MAPINFO`.
+    pub fn custom_lumps(&self) -> Vec<&Lump> {
+        let map_names = self.maps().iter().map(|m| m.name()).collect::<HashSet<_>>();
+
+        let mut custom = vec![];
+        let mut section_end = None;
+        for lump in self.iter_lumps() {
+            let name = lump.name();
+            if let Some(end) = section_end {
+                if name == end {
+                    section_end = None;
+                }
+                continue;
+            }
+            if let Some(&(_, end)) = MARKER_SECTIONS.iter().find(|(start, _)| *start == name) {
+                section_end = Some(end);
+                continue;
+            }
+            let known = lump.kind() != LumpKind::Other
+                || map_names.contains(name)
+                || is_marker(name)
+                || STANDARD_LUMPS.contains(&name)
+                || STANDARD_PREFIXES.iter().any(|p| name.starts_with(p));
+            if !known {
+                custom.push(lump);
+            }
+        }
+        custom
+    }
+
+    /// How many lumps of each kind the directory holds, map components
+    /// included, for a breakdown of what the WAD is made of. Kinds with no
+    /// lumps are left out. Everything that isn't a map component is
+    /// `LumpKind::Other`; `marker_lump_count` and `custom_lumps` break that
+    /// down further.
+    pub fn lump_count_by_kind(&self) -> BTreeMap<LumpKind, usize> {
+        let mut counts = BTreeMap::new();
+        for lump in self.iter_lumps() {
+            *counts.entry(lump.kind()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// How many lumps only mark a place in the directory: map markers and the
+    /// `_START` and `_END` lumps around sections.
+    pub fn marker_lump_count(&self) -> usize {
+        let map_names = self.maps().iter().map(|m| m.name()).collect::<HashSet<_>>();
+        self.iter_lumps()
+            .filter(|l| l.kind() == LumpKind::Other)
+            .filter(|l| map_names.contains(l.name()) || is_marker(l.name()))
+            .count()
+    }
+
+    /// The lumps between `S_START` and `S_END`, or `SS_START` and `SS_END`,
+    /// in directory order. Markers for sub-sections inside them are left out.
+    pub fn sprites(&self) -> Vec<&Lump> {
+        let mut sprites = vec![];
+        let mut section_end = None;
+        for lump in self.iter_lumps() {
+            let name = lump.name();
+            match section_end {
+                Some(end) if name == end => section_end = None,
+                Some(_) if !is_marker(name) => sprites.push(lump),
+                Some(_) => {}
+                None => {
+                    section_end = MARKER_SECTIONS[..2]
+                        .iter()
+                        .find(|(start, _)| *start == name)
+                        .map(|&(_, end)| end)
+                }
+            }
+        }
+        sprites
+    }
+
+    /// The IWAD's sprites the WAD replaces with its own, by lump name, in the
+    /// order the WAD has them. A total conversion replaces most of them.
+    pub fn replaced_iwad_sprites(&self, iwad: &Wad) -> Vec<String> {
+        let base = iwad
+            .sprites()
+            .iter()
+            .map(|l| l.name())
+            .collect::<HashSet<_>>();
+        let mut seen = HashSet::new();
+        self.sprites()
+            .iter()
+            .map(|l| l.name())
+            .filter(|name| base.contains(name) && seen.insert(*name))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The fraction of the IWAD's sprites, counted by name, that
+    /// `replaced_iwad_sprites` finds the WAD replacing, from 0 to 1. Zero for
+    /// an IWAD without sprites.
+    pub fn replaced_iwad_sprite_fraction(&self, iwad: &Wad) -> f64 {
+        let total = iwad
+            .sprites()
+            .iter()
+            .map(|l| l.name())
+            .collect::<HashSet<_>>()
+            .len();
+        if total == 0 {
+            return 0.0;
+        }
+        self.replaced_iwad_sprites(iwad).len() as f64 / total as f64
+    }
+
+    /// Like `custom_lumps`, leaving out lumps the IWAD has under the same name
+    /// with the same contents, which the WAD only carries along.
+    pub fn custom_lumps_against(&self, iwad: &Wad) -> Vec<&Lump> {
+        self.custom_lumps()
+            .into_iter()
+            .filter(|lump| {
+                iwad.lump_by_name(lump.name())
+                    .is_none_or(|base| base.as_bytes() != lump.as_bytes())
+            })
+            .collect()
+    }
+}
@@ -1,22 +1,1480 @@
-mod things;
-mod wad;
-
-use std::fmt::Display;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Cursor;
+use std::io::Read;
+use std::io::Write;
+use std::ops::Range;
+use std::path::Path;
 use std::path::PathBuf;
-use std::string::FromUtf8Error;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::time::Duration as StdDuration;
+use std::time::SystemTime;
 
+use chrono::Duration;
 use chrono::Local;
+use console::Term;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Input;
-use wad::Wad;
+use doom_map_stats::cache::CachedMap;
+use doom_map_stats::cache::CachedWad;
+use doom_map_stats::cache::StatsCache;
+use doom_map_stats::compat::Port;
+use doom_map_stats::connectivity::EdgeKind;
+use doom_map_stats::demo::DemoHeader;
+use doom_map_stats::diagnostics::Diagnostic;
+use doom_map_stats::diagnostics::Severity;
+use doom_map_stats::game::Game;
+use doom_map_stats::json::Json;
+use doom_map_stats::lint::LintOptions;
+use doom_map_stats::map::Map;
+use doom_map_stats::map::MapThing;
+use doom_map_stats::map::Skill;
+use doom_map_stats::map::NO_SIDEDEF;
+use doom_map_stats::pattern::glob_match;
+use doom_map_stats::picture::Picture;
+use doom_map_stats::render::FloorColor;
+use doom_map_stats::render::RenderOptions;
+use doom_map_stats::stats::binary_header;
+use doom_map_stats::stats::csv_field;
+use doom_map_stats::stats::write_binary;
+use doom_map_stats::stats::MapStats;
+use doom_map_stats::stats::ModeCounts;
+use doom_map_stats::stats::StatSet;
+use doom_map_stats::stats::ThingFlagsSummary;
+use doom_map_stats::stats::DETAILED_SECTOR_EDGES;
+use doom_map_stats::stats::DOOM_UNIT_METERS;
+use doom_map_stats::stats::JSON_SCHEMA;
+use doom_map_stats::things::ThingCategory;
+use doom_map_stats::things::ThingTable;
+use doom_map_stats::wad::ParseOptions;
+use doom_map_stats::wad::Wad;
+use doom_map_stats::Error;
+
+const USAGE: &str = "usage:
+    doom-map-stats                    prompt for WAD files to load
+    doom-map-stats <wad|dir>...       load WAD files, or every WAD in a directory;
+                                      gzip-compressed WADs are read too, and several
+                                      files end with a summary on stderr
+    doom-map-stats - [<wad|dir>...]   also read a WAD from standard input, which is
+                                      held in memory whole; --stdin does the same
+    doom-map-stats things <wad> <map> list every thing in a map
+    doom-map-stats dump-map <wad> <map>
+                                      print every decoded vertex, linedef, sidedef,
+                                      sector and thing of a map, one per line
+    doom-map-stats graph <wad> <map> [--dot]
+                                      list the sectors each sector leads to, or print
+                                      the graph in Graphviz's DOT language
+    doom-map-stats hash <wad>         print a hash of each map's vertexes, linedefs,
+                                      sidedefs and sectors, which node builds don't change
+    doom-map-stats compare-map <a.wad> <b.wad> <map>
+                                      show how a map's statistics changed from one WAD
+                                      to the other
+    doom-map-stats validate <wad> [--format <text|github>]
+                                      check a WAD for structural problems
+    doom-map-stats lint <wad> [--no-keys] [--no-exits] [--no-teleports] [--no-reachability]
+                      [--format <text|github>]
+                                      check that each map can be finished, failing
+                                      with status 4 if one can't; github prints
+                                      problems as GitHub Actions annotations
+    doom-map-stats custom <wad>... [--iwad <iwad> [--exclude-iwad-content]]
+                                      list lumps that aren't stock Doom content,
+                                      optionally leaving out copies of IWAD lumps
+    doom-map-stats sprites <wad>... --iwad <iwad>
+                                      list the IWAD sprites each WAD replaces, and
+                                      how much of the IWAD's set that is
+    doom-map-stats clones <wad>       list maps sharing a thing layout but not geometry
+    doom-map-stats demos <wad>        show which maps the built-in demos play
+    doom-map-stats missing <wad>... [--iwad <iwad>]
+                                      list flats and textures the maps use, and
+                                      patches the textures use, that no WAD defines;
+                                      `missing <wad> <iwad>` also works
+    doom-map-stats animations <wad>   list stock animations the WAD replaces, and any
+                                      frames it leaves out
+    doom-map-stats compat <wad>       guess the least capable source port that runs a WAD
+    doom-map-stats compat <wad> --verdict
+                                      sum up in one sentence which port the WAD
+                                      needs and why
+    doom-map-stats compat <wad> --matrix [--json]
+                                      grade each map as pass, warn or fail in vanilla,
+                                      Boom, MBF and ZDoom
+    doom-map-stats actors <wad>       list the WAD's DECORATE and ZSCRIPT lumps and how
+                                      many actors each declares
+    doom-map-stats gameinfo <wad>     show the IWAD and files a WAD's GAMEINFO or GAMECONF
+                                      says to load it with
+    doom-map-stats secrets <wad>      list the maps with secret exits and where they lead
+    doom-map-stats skills <wad>       compare monsters and items across skill levels
+    doom-map-stats sector-edges <wad> show how many linedefs bound each map's sectors,
+                                      and which sectors have the most
+    doom-map-stats thing-flags <wad> [--json]
+                                      count the things in each map setting each flag
+    doom-map-stats render <wad> <map> <png> [--things] [--skill <easy|medium|hard>]
+                      [--fill <flat|light|height>]
+                                      draw a map from above as a PNG image, optionally
+                                      with floors colored by flat, light or height
+    doom-map-stats extract-screens <wad> <dir>
+                                      save the title and other full-screen graphics as PNGs
+
+options:
+    -q, --quiet                       only print results; errors still go to stderr
+    -v, --verbose                     also describe what is being loaded and cached
+    --summary                         print a single summary line per WAD
+    --format <text|json|ndjson|csv|binary>
+                                      output format for per-map statistics; text, the
+                                      default, is a table of each WAD's maps with
+                                      their size, counts, format and port; ndjson
+                                      prints one JSON object per map and line, and
+                                      binary a compact versioned encoding; JSON
+                                      documents start with a schema version that
+                                      only changes when fields are renamed or removed
+    --output-dir <dir>                write <wad>/<map>.<ext> files under <dir>
+                                      instead of printing to stdout
+    --cache-dir <dir>                 remember per-map statistics in <dir> and reuse
+                                      them for WADs, or maps, that haven't changed since
+    --threads <n>                     parse up to <n> WADs at once when loading several;
+                                      defaults to the number of CPUs
+    --map <pattern>                   only show maps matching a glob such as 'MAP0*';
+                                      may be given more than once
+    --lump-range <start>:<end>        read directory entries <start> up to but not
+                                      including <end> as a single map, for WADs whose
+                                      maps aren't found otherwise
+    -r, --recursive                   also look for WADs in the directories' subdirectories
+    --max-depth <n>                   only look <n> levels of subdirectories deep;
+                                      implies --recursive
+    --follow-symlinks                 descend into symbolic links to directories, which
+                                      are skipped otherwise
+    --since <duration>                of the WADs found in directories, only read those
+                                      modified in the last <duration>, such as 7d,
+                                      12h, 30m or 1w2d
+    --stats <list>                    also compute some of monsters, secrets, area and
+                                      size, comma-separated; the structure counts are
+                                      always shown, and text shows all but area
+    --metric                          also give the size and floor area in meters, at
+                                      about 1.2 cm per map unit
+    --scale <meters>                  like --metric, with the given meters per map unit
+    --timings                         print how long each loading phase and statistic
+                                      took to stderr
+    --gwa <file>                      read GL nodes from <file>; otherwise a .gwa file
+                                      beside a WAD, with the same name, is read
+    --alpha                           accept maps from Doom's alpha and pre-release
+                                      builds, which may lack SEGS, SSECTORS, NODES
+                                      and BLOCKMAP
+    --url <url>                       also read a WAD from a plain http:// URL, held in
+                                      memory whole; only in builds with the http
+                                      feature (cargo build --features http)
+    --max-download-size <size>        give up on downloads over <size> bytes, such as
+                                      500K, 64M or 1G; defaults to 256M
+
+exit status:
+    0 on success, 2 if a file doesn't exist, 3 if a file isn't a WAD, 4 if lint
+    finds a map that can't be finished, 64 if the command line is wrong, and 1
+    for any other error";
+
+/// How much is written to stderr besides results, set by `--quiet` and
+/// `--verbose`. Errors are always written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Error,
+    Info,
+    Debug,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if enabled($level) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { log!(Level::Error, $($arg)*) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { log!(Level::Debug, $($arg)*) };
+}
+
+/// The exit status for a fatal error, so scripts can tell the common failures
+/// apart without parsing the message.
+fn exit_code(e: &Error) -> ExitCode {
+    match e {
+        Error::Io(e) if e.kind() == std::io::ErrorKind::NotFound => ExitCode::from(2),
+        Error::NotAWad(_) => ExitCode::from(3),
+        _ => ExitCode::FAILURE,
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+    // the verbosity flags apply to every subcommand, so they're taken out first
+    args.retain(|arg| {
+        let level = match arg.as_str() {
+            "-q" | "--quiet" => Level::Error,
+            "-v" | "--verbose" => Level::Debug,
+            _ => return true,
+        };
+        LEVEL.store(level as u8, Ordering::Relaxed);
+        false
+    });
+    match run(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            error!("A fatal error has occurred: {}", e);
+            exit_code(&e)
+        }
+    }
+}
+
+/// Prints the usage to stderr for a command line that can't be run, and gives
+/// the status to exit with, `EX_USAGE` from BSD's `sysexits.h`.
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::from(64)
+}
+
+fn run(args: &[String]) -> Result<ExitCode, Error> {
+    match args.first().map(String::as_str) {
+        None => interactive()?,
+        Some("things") if args.len() == 3 => list_things(&args[1], &args[2])?,
+        Some("compare-map") if args.len() == 4 => compare_map(&args[1], &args[2], &args[3])?,
+        Some("dump-map") if args.len() == 3 => dump_map(&args[1], &args[2])?,
+        Some("graph") if args.len() == 3 => print_graph(&args[1], &args[2], false)?,
+        Some("graph") if args.len() == 4 && args[3] == "--dot" => {
+            print_graph(&args[1], &args[2], true)?
+        }
+        Some("validate") if args.len() == 2 => validate(&args[1], ReportFormat::Text)?,
+        Some("validate") if args.len() == 4 && args[2] == "--format" => {
+            match ReportFormat::from_name(&args[3]) {
+                Some(format) => validate(&args[1], format)?,
+                None => return Ok(usage()),
+            }
+        }
+        Some("lint") if args.len() >= 2 => match parse_lint_options(&args[2..]) {
+            Some((options, format)) => return lint(&args[1], &options, format),
+            None => return Ok(usage()),
+        },
+        Some("custom") if args.len() >= 2 => match IwadOptions::parse(&args[1..]) {
+            Some(options) => list_custom_lumps(&options)?,
+            None => return Ok(usage()),
+        },
+        Some("sprites") if args.len() >= 2 => match IwadOptions::parse(&args[1..]) {
+            Some(options) if options.iwad.is_some() && !options.exclude_iwad_content => {
+                list_replaced_sprites(&options)?
+            }
+            _ => return Ok(usage()),
+        },
+        Some("hash") if args.len() == 2 => print_geometry_hashes(&args[1])?,
+        Some("clones") if args.len() == 2 => list_clones(&args[1])?,
+        Some("demos") if args.len() == 2 => list_demos(&args[1])?,
+        Some("missing") if args.len() >= 2 => match IwadOptions::parse(&args[1..]) {
+            Some(options) if !options.exclude_iwad_content => list_missing(&options)?,
+            _ => return Ok(usage()),
+        },
+        Some("animations") if args.len() == 2 => list_animations(&args[1])?,
+        Some("compat") if args.len() == 2 => show_compat(&args[1])?,
+        Some("compat") if args.len() == 3 && args[2] == "--verdict" => {
+            println!("{}", Wad::from_file(&args[1])?.port_verdict())
+        }
+        Some("compat") if args.len() == 3 && args[2] == "--matrix" => {
+            show_compat_matrix(&args[1], false)?
+        }
+        Some("compat") if args.len() == 4 && args[2] == "--matrix" && args[3] == "--json" => {
+            show_compat_matrix(&args[1], true)?
+        }
+        Some("actors") if args.len() == 2 => list_actor_definitions(&args[1])?,
+        Some("gameinfo") if args.len() == 2 => show_gameinfo(&args[1])?,
+        Some("secrets") if args.len() == 2 => list_secret_paths(&args[1])?,
+        Some("skills") if args.len() == 2 => show_skill_profiles(&args[1])?,
+        Some("sector-edges") if args.len() == 2 => show_sector_edges(&args[1])?,
+        Some("thing-flags") if args.len() == 2 => show_thing_flags(&args[1], false)?,
+        Some("thing-flags") if args.len() == 3 && args[2] == "--json" => {
+            show_thing_flags(&args[1], true)?
+        }
+        Some("extract-screens") if args.len() == 3 => extract_screens(&args[1], &args[2])?,
+        Some("render") if args.len() >= 4 => match parse_render_options(&args[4..]) {
+            Some(options) => render(&args[1], &args[2], &args[3], options)?,
+            None => return Ok(usage()),
+        },
+        Some(_) => match ScanOptions::parse(args) {
+            Some(options) => scan(&options)?,
+            None => return Ok(usage()),
+        },
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Format {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    Binary,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "ndjson" => Some(Format::Ndjson),
+            "csv" => Some(Format::Csv),
+            "binary" => Some(Format::Binary),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Text => "txt",
+            Format::Json => "json",
+            Format::Ndjson => "ndjson",
+            Format::Csv => "csv",
+            Format::Binary => "bin",
+        }
+    }
+
+    /// A complete single-map document, as written to an output directory.
+    fn render_map(self, file: &Path, stats: &MapStats) -> Vec<u8> {
+        let text = match self {
+            Format::Text => {
+                let mut line = format!(
+                    "    {}: {} things, {} linedefs, {} sectors",
+                    stats.name, stats.things, stats.linedefs, stats.sectors
+                );
+                if let Some(n) = stats.monsters {
+                    line += &format!(", {} monsters", n);
+                }
+                if let Some(n) = stats.secrets {
+                    line += &format!(", {} secrets", n);
+                }
+                match (stats.scaled_size(), stats.size) {
+                    (Some((width, height)), _) => {
+                        line += &format!(", {:.1} by {:.1} meters", width, height)
+                    }
+                    (None, Some((width, height))) => {
+                        line += &format!(", {} by {} units", width, height)
+                    }
+                    (None, None) => {}
+                }
+                match (stats.scaled_area(), stats.area) {
+                    (Some(a), _) => line += &format!(", {:.0} square meters of floor", a),
+                    (None, Some(a)) => line += &format!(", {:.0} square units of floor", a),
+                    (None, None) => {}
+                }
+                line + "\n"
+            }
+            Format::Json => format!("{}\n", with_schema(stats.to_json())),
+            Format::Ndjson => format!("{}\n", ndjson_record(file, &stats.name, Ok(stats))),
+            Format::Csv => format!(
+                "{}\n{}\n",
+                MapStats::csv_header(stats.computed(), stats.scale.is_some()),
+                stats.to_csv()
+            ),
+            Format::Binary => {
+                let mut out = binary_header();
+                write_binary(&mut out, &file.to_string_lossy(), &[stats]);
+                return out;
+            }
+        };
+        text.into_bytes()
+    }
+}
+
+#[derive(Default)]
+struct ScanOptions {
+    paths: Vec<String>,
+    maps: Vec<String>,
+    summary: bool,
+    format: Format,
+    output_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    threads: Option<usize>,
+    stats: StatSet,
+    /// Meters per map unit, for reporting sizes in meters.
+    scale: Option<f64>,
+    lump_range: Option<Range<usize>>,
+    recursive: bool,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+    /// Skip WADs in directories modified longer ago than this.
+    since: Option<StdDuration>,
+    timings: bool,
+    gwa: Option<PathBuf>,
+    alpha: bool,
+    /// The largest download `--url` accepts, if not the default.
+    max_download_size: Option<u64>,
+}
+
+impl ScanOptions {
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut options = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--map" => options.maps.push(args.next()?.clone()),
+                "--summary" => options.summary = true,
+                "--format" => options.format = Format::from_name(args.next()?)?,
+                "--output-dir" => options.output_dir = Some(PathBuf::from(args.next()?)),
+                "--cache-dir" => options.cache_dir = Some(PathBuf::from(args.next()?)),
+                "--stats" => options.stats |= StatSet::from_names(args.next()?)?,
+                "--metric" => options.scale = Some(DOOM_UNIT_METERS),
+                "--scale" => {
+                    options.scale = Some(args.next()?.parse().ok().filter(|&s: &f64| s > 0.0)?)
+                }
+                "--lump-range" => {
+                    let (start, end) = args.next()?.split_once(':')?;
+                    let range = start.parse().ok()?..end.parse().ok()?;
+                    if range.is_empty() {
+                        return None;
+                    }
+                    options.lump_range = Some(range);
+                }
+                "-r" | "--recursive" => options.recursive = true,
+                "--max-depth" => options.max_depth = Some(args.next()?.parse().ok()?),
+                "--follow-symlinks" => options.follow_symlinks = true,
+                "--since" => options.since = Some(parse_duration(args.next()?)?),
+                "--timings" => options.timings = true,
+                "--gwa" => options.gwa = Some(PathBuf::from(args.next()?)),
+                "--alpha" => options.alpha = true,
+                #[cfg(feature = "http")]
+                "--url" => options.paths.push(args.next()?.clone()),
+                "--max-download-size" => {
+                    options.max_download_size = Some(parse_size(args.next()?)?)
+                }
+                "--threads" => {
+                    options.threads = Some(args.next()?.parse().ok().filter(|&n| n > 0)?)
+                }
+                "-" | "--stdin" => options.paths.push(STDIN_PATH.to_string()),
+                a if a.starts_with('-') => return None,
+                _ => options.paths.push(arg.clone()),
+            }
+        }
+        if options.scale.is_some() {
+            options.stats |= StatSet::SIZE | StatSet::AREA;
+        }
+        // the text table shows these, and none of them takes long
+        if options.format == Format::Text {
+            options.stats |= StatSet::MONSTERS | StatSet::SECRETS | StatSet::SIZE;
+        }
+        Some(options).filter(|o| !o.paths.is_empty())
+    }
+
+    /// How many levels of subdirectories to look for WADs in.
+    fn depth(&self) -> usize {
+        match self.max_depth {
+            Some(depth) => depth,
+            None if self.recursive => usize::MAX,
+            None => 0,
+        }
+    }
+
+    fn wants_map(&self, name: &str) -> bool {
+        self.maps.is_empty() || self.maps.iter().any(|p| glob_match(p, name))
+    }
+}
+
+/// A progress bar on stderr, drawn only when stderr is a terminal so that
+/// redirected output stays clean, and never with `--quiet`.
+struct Progress {
+    term: Option<Term>,
+    label: String,
+    drawn: usize,
+}
+
+impl Progress {
+    const WIDTH: usize = 30;
+
+    fn new(label: impl Into<String>) -> Self {
+        Self {
+            term: Some(Term::stderr())
+                .filter(|_| enabled(Level::Info) && console::user_attended_stderr()),
+            label: label.into(),
+            drawn: usize::MAX,
+        }
+    }
+
+    fn set(&mut self, done: usize, total: usize) {
+        let term = match &self.term {
+            Some(t) => t,
+            None => return,
+        };
+        let filled = (done.min(total) * Self::WIDTH)
+            .checked_div(total)
+            .unwrap_or(Self::WIDTH);
+        // only redraw when the bar visibly changes
+        if filled == self.drawn {
+            return;
+        }
+        self.drawn = filled;
+        let _ = term.clear_line();
+        let _ = term.write_str(&format!(
+            "[{}{}] {}/{} {}",
+            "#".repeat(filled),
+            "-".repeat(Self::WIDTH - filled),
+            done,
+            total,
+            self.label
+        ));
+    }
+
+    fn finish(&mut self) {
+        if let Some(term) = &self.term {
+            let _ = term.clear_line();
+        }
+        self.drawn = usize::MAX;
+    }
+}
+
+/// Parses a duration such as `7d`, `90m` or `1w2d`: whole numbers, each
+/// followed by `s`, `m`, `h`, `d` or `w`.
+fn parse_duration(text: &str) -> Option<StdDuration> {
+    let mut seconds = 0u64;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let n: u64 = rest[..digits].parse().ok()?;
+        let unit = match rest[digits..].chars().next()? {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        seconds = seconds.checked_add(n.checked_mul(unit)?)?;
+        rest = &rest[digits + 1..];
+    }
+    Some(StdDuration::from_secs(seconds)).filter(|_| !text.is_empty())
+}
+
+/// Parses a size in bytes such as `4096`, `500K`, `64M` or `1G`, the
+/// suffixes counting in powers of 1024.
+fn parse_size(text: &str) -> Option<u64> {
+    let (digits, unit) = match text.char_indices().last()? {
+        (i, 'K' | 'k') => (&text[..i], 1 << 10),
+        (i, 'M' | 'm') => (&text[..i], 1 << 20),
+        (i, 'G' | 'g') => (&text[..i], 1 << 30),
+        _ => (text, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+// files whose modification time can't be read are kept
+fn modified_within(path: &Path, since: StdDuration) -> bool {
+    let age = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default()
+        });
+    age.map_or(true, |age| age <= since)
+}
+
+fn wad_files(options: &ScanOptions) -> Result<Vec<PathBuf>, Error> {
+    let mut files = vec![];
+    for arg in &options.paths {
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            let mut visited = vec![];
+            find_wad_files(&path, 0, options, &mut visited, &mut files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Adds the WADs in `dir` to `files`, walking subdirectories in name order
+/// down to `options.depth()` so that reports come out the same every run.
+fn find_wad_files(
+    dir: &Path,
+    depth: usize,
+    options: &ScanOptions,
+    visited: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Error> {
+    // a followed link can lead back to a directory already being walked
+    let canonical = fs::canonicalize(dir)?;
+    if visited.contains(&canonical) {
+        debug!("{}: skipping a directory loop", dir.to_string_lossy());
+        return Ok(());
+    }
+    visited.push(canonical);
+
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if is_wad_file(&path) {
+            if options
+                .since
+                .is_none_or(|since| modified_within(&path, since))
+            {
+                files.push(path);
+            }
+        } else if path.is_dir() {
+            let is_link = fs::symlink_metadata(&path)?.file_type().is_symlink();
+            if depth >= options.depth() {
+                debug!("{}: skipping a subdirectory", path.to_string_lossy());
+            } else if is_link && !options.follow_symlinks {
+                debug!("{}: skipping a symbolic link", path.to_string_lossy());
+            } else {
+                find_wad_files(&path, depth + 1, options, visited, files)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("pk3"))
+        {
+            debug!(
+                "{}: skipping, PK3 archives aren't supported",
+                path.to_string_lossy()
+            );
+        } else {
+            debug!("{}: skipping, not a WAD", path.to_string_lossy());
+        }
+    }
+    visited.pop();
+    Ok(())
+}
+
+fn is_wad_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_ascii_lowercase();
+    path.is_file() && (name.ends_with(".wad") || name.ends_with(".wad.gz"))
+}
+
+/// What the text table says about a map's format: whether it's in Hexen's
+/// format, and the least capable port its own data needs.
+#[derive(Debug, Clone, Copy)]
+struct MapFormat {
+    hexen: bool,
+    port: Port,
+}
+
+/// What `report` needs to know about a WAD, whether freshly parsed or cached.
+struct Loaded {
+    lumps: usize,
+    maps: Vec<(String, Result<MapStats, Error>)>,
+    /// Each map's `Map::lumps_hash`, in the order of `maps`, for the cache.
+    hashes: Vec<String>,
+    /// Each map's format, in the order of `maps`. Maps read from a lump
+    /// range aren't in the compatibility matrix, so theirs is unknown.
+    formats: Vec<Option<MapFormat>>,
+    /// Each phase of loading and computing statistics, with how long it took,
+    /// when asked for with `--timings`. Cached results have none.
+    timings: Vec<(String, StdDuration)>,
+}
+
+fn cached(file: &Path, cache: &Option<StatsCache>, stats: StatSet) -> Option<Loaded> {
+    let cached = cache
+        .as_ref()?
+        .get(file)
+        .filter(|c| c.maps.iter().all(|m| m.stats.computed() == stats))?;
+    debug!("{}: using cached statistics", file.to_string_lossy());
+    Some(Loaded {
+        lumps: cached.lumps,
+        maps: cached
+            .maps
+            .iter()
+            .map(|m| (m.stats.name.clone(), Ok(m.stats.clone())))
+            .collect(),
+        hashes: cached.maps.iter().map(|m| m.hash.clone()).collect(),
+        formats: cached
+            .maps
+            .iter()
+            .map(|m| {
+                Some(MapFormat {
+                    hexen: m.hexen_format,
+                    port: m.port,
+                })
+            })
+            .collect(),
+        timings: vec![],
+    })
+}
+
+/// The path standing for standard input, as given on the command line.
+const STDIN_PATH: &str = "-";
+
+fn is_stdin(file: &Path) -> bool {
+    file == Path::new(STDIN_PATH)
+}
+
+/// The largest download `--url` accepts without `--max-download-size`.
+#[cfg(feature = "http")]
+const DEFAULT_MAX_DOWNLOAD_SIZE: u64 = 256 << 20;
+
+fn is_url(file: &Path) -> bool {
+    let file = file.to_string_lossy();
+    file.starts_with("http://") || file.starts_with("https://")
+}
+
+// neither standard input nor a URL, so there may be a cache entry or a GWA
+// file beside it
+fn is_local(file: &Path) -> bool {
+    !is_stdin(file) && !is_url(file)
+}
+
+#[cfg(feature = "http")]
+fn download(url: &Path, options: &ScanOptions) -> Result<Vec<u8>, Error> {
+    let max_size = options
+        .max_download_size
+        .unwrap_or(DEFAULT_MAX_DOWNLOAD_SIZE);
+    let data = doom_map_stats::http::get(&url.to_string_lossy(), max_size)?;
+    debug!("{}: downloaded {} bytes", url.to_string_lossy(), data.len());
+    Ok(data)
+}
+
+#[cfg(not(feature = "http"))]
+fn download(url: &Path, _: &ScanOptions) -> Result<Vec<u8>, Error> {
+    Err(Error::Download(
+        url.to_string_lossy().into_owned(),
+        "this build doesn't include the http feature".to_string(),
+    ))
+}
+
+/// Parses a WAD and computes its maps' statistics, reusing those in
+/// `previous`, the cache's entry from before the file changed, for maps whose
+/// lumps haven't.
+fn parse(
+    file: &Path,
+    options: &ScanOptions,
+    previous: Option<&CachedWad>,
+    progress: impl FnMut(usize, usize),
+) -> Result<Loaded, Error> {
+    debug!("{}: parsing", file.to_string_lossy());
+    let parse_options = ParseOptions {
+        // the point of a lump range is to get at maps that normal parsing
+        // can't find, so problems elsewhere in the directory are let through
+        strict: options.lump_range.is_none(),
+        collect_diagnostics: false,
+        alpha: options.alpha,
+        preserve_case: false,
+        hash_iwad: false,
+    };
+    let mut wad = if is_stdin(file) {
+        // parsing seeks around the directory, so the stream is read into
+        // memory first; files are read straight from disk instead
+        let mut data = vec![];
+        std::io::stdin().lock().read_to_end(&mut data)?;
+        debug!("{}: read {} bytes", file.to_string_lossy(), data.len());
+        Wad::from_reader_with_options(Cursor::new(data), parse_options)?
+    } else if is_url(file) {
+        Wad::from_reader_with_options(Cursor::new(download(file, options)?), parse_options)?
+    } else if parse_options == ParseOptions::default() {
+        Wad::from_file_with_progress(file, progress)?
+    } else {
+        Wad::from_file_with_options(file, parse_options)?
+    };
+    let report = wad.parse_report();
+    debug!(
+        "{}: read {} bytes; lumps: {}, maps: {}",
+        file.to_string_lossy(),
+        report.bytes_read,
+        report.lumps_by_kind.values().sum::<usize>(),
+        report.maps
+    );
+    if let Some(range) = &options.lump_range {
+        let map = wad.map_from_range(range.clone())?;
+        return Ok(compute_stats(&wad, &[&map], options, None));
+    }
+    let sibling = || {
+        Some(file)
+            .filter(|f| is_local(f))
+            .and_then(Wad::sibling_gwa)
+    };
+    if let Some(gwa) = options.gwa.clone().or_else(sibling) {
+        debug!("{}: reading GL nodes", gwa.to_string_lossy());
+        wad = wad.with_gwa(gwa)?;
+        for map in wad.maps() {
+            if let Some(version) = map.gl_nodes_version() {
+                debug!("{}: version {} GL nodes", map.name(), version);
+            }
+        }
+    }
+    // stats skip ZDoom's TITLEMAP, which plays behind the title screen
+    let maps = wad.playable_maps().collect::<Vec<_>>();
+    Ok(compute_stats(&wad, &maps, options, previous))
+}
+
+fn compute_stats(
+    wad: &Wad,
+    maps: &[&Map],
+    options: &ScanOptions,
+    previous: Option<&CachedWad>,
+) -> Loaded {
+    let mut timings = vec![];
+    if options.timings {
+        let load = wad.load_timings();
+        timings.push(("directory read".to_string(), load.directory));
+        timings.push(("lump data read".to_string(), load.lump_data));
+        timings.push(("map assembly".to_string(), load.map_assembly));
+    }
+    let hashes = maps.iter().map(|m| m.lumps_hash()).collect::<Vec<_>>();
+    let table = wad.thing_table();
+    // only the text table and the cache show formats, and the matrix
+    // decodes every map's things and linedefs to build
+    let matrix = if options.format == Format::Text || options.cache_dir.is_some() {
+        Some(wad.compatibility_matrix())
+    } else {
+        None
+    };
+    let formats = maps
+        .iter()
+        .map(|m| {
+            let row = matrix
+                .as_ref()?
+                .rows
+                .iter()
+                .find(|row| row.map == m.name())?;
+            Some(MapFormat {
+                hexen: m.is_hexen_format(),
+                port: row.minimum_port,
+            })
+        })
+        .collect();
+    let mut reused = 0;
+    let maps = maps
+        .iter()
+        .zip(&hashes)
+        .map(|(m, hash)| {
+            let cached = previous.and_then(|p| p.reusable(m.name(), hash, options.stats));
+            if let Some(stats) = cached {
+                reused += 1;
+                return (m.name().to_string(), Ok(stats.clone()));
+            }
+            let (stats, parts) = m.stats_timed(options.stats, table);
+            if options.timings {
+                timings.extend(
+                    parts
+                        .into_iter()
+                        .map(|(part, time)| (format!("{} {}", m.name(), part), time)),
+                );
+            }
+            (m.name().to_string(), stats)
+        })
+        .collect::<Vec<_>>();
+    if reused > 0 {
+        debug!(
+            "{}: reused cached statistics for {} of {} maps",
+            wad.source().to_string_lossy(),
+            reused,
+            maps.len()
+        );
+    }
+    Loaded {
+        lumps: wad.length(),
+        maps,
+        hashes,
+        formats,
+        timings,
+    }
+}
+
+/// Writes `--timings` output for a file to stderr as one block, so that
+/// blocks from several files don't interleave.
+fn print_timings(file: &Path, loaded: &Loaded) {
+    let mut table = format!("{}: timings\n", file.to_string_lossy());
+    if loaded.timings.is_empty() {
+        table.push_str("    statistics were cached\n");
+    }
+    for (phase, time) in &loaded.timings {
+        table.push_str(&format!(
+            "    {:<24}  {:>10.6} s\n",
+            phase,
+            time.as_secs_f64()
+        ));
+    }
+    eprint!("{}", table);
+}
+
+fn remember(file: &Path, loaded: &Loaded, cache: &mut Option<StatsCache>) -> Result<(), Error> {
+    // maps that fail to decode are reported again next time rather than
+    // cached, and standard input can't be told apart from one run to the next
+    if let Some(cache) = cache.as_mut().filter(|_| is_local(file)) {
+        let stats = loaded
+            .maps
+            .iter()
+            .zip(&loaded.hashes)
+            .zip(&loaded.formats)
+            .map(|(((_, s), hash), format)| {
+                let format = (*format)?;
+                Some(CachedMap {
+                    hash: hash.clone(),
+                    stats: s.as_ref().ok()?.clone(),
+                    hexen_format: format.hexen,
+                    port: format.port,
+                })
+            })
+            .collect();
+        if let Some(stats) = stats {
+            cache.insert(
+                file,
+                CachedWad {
+                    lumps: loaded.lumps,
+                    maps: stats,
+                },
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn load(
+    file: &Path,
+    cache: &mut Option<StatsCache>,
+    options: &ScanOptions,
+    progress: impl FnMut(usize, usize),
+) -> Result<Loaded, Error> {
+    if let Some(loaded) = cached(file, cache, options.stats) {
+        return Ok(loaded);
+    }
+    let previous = cache.as_ref().and_then(|c| c.previous(file));
+    let loaded = parse(file, options, previous, progress)?;
+    remember(file, &loaded, cache)?;
+    Ok(loaded)
+}
+
+/// What a batch run found, written to stderr once every file is reported.
+#[derive(Default)]
+struct BatchSummary {
+    files: usize,
+    failures: Vec<(PathBuf, String)>,
+    maps: usize,
+    /// Maps that were found but whose statistics couldn't be read.
+    failed_maps: usize,
+    things: usize,
+    linedefs: usize,
+    sectors: usize,
+    monsters: usize,
+    secrets: usize,
+}
+
+impl BatchSummary {
+    fn add(&mut self, loaded: &Loaded, options: &ScanOptions) {
+        self.files += 1;
+        for (name, stats) in &loaded.maps {
+            if !options.wants_map(name) {
+                continue;
+            }
+            self.maps += 1;
+            match stats {
+                Ok(stats) => {
+                    self.things += stats.things;
+                    self.linedefs += stats.linedefs;
+                    self.sectors += stats.sectors;
+                    self.monsters += stats.monsters.unwrap_or(0);
+                    self.secrets += stats.secrets.unwrap_or(0);
+                }
+                Err(_) => self.failed_maps += 1,
+            }
+        }
+    }
+
+    fn fail(&mut self, file: &Path, e: &Error) {
+        self.files += 1;
+        self.failures.push((file.to_path_buf(), e.to_string()));
+    }
+
+    fn print(&self, options: &ScanOptions) {
+        let mut text = format!(
+            "Scanned {} WADs, {} failed, {} maps",
+            self.files,
+            self.failures.len(),
+            self.maps
+        );
+        if self.failed_maps > 0 {
+            text += &format!(" ({} unreadable)", self.failed_maps);
+        }
+        text += &format!(
+            "\n    {} things, {} linedefs, {} sectors",
+            self.things, self.linedefs, self.sectors
+        );
+        if options.stats.contains(StatSet::MONSTERS) {
+            text += &format!(", {} monsters", self.monsters);
+        }
+        if options.stats.contains(StatSet::SECRETS) {
+            text += &format!(", {} secrets", self.secrets);
+        }
+        for (file, e) in &self.failures {
+            text += &format!("\n    failed: {}: {}", file.to_string_lossy(), e);
+        }
+        eprintln!("{}", text);
+    }
+}
+
+/// Loads every file, parsing the ones that aren't cached on `threads` worker
+/// threads, and reports them in input order as soon as each one and all of
+/// those before it are done. A file that fails to load is reported and skipped,
+/// and a summary of the whole batch follows.
+fn scan_batch(
+    files: &[PathBuf],
+    cache: &mut Option<StatsCache>,
+    threads: usize,
+    options: &ScanOptions,
+) -> Result<(), Error> {
+    let mut results = files
+        .iter()
+        .map(|file| {
+            let load_start = Local::now();
+            cached(file, cache, options.stats)
+                .map(|loaded| (Ok::<_, Error>(loaded), Local::now() - load_start, false))
+        })
+        .collect::<Vec<_>>();
+    let pending = (0..files.len())
+        .filter(|&i| results[i].is_none())
+        .collect::<Vec<_>>();
+    // the workers can't borrow the cache while finished files are added to
+    // it, so they get copies of the entries they can reuse maps from
+    let previous = pending
+        .iter()
+        .map(|&i| cache.as_ref().and_then(|c| c.previous(&files[i])).cloned())
+        .collect::<Vec<_>>();
+
+    let mut progress = Progress::new("WADs");
+    let mut summary = BatchSummary::default();
+    let mut done = files.len() - pending.len();
+    let mut reported = 0;
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..threads.min(pending.len()) {
+            let sender = sender.clone();
+            let (next, pending, previous) = (&next, &pending, &previous);
+            scope.spawn(move || loop {
+                let n = next.fetch_add(1, Ordering::Relaxed);
+                let i = match pending.get(n) {
+                    Some(&i) => i,
+                    None => break,
+                };
+                let load_start = Local::now();
+                let loaded = parse(&files[i], options, previous[n].as_ref(), |_, _| {});
+                if sender.send((i, loaded, Local::now() - load_start)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut received = receiver.into_iter();
+        loop {
+            while let Some(Some((loaded, duration, fresh))) =
+                results.get_mut(reported).map(Option::take)
+            {
+                let file = &files[reported];
+                reported += 1;
+                progress.finish();
+                match loaded {
+                    Ok(loaded) => {
+                        if fresh {
+                            remember(file, &loaded, cache)?;
+                        }
+                        summary.add(&loaded, options);
+                        report(file, &loaded, duration, options)?
+                    }
+                    Err(e) => {
+                        summary.fail(file, &e);
+                        if options.format == Format::Ndjson {
+                            println!(
+                                "{}",
+                                with_schema(Json::object(vec![
+                                    ("file", file.to_string_lossy().into_owned().into()),
+                                    ("error", e.to_string().into()),
+                                ]))
+                            )
+                        } else {
+                            error!("{}: {}", file.to_string_lossy(), e)
+                        }
+                    }
+                }
+            }
+            progress.set(done, files.len());
+            match received.next() {
+                Some((i, loaded, duration)) => {
+                    results[i] = Some((loaded, duration, true));
+                    done += 1;
+                }
+                None => break,
+            }
+        }
+        Ok::<_, Error>(())
+    })?;
+    progress.finish();
+    if enabled(Level::Info) {
+        summary.print(options);
+    }
+    Ok(())
+}
+
+fn scan(options: &ScanOptions) -> Result<(), Error> {
+    let files = wad_files(options)?;
+    debug!("found {} WAD files", files.len());
+    if options.summary {
+        for file in &files {
+            match Wad::from_file(file) {
+                Ok(wad) => println!("{}", wad.summary_line()),
+                Err(e) => error!("{}: {}", file.to_string_lossy(), e),
+            }
+        }
+        return Ok(());
+    }
+    if options.output_dir.is_none() {
+        match options.format {
+            Format::Csv => println!(
+                "file,{}",
+                MapStats::csv_header(options.stats, options.scale.is_some())
+            ),
+            Format::Binary => std::io::stdout().write_all(&binary_header())?,
+            _ => {}
+        }
+    }
+    // the cache holds the maps parsing finds, not forced lump ranges
+    let mut cache = match &options.cache_dir {
+        Some(dir) if options.lump_range.is_none() => Some(StatsCache::open(dir)?),
+        _ => None,
+    };
+
+    if files.len() == 1 {
+        let mut progress = Progress::new(files[0].to_string_lossy());
+        let load_start = Local::now();
+        let loaded = load(&files[0], &mut cache, options, |done, total| {
+            progress.set(done, total)
+        });
+        progress.finish();
+        report(&files[0], &loaded?, Local::now() - load_start, options)?;
+    } else {
+        let threads = options
+            .threads
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+        scan_batch(&files, &mut cache, threads, options)?;
+    }
+
+    if let Some(cache) = &mut cache {
+        cache.save()?;
+        debug!("saved the statistics cache");
+    }
+    Ok(())
+}
+
+fn report(
+    file: &Path,
+    loaded: &Loaded,
+    load_duration: Duration,
+    options: &ScanOptions,
+) -> Result<(), Error> {
+    if options.format == Format::Text && enabled(Level::Info) {
+        print_load(file, loaded, load_duration);
+    }
+    if options.timings {
+        print_timings(file, loaded);
+    }
+
+    let stats = loaded
+        .maps
+        .iter()
+        .zip(&loaded.formats)
+        .filter(|((name, _), _)| options.wants_map(name))
+        .map(|((name, stats), &format)| {
+            let stats = stats.as_ref().map(|s| s.clone().with_scale(options.scale));
+            (name, stats, format)
+        })
+        .collect::<Vec<_>>();
+    if let Some(dir) = &options.output_dir {
+        let stem = file
+            .file_stem()
+            .unwrap_or(file.as_os_str())
+            .to_string_lossy();
+        let dir = dir.join(sanitize_file_name(&stem));
+        fs::create_dir_all(&dir)?;
+        for (name, stats, _) in &stats {
+            let path = dir.join(format!(
+                "{}.{}",
+                sanitize_file_name(name),
+                options.format.extension()
+            ));
+            match stats {
+                Ok(stats) => fs::write(path, options.format.render_map(file, stats))?,
+                Err(e) => error!("{}: {}: {}", file.to_string_lossy(), name, e),
+            }
+        }
+        return Ok(());
+    }
+
+    match options.format {
+        Format::Text => {
+            for (name, stats, _) in &stats {
+                if let Err(e) = stats {
+                    error!("{}: {}: {}", file.to_string_lossy(), name, e);
+                }
+            }
+            if stats.iter().any(|(_, stats, _)| stats.is_ok()) {
+                print!("{}", map_table(&stats).render("    "));
+            }
+        }
+        Format::Json => {
+            let maps = stats
+                .iter()
+                .map(|(name, stats, _)| match stats {
+                    Ok(stats) => stats.to_json(),
+                    Err(e) => Json::object(vec![
+                        ("map", name.as_str().into()),
+                        ("error", e.to_string().into()),
+                    ]),
+                })
+                .collect();
+            let document = with_schema(Json::object(vec![
+                ("file", file.to_string_lossy().into_owned().into()),
+                ("maps", Json::Array(maps)),
+            ]));
+            println!("{}", document);
+        }
+        Format::Ndjson => {
+            for (name, stats, _) in &stats {
+                println!(
+                    "{}",
+                    ndjson_record(file, name, stats.as_ref().map_err(|e| *e))
+                );
+            }
+        }
+        Format::Csv => {
+            for (name, stats, _) in &stats {
+                match stats {
+                    Ok(stats) => {
+                        println!("{},{}", csv_field(&file.to_string_lossy()), stats.to_csv())
+                    }
+                    Err(e) => error!("{}: {}: {}", file.to_string_lossy(), name, e),
+                }
+            }
+        }
+        Format::Binary => {
+            let mut out = vec![];
+            let mut maps = vec![];
+            for (name, stats, _) in &stats {
+                match stats {
+                    Ok(stats) => maps.push(stats),
+                    Err(e) => error!("{}: {}: {}", file.to_string_lossy(), name, e),
+                }
+            }
+            write_binary(&mut out, &file.to_string_lossy(), &maps);
+            std::io::stdout().write_all(&out)?;
+        }
+    }
+    Ok(())
+}
+
+/// One line of `--format ndjson` output: the map's statistics, or the error
+/// that stopped them from being read, tagged with the WAD's path.
+fn ndjson_record(file: &Path, name: &str, stats: Result<&MapStats, &Error>) -> Json {
+    let mut fields = vec![(
+        "file".to_string(),
+        file.to_string_lossy().into_owned().into(),
+    )];
+    match stats {
+        Ok(stats) => {
+            if let Json::Object(map_fields) = stats.to_json() {
+                fields.extend(map_fields);
+            }
+        }
+        Err(e) => {
+            fields.push(("map".to_string(), name.into()));
+            fields.push(("error".to_string(), e.to_string().into()));
+        }
+    }
+    with_schema(Json::Object(fields))
+}
+
+/// Puts the `schema` field first in a top-level JSON document.
+fn with_schema(document: Json) -> Json {
+    match document {
+        Json::Object(mut fields) => {
+            fields.insert(0, ("schema".to_string(), JSON_SCHEMA.into()));
+            Json::Object(fields)
+        }
+        other => other,
+    }
+}
+
+/// Replaces characters that aren't safe in file names on every common
+/// filesystem, and avoids the device names Windows reserves.
+fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    let stem = sanitized
+        .split('.')
+        .next()
+        .unwrap_or("")
+        .to_ascii_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit());
+    if reserved || sanitized.is_empty() || sanitized.starts_with('.') {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// Plain-text columns under a header row, each as wide as its widest cell and
+/// separated by two spaces.
+struct Table {
+    /// Each column's header, and whether it's numeric and right-aligned.
+    columns: Vec<(String, bool)>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(columns: Vec<(String, bool)>) -> Self {
+        Self {
+            columns,
+            rows: vec![],
+        }
+    }
+
+    fn push(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    /// The table with every line indented by `indent` and ending in a line
+    /// break. The last column isn't padded.
+    fn render(&self, indent: &str) -> String {
+        let width = |c: usize| {
+            self.rows
+                .iter()
+                .map(|row| row[c].chars().count())
+                .chain(std::iter::once(self.columns[c].0.chars().count()))
+                .max()
+                .unwrap_or(0)
+        };
+        let widths = (0..self.columns.len()).map(width).collect::<Vec<_>>();
+        let header = self.columns.iter().map(|(h, _)| h.clone()).collect();
+        let mut text = String::new();
+        for row in std::iter::once(&header).chain(&self.rows) {
+            let cells = row
+                .iter()
+                .zip(&self.columns)
+                .zip(&widths)
+                .map(|((cell, &(_, numeric)), &width)| {
+                    if numeric {
+                        format!("{:>1$}", cell, width)
+                    } else {
+                        format!("{:<1$}", cell, width)
+                    }
+                })
+                .collect::<Vec<_>>();
+            text += indent;
+            text += cells.join("  ").trim_end();
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// The default text report: a row for each map with its size, structure
+/// counts, monsters and secrets, its floor area if computed, and its format
+/// and the least capable port it needs.
+fn map_table(rows: &[(&String, Result<MapStats, &Error>, Option<MapFormat>)]) -> Table {
+    let scaled = rows
+        .iter()
+        .any(|(_, stats, _)| stats.as_ref().is_ok_and(|s| s.scale.is_some()));
+    let area = rows
+        .iter()
+        .any(|(_, stats, _)| stats.as_ref().is_ok_and(|s| s.area.is_some()));
+    let mut columns = vec![
+        ("map", false),
+        (if scaled { "size (m)" } else { "size" }, false),
+        ("things", true),
+        ("monsters", true),
+        ("secrets", true),
+        ("linedefs", true),
+        ("sectors", true),
+    ];
+    if area {
+        columns.push((if scaled { "area (m²)" } else { "area" }, true));
+    }
+    columns.extend([("format", false), ("port", false)]);
+    let mut table = Table::new(
+        columns
+            .iter()
+            .map(|&(name, numeric)| (name.to_string(), numeric))
+            .collect(),
+    );
 
-fn main() {
-    if let Err(e) = run() {
-        println!("A fatal error has occurred: {}", e);
+    let count = |n: Option<usize>| n.map_or("-".to_string(), |n| n.to_string());
+    for (name, stats, format) in rows {
+        let stats = match stats {
+            Ok(stats) => stats,
+            Err(_) => continue,
+        };
+        let size = match (stats.scaled_size(), stats.size) {
+            (Some((width, height)), _) => format!("{:.1} x {:.1}", width, height),
+            (None, Some((width, height))) => format!("{} x {}", width, height),
+            (None, None) => "-".to_string(),
+        };
+        let mut row = vec![
+            name.to_string(),
+            size,
+            stats.things.to_string(),
+            count(stats.monsters),
+            count(stats.secrets),
+            stats.linedefs.to_string(),
+            stats.sectors.to_string(),
+        ];
+        if area {
+            row.push(match (stats.scaled_area(), stats.area) {
+                (Some(a), _) | (None, Some(a)) => format!("{:.0}", a),
+                (None, None) => "-".to_string(),
+            });
+        }
+        match format {
+            Some(format) => row.extend([
+                if format.hexen { "Hexen" } else { "Doom" }.to_string(),
+                format.port.to_string(),
+            ]),
+            None => row.extend(["-".to_string(), "-".to_string()]),
+        }
+        table.push(row);
     }
+    table
 }
 
-fn run() -> Result<(), Error> {
+fn print_load(file: &Path, loaded: &Loaded, load_duration: Duration) {
+    println!(
+        "{}: {} maps, {} lumps loaded in {:.6} seconds",
+        file.to_string_lossy(),
+        loaded.maps.len(),
+        loaded.lumps,
+        seconds(load_duration),
+    );
+}
+
+// a negative duration, from the clock going backwards, counts as none
+fn seconds(duration: Duration) -> f64 {
+    duration.to_std().map_or(0.0, |d| d.as_secs_f64())
+}
+
+fn interactive() -> Result<(), Error> {
     loop {
         let wad_name = Input::<String>::with_theme(&ColorfulTheme::default())
             .with_prompt("Enter a WAD file name")
@@ -33,31 +1491,937 @@ fn run() -> Result<(), Error> {
         println!(
             "Loaded {} lumps in {:6} seconds (avg. {:.3} lumps/sec)",
             wad.length(),
-            load_duration.num_microseconds().unwrap() as f64 / 1e6,
-            wad.length() as f64 / (load_duration.num_microseconds().unwrap() as f64 / 1e6)
+            seconds(load_duration),
+            wad.length() as f64 / (seconds(load_duration))
+        );
+    }
+
+    Ok(())
+}
+
+// unknown types sort after every known category
+fn category_order(table: ThingTable, thing: &MapThing) -> (bool, Option<ThingCategory>) {
+    let category = table.category(thing.doomednum);
+    (category.is_none(), category)
+}
+
+/// The statistics `compare-map` shows. Area and line length are in map units.
+fn comparison_values(map: &Map, table: ThingTable) -> Result<Vec<(&'static str, f64)>, Error> {
+    let stats = map.stats_with(StatSet::all(), table)?;
+    let lengths = map.linedef_length_stats()?;
+    Ok(vec![
+        ("things", stats.things as f64),
+        ("monsters", stats.monsters.unwrap_or(0) as f64),
+        ("secrets", stats.secrets.unwrap_or(0) as f64),
+        ("linedefs", stats.linedefs as f64),
+        ("sidedefs", stats.sidedefs as f64),
+        ("vertexes", stats.vertexes as f64),
+        ("sectors", stats.sectors as f64),
+        ("area", stats.area.unwrap_or(0.0)),
+        ("line length", lengths.mean * lengths.count as f64),
+    ])
+}
+
+fn compare_map(a_name: &str, b_name: &str, map_name: &str) -> Result<(), Error> {
+    let (a, b) = (Wad::from_file(a_name)?, Wad::from_file(b_name)?);
+    let (a_map, b_map) = (a.map_by_name(map_name), b.map_by_name(map_name));
+    if a_map.is_none() && b_map.is_none() {
+        return Err(Error::MapNotFound(
+            map_name.to_string(),
+            a.maps().iter().map(|m| m.name().to_string()).collect(),
+        ));
+    }
+    let a_values = a_map
+        .map(|m| comparison_values(m, a.thing_table()))
+        .transpose()?;
+    let b_values = b_map
+        .map(|m| comparison_values(m, b.thing_table()))
+        .transpose()?;
+    for (wad, map) in [(a_name, a_map), (b_name, b_map)] {
+        if map.is_none() {
+            println!("{} isn't in {}", map_name, wad);
+        }
+    }
+
+    let number = |value: f64| format!("{:.0}", value);
+    println!(
+        "{:<12}  {:>12}  {:>12}  {:>10}",
+        "", "before", "after", "change"
+    );
+    let rows = a_values.as_ref().or(b_values.as_ref()).unwrap().len();
+    for row in 0..rows {
+        let a = a_values.as_ref().map(|v| v[row]);
+        let b = b_values.as_ref().map(|v| v[row]);
+        let (label, _) = a.or(b).unwrap();
+        let column = |v: Option<(&str, f64)>| v.map_or("-".to_string(), |v| number(v.1));
+        let change = match (a, b) {
+            (Some(a), Some(b)) if b.1 == a.1 => "0".to_string(),
+            (Some(a), Some(b)) if b.1 > a.1 => format!("+{}", number(b.1 - a.1)),
+            (Some(a), Some(b)) => number(b.1 - a.1),
+            _ => String::new(),
+        };
+        println!(
+            "{:<12}  {:>12}  {:>12}  {:>10}",
+            label,
+            column(a),
+            column(b),
+            change
+        );
+    }
+    Ok(())
+}
+
+fn list_things(wad_name: &str, map_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let map = wad.map_by_name(map_name).ok_or_else(|| {
+        Error::MapNotFound(
+            map_name.to_string(),
+            wad.maps().iter().map(|m| m.name().to_string()).collect(),
+        )
+    })?;
+
+    let table = wad.thing_table();
+    let mut things = map.things()?.into_iter().enumerate().collect::<Vec<_>>();
+    things.sort_by_key(|(_, t)| (category_order(table, t), t.x, t.y));
+
+    println!(
+        "{:>5}  {:>5}  {:<32}  {:>6}  {:>6}  skills",
+        "index", "type", "name", "x", "y"
+    );
+    let mut tally = BTreeMap::<(bool, Option<ThingCategory>), usize>::new();
+    for (i, thing) in &things {
+        let skills = thing
+            .skills()
+            .iter()
+            .map(|s| s.name())
+            .collect::<Vec<_>>()
+            .join(",");
+        println!(
+            "{:>5}  {:>5}  {:<32}  {:>6}  {:>6}  {}",
+            i,
+            thing.doomednum,
+            table.thing_name(thing.doomednum),
+            thing.x,
+            thing.y,
+            if skills.is_empty() { "none" } else { &skills }
+        );
+        *tally.entry(category_order(table, thing)).or_default() += 1;
+    }
+
+    println!();
+    for ((_, category), count) in tally {
+        match category {
+            Some(c) => println!("{:?}: {}", c, count),
+            None => println!("Unknown: {}", count),
+        }
+    }
+
+    let inventory = table
+        .inventory(&map.thing_type_counts()?)
+        .iter()
+        .map(|(name, count)| format!("{} x {}", count, name))
+        .collect::<Vec<_>>();
+    println!();
+    println!("{}", inventory.join(", "));
+
+    Ok(())
+}
+
+/// Prints the sectors each of a map's sectors leads to, marking doors, lifts
+/// and teleporters, or the whole graph as DOT.
+fn print_graph(wad_name: &str, map_name: &str, dot: bool) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let map = wad.map_by_name(map_name).ok_or_else(|| {
+        Error::MapNotFound(
+            map_name.to_string(),
+            wad.maps().iter().map(|m| m.name().to_string()).collect(),
+        )
+    })?;
+    let graph = map.connectivity_graph()?;
+    if dot {
+        print!("{}", graph.to_dot());
+        return Ok(());
+    }
+    for (sector, neighbors) in graph.adjacency_list().iter().enumerate() {
+        let neighbors = neighbors
+            .iter()
+            .map(|&(next, kind)| match kind {
+                EdgeKind::Open => next.to_string(),
+                kind => format!("{} ({:?})", next, kind).to_lowercase(),
+            })
+            .collect::<Vec<_>>();
+        println!("sector {}: {}", sector, neighbors.join(", "));
+    }
+    Ok(())
+}
+
+/// Prints a map's decoded lumps in index order, so that two versions of a map
+/// can be compared with `diff`.
+fn dump_map(wad_name: &str, map_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let map = wad.map_by_name(map_name).ok_or_else(|| {
+        Error::MapNotFound(
+            map_name.to_string(),
+            wad.maps().iter().map(|m| m.name().to_string()).collect(),
+        )
+    })?;
+    let vertexes = map.vertexes()?;
+    let linedefs = map.linedefs()?;
+    let sidedefs = map.sidedefs()?;
+    let sectors = map.sectors()?;
+    let things = map.things()?;
+    let table = wad.thing_table();
+    let side = |s: u16| {
+        if s == NO_SIDEDEF {
+            "-".to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    let point = |v: u16| match vertexes.get(v as usize) {
+        Some(v) => format!("({}, {})", v.x, v.y),
+        None => "(missing)".to_string(),
+    };
+
+    println!("VERTEXES ({})", vertexes.len());
+    println!("{:>5}  {:>6}  {:>6}", "index", "x", "y");
+    for (i, v) in vertexes.iter().enumerate() {
+        println!("{:>5}  {:>6}  {:>6}", i, v.x, v.y);
+    }
+
+    println!();
+    println!("LINEDEFS ({})", linedefs.len());
+    println!(
+        "{:>5}  {:>5}  {:>5}  {:<30}  {:>6}  {:>7}  {:>5}  {:>5}  {:>5}",
+        "index", "start", "end", "from - to", "flags", "special", "tag", "right", "left"
+    );
+    for (i, l) in linedefs.iter().enumerate() {
+        println!(
+            "{:>5}  {:>5}  {:>5}  {:<30}  {:>#6x}  {:>7}  {:>5}  {:>5}  {:>5}",
+            i,
+            l.start_vertex,
+            l.end_vertex,
+            format!("{} - {}", point(l.start_vertex), point(l.end_vertex)),
+            l.flags,
+            l.special,
+            l.tag,
+            side(l.right_sidedef),
+            side(l.left_sidedef)
+        );
+    }
+
+    println!();
+    println!("SIDEDEFS ({})", sidedefs.len());
+    println!(
+        "{:>5}  {:>6}  {:>6}  {:<8}  {:<8}  {:<8}  {:>6}",
+        "index", "x off", "y off", "upper", "lower", "middle", "sector"
+    );
+    for (i, s) in sidedefs.iter().enumerate() {
+        println!(
+            "{:>5}  {:>6}  {:>6}  {:<8}  {:<8}  {:<8}  {:>6}",
+            i, s.x_offset, s.y_offset, s.upper_texture, s.lower_texture, s.middle_texture, s.sector
+        );
+    }
+
+    println!();
+    println!("SECTORS ({})", sectors.len());
+    println!(
+        "{:>5}  {:>6}  {:>7}  {:<10}  {:<10}  {:>5}  {:>7}  {:>5}",
+        "index", "floor", "ceiling", "floor flat", "ceil flat", "light", "special", "tag"
+    );
+    for (i, s) in sectors.iter().enumerate() {
+        println!(
+            "{:>5}  {:>6}  {:>7}  {:<10}  {:<10}  {:>5}  {:>7}  {:>5}",
+            i,
+            s.floor_height,
+            s.ceiling_height,
+            s.floor_flat,
+            s.ceiling_flat,
+            s.light_level,
+            s.special,
+            s.tag
+        );
+    }
+
+    println!();
+    println!("THINGS ({})", things.len());
+    println!(
+        "{:>5}  {:>5}  {:<32}  {:>6}  {:>6}  {:>5}  {:>6}",
+        "index", "type", "name", "x", "y", "angle", "flags"
+    );
+    for (i, t) in things.iter().enumerate() {
+        println!(
+            "{:>5}  {:>5}  {:<32}  {:>6}  {:>6}  {:>5}  {:>#6x}",
+            i,
+            t.doomednum,
+            table.thing_name(t.doomednum),
+            t.x,
+            t.y,
+            t.angle,
+            t.flags
+        );
+    }
+    Ok(())
+}
+
+fn parse_render_options(args: &[String]) -> Option<RenderOptions> {
+    let mut options = RenderOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--things" => options.things = true,
+            "--skill" => {
+                let name = args.next()?;
+                options.skill = Some(*Skill::ALL.iter().find(|s| s.name() == name)?);
+            }
+            "--fill" => {
+                options.fill_floors = Some(match args.next()?.as_str() {
+                    "flat" => FloorColor::Flat,
+                    "light" => FloorColor::Light,
+                    "height" => FloorColor::Height,
+                    _ => return None,
+                })
+            }
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+fn render(
+    wad_name: &str,
+    map_name: &str,
+    out: &str,
+    mut options: RenderOptions,
+) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let map = wad.map_by_name(map_name).ok_or_else(|| {
+        Error::MapNotFound(
+            map_name.to_string(),
+            wad.maps().iter().map(|m| m.name().to_string()).collect(),
+        )
+    })?;
+    options.table = wad.thing_table();
+    fs::write(out, map.render_png(&options)?)?;
+    Ok(())
+}
+
+fn extract_screens(wad_name: &str, dir: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let palette = match wad.palette() {
+        Some(p) => p,
+        None => {
+            error!("{} has no PLAYPAL lump", wad_name);
+            return Ok(());
+        }
+    };
+    fs::create_dir_all(dir)?;
+    for lump in wad.title_screens() {
+        match Picture::decode_screen(lump) {
+            Ok(picture) => {
+                let path = Path::new(dir).join(format!("{}.png", sanitize_file_name(lump.name())));
+                match picture.to_png(&palette) {
+                    Some(png) => {
+                        fs::write(&path, png)?;
+                        println!("{}", path.to_string_lossy());
+                    }
+                    None => error!("{} is too small to save as a PNG", lump.name()),
+                }
+            }
+            Err(e) => error!("{}", e),
+        }
+    }
+    Ok(())
+}
+
+/// The arguments of `missing` and `custom`: WAD files, and the IWAD they're
+/// played with. The IWAD is read once for all of them.
+struct IwadOptions {
+    wads: Vec<String>,
+    iwad: Option<String>,
+    exclude_iwad_content: bool,
+}
+
+impl IwadOptions {
+    fn parse(args: &[String]) -> Option<Self> {
+        let mut options = Self {
+            wads: vec![],
+            iwad: None,
+            exclude_iwad_content: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--iwad" => options.iwad = Some(args.next()?.clone()),
+                "--exclude-iwad-content" => options.exclude_iwad_content = true,
+                _ => options.wads.push(arg.clone()),
+            }
+        }
+        if options.wads.is_empty() || (options.exclude_iwad_content && options.iwad.is_none()) {
+            return None;
+        }
+        Some(options)
+    }
+
+    fn load_iwad(&self) -> Result<Option<Wad>, Error> {
+        self.iwad.as_ref().map(Wad::from_file).transpose()
+    }
+}
+
+fn list_missing(options: &IwadOptions) -> Result<(), Error> {
+    let mut wads = options.wads.as_slice();
+    let mut iwad = options.load_iwad()?;
+    // `missing <wad> <iwad>`, from before there was --iwad
+    if iwad.is_none() && wads.len() == 2 {
+        let second = Wad::from_file(&wads[1])?;
+        if second.is_iwad() {
+            iwad = Some(second);
+            wads = &wads[..1];
+        }
+    }
+
+    for wad_name in wads {
+        let wad = Wad::from_file(wad_name)?;
+        if wads.len() > 1 {
+            println!("{}:", wad_name);
+        }
+        let (flats, textures, patches) = match &iwad {
+            Some(iwad) => (
+                wad.missing_flats_against(iwad),
+                wad.missing_textures_against(iwad),
+                wad.missing_patches_against(iwad),
+            ),
+            None => (
+                wad.missing_flats(),
+                wad.missing_textures(),
+                wad.missing_patches(),
+            ),
+        };
+        let print = |kind: &str, name: &str, references: usize, maps: &[String]| {
+            println!(
+                "{} {}: {} use{} in {}",
+                kind,
+                name,
+                references,
+                if references == 1 { "" } else { "s" },
+                maps.join(", ")
+            )
+        };
+        for flat in &flats {
+            print("flat", &flat.name, flat.references, &flat.maps);
+        }
+        for texture in &textures {
+            print("texture", &texture.name, texture.references, &texture.maps);
+        }
+        for patch in &patches {
+            println!(
+                "patch {}: used by {}",
+                patch.name,
+                patch.textures.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn list_animations(wad_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    for coverage in wad.texture_animations() {
+        let frames = &coverage.group.frames;
+        let range = format!("{}-{}", frames[0], frames[frames.len() - 1]);
+        if coverage.is_complete() {
+            println!("{}: complete", range);
+        } else {
+            println!("{}: missing {}", range, coverage.missing().join(", "));
+        }
+    }
+    Ok(())
+}
+
+fn show_skill_profiles(wad_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let table = wad.thing_table();
+    for map in wad.maps() {
+        let profile = match map.skill_profile(table) {
+            Ok(p) => p,
+            Err(e) => {
+                error!("{}: {}", map.name(), e);
+                continue;
+            }
+        };
+        println!("{}", map.name());
+        println!(
+            "    {:<8}  {:>8}  {:>6}  {:>6}  {:>14}",
+            "skill", "monsters", "bosses", "items", "monster health"
         );
+        for counts in &profile.skills {
+            println!(
+                "    {:<8}  {:>8}  {:>6}  {:>6}  {:>14}",
+                counts.skill.name(),
+                counts.monsters,
+                counts.bosses,
+                counts.items,
+                counts.monster_health
+            );
+        }
+        match map.coop_delta(table) {
+            Ok(delta) => {
+                let rows = [
+                    ("single", delta.single_player, ""),
+                    ("coop-only", delta.coop_only, "+"),
+                    ("sp-only", delta.single_player_only, "-"),
+                    ("coop", delta.coop, ""),
+                ];
+                // Boom's not-in-coop flag is rare, so its row is left out when unused
+                for (mode, counts, sign) in rows.iter().filter(|(mode, counts, _)| {
+                    *mode != "sp-only" || *counts != ModeCounts::default()
+                }) {
+                    println!(
+                        "    {:<9} {:>8}  {:>6}  {:>6}",
+                        mode,
+                        format!("{}{}", sign, counts.monsters),
+                        "",
+                        format!("{}{}", sign, counts.items)
+                    );
+                }
+            }
+            Err(e) => error!("{}: {}", map.name(), e),
+        }
+        match map.skill_gated_fraction() {
+            Ok(0.0) => println!("    every thing appears on every skill"),
+            Ok(gated) => println!("    {:.0}% of things are skill-gated", gated * 100.0),
+            Err(e) => error!("{}: {}", map.name(), e),
+        }
     }
+    Ok(())
+}
+
+fn show_sector_edges(wad_name: &str) -> Result<(), Error> {
+    // the ranges of linedef counts the distribution is grouped into
+    const BUCKETS: [(usize, usize); 9] = [
+        (0, 0),
+        (1, 2),
+        (3, 4),
+        (5, 8),
+        (9, 16),
+        (17, 32),
+        (33, 64),
+        (65, 128),
+        (129, usize::MAX),
+    ];
+    const OFFENDERS: usize = 5;
 
+    let wad = Wad::from_file(wad_name)?;
+    for map in wad.maps() {
+        let counts = match map.sector_edge_counts() {
+            Ok(counts) => counts,
+            Err(e) => {
+                error!("{}: {}", map.name(), e);
+                continue;
+            }
+        };
+        if counts.is_empty() {
+            println!("{}: no sectors", map.name());
+            continue;
+        }
+        let mut sorted = counts.clone();
+        sorted.sort_unstable();
+        println!(
+            "{}: {} sectors, {} to {} linedefs each, median {}, mean {:.1}",
+            map.name(),
+            counts.len(),
+            sorted[0],
+            sorted[sorted.len() - 1],
+            sorted[sorted.len() / 2],
+            counts.iter().sum::<usize>() as f64 / counts.len() as f64
+        );
+        println!("    {:<8}  {:>7}", "linedefs", "sectors");
+        for &(low, high) in &BUCKETS {
+            let n = counts
+                .iter()
+                .filter(|&&c| (low..=high).contains(&c))
+                .count();
+            if n == 0 {
+                continue;
+            }
+            let range = match (low, high) {
+                (low, high) if low == high => low.to_string(),
+                (low, usize::MAX) => format!("{}+", low),
+                (low, high) => format!("{}-{}", low, high),
+            };
+            println!("    {:<8}  {:>7}", range, n);
+        }
+        let mut ranked = counts.iter().copied().enumerate().collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let top = ranked
+            .iter()
+            .take(OFFENDERS)
+            .map(|(sector, count)| format!("sector {} ({})", sector, count))
+            .collect::<Vec<_>>();
+        println!("    most linedefs: {}", top.join(", "));
+        let flagged = [
+            (
+                map.over_detailed_sectors()?,
+                format!("over {} linedefs", DETAILED_SECTOR_EDGES),
+            ),
+            (
+                map.unclosable_sectors()?,
+                "too few linedefs to close".to_string(),
+            ),
+        ];
+        for (sectors, label) in flagged.iter().filter(|(s, _)| !s.is_empty()) {
+            let list = sectors.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            println!("    {}: {}", label, list.join(", "));
+        }
+    }
     Ok(())
 }
 
-#[derive(Debug, thiserror::Error)]
-enum Error {
-    Io(#[from] std::io::Error),
-    Utf8(#[from] FromUtf8Error),
-    NotAWad(PathBuf),
-    InvalidLumpOrder(i32, String),
+fn show_thing_flags(wad_name: &str, json: bool) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let mut summaries = vec![];
+    for map in wad.maps() {
+        match map.thing_flags_summary() {
+            Ok(summary) => summaries.push((map.name(), summary)),
+            Err(e) => error!("{}: {}", map.name(), e),
+        }
+    }
+    if json {
+        let maps = summaries
+            .iter()
+            .map(|(name, summary)| {
+                Json::object(vec![("map", (*name).into()), ("flags", summary.to_json())])
+            })
+            .collect();
+        println!("{}", Json::Array(maps));
+        return Ok(());
+    }
+    let mut header = format!("{:<8} {:>6}", "map", "things");
+    for (flag, _) in ThingFlagsSummary::default().rows() {
+        header += &format!(" {:>w$}", flag, w = flag.len());
+    }
+    println!("{}", header);
+    for (name, summary) in &summaries {
+        let mut line = format!("{:<8} {:>6}", name, summary.things);
+        for (flag, count) in summary.rows() {
+            line += &format!(" {:>w$}", count, w = flag.len());
+        }
+        println!("{}", line);
+    }
+    Ok(())
 }
 
-impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+fn list_secret_paths(wad_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    for path in wad.secret_level_paths() {
+        let exits = format!(
+            "{} secret exit{}",
+            path.exits,
+            if path.exits == 1 { "" } else { "s" }
+        );
+        match &path.to {
+            Some(to) if path.destination_in_wad => {
+                println!("{} -> {} ({})", path.from, to, exits)
+            }
+            Some(to) => println!("{} -> {} ({}; not in this WAD)", path.from, to, exits),
+            None => println!("{} -> nowhere ({}; dead end)", path.from, exits),
+        }
+    }
+    Ok(())
+}
+
+fn show_compat(wad_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let compat = wad.engine_compatibility();
+    println!("minimum port: {}", compat.minimum_port());
+    let maps = |label: &str, maps: &[String]| {
+        if !maps.is_empty() {
+            println!("    {}: {}", label, maps.join(", "));
+        }
+    };
+    if !compat.demo_versions.is_empty() {
+        let versions = compat
+            .demo_versions
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        println!("    demo versions: {}", versions.join(", "));
+    }
+    maps("over vanilla limits", &compat.limit_removing_maps);
+    maps("extended nodes", &compat.extended_node_maps);
+    maps("compressed nodes", &compat.compressed_node_maps);
+    maps("Boom specials", &compat.boom_special_maps);
+    maps("MBF features", &compat.mbf_maps);
+    maps("friendly monsters", &compat.friendly_maps);
+    maps("ZDoom lumps", &compat.zdoom_lumps);
+    maps("GZDoom lumps", &compat.gzdoom_lumps);
+    if compat.dehacked {
+        println!("    DEHACKED lump");
+    }
+    if compat.udmf {
+        println!("    UDMF maps");
+    }
+    if compat.hexen_format {
+        println!("    Hexen-format maps");
+    }
+    Ok(())
+}
+
+fn show_compat_matrix(wad_name: &str, json: bool) -> Result<(), Error> {
+    let matrix = Wad::from_file(wad_name)?.compatibility_matrix();
+    if json {
+        println!("{}", matrix.to_json());
+        return Ok(());
+    }
+    let mut header = format!("{:<8} {:<15}", "map", "needs");
+    for port in &matrix.ports {
+        header += &format!(" {:<8}", port.to_string());
+    }
+    println!("{}", header.trim_end());
+    for row in &matrix.rows {
+        let mut line = format!("{:<8} {:<15}", row.map, row.minimum_port.to_string());
+        for level in &row.levels {
+            line += &format!(" {:<8}", level.to_string());
+        }
+        println!("{}", line.trim_end());
+    }
+    Ok(())
+}
+
+fn list_actor_definitions(wad_name: &str) -> Result<(), Error> {
+    let info = Wad::from_file(wad_name)?.actor_definitions();
+    let port = match info.minimum_port() {
+        Some(port) => port,
+        None => {
+            println!("no DECORATE or ZSCRIPT lumps");
+            return Ok(());
+        }
+    };
+    for lump in &info.lumps {
+        println!(
+            "{:<8}  {:<8}  {:>8} bytes  {} declarations",
+            lump.name, lump.language, lump.size, lump.declarations
+        );
+    }
+    for include in &info.missing_includes {
+        println!("included but not in the WAD: {}", include);
+    }
+    println!(
+        "{} declarations in {} bytes, needing {}",
+        info.declarations(),
+        info.total_size(),
+        port
+    );
+    Ok(())
+}
+
+fn show_gameinfo(wad_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let info = match wad.gameinfo() {
+        Some(info) => info,
+        None => {
+            println!("no GAMEINFO or GAMECONF lump");
+            return Ok(());
+        }
+    };
+    println!("iwad: {}", info.iwad.as_deref().unwrap_or("not given"));
+    if !info.load.is_empty() {
+        println!("load: {}", info.load.join(", "));
+    }
+    if let Some(title) = &info.title {
+        println!("title: {}", title);
+    }
+    Ok(())
+}
+
+fn list_demos(wad_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let commercial = wad.detect_game() == Game::Doom2;
+    for n in 1..=3 {
+        let lump = match wad.demo(n) {
+            Some(l) => l,
+            None => continue,
+        };
+        match DemoHeader::parse(lump) {
+            Ok(demo) => println!(
+                "DEMO{}: {}, skill {}, version {}, {} player{}{}",
+                n,
+                demo.map_name(commercial),
+                demo.skill + 1,
+                demo.version_name(),
+                demo.player_count(),
+                if demo.player_count() == 1 { "" } else { "s" },
+                if demo.deathmatch { ", deathmatch" } else { "" }
+            ),
+            Err(e) => error!("DEMO{}: {}", n, e),
+        }
+    }
+    Ok(())
+}
+
+fn print_geometry_hashes(wad_name: &str) -> Result<(), Error> {
+    for map in Wad::from_file(wad_name)?.maps() {
+        println!("{:<8}  {}", map.name(), map.geometry_hash());
+    }
+    Ok(())
+}
+
+fn list_clones(wad_name: &str) -> Result<(), Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let groups = wad.thing_layout_clones();
+    for group in &groups {
+        let names = group.iter().map(|m| m.name()).collect::<Vec<_>>();
+        println!("{}", names.join(", "));
+    }
+    println!("{} groups found", groups.len());
+    Ok(())
+}
+
+fn list_custom_lumps(options: &IwadOptions) -> Result<(), Error> {
+    let iwad = options.load_iwad()?;
+    for wad_name in &options.wads {
+        let wad = Wad::from_file(wad_name)?;
+        if options.wads.len() > 1 {
+            println!("{}:", wad_name);
+        }
+        let lumps = match &iwad {
+            Some(iwad) if options.exclude_iwad_content => wad.custom_lumps_against(iwad),
+            _ => wad.custom_lumps(),
+        };
+        for lump in lumps {
+            println!("{:<8}  {:>8} bytes", lump.name(), lump.size());
+        }
+    }
+    Ok(())
+}
+
+fn list_replaced_sprites(options: &IwadOptions) -> Result<(), Error> {
+    let iwad = match options.load_iwad()? {
+        Some(iwad) => iwad,
+        None => return Ok(()),
+    };
+    for wad_name in &options.wads {
+        let wad = Wad::from_file(wad_name)?;
+        if options.wads.len() > 1 {
+            println!("{}:", wad_name);
+        }
+        let replaced = wad.replaced_iwad_sprites(&iwad);
+        let sprites = wad.sprites();
+        for name in &replaced {
+            if let Some(lump) = sprites.iter().find(|l| l.name() == name) {
+                println!("{:<8}  {:>8} bytes", lump.name(), lump.size());
+            }
+        }
+        println!(
+            "replaces {} IWAD sprites ({:.1}% of them)",
+            replaced.len(),
+            wad.replaced_iwad_sprite_fraction(&iwad) * 100.0
+        );
+    }
+    Ok(())
+}
+
+/// How `validate` and `lint` print what they find.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    Text,
+    /// GitHub Actions workflow commands, which show as annotations on a run.
+    Github,
+}
+
+impl ReportFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "github" => Some(ReportFormat::Github),
+            _ => None,
+        }
+    }
+
+    fn print(self, wad_name: &str, diagnostic: &Diagnostic) {
         match self {
-            Error::Io(i) => write!(f, "I/O error: {}", i),
-            Error::NotAWad(p) => write!(f, "not a WAD file: {}", p.to_string_lossy()),
-            Error::Utf8(u) => write!(f, "converting from UTF-8 data: {}", u),
-            Error::InvalidLumpOrder(w, n) => {
-                write!(f, "encountered out-of-order lump at offset {}: '{}'", w, n)
+            ReportFormat::Text => println!("{}", diagnostic),
+            ReportFormat::Github => println!("{}", diagnostic.to_github_annotation(wad_name)),
+        }
+    }
+}
+
+fn validate(wad_name: &str, format: ReportFormat) -> Result<(), Error> {
+    let options = ParseOptions {
+        strict: false,
+        collect_diagnostics: true,
+        alpha: false,
+        preserve_case: false,
+        hash_iwad: false,
+    };
+    let wad = Wad::from_file_with_options(wad_name, options)?;
+    let diagnostics = wad.validate();
+    for diagnostic in &diagnostics {
+        format.print(wad_name, diagnostic);
+    }
+    let problems = diagnostics
+        .iter()
+        .filter(|d| d.severity > Severity::Info)
+        .count();
+    println!("{} problems found", problems);
+    Ok(())
+}
+
+fn parse_lint_options(args: &[String]) -> Option<(LintOptions, ReportFormat)> {
+    let mut options = LintOptions::default();
+    let mut format = ReportFormat::Text;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = ReportFormat::from_name(args.next()?)?,
+            "--no-keys" => options.keys = false,
+            "--no-exits" => options.exits = false,
+            "--no-teleports" => options.teleports = false,
+            "--no-reachability" => options.reachability = false,
+            _ => return None,
+        }
+    }
+    Some((options, format))
+}
+
+fn lint(wad_name: &str, options: &LintOptions, format: ReportFormat) -> Result<ExitCode, Error> {
+    let wad = Wad::from_file(wad_name)?;
+    let options = &LintOptions {
+        table: wad.thing_table(),
+        ..*options
+    };
+    let mut failed = 0;
+    for map in wad.maps() {
+        let issues = match map.lint(options) {
+            Ok(issues) => issues,
+            Err(e) if format == ReportFormat::Github => {
+                let diagnostic = Diagnostic::new(Severity::Error, Some(map.name()), e.to_string());
+                format.print(wad_name, &diagnostic);
+                failed += 1;
+                continue;
             }
+            Err(e) => {
+                error!("{}: {}", map.name(), e);
+                failed += 1;
+                continue;
+            }
+        };
+        if issues.iter().any(|d| d.severity == Severity::Error) {
+            failed += 1;
+        }
+        for issue in &issues {
+            format.print(wad_name, issue);
         }
     }
+    if failed == 0 {
+        println!("PASS: every map can be finished");
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!(
+            "FAIL: {} of {} maps can't be finished",
+            failed,
+            wad.maps().len()
+        );
+        Ok(ExitCode::from(4))
+    }
 }
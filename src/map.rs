@@ -0,0 +1,1103 @@
+use std::collections::BTreeSet;
+
+use crate::animations::vanilla_animations;
+use crate::animations::AnimationGroup;
+use crate::animations::AnimationKind;
+use crate::wad::name_from_bytes;
+use crate::wad::name_to_bytes;
+use crate::wad::Endianness;
+use crate::wad::Lump;
+use crate::wad::LumpKind;
+use crate::Error;
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct Map {
+    pub(crate) name: String,
+    pub(crate) things: Lump,
+    pub(crate) linedefs: Lump,
+    pub(crate) sidedefs: Lump,
+    pub(crate) vertexes: Lump,
+    pub(crate) segs: Lump,
+    pub(crate) subsectors: Lump,
+    pub(crate) nodes: Lump,
+    pub(crate) sectors: Lump,
+    pub(crate) reject: Option<Lump>,
+    pub(crate) blockmap: Lump,
+    pub(crate) behavior: Option<Lump>,
+    pub(crate) scripts: Option<Lump>,
+    /// GL node lumps, from the WAD or a `.gwa` file, in the order found.
+    pub(crate) gl: Vec<Lump>,
+    /// The required lumps a partial map was kept without. Their fields hold
+    /// empty placeholders, which the accessors refuse to decode.
+    pub(crate) missing: Vec<LumpKind>,
+    pub(crate) endianness: Endianness,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapThing {
+    pub x: i16,
+    pub y: i16,
+    pub angle: i16,
+    pub doomednum: u16,
+    pub flags: u16,
+}
+
+/// The three difficulty groups a thing's flags can select. Skills 1 and 2 share
+/// the easy flag, and skills 4 and 5 share the hard flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Skill {
+    Easy,
+    Medium,
+    Hard,
+}
+
+pub const THING_FLAG_EASY: u16 = 0x0001;
+pub const THING_FLAG_MEDIUM: u16 = 0x0002;
+pub const THING_FLAG_HARD: u16 = 0x0004;
+pub const THING_FLAG_AMBUSH: u16 = 0x0008;
+pub const THING_FLAG_MULTIPLAYER: u16 = 0x0010;
+/// Boom: the thing doesn't appear in deathmatch.
+pub const THING_FLAG_NOT_DEATHMATCH: u16 = 0x0020;
+/// Boom: the thing doesn't appear in cooperative play.
+pub const THING_FLAG_NOT_COOP: u16 = 0x0040;
+/// MBF: the monster fights for the player, like MBF's helper dogs.
+pub const THING_FLAG_FRIENDLY: u16 = 0x0080;
+/// Never meant to be set. Some old editors set it along with garbage in the
+/// bits above, so Boom and MBF ignore those bits when it is.
+pub const THING_FLAG_RESERVED: u16 = 0x0100;
+
+impl Skill {
+    pub const ALL: [Skill; 3] = [Skill::Easy, Skill::Medium, Skill::Hard];
+
+    pub fn flag(self) -> u16 {
+        match self {
+            Skill::Easy => THING_FLAG_EASY,
+            Skill::Medium => THING_FLAG_MEDIUM,
+            Skill::Hard => THING_FLAG_HARD,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Skill::Easy => "easy",
+            Skill::Medium => "medium",
+            Skill::Hard => "hard",
+        }
+    }
+}
+
+impl MapThing {
+    pub fn appears_on(&self, skill: Skill) -> bool {
+        self.flags & skill.flag() != 0
+    }
+
+    /// Which of the Boom and MBF flags, `THING_FLAG_NOT_DEATHMATCH`,
+    /// `THING_FLAG_NOT_COOP` and `THING_FLAG_FRIENDLY`, the thing has as
+    /// those ports read them: none if `THING_FLAG_RESERVED` is set too.
+    pub fn port_flags(&self) -> u16 {
+        if self.flags & THING_FLAG_RESERVED != 0 {
+            return 0;
+        }
+        self.flags & (THING_FLAG_NOT_DEATHMATCH | THING_FLAG_NOT_COOP | THING_FLAG_FRIENDLY)
+    }
+
+    /// Whether MBF's friendly flag, bit 0x0080, is set and not ignored.
+    pub fn is_friendly(&self) -> bool {
+        self.port_flags() & THING_FLAG_FRIENDLY != 0
+    }
+
+    pub fn skills(&self) -> Vec<Skill> {
+        Skill::ALL
+            .iter()
+            .cloned()
+            .filter(|&s| self.appears_on(s))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Vertex {
+    pub x: i16,
+    pub y: i16,
+}
+
+/// The smallest box containing every vertex of a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    pub min_x: i16,
+    pub min_y: i16,
+    pub max_x: i16,
+    pub max_y: i16,
+}
+
+impl Bounds {
+    /// Whether a point lies inside the box grown by `margin` units on every side.
+    pub fn contains(&self, x: i16, y: i16, margin: i32) -> bool {
+        let (x, y) = (x as i32, y as i32);
+        self.min_x as i32 - margin <= x
+            && x <= self.max_x as i32 + margin
+            && self.min_y as i32 - margin <= y
+            && y <= self.max_y as i32 + margin
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Linedef {
+    pub start_vertex: u16,
+    pub end_vertex: u16,
+    pub flags: u16,
+    pub special: u16,
+    pub tag: u16,
+    pub right_sidedef: u16,
+    pub left_sidedef: u16,
+}
+
+pub const NO_SIDEDEF: u16 = 0xFFFF;
+
+/// Linedef flag bits and their names. Bits 0-8 are vanilla Doom, bit 9 is
+/// Boom's pass-use flag, and bits 12-13 come from MBF21.
+pub const LINEDEF_FLAGS: [(u16, &str); 12] = [
+    (0x0001, "impassable"),
+    (0x0002, "block monsters"),
+    (0x0004, "two-sided"),
+    (0x0008, "upper unpegged"),
+    (0x0010, "lower unpegged"),
+    (0x0020, "secret on automap"),
+    (0x0040, "block sound"),
+    (0x0080, "never on automap"),
+    (0x0100, "always on automap"),
+    (0x0200, "pass use"),
+    (0x1000, "block land monsters"),
+    (0x2000, "block players"),
+];
+
+pub const LINEDEF_FLAG_IMPASSABLE: u16 = 0x0001;
+pub const LINEDEF_FLAG_BLOCK_MONSTERS: u16 = 0x0002;
+pub const LINEDEF_FLAG_TWO_SIDED: u16 = 0x0004;
+/// Drawn as a one-sided wall on the automap, hiding the opening behind it.
+pub const LINEDEF_FLAG_SECRET: u16 = 0x0020;
+pub const LINEDEF_FLAG_NEVER_ON_AUTOMAP: u16 = 0x0080;
+pub const LINEDEF_FLAG_ALWAYS_ON_AUTOMAP: u16 = 0x0100;
+
+impl Linedef {
+    pub fn flag_names(&self) -> Vec<&'static str> {
+        LINEDEF_FLAGS
+            .iter()
+            .filter(|(bit, _)| self.flags & bit != 0)
+            .map(|&(_, name)| name)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sidedef {
+    pub x_offset: i16,
+    pub y_offset: i16,
+    pub upper_texture: String,
+    pub lower_texture: String,
+    pub middle_texture: String,
+    pub sector: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sector {
+    pub floor_height: i16,
+    pub ceiling_height: i16,
+    pub floor_flat: String,
+    pub ceiling_flat: String,
+    pub light_level: i16,
+    pub special: u16,
+    pub tag: u16,
+}
+
+impl Sector {
+    /// Whether entering the sector counts as finding a secret, either through
+    /// the vanilla special 9 or Boom's generalized secret bit.
+    pub fn is_secret(&self) -> bool {
+        self.special == 9 || self.special & 0x80 != 0
+    }
+
+    /// Whether standing on the floor hurts the player: vanilla specials 4, 5, 7,
+    /// 11 and 16, or any of Boom's generalized damage bits.
+    pub fn is_damaging(&self) -> bool {
+        matches!(self.special, 4 | 5 | 7 | 11 | 16) || self.special & 0x60 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seg {
+    pub start_vertex: u16,
+    pub end_vertex: u16,
+    pub angle: i16,
+    pub linedef: u16,
+    /// 0 if the seg runs along the linedef's right side, 1 for the left side.
+    pub direction: i16,
+    pub offset: i16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subsector {
+    pub seg_count: u16,
+    pub first_seg: u16,
+}
+
+/// A BSP node. Children with `NODE_SUBSECTOR` set are subsector indexes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Node {
+    pub x: i16,
+    pub y: i16,
+    pub dx: i16,
+    pub dy: i16,
+    pub right_bbox: [i16; 4],
+    pub left_bbox: [i16; 4],
+    pub right_child: u16,
+    pub left_child: u16,
+}
+
+pub const NODE_SUBSECTOR: u16 = 0x8000;
+
+/// The size in bytes of each of a map's lumps. The optional lumps are `None`
+/// when the map doesn't have them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentSizes {
+    pub things: usize,
+    pub linedefs: usize,
+    pub sidedefs: usize,
+    pub vertexes: usize,
+    pub segs: usize,
+    pub subsectors: usize,
+    pub nodes: usize,
+    pub sectors: usize,
+    pub reject: Option<usize>,
+    pub blockmap: usize,
+    pub behavior: Option<usize>,
+    pub scripts: Option<usize>,
+}
+
+impl ComponentSizes {
+    /// The size of all the lumps together.
+    pub fn total(&self) -> usize {
+        self.things
+            + self.linedefs
+            + self.sidedefs
+            + self.vertexes
+            + self.segs
+            + self.subsectors
+            + self.nodes
+            + self.sectors
+            + self.reject.unwrap_or(0)
+            + self.blockmap
+            + self.behavior.unwrap_or(0)
+            + self.scripts.unwrap_or(0)
+    }
+}
+
+/// The size of a thing record in the THINGS lump.
+pub const THING_SIZE: usize = 10;
+/// The size of a thing record in a Hexen-format map's THINGS lump.
+pub const HEXEN_THING_SIZE: usize = 20;
+/// The size of a vertex record in the VERTEXES lump.
+pub const VERTEX_SIZE: usize = 4;
+/// The size of a linedef record in the LINEDEFS lump.
+pub const LINEDEF_SIZE: usize = 14;
+/// The size of a linedef record in a Hexen-format map's LINEDEFS lump.
+pub const HEXEN_LINEDEF_SIZE: usize = 16;
+/// The size of a sidedef record in the SIDEDEFS lump.
+pub const SIDEDEF_SIZE: usize = 30;
+/// The size of a sector record in the SECTORS lump.
+pub const SECTOR_SIZE: usize = 26;
+/// The size of a seg record in the SEGS lump.
+pub const SEG_SIZE: usize = 12;
+/// The size of a subsector record in the SSECTORS lump.
+pub const SUBSECTOR_SIZE: usize = 4;
+/// The size of a node record in the NODES lump.
+pub const NODE_SIZE: usize = 28;
+
+// `name` is the lump's, for the error
+fn records<'a>(
+    data: &'a [u8],
+    size: usize,
+    name: &str,
+) -> Result<std::slice::ChunksExact<'a, u8>, Error> {
+    if !data.len().is_multiple_of(size) {
+        return Err(Error::InvalidLumpSize(name.to_string(), data.len()));
+    }
+    Ok(data.chunks_exact(size))
+}
+
+// Hexen's records add a thing ID, a height, a special and its five
+// arguments; only the fields `MapThing` has are kept
+fn decode_things(
+    data: &[u8],
+    e: Endianness,
+    hexen: bool,
+) -> Result<impl Iterator<Item = MapThing> + '_, Error> {
+    // where the position starts, and the angle after it
+    let (size, at, angle) = if hexen {
+        (HEXEN_THING_SIZE, 2, 8)
+    } else {
+        (THING_SIZE, 0, 4)
+    };
+    Ok(records(data, size, "THINGS")?.map(move |r| MapThing {
+        x: e.read_i16(&r[at..]),
+        y: e.read_i16(&r[at + 2..]),
+        angle: e.read_i16(&r[angle..]),
+        doomednum: e.read_u16(&r[angle + 2..]),
+        flags: e.read_u16(&r[angle + 4..]),
+    }))
+}
+
+fn decode_vertexes(data: &[u8], e: Endianness) -> Result<Vec<Vertex>, Error> {
+    Ok(records(data, VERTEX_SIZE, "VERTEXES")?
+        .map(|r| Vertex {
+            x: e.read_i16(&r[0..]),
+            y: e.read_i16(&r[2..]),
+        })
+        .collect())
+}
+
+// Hexen's specials are a byte with five byte arguments in place of the
+// special and tag; most specials that act on sectors take the tag first, so
+// that's what `tag` is given
+fn decode_linedefs(
+    data: &[u8],
+    e: Endianness,
+    hexen: bool,
+) -> Result<impl Iterator<Item = Linedef> + '_, Error> {
+    let size = if hexen {
+        HEXEN_LINEDEF_SIZE
+    } else {
+        LINEDEF_SIZE
+    };
+    Ok(records(data, size, "LINEDEFS")?.map(move |r| {
+        let (special, tag, sides) = if hexen {
+            (u16::from(r[6]), u16::from(r[7]), &r[12..])
+        } else {
+            (e.read_u16(&r[6..]), e.read_u16(&r[8..]), &r[10..])
+        };
+        Linedef {
+            start_vertex: e.read_u16(&r[0..]),
+            end_vertex: e.read_u16(&r[2..]),
+            flags: e.read_u16(&r[4..]),
+            special,
+            tag,
+            right_sidedef: e.read_u16(&sides[0..]),
+            left_sidedef: e.read_u16(&sides[2..]),
+        }
+    }))
+}
+
+fn decode_sidedefs(data: &[u8], e: Endianness) -> Result<Vec<Sidedef>, Error> {
+    Ok(records(data, SIDEDEF_SIZE, "SIDEDEFS")?
+        .map(|r| Sidedef {
+            x_offset: e.read_i16(&r[0..]),
+            y_offset: e.read_i16(&r[2..]),
+            upper_texture: name_from_bytes(&r[4..]),
+            lower_texture: name_from_bytes(&r[12..]),
+            middle_texture: name_from_bytes(&r[20..]),
+            sector: e.read_u16(&r[28..]),
+        })
+        .collect())
+}
+
+fn decode_sectors(data: &[u8], e: Endianness) -> Result<impl Iterator<Item = Sector> + '_, Error> {
+    Ok(records(data, SECTOR_SIZE, "SECTORS")?.map(move |r| Sector {
+        floor_height: e.read_i16(&r[0..]),
+        ceiling_height: e.read_i16(&r[2..]),
+        floor_flat: name_from_bytes(&r[4..]),
+        ceiling_flat: name_from_bytes(&r[12..]),
+        light_level: e.read_i16(&r[20..]),
+        special: e.read_u16(&r[22..]),
+        tag: e.read_u16(&r[24..]),
+    }))
+}
+
+fn decode_segs(data: &[u8], e: Endianness) -> Result<Vec<Seg>, Error> {
+    Ok(records(data, SEG_SIZE, "SEGS")?
+        .map(|r| Seg {
+            start_vertex: e.read_u16(&r[0..]),
+            end_vertex: e.read_u16(&r[2..]),
+            angle: e.read_i16(&r[4..]),
+            linedef: e.read_u16(&r[6..]),
+            direction: e.read_i16(&r[8..]),
+            offset: e.read_i16(&r[10..]),
+        })
+        .collect())
+}
+
+fn decode_subsectors(data: &[u8], e: Endianness) -> Result<Vec<Subsector>, Error> {
+    Ok(records(data, SUBSECTOR_SIZE, "SSECTORS")?
+        .map(|r| Subsector {
+            seg_count: e.read_u16(&r[0..]),
+            first_seg: e.read_u16(&r[2..]),
+        })
+        .collect())
+}
+
+fn decode_nodes(data: &[u8], e: Endianness) -> Result<Vec<Node>, Error> {
+    let bbox = |r: &[u8]| [0, 2, 4, 6].map(|i| e.read_i16(&r[i..]));
+    Ok(records(data, NODE_SIZE, "NODES")?
+        .map(|r| Node {
+            x: e.read_i16(&r[0..]),
+            y: e.read_i16(&r[2..]),
+            dx: e.read_i16(&r[4..]),
+            dy: e.read_i16(&r[6..]),
+            right_bbox: bbox(&r[8..]),
+            left_bbox: bbox(&r[16..]),
+            right_child: e.read_u16(&r[24..]),
+            left_child: e.read_u16(&r[26..]),
+        })
+        .collect())
+}
+
+/// Decodes a little-endian THINGS lump's `THING_SIZE`-byte records, for lump
+/// data that doesn't come from a `Wad`. Like the other `parse_` functions, it
+/// fails with `Error::InvalidLumpSize` if the data isn't a whole number of
+/// records; the values themselves aren't checked.
+pub fn parse_things(data: &[u8]) -> Result<Vec<MapThing>, Error> {
+    Ok(decode_things(data, Endianness::Little, false)?.collect())
+}
+
+/// Decodes a VERTEXES lump's `VERTEX_SIZE`-byte records. See `parse_things`.
+pub fn parse_vertexes(data: &[u8]) -> Result<Vec<Vertex>, Error> {
+    decode_vertexes(data, Endianness::Little)
+}
+
+/// Decodes a LINEDEFS lump's `LINEDEF_SIZE`-byte records. See `parse_things`.
+pub fn parse_linedefs(data: &[u8]) -> Result<Vec<Linedef>, Error> {
+    Ok(decode_linedefs(data, Endianness::Little, false)?.collect())
+}
+
+/// Decodes a SIDEDEFS lump's `SIDEDEF_SIZE`-byte records. See `parse_things`.
+pub fn parse_sidedefs(data: &[u8]) -> Result<Vec<Sidedef>, Error> {
+    decode_sidedefs(data, Endianness::Little)
+}
+
+/// Decodes a SECTORS lump's `SECTOR_SIZE`-byte records. See `parse_things`.
+pub fn parse_sectors(data: &[u8]) -> Result<Vec<Sector>, Error> {
+    Ok(decode_sectors(data, Endianness::Little)?.collect())
+}
+
+/// Decodes a SEGS lump's `SEG_SIZE`-byte records. See `parse_things`.
+pub fn parse_segs(data: &[u8]) -> Result<Vec<Seg>, Error> {
+    decode_segs(data, Endianness::Little)
+}
+
+/// Decodes a SSECTORS lump's `SUBSECTOR_SIZE`-byte records. See
+/// `parse_things`.
+pub fn parse_subsectors(data: &[u8]) -> Result<Vec<Subsector>, Error> {
+    decode_subsectors(data, Endianness::Little)
+}
+
+/// Decodes a vanilla NODES lump's `NODE_SIZE`-byte records. See
+/// `parse_things`. Extended and ZDoom nodes aren't recognized and usually
+/// fail the size check.
+pub fn parse_nodes(data: &[u8]) -> Result<Vec<Node>, Error> {
+    decode_nodes(data, Endianness::Little)
+}
+
+// fills a record field by field, little-endian, the way the `parse_`
+// functions read it
+struct Record<const N: usize> {
+    bytes: [u8; N],
+    at: usize,
+}
+
+impl<const N: usize> Record<N> {
+    fn new() -> Self {
+        Self {
+            bytes: [0; N],
+            at: 0,
+        }
+    }
+
+    fn put(mut self, field: &[u8]) -> Self {
+        self.bytes[self.at..self.at + field.len()].copy_from_slice(field);
+        self.at += field.len();
+        self
+    }
+
+    fn i16(self, value: i16) -> Self {
+        self.put(&value.to_le_bytes())
+    }
+
+    fn u16(self, value: u16) -> Self {
+        self.put(&value.to_le_bytes())
+    }
+
+    // a texture or flat name, one byte per char as it was read, cut to 8
+    // bytes and padded with NULs as vanilla editors write them
+    fn name(self, name: &str) -> Self {
+        self.put(&name_to_bytes(name))
+    }
+
+    fn finish(self) -> [u8; N] {
+        debug_assert_eq!(self.at, N);
+        self.bytes
+    }
+}
+
+impl MapThing {
+    /// The thing as a little-endian THINGS record, which `parse_things`
+    /// decodes back into an equal `MapThing`.
+    pub fn to_bytes(&self) -> [u8; THING_SIZE] {
+        Record::new()
+            .i16(self.x)
+            .i16(self.y)
+            .i16(self.angle)
+            .u16(self.doomednum)
+            .u16(self.flags)
+            .finish()
+    }
+}
+
+impl Vertex {
+    /// Like `MapThing::to_bytes`, for a VERTEXES record.
+    pub fn to_bytes(&self) -> [u8; VERTEX_SIZE] {
+        Record::new().i16(self.x).i16(self.y).finish()
+    }
+}
+
+impl Linedef {
+    /// Like `MapThing::to_bytes`, for a LINEDEFS record.
+    pub fn to_bytes(&self) -> [u8; LINEDEF_SIZE] {
+        Record::new()
+            .u16(self.start_vertex)
+            .u16(self.end_vertex)
+            .u16(self.flags)
+            .u16(self.special)
+            .u16(self.tag)
+            .u16(self.right_sidedef)
+            .u16(self.left_sidedef)
+            .finish()
+    }
+}
+
+impl Sidedef {
+    /// Like `MapThing::to_bytes`, for a SIDEDEFS record. Texture names are
+    /// padded to 8 bytes with NULs. They come back from `parse_sidedefs`
+    /// uppercased, and cut at 8 bytes or the first NUL, so the round trip
+    /// only gives an equal `Sidedef` for names that follow those rules, as
+    /// every parsed one does.
+    pub fn to_bytes(&self) -> [u8; SIDEDEF_SIZE] {
+        Record::new()
+            .i16(self.x_offset)
+            .i16(self.y_offset)
+            .name(&self.upper_texture)
+            .name(&self.lower_texture)
+            .name(&self.middle_texture)
+            .u16(self.sector)
+            .finish()
+    }
+}
+
+impl Sector {
+    /// Like `Sidedef::to_bytes`, for a SECTORS record and its flat names.
+    pub fn to_bytes(&self) -> [u8; SECTOR_SIZE] {
+        Record::new()
+            .i16(self.floor_height)
+            .i16(self.ceiling_height)
+            .name(&self.floor_flat)
+            .name(&self.ceiling_flat)
+            .i16(self.light_level)
+            .u16(self.special)
+            .u16(self.tag)
+            .finish()
+    }
+}
+
+impl Seg {
+    /// Like `MapThing::to_bytes`, for a SEGS record.
+    pub fn to_bytes(&self) -> [u8; SEG_SIZE] {
+        Record::new()
+            .u16(self.start_vertex)
+            .u16(self.end_vertex)
+            .i16(self.angle)
+            .u16(self.linedef)
+            .i16(self.direction)
+            .i16(self.offset)
+            .finish()
+    }
+}
+
+impl Subsector {
+    /// Like `MapThing::to_bytes`, for a SSECTORS record.
+    pub fn to_bytes(&self) -> [u8; SUBSECTOR_SIZE] {
+        Record::new()
+            .u16(self.seg_count)
+            .u16(self.first_seg)
+            .finish()
+    }
+}
+
+impl Node {
+    /// Like `MapThing::to_bytes`, for a vanilla NODES record.
+    pub fn to_bytes(&self) -> [u8; NODE_SIZE] {
+        let mut record = Record::new()
+            .i16(self.x)
+            .i16(self.y)
+            .i16(self.dx)
+            .i16(self.dy);
+        for &side in self.right_bbox.iter().chain(&self.left_bbox) {
+            record = record.i16(side);
+        }
+        record.u16(self.right_child).u16(self.left_child).finish()
+    }
+}
+
+impl Map {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether this is ZDoom's `TITLEMAP`, played behind the title screen
+    /// rather than as a level.
+    pub fn is_title_map(&self) -> bool {
+        self.name.eq_ignore_ascii_case("TITLEMAP")
+    }
+
+    /// Whether the map is in Hexen's format, going by its `BEHAVIOR` lump,
+    /// which Doom-format maps don't have.
+    pub fn is_hexen_format(&self) -> bool {
+        self.behavior.is_some()
+    }
+
+    /// The map's GL node lumps, `GL_VERT`, `GL_SEGS`, `GL_SSECT`, `GL_NODES`
+    /// and `GL_PVS`, those it has. See `Wad::with_gwa` for where they come
+    /// from.
+    pub fn gl_lumps(&self) -> &[Lump] {
+        &self.gl
+    }
+
+    /// The version of the map's GL nodes, going by the magic numbers glBSP
+    /// and its successors put at the start of `GL_VERT` and `GL_SEGS`: 1 with
+    /// none, otherwise 2, 3 or 5. `None` if the map has no `GL_VERT`.
+    pub fn gl_nodes_version(&self) -> Option<u8> {
+        let lump = |name: &str| self.gl.iter().find(|l| l.name == name);
+        let vertexes = lump("GL_VERT")?;
+        let segs = lump("GL_SEGS").map_or(&[][..], |l| l.data.as_slice());
+        Some(if segs.starts_with(b"gNd3") {
+            3
+        } else if vertexes.data.starts_with(b"gNd5") {
+            5
+        } else if vertexes.data.starts_with(b"gNd2") {
+            2
+        } else {
+            1
+        })
+    }
+
+    pub fn component_sizes(&self) -> ComponentSizes {
+        let size = |lump: &Option<Lump>| lump.as_ref().map(|l| l.data.len());
+        ComponentSizes {
+            things: self.things.data.len(),
+            linedefs: self.linedefs.data.len(),
+            sidedefs: self.sidedefs.data.len(),
+            vertexes: self.vertexes.data.len(),
+            segs: self.segs.data.len(),
+            subsectors: self.subsectors.data.len(),
+            nodes: self.nodes.data.len(),
+            sectors: self.sectors.data.len(),
+            reject: size(&self.reject),
+            blockmap: self.blockmap.data.len(),
+            behavior: size(&self.behavior),
+            scripts: size(&self.scripts),
+        }
+    }
+
+    /// The lumps every map needs that are present but empty, which usually
+    /// means a broken export. The node lumps and the blockmap aren't checked,
+    /// since maps that haven't been through a node builder leave them empty.
+    pub fn empty_components(&self) -> Vec<&str> {
+        [
+            &self.things,
+            &self.linedefs,
+            &self.sidedefs,
+            &self.vertexes,
+            &self.sectors,
+        ]
+        .iter()
+        .filter(|l| l.data.is_empty())
+        .map(|l| l.name.as_str())
+        .collect()
+    }
+
+    /// The required lumps the map doesn't have, in directory order. Always
+    /// empty for the maps in `Wad::maps`; only the maps lenient parsing keeps
+    /// in `Wad::partial_maps` lack any. A map read with `ParseOptions::alpha`
+    /// isn't missing the lumps that option lets it go without.
+    pub fn missing_components(&self) -> Vec<LumpKind> {
+        self.missing.clone()
+    }
+
+    /// Whether the map lacks required lumps, as from `Wad::partial_maps`.
+    pub fn is_partial(&self) -> bool {
+        !self.missing.is_empty()
+    }
+
+    // a component's data, or `Error::MissingMapLump` for a partial map's
+    // placeholder, so nothing is decoded from a lump that isn't there
+    pub(crate) fn component<'a>(&self, lump: &'a Lump) -> Result<&'a [u8], Error> {
+        if self.missing.contains(&lump.kind) {
+            return Err(Error::MissingMapLump(self.name.clone(), lump.name.clone()));
+        }
+        Ok(&lump.data)
+    }
+
+    /// Fails with `Error::EmptyLump` if a lump that the geometry depends on is
+    /// empty, for statistics that would otherwise come out as zero, or with
+    /// `Error::MissingMapLump` if a partial map doesn't have it at all.
+    pub(crate) fn require_geometry(&self) -> Result<(), Error> {
+        for lump in [
+            &self.linedefs,
+            &self.sidedefs,
+            &self.vertexes,
+            &self.sectors,
+        ] {
+            self.component(lump)?;
+        }
+        match [
+            &self.linedefs,
+            &self.sidedefs,
+            &self.vertexes,
+            &self.sectors,
+        ]
+        .iter()
+        .find(|l| l.data.is_empty())
+        {
+            Some(l) => Err(Error::EmptyLump(self.name.clone(), l.name.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Decodes the things one at a time, for a single pass that doesn't
+    /// need them all in memory. The lump's size is checked before any are
+    /// decoded. A Hexen-format map's `HEXEN_THING_SIZE`-byte records are
+    /// read for the fields Doom's have.
+    pub fn iter_things(&self) -> Result<impl Iterator<Item = MapThing> + '_, Error> {
+        decode_things(
+            self.component(&self.things)?,
+            self.endianness,
+            self.is_hexen_format(),
+        )
+    }
+
+    pub fn things(&self) -> Result<Vec<MapThing>, Error> {
+        Ok(self.iter_things()?.collect())
+    }
+
+    pub fn vertexes(&self) -> Result<Vec<Vertex>, Error> {
+        decode_vertexes(self.component(&self.vertexes)?, self.endianness)
+    }
+
+    /// Like `iter_things`, for the linedefs. A Hexen-format linedef's
+    /// special is its one-byte special, and its tag the special's first
+    /// argument.
+    pub fn iter_linedefs(&self) -> Result<impl Iterator<Item = Linedef> + '_, Error> {
+        decode_linedefs(
+            self.component(&self.linedefs)?,
+            self.endianness,
+            self.is_hexen_format(),
+        )
+    }
+
+    pub fn linedefs(&self) -> Result<Vec<Linedef>, Error> {
+        Ok(self.iter_linedefs()?.collect())
+    }
+
+    pub fn sidedefs(&self) -> Result<Vec<Sidedef>, Error> {
+        decode_sidedefs(self.component(&self.sidedefs)?, self.endianness)
+    }
+
+    /// Like `iter_things`, for the sectors.
+    pub fn iter_sectors(&self) -> Result<impl Iterator<Item = Sector> + '_, Error> {
+        decode_sectors(self.component(&self.sectors)?, self.endianness)
+    }
+
+    pub fn sectors(&self) -> Result<Vec<Sector>, Error> {
+        Ok(self.iter_sectors()?.collect())
+    }
+
+    pub fn segs(&self) -> Result<Vec<Seg>, Error> {
+        decode_segs(self.component(&self.segs)?, self.endianness)
+    }
+
+    pub fn subsectors(&self) -> Result<Vec<Subsector>, Error> {
+        decode_subsectors(self.component(&self.subsectors)?, self.endianness)
+    }
+
+    pub fn nodes(&self) -> Result<Vec<Node>, Error> {
+        decode_nodes(self.component(&self.nodes)?, self.endianness)
+    }
+
+    /// Every wall texture referenced by a sidedef, excluding the `-` placeholder.
+    pub fn used_textures(&self) -> Result<BTreeSet<String>, Error> {
+        let mut textures = BTreeSet::new();
+        for side in self.sidedefs()? {
+            for texture in [side.upper_texture, side.lower_texture, side.middle_texture] {
+                if texture != "-" && !texture.is_empty() {
+                    textures.insert(texture);
+                }
+            }
+        }
+        Ok(textures)
+    }
+
+    /// Every flat referenced by a sector floor or ceiling.
+    pub fn used_flats(&self) -> Result<BTreeSet<String>, Error> {
+        let mut flats = BTreeSet::new();
+        for sector in self.sectors()? {
+            flats.insert(sector.floor_flat);
+            flats.insert(sector.ceiling_flat);
+        }
+        Ok(flats)
+    }
+
+    pub fn animated_textures(&self) -> Result<Vec<String>, Error> {
+        self.animated_textures_in(&vanilla_animations())
+    }
+
+    pub fn animated_flats(&self) -> Result<Vec<String>, Error> {
+        self.animated_flats_in(&vanilla_animations())
+    }
+
+    /// Like `animated_textures`, but checked against a caller-supplied
+    /// animation table (e.g. the vanilla set extended with an `ANIMATED` lump).
+    pub fn animated_textures_in(&self, groups: &[AnimationGroup]) -> Result<Vec<String>, Error> {
+        Ok(animated(
+            self.used_textures()?,
+            groups,
+            AnimationKind::Texture,
+        ))
+    }
+
+    pub fn animated_flats_in(&self, groups: &[AnimationGroup]) -> Result<Vec<String>, Error> {
+        Ok(animated(self.used_flats()?, groups, AnimationKind::Flat))
+    }
+}
+
+fn animated(used: BTreeSet<String>, groups: &[AnimationGroup], kind: AnimationKind) -> Vec<String> {
+    used.into_iter()
+        .filter(|name| groups.iter().any(|g| g.kind == kind && g.contains(name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_round_trip_through_their_bytes() {
+        let thing = MapThing {
+            x: -1024,
+            y: 32767,
+            angle: 270,
+            doomednum: 3004,
+            flags: 0x0017,
+        };
+        assert_eq!(parse_things(&thing.to_bytes()).unwrap(), [thing]);
+
+        let vertex = Vertex { x: -32768, y: 96 };
+        assert_eq!(parse_vertexes(&vertex.to_bytes()).unwrap(), [vertex]);
+
+        let linedef = Linedef {
+            start_vertex: 0,
+            end_vertex: 65534,
+            flags: 0x0024,
+            special: 97,
+            tag: 12,
+            right_sidedef: 3,
+            left_sidedef: NO_SIDEDEF,
+        };
+        assert_eq!(parse_linedefs(&linedef.to_bytes()).unwrap(), [linedef]);
+
+        let sidedef = Sidedef {
+            x_offset: -8,
+            y_offset: 64,
+            upper_texture: "STARTAN3".to_string(),
+            lower_texture: "-".to_string(),
+            middle_texture: "\u{C9}CLAIR".to_string(),
+            sector: 5,
+        };
+        let bytes = sidedef.to_bytes();
+        assert_eq!(&bytes[20..28], b"\xC9CLAIR\0\0");
+        assert_eq!(parse_sidedefs(&bytes).unwrap(), [sidedef]);
+
+        let sector = Sector {
+            floor_height: -48,
+            ceiling_height: 200,
+            floor_flat: "NUKAGE1".to_string(),
+            ceiling_flat: "F_SKY1".to_string(),
+            light_level: 255,
+            special: 7,
+            tag: 3,
+        };
+        assert_eq!(parse_sectors(&sector.to_bytes()).unwrap(), [sector]);
+
+        let seg = Seg {
+            start_vertex: 4,
+            end_vertex: 5,
+            angle: -16384,
+            linedef: 9,
+            direction: 1,
+            offset: 32,
+        };
+        assert_eq!(parse_segs(&seg.to_bytes()).unwrap(), [seg]);
+
+        let subsector = Subsector {
+            seg_count: 3,
+            first_seg: 40,
+        };
+        assert_eq!(
+            parse_subsectors(&subsector.to_bytes()).unwrap(),
+            [subsector]
+        );
+
+        let node = Node {
+            x: 64,
+            y: -64,
+            dx: 0,
+            dy: 128,
+            right_bbox: [128, -128, 64, 256],
+            left_bbox: [128, -128, -192, 64],
+            right_child: 2,
+            left_child: 7 | NODE_SUBSECTOR,
+        };
+        assert_eq!(parse_nodes(&node.to_bytes()).unwrap(), [node]);
+    }
+
+    #[test]
+    fn long_names_are_cut_at_eight_chars() {
+        let sidedef = Sidedef {
+            x_offset: 0,
+            y_offset: 0,
+            upper_texture: "\u{C9}\u{C9}\u{C9}\u{C9}\u{C9}\u{C9}\u{C9}\u{C9}XX".to_string(),
+            lower_texture: "-".to_string(),
+            middle_texture: "-".to_string(),
+            sector: 0,
+        };
+        let bytes = sidedef.to_bytes();
+        assert_eq!(&bytes[4..12], &[0xC9; 8]);
+        assert_eq!(bytes[12], b'-');
+        let upper = &parse_sidedefs(&bytes).unwrap()[0].upper_texture;
+        assert_eq!(upper, &"\u{C9}".repeat(8));
+    }
+
+    #[test]
+    fn hexen_format_things_and_linedefs_are_decoded() {
+        use crate::builder::square_room;
+        use crate::builder::WadBuilder;
+        use crate::wad::Wad;
+
+        let mut things = vec![];
+        // an ettin: tid 7, at (96, -32), 16 units up, facing 180, easy only
+        for field in [7i16, 96, -32, 16, 180, 10030, 0x0001] {
+            things.extend_from_slice(&field.to_le_bytes());
+        }
+        things.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut linedefs = vec![];
+        for field in [0u16, 1, 0x0001] {
+            linedefs.extend_from_slice(&field.to_le_bytes());
+        }
+        // Door_Open, tag 5, speed 16
+        linedefs.extend_from_slice(&[11, 5, 16, 0, 0, 0]);
+        linedefs.extend_from_slice(&0u16.to_le_bytes());
+        linedefs.extend_from_slice(&NO_SIDEDEF.to_le_bytes());
+
+        let bytes = WadBuilder::new()
+            .map(
+                square_room("MAP01")
+                    .lump("THINGS", things)
+                    .lump("LINEDEFS", linedefs)
+                    .lump("BEHAVIOR", b"ACS\0\x08\0\0\0\0\0\0\0".to_vec()),
+            )
+            .build();
+        let wad = Wad::from_reader(std::io::Cursor::new(bytes)).unwrap();
+        let map = &wad.maps()[0];
+
+        assert!(map.is_hexen_format());
+        let thing = MapThing {
+            x: 96,
+            y: -32,
+            angle: 180,
+            doomednum: 10030,
+            flags: 0x0001,
+        };
+        assert_eq!(map.things().unwrap(), [thing]);
+        let linedef = Linedef {
+            start_vertex: 0,
+            end_vertex: 1,
+            flags: 0x0001,
+            special: 11,
+            tag: 5,
+            right_sidedef: 0,
+            left_sidedef: NO_SIDEDEF,
+        };
+        assert_eq!(map.linedefs().unwrap(), [linedef]);
+    }
+
+    #[test]
+    fn doom_sized_records_are_rejected_in_hexen_maps() {
+        use crate::builder::square_room;
+        use crate::builder::WadBuilder;
+        use crate::wad::Wad;
+
+        let bytes = WadBuilder::new()
+            .map(square_room("MAP01").lump("BEHAVIOR", vec![0; 16]))
+            .build();
+        let wad = Wad::from_reader(std::io::Cursor::new(bytes)).unwrap();
+        let map = &wad.maps()[0];
+        // one 10-byte thing and four 14-byte linedefs
+        assert!(matches!(map.things(), Err(Error::InvalidLumpSize(..))));
+        assert!(matches!(map.linedefs(), Err(Error::InvalidLumpSize(..))));
+    }
+
+    #[test]
+    fn empty_vertexes_fail_geometry_statistics() {
+        use crate::builder::square_room;
+        use crate::builder::WadBuilder;
+        use crate::stats::StatSet;
+        use crate::things::ThingTable;
+        use crate::wad::Wad;
+
+        let bytes = WadBuilder::new()
+            .map(square_room("MAP01").lump("VERTEXES", vec![]))
+            .build();
+        let wad = Wad::from_reader(std::io::Cursor::new(bytes)).unwrap();
+        let map = &wad.maps()[0];
+        assert_eq!(map.empty_components(), ["VERTEXES"]);
+
+        let empty = |result: Result<(), Error>| matches!(result, Err(Error::EmptyLump(m, l)) if m == "MAP01" && l == "VERTEXES");
+        assert!(empty(map.sector_areas().map(drop)));
+        assert!(empty(map.floor_area().map(drop)));
+        assert!(empty(map.detail_ratio().map(drop)));
+        assert!(empty(map.bounding_box_aspect_ratio().map(drop)));
+        assert!(empty(map.shape().map(drop)));
+        assert!(empty(map.detail_line_ratio().map(drop)));
+        assert!(empty(map.geometry_centroid().map(drop)));
+        assert!(empty(map.linedef_length_stats().map(drop)));
+        assert!(empty(
+            map.thing_heatmap(64, &[], ThingTable::Doom).map(drop)
+        ));
+        assert!(empty(map.out_of_bounds_things().map(drop)));
+        assert!(empty(map.embedded_things(ThingTable::Doom).map(drop)));
+        assert!(empty(map.floating_things(ThingTable::Doom).map(drop)));
+        assert!(empty(
+            map.stats_with(StatSet::AREA, ThingTable::Doom).map(drop)
+        ));
+        assert!(empty(
+            map.stats_with(StatSet::SIZE, ThingTable::Doom).map(drop)
+        ));
+        // the counts alone don't need the geometry
+        assert_eq!(map.stats().unwrap().vertexes, 0);
+
+        let diagnostics = wad.validate();
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.message == "the VERTEXES lump is empty"));
+        assert!(!diagnostics
+            .iter()
+            .any(|d| d.message.contains("empty VERTEXES")));
+    }
+}
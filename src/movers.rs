@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use crate::map::Map;
+use crate::map::NO_SIDEDEF;
+use crate::Error;
+
+/// What a linedef special moves in the sectors it acts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoverKind {
+    /// Doors, opening or closing the ceiling.
+    Door,
+    /// Floors raising or lowering, stairs and donuts included.
+    Floor,
+    /// Ceilings raising or lowering, crushers included.
+    Ceiling,
+    /// Lifts and the other platforms, which lower or raise a floor and may
+    /// return.
+    Lift,
+}
+
+// vanilla doors opened remotely through the line's tag
+const TAGGED_DOORS: [u16; 33] = [
+    2, 3, 4, 16, 29, 42, 46, 50, 61, 63, 75, 76, 86, 90, 99, 103, 105, 106, 107, 108, 109, 110,
+    111, 112, 113, 114, 115, 116, 133, 134, 135, 136, 137,
+];
+// vanilla doors opened from the line itself, moving the sector behind it
+const MANUAL_DOORS: [u16; 10] = [1, 26, 27, 28, 31, 32, 33, 34, 117, 118];
+const FLOORS: [u16; 43] = [
+    5, 7, 8, 9, 18, 19, 23, 24, 30, 36, 37, 38, 45, 55, 56, 58, 59, 60, 64, 65, 69, 70, 71, 82, 83,
+    84, 91, 92, 93, 94, 96, 98, 100, 101, 102, 119, 127, 128, 129, 130, 131, 132, 140,
+];
+const LIFTS: [u16; 19] = [
+    10, 14, 15, 20, 21, 22, 47, 53, 62, 66, 67, 68, 87, 88, 95, 120, 121, 122, 123,
+];
+const CEILINGS: [u16; 11] = [6, 25, 40, 41, 43, 44, 49, 72, 73, 77, 141];
+
+// Boom's generalized linedef ranges
+const GENERALIZED_CRUSHERS: Range<u16> = 0x2F80..0x3000;
+const GENERALIZED_STAIRS: Range<u16> = 0x3000..0x3400;
+const GENERALIZED_LIFTS: Range<u16> = 0x3400..0x3800;
+const GENERALIZED_DOORS: Range<u16> = 0x3800..0x4000;
+const GENERALIZED_CEILINGS: Range<u16> = 0x4000..0x6000;
+const GENERALIZED_FLOORS: Range<u16> = 0x6000..0x8000;
+
+/// What a special moves, and whether it acts on the sector behind the line
+/// rather than the sectors with the line's tag.
+fn mover(special: u16) -> Option<(MoverKind, bool)> {
+    Some(match special {
+        s if MANUAL_DOORS.contains(&s) => (MoverKind::Door, true),
+        s if TAGGED_DOORS.contains(&s) => (MoverKind::Door, false),
+        // a generalized door's trigger is manual for D1 and DR, 6 and 7
+        s if GENERALIZED_DOORS.contains(&s) => (MoverKind::Door, s & 7 >= 6),
+        s if FLOORS.contains(&s)
+            || GENERALIZED_FLOORS.contains(&s)
+            || GENERALIZED_STAIRS.contains(&s) =>
+        {
+            (MoverKind::Floor, false)
+        }
+        s if CEILINGS.contains(&s)
+            || GENERALIZED_CEILINGS.contains(&s)
+            || GENERALIZED_CRUSHERS.contains(&s) =>
+        {
+            (MoverKind::Ceiling, false)
+        }
+        s if LIFTS.contains(&s) || GENERALIZED_LIFTS.contains(&s) => (MoverKind::Lift, false),
+        _ => return None,
+    })
+}
+
+/// How many of a map's sectors each kind of mover acts on. A sector moved
+/// in more than one way counts once under each kind, and once in `total`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DynamicSectors {
+    pub doors: usize,
+    pub floors: usize,
+    pub ceilings: usize,
+    pub lifts: usize,
+    pub total: usize,
+}
+
+impl Map {
+    /// The sectors that linedef specials move, by kind. A tagged special acts
+    /// on every sector with its tag, and a manual door on the sector behind
+    /// its line. The specials are Doom's and Boom's:
+    ///
+    /// - Doors: 1-4, 16, 26-29, 31-34, 42, 46, 50, 61, 63, 75, 76, 86, 90, 99,
+    ///   103, 105-118 and 133-137, and generalized doors and locked doors.
+    /// - Floors: 5, 7-9, 18, 19, 23, 24, 30, 36-38, 45, 55, 56, 58-60, 64,
+    ///   65, 69-71, 82-84, 91-94, 96, 98, 100-102, 119, 127-132 and 140, and
+    ///   generalized floors and stairs.
+    /// - Ceilings: 6, 25, 40, 41, 43, 44, 49, 72, 73, 77 and 141, and
+    ///   generalized ceilings and crushers.
+    /// - Lifts: 10, 14, 15, 20-22, 47, 53, 62, 66-68, 87, 88, 95 and 120-123,
+    ///   and generalized lifts.
+    ///
+    /// Tagged specials with tag 0 are left out; vanilla would move the
+    /// untagged sectors, which is never intended. Stairs and donuts are
+    /// counted by the sector they're tagged to, not the ones they spread to.
+    pub fn dynamic_sectors(&self) -> Result<DynamicSectors, Error> {
+        let sidedefs = self.sidedefs()?;
+        let sectors = self.sectors()?;
+        let mut moved = [
+            BTreeSet::new(),
+            BTreeSet::new(),
+            BTreeSet::new(),
+            BTreeSet::new(),
+        ];
+        for line in self.linedefs()? {
+            let (kind, manual) = match mover(line.special) {
+                Some(mover) => mover,
+                None => continue,
+            };
+            let set = &mut moved[kind as usize];
+            if manual {
+                if line.left_sidedef == NO_SIDEDEF {
+                    continue;
+                }
+                if let Some(side) = sidedefs.get(line.left_sidedef as usize) {
+                    if (side.sector as usize) < sectors.len() {
+                        set.insert(side.sector as usize);
+                    }
+                }
+            } else if line.tag != 0 {
+                set.extend(
+                    sectors
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, s)| s.tag == line.tag)
+                        .map(|(i, _)| i),
+                );
+            }
+        }
+        let [doors, floors, ceilings, lifts] = &moved;
+        Ok(DynamicSectors {
+            doors: doors.len(),
+            floors: floors.len(),
+            ceilings: ceilings.len(),
+            lifts: lifts.len(),
+            total: moved.iter().flatten().collect::<BTreeSet<_>>().len(),
+        })
+    }
+
+    /// How many sectors a linedef special moves, as counted by
+    /// `dynamic_sectors`. The rest are static geometry.
+    pub fn dynamic_sector_count(&self) -> Result<usize, Error> {
+        Ok(self.dynamic_sectors()?.total)
+    }
+
+    /// The linedefs using each nonzero tag, in tag order and then linedef
+    /// order. A tag with several linedefs is driven by several triggers, as
+    /// in traps and sequences that reuse one set of sectors; one with a single
+    /// linedef has a single trigger. Tagged linedefs without a special are
+    /// included, since Boom's translucency and scrolling specials pick out
+    /// other linedefs by their tag.
+    pub fn shared_tags(&self) -> Result<BTreeMap<u16, Vec<usize>>, Error> {
+        let mut tags = BTreeMap::<u16, Vec<usize>>::new();
+        for (i, line) in self.linedefs()?.iter().enumerate() {
+            if line.tag != 0 {
+                tags.entry(line.tag).or_default().push(i);
+            }
+        }
+        Ok(tags)
+    }
+}
@@ -0,0 +1,33 @@
+use crate::game::is_sky_flat;
+use crate::map::Map;
+use crate::Error;
+
+/// The height that sky sectors are capped at by `Map::openness`.
+pub const DEFAULT_SKY_CAP: i16 = 256;
+
+impl Map {
+    /// The area-weighted mean of ceiling minus floor height across sectors, with
+    /// the default sky cap. Low values mean cramped corridors, high values open
+    /// spaces.
+    pub fn openness(&self) -> Result<f64, Error> {
+        self.openness_with_sky_cap(DEFAULT_SKY_CAP)
+    }
+
+    /// Like `openness`, counting sectors with a sky ceiling as at most `sky_cap`
+    /// units tall so that outdoor areas with tall skies don't dominate. Closed
+    /// sectors such as shut doors count as zero height.
+    pub fn openness_with_sky_cap(&self, sky_cap: i16) -> Result<f64, Error> {
+        let sectors = self.sectors()?;
+        let areas = self.sector_areas()?;
+        let (mut weighted, mut total) = (0.0, 0.0);
+        for (sector, area) in sectors.iter().zip(areas).filter(|(_, a)| !a.is_nan()) {
+            let mut height = (sector.ceiling_height as f64 - sector.floor_height as f64).max(0.0);
+            if is_sky_flat(&sector.ceiling_flat) {
+                height = height.min(sky_cap as f64);
+            }
+            weighted += height * area;
+            total += area;
+        }
+        Ok(if total > 0.0 { weighted / total } else { 0.0 })
+    }
+}
@@ -0,0 +1,56 @@
+/// Case-insensitive glob matching supporting `*`, `?` and `[...]` classes
+/// (with `!` or `^` negation and `a-z` ranges).
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_ascii_uppercase().chars().collect::<Vec<_>>();
+    let name = name.to_ascii_uppercase().chars().collect::<Vec<_>>();
+    matches(&pattern, &name)
+}
+
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|i| matches(&pattern[1..], &name[i..])),
+        Some('?') => !name.is_empty() && matches(&pattern[1..], &name[1..]),
+        Some('[') => match (class_end(pattern), name.first()) {
+            (Some(end), Some(&c)) => {
+                in_class(&pattern[1..end], c) && matches(&pattern[end + 1..], &name[1..])
+            }
+            // an unterminated class is a literal '['
+            (None, Some('[')) => matches(&pattern[1..], &name[1..]),
+            _ => false,
+        },
+        Some(&p) => name.first() == Some(&p) && matches(&pattern[1..], &name[1..]),
+    }
+}
+
+fn class_end(pattern: &[char]) -> Option<usize> {
+    // a ']' right after the opening bracket (or negation) is a literal member
+    let start = match pattern.get(1) {
+        Some('!') | Some('^') => 3,
+        _ => 2,
+    };
+    pattern
+        .iter()
+        .skip(start)
+        .position(|&c| c == ']')
+        .map(|i| i + start)
+}
+
+fn in_class(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
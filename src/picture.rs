@@ -0,0 +1,693 @@
+use std::sync::OnceLock;
+
+use byteorder::ByteOrder;
+use byteorder::LE;
+
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::Severity;
+use crate::game::Game;
+use crate::lumps::MARKER_SECTIONS;
+use crate::png::encode_rgb;
+use crate::wad::Lump;
+use crate::wad::Wad;
+use crate::Error;
+
+/// The 256 colors palette indexes stand for, such as the first palette in
+/// `PLAYPAL`.
+#[derive(Debug, Clone)]
+pub struct Palette {
+    pub colors: [[u8; 3]; 256],
+    // for each cell of the color cube, the colors that can be nearest to a
+    // point in it, built the first time a color is looked up
+    candidates: OnceLock<Vec<Vec<u8>>>,
+}
+
+// the color cube is split into GRID_CELLS cells along each channel
+const GRID_CELLS: usize = 16;
+const CELL_WIDTH: usize = 256 / GRID_CELLS;
+
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(&b)
+        .map(|(&a, &b)| (a as i32 - b as i32).pow(2) as u32)
+        .sum()
+}
+
+impl Palette {
+    pub fn new(colors: [[u8; 3]; 256]) -> Self {
+        Self {
+            colors,
+            candidates: OnceLock::new(),
+        }
+    }
+
+    /// The index of the palette color nearest `rgb`, by squared distance in
+    /// RGB, and the lowest such index if several are as near. This inverts
+    /// the palette for converting images to Doom graphics: each of the
+    /// palette's own colors gives back its first index.
+    ///
+    /// The first lookup splits the color cube into a grid and notes, for each
+    /// cell, the few colors that can be nearest to a point in it, so later
+    /// lookups only compare against those.
+    pub fn nearest_index(&self, rgb: [u8; 3]) -> u8 {
+        let candidates = self.candidates.get_or_init(|| self.build_candidates());
+        let cell = rgb
+            .iter()
+            .fold(0, |cell, &c| cell * GRID_CELLS + c as usize / CELL_WIDTH);
+        let mut best = (u32::MAX, 0);
+        for &i in &candidates[cell] {
+            let d = distance(rgb, self.colors[i as usize]);
+            if d < best.0 {
+                best = (d, i);
+            }
+        }
+        best.1
+    }
+
+    fn build_candidates(&self) -> Vec<Vec<u8>> {
+        // the nearest and farthest a color can be from a point in the span
+        let channel_range = |c: u8, low: usize| {
+            let (c, high) = (c as i64, low as i64 + CELL_WIDTH as i64 - 1);
+            let low = low as i64;
+            let near = if c < low {
+                low - c
+            } else if c > high {
+                c - high
+            } else {
+                0
+            };
+            let far = (c - low).abs().max((c - high).abs());
+            (near * near, far * far)
+        };
+        let mut cells = Vec::with_capacity(GRID_CELLS.pow(3));
+        for r in 0..GRID_CELLS {
+            for g in 0..GRID_CELLS {
+                for b in 0..GRID_CELLS {
+                    let corner = [r, g, b].map(|n| n * CELL_WIDTH);
+                    let ranges = self
+                        .colors
+                        .iter()
+                        .map(|color| {
+                            (0..3).fold((0, 0), |(near, far), channel| {
+                                let (n, f) = channel_range(color[channel], corner[channel]);
+                                (near + n, far + f)
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    // no color farther than this from the whole cell can win
+                    let bound = ranges.iter().map(|&(_, far)| far).min().unwrap_or(0);
+                    cells.push(
+                        (0..=255u8)
+                            .filter(|&i| ranges[i as usize].0 <= bound)
+                            .collect(),
+                    );
+                }
+            }
+        }
+        cells
+    }
+}
+
+const RAW_SCREEN_WIDTH: u16 = 320;
+const RAW_SCREEN_HEIGHT: u16 = 200;
+
+/// An image in Doom's column-based picture format, as used for patches, sprites
+/// and full-screen graphics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Picture {
+    pub width: u16,
+    pub height: u16,
+    pub left_offset: i16,
+    pub top_offset: i16,
+    /// Palette indexes row by row from the top left; `None` is transparent.
+    pub pixels: Vec<Option<u8>>,
+}
+
+impl Picture {
+    pub fn decode(lump: &Lump) -> Result<Self, Error> {
+        let data = &lump.data;
+        let invalid = || Error::InvalidPicture(lump.name.clone());
+        if data.len() < 8 {
+            return Err(invalid());
+        }
+        let width = LE::read_u16(&data[0..]);
+        let height = LE::read_u16(&data[2..]);
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        // every column has to start inside the lump before its pixels are
+        // allocated, so a bogus size can't ask for gigabytes
+        let offsets = data
+            .get(8..8 + width as usize * 4)
+            .ok_or_else(invalid)?
+            .chunks_exact(4)
+            .map(|offset| LE::read_u32(offset) as usize)
+            .collect::<Vec<_>>();
+        if offsets.iter().any(|&offset| offset >= data.len()) {
+            return Err(invalid());
+        }
+        let mut pixels = vec![None; width as usize * height as usize];
+
+        for (x, &offset) in offsets.iter().enumerate() {
+            let mut pos = offset;
+            let mut last_top = None;
+            loop {
+                let top = *data.get(pos).ok_or_else(invalid)? as usize;
+                if top == 0xFF {
+                    break;
+                }
+                // tall patches continue a column with a delta relative to the last post
+                let top = match last_top {
+                    Some(last) if top <= last => last + top,
+                    _ => top,
+                };
+                last_top = Some(top);
+                let length = *data.get(pos + 1).ok_or_else(invalid)? as usize;
+                let post = data.get(pos + 3..pos + 3 + length).ok_or_else(invalid)?;
+                for (y, &index) in (top..).zip(post) {
+                    if y < height as usize {
+                        pixels[y * width as usize + x] = Some(index);
+                    }
+                }
+                pos += length + 4;
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            left_offset: LE::read_i16(&data[4..]),
+            top_offset: LE::read_i16(&data[6..]),
+            pixels,
+        })
+    }
+
+    /// Decodes a full-screen graphic. Heretic and Hexen store theirs as raw
+    /// 320x200 palette indexes rather than as pictures, so a lump of exactly
+    /// that size is read that way.
+    pub fn decode_screen(lump: &Lump) -> Result<Self, Error> {
+        if lump.data.len() != RAW_SCREEN_WIDTH as usize * RAW_SCREEN_HEIGHT as usize {
+            return Self::decode(lump);
+        }
+        Ok(Self {
+            width: RAW_SCREEN_WIDTH,
+            height: RAW_SCREEN_HEIGHT,
+            left_offset: 0,
+            top_offset: 0,
+            pixels: lump.data.iter().copied().map(Some).collect(),
+        })
+    }
+
+    /// Encodes the picture as a PNG, with transparent pixels drawn black.
+    /// `None` for a picture with no width or height, which PNG can't hold, or
+    /// whose pixels don't fill it; `decode` gives neither.
+    pub fn to_png(&self, palette: &Palette) -> Option<Vec<u8>> {
+        let mut rgb = Vec::with_capacity(self.pixels.len() * 3);
+        for pixel in &self.pixels {
+            match pixel {
+                Some(index) => rgb.extend_from_slice(&palette.colors[*index as usize]),
+                None => rgb.extend_from_slice(&[0, 0, 0]),
+            }
+        }
+        encode_rgb(self.width as u32, self.height as u32, &rgb)
+    }
+}
+
+/// The first palette in the `PLAYPAL` of Doom, Doom II and Final Doom, which
+/// all share it. A `PLAYPAL` starting with it is taken for the stock one; the
+/// other 13 palettes, for pain and pickup flashes, aren't compared.
+const DOOM_PALETTE: [[u8; 3]; 256] = [
+    [0, 0, 0],
+    [31, 23, 11],
+    [23, 15, 7],
+    [75, 75, 75],
+    [255, 255, 255],
+    [27, 27, 27],
+    [19, 19, 19],
+    [11, 11, 11],
+    [7, 7, 7],
+    [47, 55, 31],
+    [35, 43, 15],
+    [23, 31, 7],
+    [15, 23, 0],
+    [79, 59, 43],
+    [71, 51, 35],
+    [63, 43, 27],
+    [255, 183, 183],
+    [247, 171, 171],
+    [243, 163, 163],
+    [235, 151, 151],
+    [231, 143, 143],
+    [223, 135, 135],
+    [219, 123, 123],
+    [211, 115, 115],
+    [203, 107, 107],
+    [199, 99, 99],
+    [191, 91, 91],
+    [187, 87, 87],
+    [179, 79, 79],
+    [175, 71, 71],
+    [167, 63, 63],
+    [163, 59, 59],
+    [155, 51, 51],
+    [151, 47, 47],
+    [143, 43, 43],
+    [139, 35, 35],
+    [131, 31, 31],
+    [127, 27, 27],
+    [119, 23, 23],
+    [115, 19, 19],
+    [107, 15, 15],
+    [103, 11, 11],
+    [95, 7, 7],
+    [91, 7, 7],
+    [83, 7, 7],
+    [79, 0, 0],
+    [71, 0, 0],
+    [67, 0, 0],
+    [255, 235, 223],
+    [255, 227, 211],
+    [255, 219, 199],
+    [255, 211, 187],
+    [255, 207, 179],
+    [255, 199, 167],
+    [255, 191, 155],
+    [255, 187, 147],
+    [255, 179, 131],
+    [247, 171, 123],
+    [239, 163, 115],
+    [231, 155, 107],
+    [223, 147, 99],
+    [215, 139, 91],
+    [207, 131, 83],
+    [203, 127, 79],
+    [191, 123, 75],
+    [179, 115, 71],
+    [171, 111, 67],
+    [163, 107, 63],
+    [155, 99, 59],
+    [143, 95, 55],
+    [135, 87, 51],
+    [127, 83, 47],
+    [119, 79, 43],
+    [107, 71, 39],
+    [95, 67, 35],
+    [83, 63, 31],
+    [75, 55, 27],
+    [63, 47, 23],
+    [51, 43, 19],
+    [43, 35, 15],
+    [239, 239, 239],
+    [231, 231, 231],
+    [223, 223, 223],
+    [219, 219, 219],
+    [211, 211, 211],
+    [203, 203, 203],
+    [199, 199, 199],
+    [191, 191, 191],
+    [183, 183, 183],
+    [179, 179, 179],
+    [171, 171, 171],
+    [167, 167, 167],
+    [159, 159, 159],
+    [151, 151, 151],
+    [147, 147, 147],
+    [139, 139, 139],
+    [131, 131, 131],
+    [127, 127, 127],
+    [119, 119, 119],
+    [111, 111, 111],
+    [107, 107, 107],
+    [99, 99, 99],
+    [91, 91, 91],
+    [87, 87, 87],
+    [79, 79, 79],
+    [71, 71, 71],
+    [67, 67, 67],
+    [59, 59, 59],
+    [55, 55, 55],
+    [47, 47, 47],
+    [39, 39, 39],
+    [35, 35, 35],
+    [119, 255, 111],
+    [111, 239, 103],
+    [103, 223, 95],
+    [95, 207, 87],
+    [91, 191, 79],
+    [83, 175, 71],
+    [75, 159, 63],
+    [67, 147, 55],
+    [63, 131, 47],
+    [55, 115, 43],
+    [47, 99, 35],
+    [39, 83, 27],
+    [31, 67, 23],
+    [23, 51, 15],
+    [19, 35, 11],
+    [11, 23, 7],
+    [191, 167, 143],
+    [183, 159, 135],
+    [175, 151, 127],
+    [167, 143, 119],
+    [159, 135, 111],
+    [155, 127, 107],
+    [147, 123, 99],
+    [139, 115, 91],
+    [131, 107, 87],
+    [123, 99, 79],
+    [119, 95, 75],
+    [111, 87, 67],
+    [103, 83, 63],
+    [95, 75, 55],
+    [87, 67, 51],
+    [83, 63, 47],
+    [159, 131, 99],
+    [143, 119, 83],
+    [131, 107, 75],
+    [119, 95, 63],
+    [103, 83, 51],
+    [91, 71, 43],
+    [79, 59, 35],
+    [67, 51, 27],
+    [123, 127, 99],
+    [111, 115, 87],
+    [103, 107, 79],
+    [91, 99, 71],
+    [83, 87, 59],
+    [71, 79, 51],
+    [63, 71, 43],
+    [55, 63, 39],
+    [255, 255, 115],
+    [235, 219, 87],
+    [215, 187, 67],
+    [195, 155, 47],
+    [175, 123, 31],
+    [155, 91, 19],
+    [135, 67, 7],
+    [115, 43, 0],
+    [255, 255, 255],
+    [255, 219, 219],
+    [255, 187, 187],
+    [255, 155, 155],
+    [255, 123, 123],
+    [255, 95, 95],
+    [255, 63, 63],
+    [255, 31, 31],
+    [255, 0, 0],
+    [239, 0, 0],
+    [227, 0, 0],
+    [215, 0, 0],
+    [203, 0, 0],
+    [191, 0, 0],
+    [179, 0, 0],
+    [167, 0, 0],
+    [155, 0, 0],
+    [139, 0, 0],
+    [127, 0, 0],
+    [115, 0, 0],
+    [103, 0, 0],
+    [91, 0, 0],
+    [79, 0, 0],
+    [67, 0, 0],
+    [231, 231, 255],
+    [199, 199, 255],
+    [171, 171, 255],
+    [143, 143, 255],
+    [115, 115, 255],
+    [83, 83, 255],
+    [55, 55, 255],
+    [27, 27, 255],
+    [0, 0, 255],
+    [0, 0, 227],
+    [0, 0, 203],
+    [0, 0, 179],
+    [0, 0, 155],
+    [0, 0, 131],
+    [0, 0, 107],
+    [0, 0, 83],
+    [255, 255, 255],
+    [255, 235, 219],
+    [255, 215, 187],
+    [255, 199, 155],
+    [255, 179, 123],
+    [255, 163, 91],
+    [255, 143, 59],
+    [255, 127, 27],
+    [243, 115, 23],
+    [235, 111, 15],
+    [223, 103, 15],
+    [215, 95, 11],
+    [203, 87, 7],
+    [195, 79, 0],
+    [183, 71, 0],
+    [175, 67, 0],
+    [255, 255, 255],
+    [255, 255, 215],
+    [255, 255, 179],
+    [255, 255, 143],
+    [255, 255, 107],
+    [255, 255, 71],
+    [255, 255, 35],
+    [255, 255, 0],
+    [167, 63, 0],
+    [159, 55, 0],
+    [147, 47, 0],
+    [135, 35, 0],
+    [79, 59, 39],
+    [67, 47, 27],
+    [55, 35, 19],
+    [47, 27, 11],
+    [0, 0, 83],
+    [0, 0, 71],
+    [0, 0, 59],
+    [0, 0, 47],
+    [0, 0, 35],
+    [0, 0, 23],
+    [0, 0, 11],
+    [0, 0, 0],
+    [255, 159, 67],
+    [255, 231, 75],
+    [255, 123, 255],
+    [255, 0, 255],
+    [207, 0, 207],
+    [159, 0, 155],
+    [111, 0, 107],
+    [167, 107, 107],
+];
+
+impl Wad {
+    /// Checks that the WAD's graphics and palette agree: a PWAD with its own
+    /// sprites, flats or patches but no `PLAYPAL` is drawn with whatever
+    /// palette the IWAD has, and a `PLAYPAL` that isn't the stock one recolors
+    /// every graphic in the game. Only Doom's stock palette is known, so the
+    /// second check is skipped for the other games.
+    pub fn palette_consistency(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        let doom = matches!(self.detect_game(), Game::Doom | Game::Doom2 | Game::Unknown);
+        match self.lump_by_name("PLAYPAL") {
+            None => {
+                let mut section_end = None;
+                let graphics = self.iter_lumps().any(|lump| match section_end {
+                    Some(end) if lump.name == end => {
+                        section_end = None;
+                        false
+                    }
+                    Some(_) => !lump.data.is_empty(),
+                    None => {
+                        section_end = MARKER_SECTIONS
+                            .iter()
+                            .find(|(start, _)| *start == lump.name)
+                            .map(|&(_, end)| end);
+                        false
+                    }
+                });
+                if graphics && !self.is_iwad() {
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Warning,
+                        None,
+                        "the WAD has its own sprites, flats or patches but no PLAYPAL, \
+                         so they rely on the IWAD's palette",
+                    ));
+                }
+            }
+            Some(lump) if lump.data.len() < 768 => diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                None,
+                format!(
+                    "PLAYPAL is {} bytes, too short for a palette",
+                    lump.data.len()
+                ),
+            )),
+            Some(lump) => {
+                let stock = lump.data[..768]
+                    .chunks_exact(3)
+                    .eq(DOOM_PALETTE.iter().map(|c| &c[..]));
+                if doom && !stock {
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Warning,
+                        None,
+                        "PLAYPAL isn't Doom's stock palette, so it recolors every graphic",
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// The first palette in `PLAYPAL`, which is the one used for normal play.
+    pub fn palette(&self) -> Option<Palette> {
+        let data = &self.lump_by_name("PLAYPAL")?.data;
+        let mut colors = [[0u8; 3]; 256];
+        for (color, rgb) in colors.iter_mut().zip(data.get(..768)?.chunks_exact(3)) {
+            color.copy_from_slice(rgb);
+        }
+        Some(Palette::new(colors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::builder::WadBuilder;
+    use crate::wad::LumpKind;
+
+    fn playpal(colors: &[[u8; 3]; 256]) -> Vec<u8> {
+        colors.iter().flatten().copied().collect()
+    }
+
+    fn playpal_warnings(data: Vec<u8>) -> usize {
+        let bytes = WadBuilder::new().lump("PLAYPAL", data).build();
+        let wad = Wad::from_reader(Cursor::new(bytes)).unwrap();
+        wad.palette_consistency().len()
+    }
+
+    #[test]
+    fn the_stock_palette_is_recognized_in_full() {
+        let mut data = playpal(&DOOM_PALETTE);
+        assert_eq!(playpal_warnings(data.clone()), 0);
+        // the flash palettes after the first aren't compared
+        data.extend(std::iter::repeat_n(99, 13 * 768));
+        assert_eq!(playpal_warnings(data), 0);
+
+        for index in [0, 15, 16, 200, 255] {
+            let mut colors = DOOM_PALETTE;
+            colors[index][1] ^= 4;
+            assert_eq!(playpal_warnings(playpal(&colors)), 1, "color {}", index);
+        }
+    }
+
+    #[test]
+    fn each_palette_color_maps_back_to_its_first_index() {
+        let palette = Palette::new(DOOM_PALETTE);
+        for (index, &color) in DOOM_PALETTE.iter().enumerate() {
+            let first = DOOM_PALETTE.iter().position(|&c| c == color).unwrap();
+            assert_eq!(
+                palette.nearest_index(color) as usize,
+                first,
+                "color {}",
+                index
+            );
+        }
+        // black is both 0 and 247, and white 4, 168, 208 and 224
+        assert_eq!(palette.nearest_index([0, 0, 0]), 0);
+        assert_eq!(palette.nearest_index([255, 255, 255]), 4);
+    }
+
+    #[test]
+    fn distinct_colors_map_back_to_their_own_index() {
+        let mut colors = [[0u8; 3]; 256];
+        for (i, color) in colors.iter_mut().enumerate() {
+            let i = i as u8;
+            *color = [i, i.wrapping_mul(97), 255 - i];
+        }
+        let palette = Palette::new(colors);
+        for (index, &color) in colors.iter().enumerate() {
+            assert_eq!(palette.nearest_index(color) as usize, index);
+        }
+    }
+
+    #[test]
+    fn off_palette_colors_take_the_nearest() {
+        let palette = Palette::new(DOOM_PALETTE);
+        assert_eq!(palette.nearest_index([254, 1, 1]), 176);
+        assert_eq!(palette.nearest_index([1, 1, 250]), 200);
+        assert_eq!(palette.nearest_index([5, 5, 5]), 8);
+    }
+
+    fn lump(data: Vec<u8>) -> Lump {
+        Lump {
+            name: "PATCH".to_string(),
+            offset: 0,
+            data,
+            kind: LumpKind::Other,
+        }
+    }
+
+    // a 2 by 3 picture: column 0 has indexes 10 and 11 from the top, column
+    // 1 has index 20 in its middle row
+    fn patch() -> Vec<u8> {
+        let mut data = vec![];
+        for field in [2u16, 3, 0xFFFF, 0] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        for offset in [16u32, 23] {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(&[0, 2, 0, 10, 11, 0, 0xFF]);
+        data.extend_from_slice(&[1, 1, 0, 20, 0, 0xFF]);
+        data
+    }
+
+    #[test]
+    fn columns_are_decoded_into_rows() {
+        let picture = Picture::decode(&lump(patch())).unwrap();
+        assert_eq!((picture.width, picture.height), (2, 3));
+        assert_eq!((picture.left_offset, picture.top_offset), (-1, 0));
+        assert_eq!(
+            picture.pixels,
+            [Some(10), None, Some(11), Some(20), None, None]
+        );
+        let png = picture.to_png(&Palette::new(DOOM_PALETTE)).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn empty_sizes_are_rejected() {
+        for (width, height) in [(0u16, 3u16), (2, 0), (0, 0)] {
+            let mut data = patch();
+            data[0..2].copy_from_slice(&width.to_le_bytes());
+            data[2..4].copy_from_slice(&height.to_le_bytes());
+            assert!(matches!(
+                Picture::decode(&lump(data)),
+                Err(Error::InvalidPicture(_))
+            ));
+        }
+        let empty = Picture {
+            width: 0,
+            height: 4,
+            left_offset: 0,
+            top_offset: 0,
+            pixels: vec![],
+        };
+        assert!(empty.to_png(&Palette::new(DOOM_PALETTE)).is_none());
+    }
+
+    #[test]
+    fn column_offsets_must_lie_in_the_lump() {
+        // a width far past the column table
+        let mut data = patch();
+        data[0..2].copy_from_slice(&0xFFFFu16.to_le_bytes());
+        assert!(Picture::decode(&lump(data)).is_err());
+
+        let mut data = patch();
+        let end = data.len() as u32;
+        data[12..16].copy_from_slice(&end.to_le_bytes());
+        assert!(Picture::decode(&lump(data)).is_err());
+
+        let mut data = patch();
+        data[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Picture::decode(&lump(data)).is_err());
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+
+use crate::bsp::subsector_at;
+use crate::bsp::subsector_sector;
+use crate::geometry::contact;
+use crate::geometry::sector_at;
+use crate::geometry::Contact;
+use crate::map::Linedef;
+use crate::map::Map;
+use crate::map::Node;
+use crate::map::Seg;
+use crate::map::Sidedef;
+use crate::map::Skill;
+use crate::map::Subsector;
+use crate::map::Vertex;
+use crate::map::LINEDEF_FLAG_IMPASSABLE;
+use crate::map::NO_SIDEDEF;
+use crate::map::THING_FLAG_MULTIPLAYER;
+use crate::things::Thing;
+use crate::things::ThingCategory;
+use crate::things::ThingTable;
+use crate::Error;
+
+/// How far outside the map's bounds a thing may sit before it's reported.
+pub const BOUNDS_MARGIN: i32 = 32;
+
+/// The tallest step a player or monster climbs without help.
+pub const MAX_STEP: i32 = 24;
+
+// the radius the engine gives every hanging decoration
+const HANGING_RADIUS: i16 = 16;
+
+// the lumps finding a thing's sector needs, decoded once for all of a map's
+// things
+struct SpawnGeometry {
+    vertexes: Vec<Vertex>,
+    linedefs: Vec<Linedef>,
+    sidedefs: Vec<Sidedef>,
+    segs: Vec<Seg>,
+    subsectors: Vec<Subsector>,
+    nodes: Vec<Node>,
+}
+
+impl SpawnGeometry {
+    fn new(map: &Map) -> Result<Self, Error> {
+        map.require_geometry()?;
+        Ok(Self {
+            vertexes: map.vertexes()?,
+            linedefs: map.linedefs()?,
+            sidedefs: map.sidedefs()?,
+            segs: map.segs()?,
+            subsectors: map.subsectors()?,
+            nodes: map.nodes()?,
+        })
+    }
+
+    // the sector a thing spawns in, found through the node tree like the
+    // engine does, or `None` for a thing in the void. The void is found with
+    // a ray cast, since the tree assigns every point to a subsector.
+    fn spawn_sector(&self, x: f64, y: f64) -> Option<u16> {
+        let ray_sector = sector_at(&self.vertexes, &self.linedefs, &self.sidedefs, x, y)?;
+        Some(
+            subsector_at(&self.nodes, x, y)
+                .and_then(|s| self.subsectors.get(s as usize))
+                .and_then(|s| subsector_sector(s, &self.segs, &self.linedefs, &self.sidedefs))
+                .unwrap_or(ray_sector),
+        )
+    }
+}
+
+impl Map {
+    /// Indexes of things lying outside the map's bounds, grown by
+    /// `BOUNDS_MARGIN`. Fails with `Error::EmptyLump` for a map without
+    /// geometry.
+    pub fn out_of_bounds_things(&self) -> Result<Vec<usize>, Error> {
+        self.require_geometry()?;
+        let bounds = self.bounds()?.expect("a map with geometry has vertexes");
+        Ok(self
+            .things()?
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| !bounds.contains(t.x, t.y, BOUNDS_MARGIN))
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    /// Indexes of things inside the rectangle from `min` to `max`, as `(x, y)`
+    /// pairs. Both corners are inclusive, so things lying exactly on an edge
+    /// count. A rectangle with `min` past `max` on either axis holds nothing.
+    pub fn things_in_rect(&self, min: (i16, i16), max: (i16, i16)) -> Result<Vec<usize>, Error> {
+        Ok(self
+            .things()?
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| (min.0..=max.0).contains(&t.x) && (min.1..=max.1).contains(&t.y))
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    /// The mean position of every thing, as `(x, y)`, or `None` for a map
+    /// without things. Compare `geometry_centroid` to see whether a map's
+    /// content sits away from the middle of its space.
+    pub fn thing_centroid(&self) -> Result<Option<(f64, f64)>, Error> {
+        let things = self.things()?;
+        if things.is_empty() {
+            return Ok(None);
+        }
+        let (x, y) = things
+            .iter()
+            .fold((0.0, 0.0), |(x, y), t| (x + t.x as f64, y + t.y as f64));
+        let n = things.len() as f64;
+        Ok(Some((x / n, y / n)))
+    }
+
+    /// Indexes of things with no skill flags and no multiplayer flag, which
+    /// no game mode spawns. Things flagged for multiplayer alone aren't
+    /// included; vanilla leaves them out of single player, but they're meant
+    /// for co-op and deathmatch.
+    pub fn unspawnable_things(&self) -> Result<Vec<usize>, Error> {
+        Ok(self
+            .things()?
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.skills().is_empty() && t.flags & THING_FLAG_MULTIPLAYER == 0)
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    /// Groups of thing indexes that share exact coordinates, which is often an
+    /// accidental copy and paste in an editor.
+    ///
+    /// Stacks that are usually deliberate are left out. Player and deathmatch
+    /// starts are ignored, since ports commonly put them on one spot and
+    /// `voodoo_dolls` covers stacked player starts. Things only count as
+    /// stacked if at least two of them appear on the same skill level, so a
+    /// different monster on each skill at the same spot isn't reported.
+    /// Starts are told apart from other things by `table`.
+    pub fn stacked_things(&self, table: ThingTable) -> Result<Vec<Vec<usize>>, Error> {
+        let things = self.things()?;
+        let mut positions = BTreeMap::<(i16, i16), Vec<usize>>::new();
+        for (i, thing) in things.iter().enumerate() {
+            let start = table.category(thing.doomednum) == Some(ThingCategory::PlayerStart);
+            if !start {
+                positions.entry((thing.x, thing.y)).or_default().push(i);
+            }
+        }
+        let mut stacks = positions
+            .into_values()
+            .filter(|stack| {
+                Skill::ALL.iter().any(|&skill| {
+                    stack
+                        .iter()
+                        .filter(|&&i| things[i].appears_on(skill))
+                        .count()
+                        > 1
+                })
+            })
+            .collect::<Vec<_>>();
+        stacks.sort();
+        Ok(stacks)
+    }
+
+    /// Indexes of things that can't spawn properly: things in the void, and
+    /// players or monsters in a sector shorter than they are or overlapping a
+    /// one-sided or impassable wall.
+    ///
+    /// The sector is found through the node tree like the engine does, and the
+    /// void with a ray cast, since the tree assigns every point to a subsector.
+    /// Only Doom's actor sizes are known, through `ThingTable::doom_thing`, so
+    /// in other games' tables and for unrecognized types things get the void
+    /// check alone. Two-sided lines with steps
+    /// too tall to climb aren't treated as walls. Fails with
+    /// `Error::EmptyLump` for a map without geometry, where every thing
+    /// would be in the void.
+    pub fn embedded_things(&self, table: ThingTable) -> Result<Vec<usize>, Error> {
+        let geometry = SpawnGeometry::new(self)?;
+        let (vertexes, linedefs) = (&geometry.vertexes, &geometry.linedefs);
+        let sectors = self.sectors()?;
+
+        let mut embedded = vec![];
+        for (i, thing) in self.things()?.iter().enumerate() {
+            let (x, y) = (thing.x as f64, thing.y as f64);
+            let sector = match geometry.spawn_sector(x, y) {
+                Some(s) => s,
+                None => {
+                    embedded.push(i);
+                    continue;
+                }
+            };
+            let (radius, height) = match table.doom_thing(thing.doomednum).and_then(Thing::size) {
+                Some(size) => size,
+                None => continue,
+            };
+
+            let too_short = sectors
+                .get(sector as usize)
+                .is_some_and(|s| (s.ceiling_height as i32 - s.floor_height as i32) < height as i32);
+
+            let r = radius as f64;
+            let in_wall = linedefs.iter().any(|line| {
+                if line.left_sidedef != NO_SIDEDEF && line.flags & LINEDEF_FLAG_IMPASSABLE == 0 {
+                    return false;
+                }
+                match (
+                    vertexes.get(line.start_vertex as usize),
+                    vertexes.get(line.end_vertex as usize),
+                ) {
+                    (Some(v1), Some(v2)) => {
+                        contact(
+                            (v1.x as f64, v1.y as f64),
+                            (v2.x as f64, v2.y as f64),
+                            (x - r, y - r),
+                            (x + r, y + r),
+                        ) == Contact::Interior
+                    }
+                    _ => false,
+                }
+            });
+
+            if too_short || in_wall {
+                embedded.push(i);
+            }
+        }
+        Ok(embedded)
+    }
+
+    /// Indexes of things whose spawn height leaves them below a floor.
+    ///
+    /// Doom-format things have no height of their own; the engine places each
+    /// one by type. Most stand on the floor of the sector holding their
+    /// center, found as in `embedded_things`, so they never spawn above it.
+    /// The decorations in `Thing::hanging_height` hang from the ceiling
+    /// instead, with their bottom that far below it, so one in a sector
+    /// shorter than itself pokes out under the floor. Such a thing is
+    /// reported, and so is any thing whose bottom is more than `MAX_STEP`
+    /// below the floor of a sector on the other side of a two-sided line its
+    /// box overlaps: a monster there is stuck in the step, since it can't
+    /// climb onto it.
+    ///
+    /// Players and monsters are sized by `Thing::size` and hanging
+    /// decorations by `HANGING_RADIUS`; pickups and other decorations are
+    /// skipped, as are things in the void, which `embedded_things` reports,
+    /// and everything outside Doom's table. Fails with `Error::EmptyLump`
+    /// like `embedded_things`.
+    pub fn floating_things(&self, table: ThingTable) -> Result<Vec<usize>, Error> {
+        let geometry = SpawnGeometry::new(self)?;
+        let (vertexes, linedefs, sidedefs) =
+            (&geometry.vertexes, &geometry.linedefs, &geometry.sidedefs);
+        let sectors = self.sectors()?;
+        let floor = |side: u16| {
+            sidedefs
+                .get(side as usize)
+                .and_then(|s| sectors.get(s.sector as usize))
+                .map(|s| s.floor_height as i32)
+        };
+
+        let mut floating = vec![];
+        for (i, thing) in self.things()?.iter().enumerate() {
+            let kind = match table.doom_thing(thing.doomednum) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let (radius, hanging) = match (kind.hanging_height(), kind.size()) {
+                (Some(height), _) => (HANGING_RADIUS, Some(height)),
+                (None, Some((radius, _))) => (radius, None),
+                (None, None) => continue,
+            };
+            let (x, y) = (thing.x as f64, thing.y as f64);
+            let sector = match geometry
+                .spawn_sector(x, y)
+                .and_then(|s| sectors.get(s as usize))
+            {
+                Some(s) => s,
+                None => continue,
+            };
+            let bottom = match hanging {
+                Some(height) => sector.ceiling_height as i32 - height as i32,
+                None => sector.floor_height as i32,
+            };
+            if bottom < sector.floor_height as i32 {
+                floating.push(i);
+                continue;
+            }
+
+            let r = radius as f64;
+            let clipped = linedefs.iter().any(|line| {
+                if line.left_sidedef == NO_SIDEDEF {
+                    return false;
+                }
+                let touching = match (
+                    vertexes.get(line.start_vertex as usize),
+                    vertexes.get(line.end_vertex as usize),
+                ) {
+                    (Some(v1), Some(v2)) => {
+                        contact(
+                            (v1.x as f64, v1.y as f64),
+                            (v2.x as f64, v2.y as f64),
+                            (x - r, y - r),
+                            (x + r, y + r),
+                        ) == Contact::Interior
+                    }
+                    _ => false,
+                };
+                touching
+                    && [line.right_sidedef, line.left_sidedef]
+                        .iter()
+                        .filter_map(|&side| floor(side))
+                        .any(|floor| floor - bottom > MAX_STEP)
+            });
+            if clipped {
+                floating.push(i);
+            }
+        }
+        Ok(floating)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::builder::square_room;
+    use crate::builder::WadBuilder;
+    use crate::map::MapThing;
+    use crate::things::ThingTable;
+    use crate::wad::Wad;
+
+    #[test]
+    fn things_in_the_void_or_a_wall_are_embedded() {
+        let imp = |x, y| MapThing {
+            x,
+            y,
+            angle: 0,
+            doomednum: 3001,
+            flags: 7,
+        };
+        let room = square_room("MAP01")
+            .thing(imp(128, 128))
+            .thing(imp(1000, 1000))
+            .thing(imp(4, 128));
+        let bytes = WadBuilder::new().map(room).build();
+        let wad = Wad::from_reader(Cursor::new(bytes)).unwrap();
+        let map = &wad.maps()[0];
+
+        assert_eq!(map.embedded_things(ThingTable::Doom).unwrap(), [2, 3]);
+        assert!(map.floating_things(ThingTable::Doom).unwrap().is_empty());
+    }
+}
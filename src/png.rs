@@ -0,0 +1,113 @@
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes `pixels`, given as rows of RGB triples from the top left, as a PNG file.
+/// The image data goes in uncompressed deflate blocks, trading file size for a
+/// much simpler encoder. `None` if the image has no width or height, which PNG
+/// doesn't allow, or `pixels` isn't `width` by `height` triples.
+pub(crate) fn encode_rgb(width: u32, height: u32, pixels: &[u8]) -> Option<Vec<u8>> {
+    if width == 0 || height == 0 || pixels.len() != width as usize * height as usize * 3 {
+        return None;
+    }
+
+    let mut raw = Vec::with_capacity(pixels.len() + height as usize);
+    for row in pixels.chunks_exact(width as usize * 3) {
+        // filter type 0: no filtering
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        zlib.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = vec![];
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGB, default compression, filtering and no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib);
+    chunk(&mut out, b"IEND", &[]);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_or_mismatched_images_are_refused() {
+        assert!(encode_rgb(0, 4, &[]).is_none());
+        assert!(encode_rgb(4, 0, &[]).is_none());
+        assert!(encode_rgb(2, 2, &[0; 9]).is_none());
+    }
+
+    #[test]
+    fn chunks_carry_the_size_and_their_checksums() {
+        let pixels = [255, 0, 0, 0, 255, 0, 0, 0, 255, 9, 9, 9];
+        let png = encode_rgb(2, 2, &pixels).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let mut chunks = vec![];
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let (body, crc) = rest[4..].split_at(4 + len);
+            assert_eq!(crc32(body).to_be_bytes(), crc[..4]);
+            chunks.push((&body[..4], &body[4..]));
+            rest = &crc[4..];
+        }
+        let kinds = chunks.iter().map(|&(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(&chunks[0].1[..8], [0, 0, 0, 2, 0, 0, 0, 2]);
+
+        // one stored block holding each row after its filter byte
+        let zlib = chunks[1].1;
+        let raw = [&[0], &pixels[..6], &[0], &pixels[6..]].concat();
+        assert_eq!(&zlib[7..7 + raw.len()], &raw[..]);
+        assert_eq!(zlib[7 + raw.len()..], adler32(&raw).to_be_bytes());
+    }
+}
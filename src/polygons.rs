@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::map::Map;
+use crate::map::Vertex;
+use crate::Error;
+
+/// One connected piece of a sector's floor: its boundary and the holes cut
+/// out of it. Loops don't repeat their first vertex at the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolygonShape {
+    /// Counterclockwise, with the floor on the left.
+    pub outer: Vec<Vertex>,
+    /// Clockwise, each lying inside `outer`: the sectors and voids the floor
+    /// surrounds.
+    pub holes: Vec<Vec<Vertex>>,
+}
+
+/// A sector's floor as polygons, for filling or exporting it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectorPolygon {
+    pub sector: usize,
+    /// More than one for a sector made of separate areas, which is common for
+    /// sectors sharing a light level or tag.
+    pub shapes: Vec<PolygonShape>,
+    /// What couldn't be traced: edges that don't join up into a closed loop,
+    /// lines with a missing vertex and holes outside every outer loop. Those
+    /// parts are left out of `shapes`.
+    pub warnings: Vec<String>,
+}
+
+impl SectorPolygon {
+    pub fn is_complete(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+// twice the signed area, positive for counterclockwise loops
+fn doubled_area(points: &[Vertex]) -> f64 {
+    (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a.x as f64 * b.y as f64 - b.x as f64 * a.y as f64
+        })
+        .sum()
+}
+
+// six times the centroid's first moments, the sums the centroid divides by
+// three times the doubled area
+fn moments(points: &[Vertex]) -> (f64, f64) {
+    (0..points.len()).fold((0.0, 0.0), |(mx, my), i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let (ax, ay, bx, by) = (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+        let cross = ax * by - bx * ay;
+        (mx + (ax + bx) * cross, my + (ay + by) * cross)
+    })
+}
+
+// even-odd point in polygon
+fn inside(point: Vertex, points: &[Vertex]) -> bool {
+    let (x, y) = (point.x as f64, point.y as f64);
+    let mut inside = false;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let (ax, ay, bx, by) = (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+        if (ay > y) != (by > y) && x < ax + (y - ay) * (bx - ax) / (by - ay) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Joins a sector's directed edges into loops. At a vertex with several ways
+/// on, the sharpest turn towards the sector is taken, so loops touching at a
+/// vertex come out as separate simple loops. Returns the loops as vertex
+/// indexes, and the edges that ran into a dead end.
+fn trace_loops(edges: &[(u16, u16)], vertexes: &[Vertex]) -> (Vec<Vec<u16>>, Vec<(u16, u16)>) {
+    let mut outgoing = HashMap::<u16, Vec<usize>>::new();
+    for (i, &(from, _)) in edges.iter().enumerate() {
+        outgoing.entry(from).or_default().push(i);
+    }
+    let direction = |(from, to): (u16, u16)| {
+        let (a, b) = (vertexes[from as usize], vertexes[to as usize]);
+        (b.x as f64 - a.x as f64, b.y as f64 - a.y as f64)
+    };
+    let mut used = vec![false; edges.len()];
+    let mut loops = vec![];
+    let mut dead_ends = vec![];
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let start = edges[first].0;
+        let mut chain = vec![first];
+        loop {
+            let current = edges[*chain.last().unwrap()];
+            if current.1 == start {
+                loops.push(chain.iter().map(|&e| edges[e].0).collect());
+                break;
+            }
+            let (dx, dy) = direction(current);
+            // the sector is on the right, so the rightmost turn hugs it
+            let next = outgoing.get(&current.1).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|&e| !used[e])
+                    .map(|e| {
+                        let (ex, ey) = direction(edges[e]);
+                        let turn = (dx * ey - dy * ex).atan2(dx * ex + dy * ey);
+                        (e, turn)
+                    })
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(e, _)| e)
+            });
+            match next {
+                Some(e) => {
+                    used[e] = true;
+                    chain.push(e);
+                }
+                None => {
+                    dead_ends.extend(chain.iter().map(|&e| edges[e]));
+                    break;
+                }
+            }
+        }
+    }
+    (loops, dead_ends)
+}
+
+impl Map {
+    /// Each sector's floor traced from its sidedefs into closed loops, indexed
+    /// by sector number.
+    ///
+    /// A sidedef's linedef becomes an edge of the sector it faces, running
+    /// clockwise around it: start to end vertex for a front side, end to
+    /// start for a back side. Edges cancelling out, such as a line with both
+    /// sides in the same sector, are dropped. The edges are joined into loops,
+    /// and loops running counterclockwise around the sector are holes, each
+    /// given to the smallest outer loop containing it. Every loop is then
+    /// reversed, so the result follows GeoJSON's winding: outer loops
+    /// counterclockwise, holes clockwise.
+    ///
+    /// Whatever can't be traced is left out and described in the sector's
+    /// `warnings`, so one broken sector doesn't lose the rest of the map.
+    pub fn sector_polygons(&self) -> Result<Vec<SectorPolygon>, Error> {
+        let vertexes = self.vertexes()?;
+        let sidedefs = self.sidedefs()?;
+        let mut polygons = (0..self.sectors()?.len())
+            .map(|sector| SectorPolygon {
+                sector,
+                shapes: vec![],
+                warnings: vec![],
+            })
+            .collect::<Vec<_>>();
+        let mut edges = vec![vec![]; polygons.len()];
+        for (i, line) in self.linedefs()?.iter().enumerate() {
+            let sides = [
+                (line.right_sidedef, (line.start_vertex, line.end_vertex)),
+                (line.left_sidedef, (line.end_vertex, line.start_vertex)),
+            ];
+            for (side, edge) in sides {
+                let sector = match sidedefs.get(side as usize) {
+                    Some(s) if (s.sector as usize) < polygons.len() => s.sector as usize,
+                    _ => continue,
+                };
+                if [edge.0, edge.1]
+                    .iter()
+                    .any(|&v| v as usize >= vertexes.len())
+                {
+                    polygons[sector]
+                        .warnings
+                        .push(format!("linedef {} references a missing vertex", i));
+                    continue;
+                }
+                let sector_edges: &mut Vec<(u16, u16)> = &mut edges[sector];
+                match sector_edges.iter().position(|&e| e == (edge.1, edge.0)) {
+                    Some(reverse) => {
+                        sector_edges.swap_remove(reverse);
+                    }
+                    None => sector_edges.push(edge),
+                }
+            }
+        }
+
+        for (polygon, edges) in polygons.iter_mut().zip(&edges) {
+            let (loops, dead_ends) = trace_loops(edges, &vertexes);
+            if !dead_ends.is_empty() {
+                let mut list = dead_ends
+                    .iter()
+                    .map(|(a, b)| format!("{}-{}", a, b))
+                    .collect::<Vec<_>>();
+                list.sort();
+                polygon.warnings.push(format!(
+                    "the edges between vertexes {} don't close",
+                    list.join(", ")
+                ));
+            }
+            let mut outers = vec![];
+            let mut holes = vec![];
+            for indexes in loops {
+                let mut points = indexes
+                    .iter()
+                    .map(|&v| vertexes[v as usize])
+                    .collect::<Vec<_>>();
+                points.reverse();
+                let area = doubled_area(&points);
+                if area > 0.0 {
+                    outers.push((area, points));
+                } else if area < 0.0 {
+                    holes.push(points);
+                }
+            }
+            // smallest first, so a hole goes to the innermost loop around it
+            outers.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut shapes = outers
+                .into_iter()
+                .map(|(_, outer)| PolygonShape {
+                    outer,
+                    holes: vec![],
+                })
+                .collect::<Vec<_>>();
+            for hole in holes {
+                let owner = shapes.iter().position(|shape| {
+                    // a vertex the loops share says nothing, so test another
+                    hole.iter()
+                        .find(|v| !shape.outer.contains(v))
+                        .is_some_and(|&v| inside(v, &shape.outer))
+                });
+                match owner {
+                    Some(i) => shapes[i].holes.push(hole),
+                    None => polygon.warnings.push(format!(
+                        "a hole at ({}, {}) isn't inside any of the sector's outlines",
+                        hole[0].x, hole[0].y
+                    )),
+                }
+            }
+            polygon.shapes = shapes;
+        }
+        Ok(polygons)
+    }
+
+    /// The map's center of mass as `(x, y)`: the centroid of every sector's
+    /// floor from `sector_polygons`, weighted by area, with holes taken away.
+    /// When no sector can be traced into polygons with any area, it falls
+    /// back to the mean position of the vertexes. Fails with
+    /// `Error::EmptyLump` for a map without geometry.
+    pub fn geometry_centroid(&self) -> Result<(f64, f64), Error> {
+        self.require_geometry()?;
+        let (mut area, mut mx, mut my) = (0.0, 0.0, 0.0);
+        let polygons = self.sector_polygons()?;
+        let rings = polygons
+            .iter()
+            .flat_map(|p| &p.shapes)
+            .flat_map(|shape| std::iter::once(&shape.outer).chain(&shape.holes));
+        // outer loops run counterclockwise and holes clockwise, so the holes'
+        // negative areas and moments cut them out
+        for ring in rings {
+            let (x, y) = moments(ring);
+            area += doubled_area(ring);
+            mx += x;
+            my += y;
+        }
+        if area > 0.0 {
+            return Ok((mx / (3.0 * area), my / (3.0 * area)));
+        }
+        let vertexes = self.vertexes()?;
+        let (x, y) = vertexes
+            .iter()
+            .fold((0.0, 0.0), |(x, y), v| (x + v.x as f64, y + v.y as f64));
+        let n = vertexes.len() as f64;
+        Ok((x / n, y / n))
+    }
+}
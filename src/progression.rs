@@ -0,0 +1,325 @@
+use crate::game::Game;
+use crate::map::Map;
+use crate::map::Skill;
+use crate::things::ThingTable;
+use crate::wad::Wad;
+
+// S1, W1 and Boom's G1 secret exits
+const SECRET_EXIT_SPECIALS: [u16; 3] = [51, 124, 198];
+
+// the lumps giving map progression, most specific port first
+const MAPINFO_LUMPS: [&str; 3] = ["ZMAPINFO", "MAPINFO", "UMAPINFO"];
+
+/// Where a secret exit's destination comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathSource {
+    /// The WAD's `ZMAPINFO`, `MAPINFO` or `UMAPINFO` defines the map.
+    Mapinfo,
+    /// The game's built-in episode structure.
+    Vanilla,
+}
+
+/// A map with secret exits, and the map they lead to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretPath {
+    pub from: String,
+    /// `None` for a dead end: the map has no secret destination, so its secret
+    /// exits act like its normal exit.
+    pub to: Option<String>,
+    /// The number of secret exit lines in the map.
+    pub exits: usize,
+    pub source: PathSource,
+    /// Whether `to` names a map in this WAD. A PWAD's secret map may still
+    /// come from the IWAD.
+    pub destination_in_wad: bool,
+}
+
+impl SecretPath {
+    pub fn is_dead_end(&self) -> bool {
+        self.to.is_none()
+    }
+}
+
+impl Map {
+    /// The number of linedefs with a secret exit special.
+    pub fn secret_exit_count(&self) -> usize {
+        self.iter_linedefs().map_or(0, |lines| {
+            lines
+                .filter(|l| SECRET_EXIT_SPECIALS.contains(&l.special))
+                .count()
+        })
+    }
+}
+
+// Splits a MAPINFO lump in any of its dialects into words and quoted strings,
+// dropping the quotes, `;`, `//` and `/* */` comments, and the punctuation of
+// ZDoom's block syntax.
+fn mapinfo_tokens(text: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => tokens.push(chars.by_ref().take_while(|&c| c != '"').collect()),
+            ';' => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            c if c.is_whitespace() || "{}=,".contains(c) => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}=,;\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(word);
+            }
+        }
+    }
+    tokens
+}
+
+// Hexen's MAPINFO gives maps by number
+fn map_name(token: &str) -> String {
+    match token.parse::<u32>() {
+        Ok(n) => format!("MAP{:02}", n),
+        Err(_) => token.to_ascii_uppercase(),
+    }
+}
+
+/// The maps a MAPINFO-style lump defines, each with its secret destination if
+/// it has one, in the order they're defined.
+fn parse_secret_destinations(text: &str) -> Vec<(String, Option<String>)> {
+    let tokens = mapinfo_tokens(text);
+    let mut maps: Vec<(String, Option<String>)> = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i].to_ascii_lowercase();
+        match (token.as_str(), tokens.get(i + 1)) {
+            ("map", Some(name)) => {
+                maps.push((map_name(name), None));
+                i += 2;
+            }
+            ("secretnext" | "nextsecret", Some(destination)) => {
+                if let Some((_, secret)) = maps.last_mut() {
+                    *secret = Some(map_name(destination));
+                }
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    maps
+}
+
+/// The sky texture each map a MAPINFO-style lump defines names, in the order
+/// they're defined: `sky1` in ZDoom's and Hexen's dialects, `skytexture` in
+/// UMAPINFO's.
+fn parse_sky_textures(text: &str) -> Vec<(String, Option<String>)> {
+    let tokens = mapinfo_tokens(text);
+    let mut maps: Vec<(String, Option<String>)> = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i].to_ascii_lowercase();
+        match (token.as_str(), tokens.get(i + 1)) {
+            ("map", Some(name)) => {
+                maps.push((map_name(name), None));
+                i += 2;
+            }
+            ("sky1" | "skytexture", Some(texture)) => {
+                if let Some((_, sky)) = maps.last_mut() {
+                    *sky = Some(texture.to_ascii_uppercase());
+                }
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    maps
+}
+
+fn episode_and_map(name: &str) -> Option<(u8, u8)> {
+    let b = name.as_bytes();
+    if b.len() == 4 && b[0] == b'E' && b[2] == b'M' && b[1].is_ascii_digit() {
+        Some((b[1] - b'0', b[3].checked_sub(b'0').filter(|&m| m <= 9)?))
+    } else {
+        None
+    }
+}
+
+/// Where vanilla sends a map's secret exit. Doom and Heretic send any secret
+/// exit outside the secret map to the episode's ninth map; Doom 2 only has
+/// the MAP15 to MAP31 and MAP31 to MAP32 exits. Hexen and Strife have no
+/// secret exits.
+fn vanilla_secret_destination(game: Game, name: &str) -> Option<String> {
+    match (game, episode_and_map(name)) {
+        (Game::Doom | Game::Heretic | Game::Unknown, Some((episode, map))) if map != 9 => {
+            Some(format!("E{}M9", episode))
+        }
+        (Game::Doom2 | Game::Unknown, None) => match name {
+            "MAP15" => Some("MAP31".to_string()),
+            "MAP31" => Some("MAP32".to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn map_number(name: &str) -> Option<u8> {
+    name.strip_prefix("MAP")?.parse().ok()
+}
+
+impl Map {
+    /// The sky texture the game's executable picks for the map from its name,
+    /// without a MAPINFO to say otherwise. Doom uses `SKY1` to `SKY4` by
+    /// episode and Heretic `SKY1`, `SKY2`, `SKY3`, `SKY1` and `SKY3` for its
+    /// five. Doom 2 uses `SKY1` up to MAP11, `SKY2` up to MAP20 and `SKY3`
+    /// after, and Strife `SKYMNT01` from MAP09 to MAP31 and `SKYMNT02`
+    /// elsewhere. For an unknown game the map's name decides between Doom's
+    /// and Doom 2's rules. `None` for Hexen, whose skies only its MAPINFO
+    /// gives, and for maps the game has no sky for, such as a Doom `E5M1`.
+    pub fn vanilla_sky_texture(&self, game: Game) -> Option<String> {
+        let name = self.name();
+        let sky = match (game, episode_and_map(name), map_number(name)) {
+            (Game::Doom | Game::Unknown, Some((episode @ 1..=4, _)), _) => {
+                return Some(format!("SKY{}", episode))
+            }
+            (Game::Heretic, Some((episode @ 1..=5, _)), _) => {
+                ["SKY1", "SKY2", "SKY3", "SKY1", "SKY3"][episode as usize - 1]
+            }
+            (Game::Doom2 | Game::Unknown, None, Some(number)) => match number {
+                0..=11 => "SKY1",
+                12..=20 => "SKY2",
+                _ => "SKY3",
+            },
+            (Game::Strife, None, Some(9..=31)) => "SKYMNT01",
+            (Game::Strife, None, Some(_)) => "SKYMNT02",
+            _ => return None,
+        };
+        Some(sky.to_string())
+    }
+}
+
+impl Wad {
+    /// Every map with a secret exit line, in WAD order, and where the exit
+    /// leads. A map defined in the first of `ZMAPINFO`, `MAPINFO` or
+    /// `UMAPINFO` the WAD has goes where its `secretnext` or `nextsecret`
+    /// says, and nowhere secret without one. Other maps follow the detected
+    /// game's vanilla progression.
+    pub fn secret_level_paths(&self) -> Vec<SecretPath> {
+        let mapinfo = MAPINFO_LUMPS
+            .iter()
+            .find_map(|name| self.lump_by_name(name))
+            .map(|lump| parse_secret_destinations(&String::from_utf8_lossy(lump.as_bytes())))
+            .unwrap_or_default();
+        let game = self.detect_game();
+        let in_wad = |name: &str| self.maps().iter().any(|m| m.name() == name);
+
+        self.maps()
+            .iter()
+            .filter_map(|map| {
+                let exits = map.secret_exit_count();
+                if exits == 0 {
+                    return None;
+                }
+                let (to, source) = match mapinfo.iter().find(|(name, _)| name == map.name()) {
+                    Some((_, to)) => (to.clone(), PathSource::Mapinfo),
+                    None => (
+                        vanilla_secret_destination(game, map.name()),
+                        PathSource::Vanilla,
+                    ),
+                };
+                Some(SecretPath {
+                    from: map.name().to_string(),
+                    destination_in_wad: to.as_deref().is_some_and(in_wad),
+                    to,
+                    exits,
+                    source,
+                })
+            })
+            .collect()
+    }
+
+    /// The WAD's maps in the order they're played: `ExMy` maps by episode
+    /// then map, `MAPxx` maps by number, and any other names after them in
+    /// directory order. Of maps sharing a name only the last, the one the
+    /// game loads, is kept. Secret maps sort by their number rather than
+    /// where their secret exit leads from.
+    pub fn maps_in_level_order(&self) -> Vec<&Map> {
+        let mut maps = self
+            .maps()
+            .iter()
+            .enumerate()
+            .filter(|(_, map)| {
+                self.map_by_name(map.name())
+                    .is_some_and(|m| std::ptr::eq(m, *map))
+            })
+            .map(|(i, map)| {
+                let level = episode_and_map(map.name())
+                    .map(|(episode, number)| (episode, number as u16))
+                    .or_else(|| Some((0, map_number(map.name())? as u16)));
+                (level.is_none(), level, i, map)
+            })
+            .collect::<Vec<_>>();
+        maps.sort_by_key(|&(unnumbered, level, i, _)| (unnumbered, level, i));
+        maps.into_iter().map(|(_, _, _, map)| map).collect()
+    }
+
+    /// Each map's difficulty on `skill` in the order of `maps_in_level_order`,
+    /// to see whether a megawad ramps up steadily or spikes. A map's
+    /// difficulty is its monsters' combined spawn health on that skill,
+    /// `SkillCounts::monster_health`. A map whose things can't be read gets
+    /// `None`, leaving a gap in the curve, and so does every map of a game
+    /// other than Doom, whose monsters' health isn't known.
+    pub fn difficulty_curve(&self, skill: Skill) -> Vec<(String, Option<f64>)> {
+        let table = self.thing_table();
+        self.maps_in_level_order()
+            .into_iter()
+            .map(|map| {
+                if table != ThingTable::Doom {
+                    return (map.name().to_string(), None);
+                }
+                let difficulty = map.skill_profile(table).ok().and_then(|profile| {
+                    profile
+                        .skills
+                        .iter()
+                        .find(|counts| counts.skill == skill)
+                        .map(|counts| counts.monster_health as f64)
+                });
+                (map.name().to_string(), difficulty)
+            })
+            .collect()
+    }
+
+    /// The sky texture a map of the WAD is drawn with. A map given a sky in
+    /// the first of `ZMAPINFO`, `MAPINFO` or `UMAPINFO` the WAD has uses that
+    /// one; otherwise the detected game's default applies, as
+    /// `Map::vanilla_sky_texture` describes. Texture names are uppercase.
+    pub fn sky_texture(&self, map: &Map) -> Option<String> {
+        let mapinfo_sky = MAPINFO_LUMPS
+            .iter()
+            .find_map(|name| self.lump_by_name(name))
+            .map(|lump| parse_sky_textures(&String::from_utf8_lossy(lump.as_bytes())))
+            .and_then(|skies| {
+                skies
+                    .into_iter()
+                    .find(|(name, _)| name == map.name())
+                    .and_then(|(_, sky)| sky)
+            });
+        mapinfo_sky.or_else(|| map.vanilla_sky_texture(self.detect_game()))
+    }
+}
@@ -0,0 +1,275 @@
+use crate::game::is_sky_flat;
+use crate::map::Map;
+use crate::map::Skill;
+use crate::map::NO_SIDEDEF;
+use crate::png::crc32;
+use crate::png::encode_rgb;
+use crate::things::ThingCategory;
+use crate::things::ThingTable;
+use crate::Error;
+
+type Rgb = [u8; 3];
+
+const BACKGROUND: Rgb = [0, 0, 0];
+const ONE_SIDED: Rgb = [255, 255, 255];
+const TWO_SIDED: Rgb = [110, 110, 110];
+const SKY: Rgb = [70, 120, 200];
+const LOWEST_FLOOR: Rgb = [30, 40, 100];
+const HIGHEST_FLOOR: Rgb = [220, 150, 50];
+const MARGIN: u32 = 8;
+
+/// What the color of a sector's floor is based on, when floors are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloorColor {
+    /// A muted color picked from the floor flat's name, the same for every
+    /// sector using the flat.
+    Flat,
+    /// Grey by light level, dimmed so the lines stay visible.
+    Light,
+    /// Blue for the map's lowest floor through to orange for its highest.
+    Height,
+}
+
+/// Settings for `Map::render_png`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// The length in pixels of the image's longer side.
+    pub size: u32,
+    /// Whether to draw monsters, items, keys and player starts over the geometry.
+    pub things: bool,
+    /// The radius in pixels of each thing's dot.
+    pub thing_radius: u32,
+    /// Only draw things that appear on this skill.
+    pub skill: Option<Skill>,
+    /// The table used to tell monsters from items.
+    pub table: ThingTable,
+    /// Fill each sector's floor, colored by this, under the lines. Sectors
+    /// with a sky ceiling are sky blue whatever the color source.
+    pub fill_floors: Option<FloorColor>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            size: 1024,
+            things: false,
+            thing_radius: 2,
+            skill: None,
+            table: ThingTable::Doom,
+            fill_floors: None,
+        }
+    }
+}
+
+fn thing_color(category: ThingCategory) -> Option<Rgb> {
+    use ThingCategory::*;
+    match category {
+        Monster => Some([230, 40, 40]),
+        Weapon | Ammo | Health | Armor | Powerup => Some([40, 200, 40]),
+        PlayerStart => Some([60, 100, 255]),
+        Key => Some([255, 220, 0]),
+        Decoration | Special => None,
+    }
+}
+
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+        for _ in 0..width as usize * height as usize {
+            pixels.extend_from_slice(&BACKGROUND);
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn plot(&mut self, x: i64, y: i64, color: Rgb) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&color);
+    }
+
+    fn line(&mut self, (mut x0, mut y0): (i64, i64), (x1, y1): (i64, i64), color: Rgb) {
+        // Bresenham's line algorithm
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let mut err = dx + dy;
+        loop {
+            self.plot(x0, y0, color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    /// Fills the pixels whose centers lie inside the rings by the even-odd
+    /// rule, so rings inside another cut holes in it.
+    fn fill(&mut self, rings: &[Vec<(f64, f64)>], color: Rgb) {
+        let ys = rings.iter().flatten().map(|p| p.1);
+        let top = ys.clone().fold(f64::INFINITY, f64::min).ceil().max(0.0) as i64;
+        let bottom = ys.fold(f64::NEG_INFINITY, f64::max).floor() as i64;
+        let mut crossings = vec![];
+        for y in top..=bottom.min(self.height as i64 - 1) {
+            let yf = y as f64;
+            crossings.clear();
+            for ring in rings {
+                for i in 0..ring.len() {
+                    let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+                    if (a.1 > yf) != (b.1 > yf) {
+                        crossings.push(a.0 + (yf - a.1) * (b.0 - a.0) / (b.1 - a.1));
+                    }
+                }
+            }
+            crossings.sort_by(f64::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                for x in pair[0].ceil() as i64..=pair[1].floor() as i64 {
+                    self.plot(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn dot(&mut self, (x, y): (i64, i64), radius: u32, color: Rgb) {
+        let r = radius as i64;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx * dx + dy * dy <= r * r {
+                    self.plot(x + dx, y + dy, color);
+                }
+            }
+        }
+    }
+}
+
+fn flat_color(name: &str) -> Rgb {
+    let hash = crc32(name.to_ascii_uppercase().as_bytes()).to_le_bytes();
+    // keep each channel to the middle of its range, away from the line colors
+    [0, 1, 2].map(|i| 60 + hash[i] % 120)
+}
+
+fn blend(a: Rgb, b: Rgb, t: f64) -> Rgb {
+    [0, 1, 2].map(|i| (a[i] as f64 + (b[i] as f64 - a[i] as f64) * t).round() as u8)
+}
+
+impl Map {
+    /// Draws the map's linedefs from above as a PNG image, one-sided lines in
+    /// white and two-sided lines in grey, optionally over filled floors from
+    /// `sector_polygons` and under things as colored dots: monsters red,
+    /// items green, player starts blue and keys yellow.
+    pub fn render_png(&self, options: &RenderOptions) -> Result<Vec<u8>, Error> {
+        let vertexes = self.vertexes()?;
+        let linedefs = self.linedefs()?;
+        let bounds = match self.bounds()? {
+            Some(b) => b,
+            None => return Ok(encode_rgb(1, 1, &BACKGROUND).expect("one pixel is an image")),
+        };
+
+        let map_width = (bounds.max_x as i64 - bounds.min_x as i64).max(1) as f64;
+        let map_height = (bounds.max_y as i64 - bounds.min_y as i64).max(1) as f64;
+        let inner = options.size.saturating_sub(2 * MARGIN + 1).max(1) as f64;
+        let scale = inner / map_width.max(map_height);
+        let width = (map_width * scale).ceil() as u32 + 2 * MARGIN + 1;
+        let height = (map_height * scale).ceil() as u32 + 2 * MARGIN + 1;
+
+        // map y grows upwards, image y downwards
+        let project = |x: i16, y: i16| {
+            (
+                ((x as i64 - bounds.min_x as i64) as f64 * scale).round() as i64 + MARGIN as i64,
+                ((bounds.max_y as i64 - y as i64) as f64 * scale).round() as i64 + MARGIN as i64,
+            )
+        };
+
+        let mut canvas = Canvas::new(width, height);
+        if let Some(source) = options.fill_floors {
+            let sectors = self.sectors()?;
+            let (lowest, highest) = sectors.iter().fold((i16::MAX, i16::MIN), |(lo, hi), s| {
+                (lo.min(s.floor_height), hi.max(s.floor_height))
+            });
+            let project_exact = |x: i16, y: i16| {
+                (
+                    (x as i64 - bounds.min_x as i64) as f64 * scale + MARGIN as f64,
+                    (bounds.max_y as i64 - y as i64) as f64 * scale + MARGIN as f64,
+                )
+            };
+            for polygon in self.sector_polygons()? {
+                let sector = &sectors[polygon.sector];
+                let color = if is_sky_flat(&sector.ceiling_flat) {
+                    SKY
+                } else {
+                    match source {
+                        FloorColor::Flat => flat_color(&sector.floor_flat),
+                        FloorColor::Light => {
+                            let grey = (sector.light_level.clamp(0, 255) as f64 * 0.6) as u8;
+                            [grey; 3]
+                        }
+                        FloorColor::Height => {
+                            let range = (highest as f64 - lowest as f64).max(1.0);
+                            let t = (sector.floor_height as f64 - lowest as f64) / range;
+                            blend(LOWEST_FLOOR, HIGHEST_FLOOR, t)
+                        }
+                    }
+                };
+                for shape in &polygon.shapes {
+                    let rings = std::iter::once(&shape.outer)
+                        .chain(&shape.holes)
+                        .map(|ring| ring.iter().map(|v| project_exact(v.x, v.y)).collect())
+                        .collect::<Vec<_>>();
+                    canvas.fill(&rings, color);
+                }
+            }
+        }
+        // two-sided lines first so that walls are drawn over them
+        let mut lines = linedefs.iter().collect::<Vec<_>>();
+        lines.sort_by_key(|l| l.left_sidedef == NO_SIDEDEF);
+        for line in lines {
+            if let (Some(v1), Some(v2)) = (
+                vertexes.get(line.start_vertex as usize),
+                vertexes.get(line.end_vertex as usize),
+            ) {
+                let color = if line.left_sidedef == NO_SIDEDEF {
+                    ONE_SIDED
+                } else {
+                    TWO_SIDED
+                };
+                canvas.line(project(v1.x, v1.y), project(v2.x, v2.y), color);
+            }
+        }
+
+        if options.things {
+            for thing in self.things()? {
+                if options.skill.is_some_and(|s| !thing.appears_on(s)) {
+                    continue;
+                }
+                if let Some(color) = options
+                    .table
+                    .category(thing.doomednum)
+                    .and_then(thing_color)
+                {
+                    canvas.dot(project(thing.x, thing.y), options.thing_radius, color);
+                }
+            }
+        }
+
+        Ok(encode_rgb(canvas.width, canvas.height, &canvas.pixels)
+            .expect("the canvas is at least the margins across"))
+    }
+}
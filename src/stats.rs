@@ -0,0 +1,1464 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::json::Json;
+use crate::map::Map;
+use crate::map::Skill;
+use crate::map::LINEDEF_FLAG_ALWAYS_ON_AUTOMAP;
+use crate::map::LINEDEF_FLAG_BLOCK_MONSTERS;
+use crate::map::LINEDEF_FLAG_IMPASSABLE;
+use crate::map::LINEDEF_FLAG_NEVER_ON_AUTOMAP;
+use crate::map::LINEDEF_FLAG_SECRET;
+use crate::map::NO_SIDEDEF;
+use crate::map::THING_FLAG_AMBUSH;
+use crate::map::THING_FLAG_EASY;
+use crate::map::THING_FLAG_FRIENDLY;
+use crate::map::THING_FLAG_HARD;
+use crate::map::THING_FLAG_MEDIUM;
+use crate::map::THING_FLAG_MULTIPLAYER;
+use crate::map::THING_FLAG_NOT_COOP;
+use crate::map::THING_FLAG_NOT_DEATHMATCH;
+use crate::map::THING_FLAG_RESERVED;
+use crate::things::Thing;
+use crate::things::ThingCategory;
+use crate::things::ThingTable;
+use crate::wad::Wad;
+use crate::Error;
+
+/// Which of the optional statistics `Map::stats_with` computes, as flags
+/// combined with `|`. The structure counts are always computed, since they
+/// only take sizing up the map's lumps; the default, empty set computes
+/// nothing else.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StatSet(u8);
+
+impl StatSet {
+    /// Monsters that appear in single player on the hardest skills.
+    pub const MONSTERS: StatSet = StatSet(1);
+    /// Secret sectors.
+    pub const SECRETS: StatSet = StatSet(2);
+    /// The floor area, which means tracing every sector's outline.
+    pub const AREA: StatSet = StatSet(4);
+    /// The width and height of the map's bounding box.
+    pub const SIZE: StatSet = StatSet(8);
+
+    /// Each flag with its name in `from_names`, in column order.
+    pub const NAMES: [(StatSet, &'static str); 4] = [
+        (StatSet::MONSTERS, "monsters"),
+        (StatSet::SECRETS, "secrets"),
+        (StatSet::AREA, "area"),
+        (StatSet::SIZE, "size"),
+    ];
+
+    pub fn all() -> Self {
+        Self::MONSTERS | Self::SECRETS | Self::AREA | Self::SIZE
+    }
+
+    pub fn contains(self, other: StatSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Parses a comma-separated list such as `monsters,area`. Returns `None`
+    /// on an unknown name.
+    pub fn from_names(list: &str) -> Option<Self> {
+        list.split(',')
+            .filter(|name| !name.is_empty())
+            .try_fold(StatSet::default(), |set, name| {
+                let (flag, _) = Self::NAMES.iter().find(|(_, n)| *n == name)?;
+                Some(set | *flag)
+            })
+    }
+}
+
+impl std::ops::BitOr for StatSet {
+    type Output = StatSet;
+
+    fn bitor(self, rhs: StatSet) -> StatSet {
+        StatSet(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for StatSet {
+    fn bitor_assign(&mut self, rhs: StatSet) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Roughly how many meters one map unit stands for, going by the usual
+/// comparison of Doom's textures and actors with real-world sizes.
+pub const DOOM_UNIT_METERS: f64 = 0.012;
+
+/// The per-map numbers shown by the report formats. The optional fields are
+/// only filled in when asked for with a `StatSet`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapStats {
+    pub name: String,
+    pub things: usize,
+    pub linedefs: usize,
+    pub sidedefs: usize,
+    pub vertexes: usize,
+    pub sectors: usize,
+    pub monsters: Option<usize>,
+    pub secrets: Option<usize>,
+    /// The floor area in square map units, leaving out sectors whose outline
+    /// can't be traced.
+    pub area: Option<f64>,
+    /// The bounding box's width and height in map units, 0 by 0 for a map
+    /// without vertexes.
+    pub size: Option<(u32, u32)>,
+    /// Meters per map unit. When set, the report formats also give the size
+    /// and area in meters. It's only for presentation and isn't stored in
+    /// the binary format.
+    pub scale: Option<f64>,
+}
+
+impl MapStats {
+    pub const CSV_HEADER: &'static str = "map,things,linedefs,sidedefs,vertexes,sectors";
+
+    /// `CSV_HEADER` followed by a column for each statistic in `stats`, two
+    /// for the size, and the sizes in meters if `scaled`.
+    pub fn csv_header(stats: StatSet, scaled: bool) -> String {
+        let mut header = Self::CSV_HEADER.to_string();
+        for (flag, name) in StatSet::NAMES {
+            if stats.contains(flag) {
+                header.push(',');
+                header.push_str(if flag == StatSet::SIZE {
+                    "width,height"
+                } else {
+                    name
+                });
+            }
+        }
+        if scaled {
+            if stats.contains(StatSet::SIZE) {
+                header.push_str(",width_m,height_m");
+            }
+            if stats.contains(StatSet::AREA) {
+                header.push_str(",area_m2");
+            }
+        }
+        header
+    }
+
+    pub fn with_scale(self, scale: Option<f64>) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// The bounding box's width and height in meters, if both the size and a
+    /// scale are known.
+    pub fn scaled_size(&self) -> Option<(f64, f64)> {
+        let ((width, height), scale) = (self.size?, self.scale?);
+        Some((width as f64 * scale, height as f64 * scale))
+    }
+
+    /// The floor area in square meters, if both the area and a scale are
+    /// known.
+    pub fn scaled_area(&self) -> Option<f64> {
+        Some(self.area? * self.scale?.powi(2))
+    }
+
+    /// The optional statistics that were computed.
+    pub fn computed(&self) -> StatSet {
+        let mut set = StatSet::default();
+        for (flag, present) in [
+            (StatSet::MONSTERS, self.monsters.is_some()),
+            (StatSet::SECRETS, self.secrets.is_some()),
+            (StatSet::AREA, self.area.is_some()),
+            (StatSet::SIZE, self.size.is_some()),
+        ] {
+            if present {
+                set |= flag;
+            }
+        }
+        set
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("map", self.name.as_str().into()),
+            ("things", self.things.into()),
+            ("linedefs", self.linedefs.into()),
+            ("sidedefs", self.sidedefs.into()),
+            ("vertexes", self.vertexes.into()),
+            ("sectors", self.sectors.into()),
+        ];
+        if let Some(n) = self.monsters {
+            fields.push(("monsters", n.into()));
+        }
+        if let Some(n) = self.secrets {
+            fields.push(("secrets", n.into()));
+        }
+        if let Some(a) = self.area {
+            fields.push(("area", a.into()));
+        }
+        if let Some((width, height)) = self.size {
+            fields.push(("width", width.into()));
+            fields.push(("height", height.into()));
+        }
+        if let Some(scale) = self.scale {
+            fields.push(("scale", scale.into()));
+        }
+        if let Some((width, height)) = self.scaled_size() {
+            fields.push(("width_m", width.into()));
+            fields.push(("height_m", height.into()));
+        }
+        if let Some(a) = self.scaled_area() {
+            fields.push(("area_m2", a.into()));
+        }
+        Json::object(fields)
+    }
+
+    /// Reads back the output of `to_json`.
+    pub fn from_json(json: &Json) -> Option<Self> {
+        let number = |key: &str| json.get(key)?.as_f64();
+        let count = |key: &str| number(key).map(|n| n as usize);
+        Some(Self {
+            name: json.get("map")?.as_str()?.to_string(),
+            things: count("things")?,
+            linedefs: count("linedefs")?,
+            sidedefs: count("sidedefs")?,
+            vertexes: count("vertexes")?,
+            sectors: count("sectors")?,
+            monsters: count("monsters"),
+            secrets: count("secrets"),
+            area: number("area"),
+            size: count("width")
+                .zip(count("height"))
+                .map(|(w, h)| (w as u32, h as u32)),
+            scale: number("scale"),
+        })
+    }
+
+    /// The counts in `CSV_HEADER` order, then whichever optional statistics
+    /// were computed, matching `csv_header`.
+    pub fn to_csv(&self) -> String {
+        let mut row = format!(
+            "{},{},{},{},{},{}",
+            csv_field(&self.name),
+            self.things,
+            self.linedefs,
+            self.sidedefs,
+            self.vertexes,
+            self.sectors
+        );
+        for n in [self.monsters, self.secrets].iter().flatten() {
+            row.push_str(&format!(",{}", n));
+        }
+        if let Some(a) = self.area {
+            row.push_str(&format!(",{}", a));
+        }
+        if let Some((width, height)) = self.size {
+            row.push_str(&format!(",{},{}", width, height));
+        }
+        if let Some((width, height)) = self.scaled_size() {
+            row.push_str(&format!(",{},{}", width, height));
+        }
+        if let Some(a) = self.scaled_area() {
+            row.push_str(&format!(",{}", a));
+        }
+        row
+    }
+}
+
+/// The `schema` field at the top of every JSON and NDJSON document. Within a
+/// schema version, changes are only additive: fields may be added, such as
+/// new optional statistics, but existing ones keep their names and meanings.
+/// Renaming, removing or retyping a field bumps it.
+///
+/// Version 1 has `file` and a `maps` array per WAD for JSON, and `file` plus
+/// the map's fields per line for NDJSON. A map has `map`, `things`,
+/// `linedefs`, `sidedefs`, `vertexes` and `sectors`, plus `monsters`,
+/// `secrets`, `area`, and `width` and `height` when they were asked for, or
+/// `map` and `error` if it couldn't be read. With a scale there are also
+/// `scale`, `width_m`, `height_m` and `area_m2`.
+pub const JSON_SCHEMA: u32 = 1;
+
+/// The first bytes of `--format binary` output.
+pub const BINARY_MAGIC: [u8; 4] = *b"DMSB";
+/// Bumped whenever the binary record layout changes.
+pub const BINARY_VERSION: u16 = 3;
+
+/// The header that starts a binary statistics stream: the magic bytes, then
+/// the format version as a little-endian `u16`.
+pub fn binary_header() -> Vec<u8> {
+    let mut out = BINARY_MAGIC.to_vec();
+    out.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+    out
+}
+
+/// Appends one WAD's record to a binary statistics stream. A record is the
+/// file name, then a `u32` map count and each map's name followed by its five
+/// counts in `CSV_HEADER` order as `u32`s. Then comes a byte holding the
+/// `StatSet` of optional statistics present, followed by those present: the
+/// monster and secret counts as `u32`s, the area as an `f64`, and the width
+/// and height as `u32`s. Strings are a
+/// `u32` byte length followed by UTF-8; every number is little-endian.
+pub fn write_binary(out: &mut Vec<u8>, file: &str, maps: &[&MapStats]) {
+    let write_str = |out: &mut Vec<u8>, s: &str| {
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    };
+    write_str(out, file);
+    out.extend_from_slice(&(maps.len() as u32).to_le_bytes());
+    for stats in maps {
+        write_str(out, &stats.name);
+        for count in [
+            stats.things,
+            stats.linedefs,
+            stats.sidedefs,
+            stats.vertexes,
+            stats.sectors,
+        ] {
+            out.extend_from_slice(&(count as u32).to_le_bytes());
+        }
+        out.push(stats.computed().0);
+        for count in [stats.monsters, stats.secrets].iter().flatten() {
+            out.extend_from_slice(&(*count as u32).to_le_bytes());
+        }
+        if let Some(area) = stats.area {
+            out.extend_from_slice(&area.to_le_bytes());
+        }
+        if let Some((width, height)) = stats.size {
+            out.extend_from_slice(&width.to_le_bytes());
+            out.extend_from_slice(&height.to_le_bytes());
+        }
+    }
+}
+
+/// Reads back a whole binary statistics stream as `(file, maps)` records.
+/// Returns `None` if the header is missing or from another version, or the
+/// data is truncated.
+pub fn read_binary(data: &[u8]) -> Option<Vec<(String, Vec<MapStats>)>> {
+    fn read_u32(data: &mut &[u8]) -> Option<u32> {
+        let (n, rest) = data.split_first_chunk::<4>()?;
+        *data = rest;
+        Some(u32::from_le_bytes(*n))
+    }
+    fn read_f64(data: &mut &[u8]) -> Option<f64> {
+        let (n, rest) = data.split_first_chunk::<8>()?;
+        *data = rest;
+        Some(f64::from_le_bytes(*n))
+    }
+    fn read_str(data: &mut &[u8]) -> Option<String> {
+        let len = read_u32(data)? as usize;
+        let s = String::from_utf8(data.get(..len)?.to_vec()).ok()?;
+        *data = &data[len..];
+        Some(s)
+    }
+
+    let header = binary_header();
+    let mut data = data.strip_prefix(header.as_slice())?;
+    let mut records = vec![];
+    while !data.is_empty() {
+        let file = read_str(&mut data)?;
+        let mut maps = vec![];
+        for _ in 0..read_u32(&mut data)? {
+            let name = read_str(&mut data)?;
+            let mut counts = [0usize; 5];
+            for c in &mut counts {
+                *c = read_u32(&mut data)? as usize;
+            }
+            let (&present, rest) = data.split_first()?;
+            data = rest;
+            let present = StatSet(present);
+            let mut count = |flag| {
+                Some(if present.contains(flag) {
+                    Some(read_u32(&mut data)? as usize)
+                } else {
+                    None
+                })
+            };
+            let monsters = count(StatSet::MONSTERS)?;
+            let secrets = count(StatSet::SECRETS)?;
+            let area = if present.contains(StatSet::AREA) {
+                Some(read_f64(&mut data)?)
+            } else {
+                None
+            };
+            let size = if present.contains(StatSet::SIZE) {
+                Some((read_u32(&mut data)?, read_u32(&mut data)?))
+            } else {
+                None
+            };
+            maps.push(MapStats {
+                name,
+                things: counts[0],
+                linedefs: counts[1],
+                sidedefs: counts[2],
+                vertexes: counts[3],
+                sectors: counts[4],
+                monsters,
+                secrets,
+                area,
+                size,
+                scale: None,
+            });
+        }
+        records.push((file, maps));
+    }
+    Some(records)
+}
+
+/// Every map's statistics, with all of `StatSet::all` computed, and their
+/// sums. A map that can't be read keeps its error in place of its
+/// statistics and is left out of the sums.
+#[derive(Debug)]
+pub struct WadStats {
+    pub maps: Vec<(String, Result<MapStats, Error>)>,
+    pub totals: WadTotals,
+}
+
+/// The sums of the statistics of a WAD's readable maps.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WadTotals {
+    pub maps: usize,
+    /// Maps whose statistics couldn't be computed.
+    pub failed: usize,
+    pub things: usize,
+    pub linedefs: usize,
+    pub sidedefs: usize,
+    pub vertexes: usize,
+    pub sectors: usize,
+    pub monsters: usize,
+    pub secrets: usize,
+    pub area: f64,
+}
+
+impl WadTotals {
+    fn add(&mut self, stats: &MapStats) {
+        self.things += stats.things;
+        self.linedefs += stats.linedefs;
+        self.sidedefs += stats.sidedefs;
+        self.vertexes += stats.vertexes;
+        self.sectors += stats.sectors;
+        self.monsters += stats.monsters.unwrap_or(0);
+        self.secrets += stats.secrets.unwrap_or(0);
+        self.area += stats.area.unwrap_or(0.0);
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("maps", self.maps.into()),
+            ("failed", self.failed.into()),
+            ("things", self.things.into()),
+            ("linedefs", self.linedefs.into()),
+            ("sidedefs", self.sidedefs.into()),
+            ("vertexes", self.vertexes.into()),
+            ("sectors", self.sectors.into()),
+            ("monsters", self.monsters.into()),
+            ("secrets", self.secrets.into()),
+            ("area", self.area.into()),
+        ])
+    }
+}
+
+impl WadStats {
+    /// The maps in the same shape as the JSON report, an unreadable map
+    /// giving its name and error, followed by the totals.
+    pub fn to_json(&self) -> Json {
+        let maps = self
+            .maps
+            .iter()
+            .map(|(name, stats)| match stats {
+                Ok(stats) => stats.to_json(),
+                Err(e) => Json::object(vec![
+                    ("map", name.as_str().into()),
+                    ("error", e.to_string().into()),
+                ]),
+            })
+            .collect();
+        Json::object(vec![
+            ("maps", Json::Array(maps)),
+            ("totals", self.totals.to_json()),
+        ])
+    }
+}
+
+/// What a map holds for single player on one skill level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkillCounts {
+    pub skill: Skill,
+    pub monsters: usize,
+    /// The monsters that are bosses by `ThingTable::is_boss`.
+    pub bosses: usize,
+    /// Weapons, ammo, health, armor and powerups.
+    pub items: usize,
+    /// The monsters' combined spawn health, as a rough measure of difficulty:
+    /// the damage the player has to deal to clear the map. Only Doom's
+    /// monsters have a known health, so it's 0 in other games.
+    pub monster_health: u32,
+}
+
+/// A map's monsters and items on each skill level, side by side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkillProfile {
+    /// One entry per `Skill::ALL`, in order.
+    pub skills: Vec<SkillCounts>,
+}
+
+impl SkillProfile {
+    pub fn to_json(&self) -> Json {
+        Json::Array(
+            self.skills
+                .iter()
+                .map(|s| {
+                    Json::object(vec![
+                        ("skill", s.skill.name().into()),
+                        ("monsters", s.monsters.into()),
+                        ("bosses", s.bosses.into()),
+                        ("items", s.items.into()),
+                        ("monster_health", s.monster_health.into()),
+                    ])
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Monsters and items in one game mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModeCounts {
+    pub monsters: usize,
+    /// Weapons, ammo, health, armor and powerups.
+    pub items: usize,
+}
+
+/// How cooperative play changes what a map holds on the hardest skill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoopDelta {
+    pub single_player: ModeCounts,
+    /// The things flagged multiplayer-only, which coop adds.
+    pub coop_only: ModeCounts,
+    /// Single-player things that Boom's not-in-coop flag leaves out of coop.
+    pub single_player_only: ModeCounts,
+    /// What coop holds: the single-player things, with `coop_only` added and
+    /// `single_player_only` taken away.
+    pub coop: ModeCounts,
+}
+
+impl CoopDelta {
+    pub fn to_json(&self) -> Json {
+        let counts = |c: &ModeCounts| {
+            Json::object(vec![
+                ("monsters", c.monsters.into()),
+                ("items", c.items.into()),
+            ])
+        };
+        Json::object(vec![
+            ("single_player", counts(&self.single_player)),
+            ("coop_only", counts(&self.coop_only)),
+            ("single_player_only", counts(&self.single_player_only)),
+            ("coop", counts(&self.coop)),
+        ])
+    }
+}
+
+/// Lines shorter than this are counted by `Map::linedef_length_stats`.
+pub const DEFAULT_SHORT_LINEDEF: f64 = 8.0;
+
+/// The number of buckets in `LengthStats::histogram`.
+pub const LENGTH_BUCKETS: usize = 12;
+
+/// A summary of a map's linedef lengths, in map units.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LengthStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Line counts by powers of two: bucket 0 holds lines shorter than 1 unit,
+    /// bucket `n` lines from `2^(n-1)` up to `2^n`, and the last bucket
+    /// everything longer.
+    pub histogram: [usize; LENGTH_BUCKETS],
+    /// The length below which lines were counted as short.
+    pub short_threshold: f64,
+    pub short: usize,
+}
+
+impl LengthStats {
+    /// The lower bound of a histogram bucket.
+    pub fn bucket_start(bucket: usize) -> f64 {
+        match bucket {
+            0 => 0.0,
+            n => (1u64 << (n - 1)) as f64,
+        }
+    }
+}
+
+/// Subsectors with fewer segs than this can't enclose an area.
+pub const MIN_SUBSECTOR_SEGS: u16 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubsectorStats {
+    pub subsectors: usize,
+    pub mean_segs: f64,
+    /// Indexes of subsectors with fewer than `MIN_SUBSECTOR_SEGS` segs, fewest
+    /// segs first.
+    pub degenerate: Vec<usize>,
+}
+
+/// Linedefs carrying each automap flag. A line can carry several.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AutomapFlagCounts {
+    pub secret: usize,
+    pub never_shown: usize,
+    pub always_shown: usize,
+}
+
+/// Things setting each flag bit, from `Map::thing_flags_summary`. A thing
+/// can set several.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThingFlagsSummary {
+    /// Every thing, whatever its flags.
+    pub things: usize,
+    pub easy: usize,
+    pub medium: usize,
+    pub hard: usize,
+    pub ambush: usize,
+    pub multiplayer: usize,
+    /// The Boom and MBF bits, counted as those ports read them, so not for
+    /// things with the reserved bit set.
+    pub not_deathmatch: usize,
+    pub not_coop: usize,
+    pub friendly: usize,
+    pub reserved: usize,
+}
+
+impl ThingFlagsSummary {
+    /// Each flag's name and count, in bit order.
+    pub fn rows(&self) -> [(&'static str, usize); 9] {
+        [
+            ("easy", self.easy),
+            ("medium", self.medium),
+            ("hard", self.hard),
+            ("ambush", self.ambush),
+            ("multiplayer", self.multiplayer),
+            ("not_deathmatch", self.not_deathmatch),
+            ("not_coop", self.not_coop),
+            ("friendly", self.friendly),
+            ("reserved", self.reserved),
+        ]
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![("things", self.things.into())];
+        fields.extend(
+            self.rows()
+                .iter()
+                .map(|&(name, count)| (name, count.into())),
+        );
+        Json::object(fields)
+    }
+}
+
+/// Linedefs by what they stop. Every linedef is counted in exactly one field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PassabilityStats {
+    /// Lines with no left sidedef, which are always solid walls.
+    pub one_sided: usize,
+    /// Two-sided lines anything can cross.
+    pub passable: usize,
+    /// Two-sided lines flagged impassable, which play as invisible walls.
+    pub impassable: usize,
+    /// Two-sided lines that only stop monsters.
+    pub monster_blocking: usize,
+}
+
+/// The directions `AngleHistogram` buckets angles into, counterclockwise from
+/// east like Doom's angles.
+pub const DIRECTIONS: [&str; 8] = [
+    "east",
+    "northeast",
+    "north",
+    "northwest",
+    "west",
+    "southwest",
+    "south",
+    "southeast",
+];
+
+/// Thing counts by the direction they face, indexed like `DIRECTIONS`. Angles
+/// between two directions go to the nearest one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AngleHistogram {
+    pub all: [usize; 8],
+    /// Player 1 to 4 starts.
+    pub player_starts: [usize; 8],
+    pub deathmatch_starts: [usize; 8],
+}
+
+/// Thing counts over a grid laid across the map's bounds, from
+/// `Map::thing_heatmap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    /// The map coordinates of the grid's bottom left corner.
+    pub origin: (i32, i32),
+    pub cell_size: i32,
+    pub columns: usize,
+    pub rows: usize,
+    /// The counts row by row from the bottom, so `cells[row][column]` covers
+    /// the cell at `origin + (column, row) * cell_size`.
+    pub cells: Vec<Vec<usize>>,
+}
+
+/// The `DIRECTIONS` index nearest to an angle in degrees.
+pub fn direction(angle: i16) -> usize {
+    ((angle as i32 + 22).rem_euclid(360) / 45) as usize
+}
+
+/// Which area `Map::detail_ratio` divided by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AreaSource {
+    Floor,
+    BoundingBox,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetailRatio {
+    /// Linedefs per square map unit, or 0 for a map without any area.
+    pub ratio: f64,
+    pub area: f64,
+    pub source: AreaSource,
+}
+
+/// How much wider than tall, or taller than wide, a map's bounding box may
+/// be and still count as `ShapeKind::Square`.
+pub const SQUARE_ASPECT_LIMIT: f64 = 1.5;
+
+/// The fraction of its bounding box a map's floor has to fill not to count
+/// as `ShapeKind::Sprawling`.
+pub const SPRAWLING_FILL: f64 = 0.25;
+
+/// How many linedefs may bound a sector before `Map::over_detailed_sectors`
+/// reports it.
+pub const DETAILED_SECTOR_EDGES: usize = 128;
+
+/// The overall form of a map's footprint, from `Map::shape`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapeKind {
+    Square,
+    /// Wider, east to west, than `SQUARE_ASPECT_LIMIT` allows.
+    Wide,
+    Tall,
+    /// Filling less than `SPRAWLING_FILL` of its bounding box, such as a map
+    /// of long corridors or scattered areas, whatever the box's shape.
+    Sprawling,
+}
+
+impl ShapeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ShapeKind::Square => "square",
+            ShapeKind::Wide => "wide",
+            ShapeKind::Tall => "tall",
+            ShapeKind::Sprawling => "sprawling",
+        }
+    }
+}
+
+/// A map's footprint: its classification and the measures it's made from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapShape {
+    pub kind: ShapeKind,
+    /// The bounding box's width over its height.
+    pub aspect_ratio: f64,
+    /// The floor area over the bounding box's area, from 0 to 1.
+    pub fill: f64,
+}
+
+impl MapShape {
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("kind", self.kind.name().into()),
+            ("aspect_ratio", self.aspect_ratio.into()),
+            ("fill", self.fill.into()),
+        ])
+    }
+}
+
+/// How `Map::detail_line_ratio_with` tells a two-sided line that's only
+/// there for looks, such as the edge of a trim, light strip or floor pattern,
+/// from one shaping the space. A line is detail when the sectors on its two
+/// sides differ by no more than the given steps, whatever their light levels
+/// and flats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetailLineRule {
+    /// Only count impassable lines as detail.
+    pub impassable_only: bool,
+    /// The most the floor heights on either side may differ by.
+    pub max_floor_step: u16,
+    /// The most the ceiling heights on either side may differ by.
+    pub max_ceiling_step: u16,
+}
+
+impl Default for DetailLineRule {
+    fn default() -> Self {
+        Self {
+            impassable_only: true,
+            max_floor_step: 8,
+            max_ceiling_step: 8,
+        }
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+pub fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+impl Map {
+    /// The structure counts, without any of the optional statistics.
+    pub fn stats(&self) -> Result<MapStats, Error> {
+        // none of the structure counts tell things apart
+        self.stats_with(StatSet::default(), ThingTable::Doom)
+    }
+
+    /// The structure counts and the optional statistics in `stats`, skipping
+    /// the work for the rest. Monsters are told apart from other things by
+    /// `table`.
+    pub fn stats_with(&self, stats: StatSet, table: ThingTable) -> Result<MapStats, Error> {
+        self.stats_timed(stats, table).0
+    }
+
+    /// Like `stats_with`, also giving how long each part took: `counts` for
+    /// the structure counts, then each statistic in `stats` under its
+    /// `StatSet::NAMES` name. Parts after a failure are missing.
+    pub fn stats_timed(
+        &self,
+        stats: StatSet,
+        table: ThingTable,
+    ) -> (Result<MapStats, Error>, Vec<(&'static str, Duration)>) {
+        let mut timings = vec![];
+        let result = self.timed_stats(stats, table, &mut timings);
+        (result, timings)
+    }
+
+    fn timed_stats(
+        &self,
+        stats: StatSet,
+        table: ThingTable,
+        timings: &mut Vec<(&'static str, Duration)>,
+    ) -> Result<MapStats, Error> {
+        fn timed<T>(
+            timings: &mut Vec<(&'static str, Duration)>,
+            name: &'static str,
+            f: impl FnOnce() -> T,
+        ) -> T {
+            let started = Instant::now();
+            let value = f();
+            timings.push((name, started.elapsed()));
+            value
+        }
+
+        let (things, sectors, linedefs, sidedefs, vertexes) = timed(timings, "counts", || {
+            Ok::<_, Error>((
+                self.things()?,
+                self.sectors()?,
+                self.linedefs()?.len(),
+                self.sidedefs()?.len(),
+                self.vertexes()?.len(),
+            ))
+        })?;
+        let monsters = if stats.contains(StatSet::MONSTERS) {
+            Some(timed(timings, "monsters", || {
+                things
+                    .iter()
+                    .filter(|t| t.flags & THING_FLAG_MULTIPLAYER == 0 && t.appears_on(Skill::Hard))
+                    .filter(|t| table.category(t.doomednum) == Some(ThingCategory::Monster))
+                    .count()
+            }))
+        } else {
+            None
+        };
+        let secrets = if stats.contains(StatSet::SECRETS) {
+            Some(timed(timings, "secrets", || {
+                sectors.iter().filter(|s| s.is_secret()).count()
+            }))
+        } else {
+            None
+        };
+        let area = if stats.contains(StatSet::AREA) {
+            Some(timed(timings, "area", || self.floor_area())?)
+        } else {
+            None
+        };
+        let size = if stats.contains(StatSet::SIZE) {
+            let bounds = timed(timings, "size", || {
+                self.require_geometry()?;
+                self.bounds()
+            })?;
+            Some(bounds.map_or((0, 0), |b| {
+                (
+                    (b.max_x as i32 - b.min_x as i32) as u32,
+                    (b.max_y as i32 - b.min_y as i32) as u32,
+                )
+            }))
+        } else {
+            None
+        };
+        Ok(MapStats {
+            name: self.name.clone(),
+            things: things.len(),
+            linedefs,
+            sidedefs,
+            vertexes,
+            sectors: sectors.len(),
+            monsters,
+            secrets,
+            area,
+            size,
+            scale: None,
+        })
+    }
+
+    /// How many of the monsters the `monsters` statistic counts are bosses
+    /// by `ThingTable::is_boss`: those on the hardest skill in single player.
+    pub fn boss_count(&self, table: ThingTable) -> Result<usize, Error> {
+        Ok(self
+            .things()?
+            .iter()
+            .filter(|t| t.flags & THING_FLAG_MULTIPLAYER == 0 && t.appears_on(Skill::Hard))
+            .filter(|t| table.is_boss(t.doomednum))
+            .count())
+    }
+
+    /// How many monsters MBF's friendly flag puts on the player's side, on
+    /// any skill. Flags that Boom and MBF ignore, because the reserved bit
+    /// is set too, don't count, so vanilla maps with junk in their flags
+    /// aren't taken for MBF maps.
+    pub fn friendly_monsters(&self, table: ThingTable) -> Result<usize, Error> {
+        Ok(self
+            .things()?
+            .iter()
+            .filter(|t| t.is_friendly())
+            .filter(|t| table.category(t.doomednum) == Some(ThingCategory::Monster))
+            .count())
+    }
+
+    /// Monster and item counts for each skill level in single player, going by
+    /// `table`. Monster health is only known for Doom's monsters.
+    pub fn skill_profile(&self, table: ThingTable) -> Result<SkillProfile, Error> {
+        let things = self
+            .things()?
+            .into_iter()
+            .filter(|t| t.flags & THING_FLAG_MULTIPLAYER == 0)
+            .filter_map(|t| Some((t, table.category(t.doomednum)?)))
+            .collect::<Vec<_>>();
+        let skills = Skill::ALL
+            .iter()
+            .map(|&skill| {
+                let mut counts = SkillCounts {
+                    skill,
+                    monsters: 0,
+                    bosses: 0,
+                    items: 0,
+                    monster_health: 0,
+                };
+                for (thing, category) in things.iter().filter(|(t, _)| t.appears_on(skill)) {
+                    match category {
+                        ThingCategory::Monster => {
+                            counts.monsters += 1;
+                            counts.bosses += table.is_boss(thing.doomednum) as usize;
+                            counts.monster_health += table
+                                .doom_thing(thing.doomednum)
+                                .and_then(Thing::health)
+                                .unwrap_or(0);
+                        }
+                        ThingCategory::Weapon
+                        | ThingCategory::Ammo
+                        | ThingCategory::Health
+                        | ThingCategory::Armor
+                        | ThingCategory::Powerup => counts.items += 1,
+                        _ => {}
+                    }
+                }
+                counts
+            })
+            .collect();
+        Ok(SkillProfile { skills })
+    }
+
+    /// The monsters and items of single player and of cooperative play on
+    /// the hardest skill, going by `table`. Coop adds the things flagged
+    /// multiplayer-only and, in Boom-compatible ports, drops those flagged
+    /// not-in-coop.
+    pub fn coop_delta(&self, table: ThingTable) -> Result<CoopDelta, Error> {
+        let mut delta = CoopDelta {
+            single_player: ModeCounts::default(),
+            coop_only: ModeCounts::default(),
+            single_player_only: ModeCounts::default(),
+            coop: ModeCounts::default(),
+        };
+        for thing in self.things()? {
+            if !thing.appears_on(Skill::Hard) {
+                continue;
+            }
+            let add = |counts: &mut ModeCounts| match table.category(thing.doomednum) {
+                Some(ThingCategory::Monster) => counts.monsters += 1,
+                Some(
+                    ThingCategory::Weapon
+                    | ThingCategory::Ammo
+                    | ThingCategory::Health
+                    | ThingCategory::Armor
+                    | ThingCategory::Powerup,
+                ) => counts.items += 1,
+                _ => {}
+            };
+            let multiplayer = thing.flags & THING_FLAG_MULTIPLAYER != 0;
+            let in_coop = thing.port_flags() & THING_FLAG_NOT_COOP == 0;
+            if !multiplayer {
+                add(&mut delta.single_player);
+            }
+            match (multiplayer, in_coop) {
+                (true, true) => add(&mut delta.coop_only),
+                (false, false) => add(&mut delta.single_player_only),
+                _ => {}
+            }
+            if in_coop {
+                add(&mut delta.coop);
+            }
+        }
+        Ok(delta)
+    }
+
+    /// Whether some things appear on some skill levels but not others, so that
+    /// the map plays differently on each. Many maps put every thing on every
+    /// skill.
+    pub fn differentiates_skill(&self) -> Result<bool, Error> {
+        Ok(self.skill_gated_fraction()? > 0.0)
+    }
+
+    /// The fraction of things that appear on some skill levels but not
+    /// others. Things on no skill at all aren't gated, since no skill shows
+    /// them. A map without things gives 0.
+    pub fn skill_gated_fraction(&self) -> Result<f64, Error> {
+        let things = self.things()?;
+        let gated = things
+            .iter()
+            .filter(|t| {
+                let skills = t.skills().len();
+                skills > 0 && skills < Skill::ALL.len()
+            })
+            .count();
+        Ok(if things.is_empty() {
+            0.0
+        } else {
+            gated as f64 / things.len() as f64
+        })
+    }
+
+    /// The fraction of things that are only decoration, such as torches,
+    /// corpses and gore, out of all the things `table` knows. Unknown types
+    /// don't count either way. A map without known things gives 0.
+    pub fn decoration_ratio(&self, table: ThingTable) -> Result<f64, Error> {
+        let categories = self
+            .things()?
+            .iter()
+            .filter_map(|t| table.category(t.doomednum))
+            .collect::<Vec<_>>();
+        let decorations = categories
+            .iter()
+            .filter(|&&c| c == ThingCategory::Decoration)
+            .count();
+        Ok(if categories.is_empty() {
+            0.0
+        } else {
+            decorations as f64 / categories.len() as f64
+        })
+    }
+
+    /// How many things of each type number the map has, including numbers no
+    /// thing table knows.
+    pub fn thing_type_counts(&self) -> Result<BTreeMap<u16, usize>, Error> {
+        let mut counts = BTreeMap::new();
+        for thing in self.things()? {
+            *counts.entry(thing.doomednum).or_default() += 1;
+        }
+        Ok(counts)
+    }
+
+    /// Linedefs flagged secret, which the automap draws as solid walls so the
+    /// area behind them doesn't show.
+    pub fn automap_secret_lines(&self) -> Result<usize, Error> {
+        Ok(self.automap_flag_counts()?.secret)
+    }
+
+    pub fn automap_flag_counts(&self) -> Result<AutomapFlagCounts, Error> {
+        let mut counts = AutomapFlagCounts::default();
+        for line in self.linedefs()? {
+            counts.secret += (line.flags & LINEDEF_FLAG_SECRET != 0) as usize;
+            counts.never_shown += (line.flags & LINEDEF_FLAG_NEVER_ON_AUTOMAP != 0) as usize;
+            counts.always_shown += (line.flags & LINEDEF_FLAG_ALWAYS_ON_AUTOMAP != 0) as usize;
+        }
+        Ok(counts)
+    }
+
+    /// Tallies how many things set each recognized flag bit, to spot a map
+    /// where, say, nothing appears on hard.
+    pub fn thing_flags_summary(&self) -> Result<ThingFlagsSummary, Error> {
+        let mut summary = ThingFlagsSummary::default();
+        for thing in self.things()? {
+            let port_flags = thing.port_flags();
+            summary.things += 1;
+            summary.easy += (thing.flags & THING_FLAG_EASY != 0) as usize;
+            summary.medium += (thing.flags & THING_FLAG_MEDIUM != 0) as usize;
+            summary.hard += (thing.flags & THING_FLAG_HARD != 0) as usize;
+            summary.ambush += (thing.flags & THING_FLAG_AMBUSH != 0) as usize;
+            summary.multiplayer += (thing.flags & THING_FLAG_MULTIPLAYER != 0) as usize;
+            summary.not_deathmatch += (port_flags & THING_FLAG_NOT_DEATHMATCH != 0) as usize;
+            summary.not_coop += (port_flags & THING_FLAG_NOT_COOP != 0) as usize;
+            summary.friendly += (port_flags & THING_FLAG_FRIENDLY != 0) as usize;
+            summary.reserved += (thing.flags & THING_FLAG_RESERVED != 0) as usize;
+        }
+        Ok(summary)
+    }
+
+    /// Sorts the linedefs by what can cross them. Like the engine, this goes
+    /// by whether a line has a left sidedef rather than by its two-sided
+    /// flag, so a line flagged two-sided with only one side counts as one-sided.
+    pub fn passability_stats(&self) -> Result<PassabilityStats, Error> {
+        let mut stats = PassabilityStats::default();
+        for line in self.linedefs()? {
+            let count = if line.left_sidedef == NO_SIDEDEF {
+                &mut stats.one_sided
+            } else if line.flags & LINEDEF_FLAG_IMPASSABLE != 0 {
+                &mut stats.impassable
+            } else if line.flags & LINEDEF_FLAG_BLOCK_MONSTERS != 0 {
+                &mut stats.monster_blocking
+            } else {
+                &mut stats.passable
+            };
+            *count += 1;
+        }
+        Ok(stats)
+    }
+
+    /// Which way the map's things face. Deathmatch maps usually point starts
+    /// into the room rather than at the nearest wall. Every game numbers its
+    /// starts like Doom, but `table` decides which numbers are starts.
+    pub fn thing_angles(&self, table: ThingTable) -> Result<AngleHistogram, Error> {
+        let mut histogram = AngleHistogram::default();
+        for thing in self.things()? {
+            let d = direction(thing.angle);
+            histogram.all[d] += 1;
+            if table.category(thing.doomednum) != Some(ThingCategory::PlayerStart) {
+                continue;
+            }
+            if thing.doomednum == Thing::DeathmatchStart.doomednum() {
+                histogram.deathmatch_starts[d] += 1;
+            } else {
+                histogram.player_starts[d] += 1;
+            }
+        }
+        Ok(histogram)
+    }
+
+    /// Bins things into square cells of `cell_size` map units covering the
+    /// map's bounds, counting only things in `categories` by `table`, or
+    /// every thing if it's empty. Things outside the bounds aren't
+    /// counted. Cell sizes below 1 are taken as 1. Fails with
+    /// `Error::EmptyLump` for a map without geometry.
+    pub fn thing_heatmap(
+        &self,
+        cell_size: i32,
+        categories: &[ThingCategory],
+        table: ThingTable,
+    ) -> Result<Heatmap, Error> {
+        self.require_geometry()?;
+        let cell_size = cell_size.max(1);
+        let bounds = self.bounds()?.expect("a map with geometry has vertexes");
+        let origin = (bounds.min_x as i32, bounds.min_y as i32);
+        // things on the far edges go in the last cells rather than ones of their own
+        let cells_across = |min: i16, max: i16| {
+            ((max as i32 - min as i32 + cell_size - 1) / cell_size).max(1) as usize
+        };
+        let columns = cells_across(bounds.min_x, bounds.max_x);
+        let rows = cells_across(bounds.min_y, bounds.max_y);
+        let mut cells = vec![vec![0; columns]; rows];
+        for thing in self.things()? {
+            let wanted = categories.is_empty()
+                || table
+                    .category(thing.doomednum)
+                    .is_some_and(|c| categories.contains(&c));
+            if !wanted || !bounds.contains(thing.x, thing.y, 0) {
+                continue;
+            }
+            let column = (((thing.x as i32 - origin.0) / cell_size) as usize).min(columns - 1);
+            let row = (((thing.y as i32 - origin.1) / cell_size) as usize).min(rows - 1);
+            cells[row][column] += 1;
+        }
+        Ok(Heatmap {
+            origin,
+            cell_size,
+            columns,
+            rows,
+            cells,
+        })
+    }
+
+    /// Seg counts per subsector from the SSECTORS lump. Degenerate subsectors
+    /// come from a bad node build and show up as holes in the rendered world.
+    pub fn subsector_stats(&self) -> Result<SubsectorStats, Error> {
+        let subsectors = self.subsectors()?;
+        let total = subsectors
+            .iter()
+            .map(|s| s.seg_count as usize)
+            .sum::<usize>();
+        let mut degenerate = subsectors
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.seg_count < MIN_SUBSECTOR_SEGS)
+            .map(|(i, s)| (s.seg_count, i))
+            .collect::<Vec<_>>();
+        degenerate.sort_unstable();
+        Ok(SubsectorStats {
+            subsectors: subsectors.len(),
+            mean_segs: if subsectors.is_empty() {
+                0.0
+            } else {
+                total as f64 / subsectors.len() as f64
+            },
+            degenerate: degenerate.into_iter().map(|(_, i)| i).collect(),
+        })
+    }
+
+    /// Linedef length statistics, counting lines shorter than
+    /// `DEFAULT_SHORT_LINEDEF` as short.
+    pub fn linedef_length_stats(&self) -> Result<LengthStats, Error> {
+        self.linedef_length_stats_with_threshold(DEFAULT_SHORT_LINEDEF)
+    }
+
+    /// Like `linedef_length_stats`, with a custom threshold for short lines.
+    /// Very short lines are often editor leftovers, and many of them make a
+    /// map harder for node builders. Lines with a missing vertex are skipped.
+    /// Fails with `Error::EmptyLump` for a map without geometry.
+    pub fn linedef_length_stats_with_threshold(
+        &self,
+        short_threshold: f64,
+    ) -> Result<LengthStats, Error> {
+        self.require_geometry()?;
+        let vertexes = self.vertexes()?;
+        let lengths = self
+            .linedefs()?
+            .iter()
+            .filter_map(|line| {
+                let v1 = vertexes.get(line.start_vertex as usize)?;
+                let v2 = vertexes.get(line.end_vertex as usize)?;
+                let (dx, dy) = (v2.x as f64 - v1.x as f64, v2.y as f64 - v1.y as f64);
+                Some(dx.hypot(dy))
+            })
+            .collect::<Vec<_>>();
+
+        let mut histogram = [0; LENGTH_BUCKETS];
+        for &length in &lengths {
+            let bucket = (1..LENGTH_BUCKETS)
+                .take_while(|&b| length >= LengthStats::bucket_start(b))
+                .last()
+                .unwrap_or(0);
+            histogram[bucket] += 1;
+        }
+
+        let count = lengths.len();
+        Ok(LengthStats {
+            count,
+            min: lengths.iter().cloned().reduce(f64::min).unwrap_or(0.0),
+            max: lengths.iter().cloned().reduce(f64::max).unwrap_or(0.0),
+            mean: if count > 0 {
+                lengths.iter().sum::<f64>() / count as f64
+            } else {
+                0.0
+            },
+            histogram,
+            short_threshold,
+            short: lengths.iter().filter(|&&l| l < short_threshold).count(),
+        })
+    }
+}
+
+impl Wad {
+    /// Computes every statistic for every map in one call, telling things
+    /// apart with `Wad::thing_table`. A malformed map records its error rather
+    /// than failing the rest.
+    pub fn stats(&self) -> WadStats {
+        let table = self.thing_table();
+        let mut totals = WadTotals::default();
+        let maps = self
+            .maps()
+            .iter()
+            .map(|map| {
+                let stats = map.stats_with(StatSet::all(), table);
+                totals.maps += 1;
+                match &stats {
+                    Ok(stats) => totals.add(stats),
+                    Err(_) => totals.failed += 1,
+                }
+                (map.name().to_string(), stats)
+            })
+            .collect();
+        WadStats { maps, totals }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::builder::square_room;
+    use crate::builder::MapBuilder;
+    use crate::builder::WadBuilder;
+    use crate::map::MapThing;
+
+    fn with_things(map: MapBuilder, doomednums: &[u16]) -> MapBuilder {
+        doomednums.iter().fold(map, |map, &doomednum| {
+            map.thing(MapThing {
+                x: 128,
+                y: 128,
+                angle: 0,
+                doomednum,
+                flags: 7,
+            })
+        })
+    }
+
+    fn stats(name: &str) -> MapStats {
+        MapStats {
+            name: name.to_string(),
+            things: 1,
+            linedefs: 2,
+            sidedefs: 3,
+            vertexes: 4,
+            sectors: 5,
+            monsters: None,
+            secrets: None,
+            area: None,
+            size: None,
+            scale: None,
+        }
+    }
+
+    #[test]
+    fn binary_records_read_back_the_same() {
+        let bare = stats("MAP01");
+        let full = MapStats {
+            monsters: Some(30),
+            secrets: Some(2),
+            area: Some(4096.5),
+            size: Some((128, 256)),
+            ..stats("MAP02")
+        };
+        let some = MapStats {
+            secrets: Some(0),
+            size: Some((0, 0)),
+            ..stats("É1M1")
+        };
+        let mut out = binary_header();
+        write_binary(&mut out, "a.wad", &[&bare, &full]);
+        write_binary(&mut out, "b.wad", &[]);
+        write_binary(&mut out, "c.wad", &[&some]);
+
+        let records = read_binary(&out).unwrap();
+        assert_eq!(
+            records,
+            vec![
+                ("a.wad".to_string(), vec![bare, full]),
+                ("b.wad".to_string(), vec![]),
+                ("c.wad".to_string(), vec![some]),
+            ]
+        );
+    }
+
+    #[test]
+    fn scales_are_not_stored_in_binary_records() {
+        let scaled = MapStats {
+            scale: Some(0.03125),
+            ..stats("MAP01")
+        };
+        let mut out = binary_header();
+        write_binary(&mut out, "a.wad", &[&scaled]);
+        let records = read_binary(&out).unwrap();
+        assert_eq!(records[0].1[0].scale, None);
+    }
+
+    #[test]
+    fn damaged_binary_streams_are_rejected() {
+        let full = MapStats {
+            monsters: Some(30),
+            area: Some(1.0),
+            size: Some((1, 2)),
+            ..stats("MAP01")
+        };
+        let mut out = binary_header();
+        write_binary(&mut out, "a.wad", &[&full]);
+
+        assert_eq!(read_binary(&binary_header()), Some(vec![]));
+        for len in binary_header().len() + 1..out.len() {
+            assert_eq!(read_binary(&out[..len]), None, "truncated to {}", len);
+        }
+        let mut old = out.clone();
+        old[4] = old[4].wrapping_sub(1);
+        assert_eq!(read_binary(&old), None);
+        assert_eq!(read_binary(&out[1..]), None);
+    }
+
+    #[test]
+    fn heretic_monsters_are_counted_by_heretic_numbers() {
+        // two gargoyles and an iron lich, then a wand crystal and the green
+        // key, which Doom would take for two revenants, a yellow keycard, a
+        // bloody mess and a hanging victim
+        let map = with_things(square_room("E1M1"), &[66, 66, 6, 10, 73]);
+        let bytes = WadBuilder::new().lump("M_HTIC", vec![]).map(map).build();
+        let wad = Wad::from_reader(Cursor::new(bytes)).unwrap();
+        assert_eq!(wad.thing_table(), ThingTable::Heretic);
+
+        let stats = wad.stats();
+        assert_eq!(stats.maps[0].1.as_ref().unwrap().monsters, Some(3));
+        assert_eq!(stats.totals.monsters, 3);
+        let map = &wad.maps()[0];
+        assert_eq!(map.boss_count(ThingTable::Heretic).unwrap(), 1);
+        let hard = &map.skill_profile(ThingTable::Heretic).unwrap().skills[2];
+        assert_eq!((hard.monsters, hard.bosses, hard.items), (3, 1, 1));
+        assert_eq!(
+            map.stats_with(StatSet::MONSTERS, ThingTable::Doom)
+                .unwrap()
+                .monsters,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn hexen_monsters_are_counted_by_hexen_numbers() {
+        // two ettins, an afrit and Korax, then blue mana and a polyobject
+        // spot, which Doom would take for an imp
+        let map = with_things(
+            square_room("MAP01"),
+            &[10030, 10030, 10060, 10200, 122, 3001],
+        );
+        let bytes = WadBuilder::new().map(map.hexen()).build();
+        let wad = Wad::from_reader(Cursor::new(bytes)).unwrap();
+        assert_eq!(wad.thing_table(), ThingTable::Hexen);
+
+        let stats = wad.stats();
+        assert_eq!(stats.maps[0].1.as_ref().unwrap().monsters, Some(4));
+        let map = &wad.maps()[0];
+        assert_eq!(map.boss_count(ThingTable::Hexen).unwrap(), 1);
+        assert_eq!(map.boss_count(ThingTable::Doom).unwrap(), 0);
+        let coop = map.coop_delta(ThingTable::Hexen).unwrap().single_player;
+        assert_eq!((coop.monsters, coop.items), (4, 1));
+        assert_eq!(
+            map.stats_with(StatSet::MONSTERS, ThingTable::Doom)
+                .unwrap()
+                .monsters,
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use byteorder::ByteOrder;
+use byteorder::LE;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::game::is_sky_flat;
+use crate::map::Map;
+use crate::wad::name_from_bytes;
+use crate::wad::Wad;
+use crate::Error;
+
+/// Reads the texture names defined by a `TEXTURE1`/`TEXTURE2` lump. Entries
+/// whose offsets point outside the lump are skipped.
+pub fn parse_texture_names(data: &[u8]) -> Vec<String> {
+    if data.len() < 4 {
+        return vec![];
+    }
+    let count = LE::read_i32(data).max(0) as usize;
+    (0..count)
+        .filter_map(|i| {
+            let offset = LE::read_i32(data.get(4 + i * 4..8 + i * 4)?) as usize;
+            data.get(offset..)?.get(..8).map(name_from_bytes)
+        })
+        .collect()
+}
+
+/// Reads the patch names listed in a `PNAMES` lump, in order.
+pub fn parse_patch_names(data: &[u8]) -> Vec<String> {
+    if data.len() < 4 {
+        return vec![];
+    }
+    let count = LE::read_i32(data).max(0) as usize;
+    data[4..]
+        .chunks_exact(8)
+        .take(count)
+        .map(name_from_bytes)
+        .collect()
+}
+
+/// The indexes into `PNAMES` that a `TEXTURE1`/`TEXTURE2` lump's textures are
+/// made of, with each texture's name.
+fn texture_patches(data: &[u8]) -> Vec<(String, Vec<usize>)> {
+    if data.len() < 4 {
+        return vec![];
+    }
+    let count = LE::read_i32(data).max(0) as usize;
+    (0..count)
+        .filter_map(|i| {
+            let offset = LE::read_i32(data.get(4 + i * 4..8 + i * 4)?) as usize;
+            let texture = data.get(offset..)?;
+            let name = name_from_bytes(texture.get(..8)?);
+            let patch_count = LE::read_i16(texture.get(20..22)?).max(0) as usize;
+            let patches = (0..patch_count)
+                .filter_map(|p| {
+                    let entry = texture.get(22 + p * 10..32 + p * 10)?;
+                    Some(LE::read_i16(&entry[4..]).max(0) as usize)
+                })
+                .collect();
+            Some((name, patches))
+        })
+        .collect()
+}
+
+/// A patch name from `PNAMES`, and where the patch comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchStatus {
+    pub name: String,
+    /// Whether the WAD has the patch between `P_START`/`P_END` or
+    /// `PP_START`/`PP_END` markers. If not, it has to come from the IWAD.
+    pub in_wad: bool,
+    /// The textures drawing the patch, in `TEXTURE1` then `TEXTURE2` order.
+    pub textures: Vec<String>,
+}
+
+/// A flat that sectors use but no WAD provides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingFlatRef {
+    pub name: String,
+    /// The maps using the flat, in WAD order.
+    pub maps: Vec<String>,
+    /// How many sector floors and ceilings use it across all maps.
+    pub references: usize,
+}
+
+/// A wall texture that sidedefs use but no `TEXTURE1`/`TEXTURE2` defines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingTextureRef {
+    pub name: String,
+    /// The maps using the texture, in WAD order.
+    pub maps: Vec<String>,
+    /// How many upper, lower and middle textures use it across all maps.
+    pub references: usize,
+}
+
+/// How many different wall textures and flats a map uses, as a measure of
+/// its visual variety. The most used of each comes with its reference count,
+/// ties going to the name that sorts first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TexturePaletteStats {
+    /// Distinct upper, lower and middle textures, leaving out `-`.
+    pub textures: usize,
+    /// Distinct floor and ceiling flats, leaving out the sky.
+    pub flats: usize,
+    pub most_used_texture: Option<(String, usize)>,
+    pub most_used_flat: Option<(String, usize)>,
+}
+
+fn most_used(counts: &BTreeMap<String, usize>) -> Option<(String, usize)> {
+    counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(name, &n)| (name.clone(), n))
+}
+
+impl Map {
+    /// How many times each upper, lower and middle texture is used, by
+    /// uppercased name, leaving out `-`.
+    pub(crate) fn texture_counts(&self) -> Result<BTreeMap<String, usize>, Error> {
+        let mut textures = BTreeMap::<String, usize>::new();
+        for side in self.sidedefs()? {
+            for texture in [side.upper_texture, side.lower_texture, side.middle_texture] {
+                let texture = texture.to_ascii_uppercase();
+                if texture != "-" && !texture.is_empty() {
+                    *textures.entry(texture).or_default() += 1;
+                }
+            }
+        }
+        Ok(textures)
+    }
+
+    /// How many times each floor and ceiling flat is used, by uppercased
+    /// name, leaving out the sky.
+    pub(crate) fn flat_counts(&self) -> Result<BTreeMap<String, usize>, Error> {
+        let mut flats = BTreeMap::<String, usize>::new();
+        for sector in self.sectors()? {
+            for flat in [sector.floor_flat, sector.ceiling_flat] {
+                let flat = flat.to_ascii_uppercase();
+                if !is_sky_flat(&flat) && !flat.is_empty() {
+                    *flats.entry(flat).or_default() += 1;
+                }
+            }
+        }
+        Ok(flats)
+    }
+
+    /// Counts the textures and flats the map draws with.
+    pub fn palette_complexity(&self) -> Result<TexturePaletteStats, Error> {
+        let textures = self.texture_counts()?;
+        let flats = self.flat_counts()?;
+        Ok(TexturePaletteStats {
+            textures: textures.len(),
+            flats: flats.len(),
+            most_used_texture: most_used(&textures),
+            most_used_flat: most_used(&flats),
+        })
+    }
+}
+
+impl Wad {
+    /// Sidedef textures that this WAD doesn't define, ignoring the `-`
+    /// placeholder for no texture.
+    pub fn missing_textures(&self) -> Vec<MissingTextureRef> {
+        self.missing_textures_in(&[])
+    }
+
+    /// Like `missing_textures`, also accepting textures from the IWAD the WAD
+    /// is played with.
+    pub fn missing_textures_against(&self, iwad: &Wad) -> Vec<MissingTextureRef> {
+        self.missing_textures_in(&iwad.texture_names())
+    }
+
+    fn missing_textures_in(&self, base: &[String]) -> Vec<MissingTextureRef> {
+        let defined = self
+            .texture_names()
+            .iter()
+            .chain(base)
+            .map(|t| t.to_ascii_uppercase())
+            .collect::<BTreeSet<_>>();
+        let mut missing = BTreeMap::<String, MissingTextureRef>::new();
+        for map in self.maps() {
+            let sidedefs = match map.sidedefs() {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            for side in &sidedefs {
+                for texture in [
+                    &side.upper_texture,
+                    &side.lower_texture,
+                    &side.middle_texture,
+                ] {
+                    let texture = texture.to_ascii_uppercase();
+                    if texture == "-" || texture.is_empty() || defined.contains(&texture) {
+                        continue;
+                    }
+                    let entry = missing.entry(texture.clone()).or_insert(MissingTextureRef {
+                        name: texture,
+                        maps: vec![],
+                        references: 0,
+                    });
+                    entry.references += 1;
+                    if entry.maps.last().map(String::as_str) != Some(map.name()) {
+                        entry.maps.push(map.name().to_string());
+                    }
+                }
+            }
+        }
+        missing.into_values().collect()
+    }
+
+    /// Sector flats that this WAD doesn't define. Sky flats are left out, since
+    /// the engine draws the sky texture in their place.
+    pub fn missing_flats(&self) -> Vec<MissingFlatRef> {
+        self.missing_flats_in(&[])
+    }
+
+    /// Like `missing_flats`, also accepting flats from the IWAD the WAD is
+    /// played with.
+    pub fn missing_flats_against(&self, iwad: &Wad) -> Vec<MissingFlatRef> {
+        self.missing_flats_in(&iwad.flat_names())
+    }
+
+    fn missing_flats_in(&self, base: &[String]) -> Vec<MissingFlatRef> {
+        let defined = self
+            .flat_names()
+            .iter()
+            .chain(base)
+            .map(|f| f.to_ascii_uppercase())
+            .collect::<BTreeSet<_>>();
+        let mut missing = BTreeMap::<String, MissingFlatRef>::new();
+        for map in self.maps() {
+            let sectors = match map.sectors() {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            for sector in &sectors {
+                for flat in [&sector.floor_flat, &sector.ceiling_flat] {
+                    let flat = flat.to_ascii_uppercase();
+                    if is_sky_flat(&flat) || defined.contains(&flat) {
+                        continue;
+                    }
+                    let entry = missing.entry(flat.clone()).or_insert(MissingFlatRef {
+                        name: flat,
+                        maps: vec![],
+                        references: 0,
+                    });
+                    entry.references += 1;
+                    if entry.maps.last().map(String::as_str) != Some(map.name()) {
+                        entry.maps.push(map.name().to_string());
+                    }
+                }
+            }
+        }
+        missing.into_values().collect()
+    }
+
+    /// Every texture defined by the WAD's `TEXTURE1` and `TEXTURE2` lumps.
+    pub fn texture_names(&self) -> Vec<String> {
+        ["TEXTURE1", "TEXTURE2"]
+            .iter()
+            .filter_map(|n| self.lump_by_name(n))
+            .flat_map(|l| parse_texture_names(&l.data))
+            .collect()
+    }
+
+    /// Every patch listed in `PNAMES`, in order with repeats left out, and
+    /// whether the WAD supplies it. Vanilla Doom finds patches anywhere in the
+    /// directory, but ports that merge marker sections only look between the
+    /// markers, so a patch elsewhere counts as coming from the IWAD.
+    pub fn patch_presence(&self) -> Vec<PatchStatus> {
+        let pnames = match self.lump_by_name("PNAMES") {
+            Some(l) => parse_patch_names(&l.data),
+            None => return vec![],
+        };
+        let mut users = vec![vec![]; pnames.len()];
+        for lump in ["TEXTURE1", "TEXTURE2"]
+            .iter()
+            .filter_map(|n| self.lump_by_name(n))
+        {
+            for (texture, patches) in texture_patches(&lump.data) {
+                for patch in patches {
+                    if let Some(users) = users.get_mut(patch) {
+                        if users.last() != Some(&texture) {
+                            users.push(texture.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let present = self.patch_names().into_iter().collect::<BTreeSet<_>>();
+        let mut statuses = Vec::<PatchStatus>::new();
+        for (name, textures) in pnames.into_iter().zip(users) {
+            match statuses.iter_mut().find(|s| s.name == name) {
+                Some(status) => {
+                    for texture in textures {
+                        if !status.textures.contains(&texture) {
+                            status.textures.push(texture);
+                        }
+                    }
+                }
+                None => statuses.push(PatchStatus {
+                    in_wad: present.contains(&name),
+                    name,
+                    textures,
+                }),
+            }
+        }
+        statuses
+    }
+
+    /// `PNAMES` patches that a texture draws but this WAD doesn't supply.
+    /// Unused entries are left out, since the engine only looks up the patches
+    /// textures use.
+    pub fn missing_patches(&self) -> Vec<PatchStatus> {
+        self.missing_patches_in(&[])
+    }
+
+    /// Like `missing_patches`, also accepting patches between the patch
+    /// markers of the IWAD the WAD is played with.
+    pub fn missing_patches_against(&self, iwad: &Wad) -> Vec<PatchStatus> {
+        self.missing_patches_in(&iwad.patch_names())
+    }
+
+    fn missing_patches_in(&self, base: &[String]) -> Vec<PatchStatus> {
+        self.patch_presence()
+            .into_iter()
+            .filter(|p| !p.in_wad && !p.textures.is_empty() && !base.contains(&p.name))
+            .collect()
+    }
+
+    /// Every lump between `P_START`/`P_END` or `PP_START`/`PP_END` markers,
+    /// leaving out the nested `P1_START`-style markers.
+    pub fn patch_names(&self) -> Vec<String> {
+        let mut inside = false;
+        let mut patches = vec![];
+        for lump in self.iter_lumps() {
+            match lump.name() {
+                "P_START" | "PP_START" => inside = true,
+                "P_END" | "PP_END" => inside = false,
+                n if n.ends_with("_START") || n.ends_with("_END") => {}
+                n if inside => patches.push(n.to_string()),
+                _ => {}
+            }
+        }
+        patches
+    }
+
+    /// Every flat between `F_START`/`F_END` or `FF_START`/`FF_END` markers,
+    /// leaving out the nested `F1_START`-style markers.
+    pub fn flat_names(&self) -> Vec<String> {
+        let mut inside = false;
+        let mut flats = vec![];
+        for lump in self.iter_lumps() {
+            match lump.name() {
+                "F_START" | "FF_START" => inside = true,
+                "F_END" | "FF_END" => inside = false,
+                n if n.ends_with("_START") || n.ends_with("_END") => {}
+                n if inside => flats.push(n.to_string()),
+                _ => {}
+            }
+        }
+        flats
+    }
+}
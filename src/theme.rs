@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use crate::map::Map;
+
+/// The look of a map, going by the stock textures it's built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Theme {
+    /// Bases and computer rooms: metal, panels and computers.
+    Tech,
+    /// Flesh, fire, lava and gothic stone.
+    Hell,
+    /// Brick, cement and stucco, as in cities and Doom 2's later maps.
+    Urban,
+    /// Wood panelling and floors.
+    Wood,
+    Marble,
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Theme::Tech => "Tech",
+            Theme::Hell => "Hell",
+            Theme::Urban => "Urban",
+            Theme::Wood => "Wood",
+            Theme::Marble => "Marble",
+        })
+    }
+}
+
+/// Wall texture name prefixes and the theme each family belongs to. A name
+/// takes the first prefix it starts with, so more specific prefixes come
+/// first.
+pub const TEXTURE_THEMES: &[(&str, Theme)] = &[
+    ("MARBFAC", Theme::Hell),
+    ("MARB", Theme::Marble),
+    ("STARTAN", Theme::Tech),
+    ("STARG", Theme::Tech),
+    ("STARBR", Theme::Tech),
+    ("TEK", Theme::Tech),
+    ("COMP", Theme::Tech),
+    ("SILVER", Theme::Tech),
+    ("SHAWN", Theme::Tech),
+    ("SUPPORT", Theme::Tech),
+    ("PIPE", Theme::Tech),
+    ("METAL", Theme::Tech),
+    ("PLAT", Theme::Tech),
+    ("LITE", Theme::Tech),
+    ("SPACEW", Theme::Tech),
+    ("BRNBIGC", Theme::Tech),
+    ("SKIN", Theme::Hell),
+    ("SKSNAKE", Theme::Hell),
+    ("SKSPINE", Theme::Hell),
+    ("SP_", Theme::Hell),
+    ("FIRE", Theme::Hell),
+    ("GST", Theme::Hell),
+    ("BLOD", Theme::Hell),
+    ("SLOPPY", Theme::Hell),
+    ("ROCKRED", Theme::Hell),
+    ("REDWALL", Theme::Hell),
+    ("SLADWALL", Theme::Hell),
+    ("BRICK", Theme::Urban),
+    ("BIGBRIK", Theme::Urban),
+    ("BRKBRN", Theme::Urban),
+    ("BSTONE", Theme::Urban),
+    ("CITY", Theme::Urban),
+    ("STUCCO", Theme::Urban),
+    ("CEMENT", Theme::Urban),
+    ("MODWALL", Theme::Urban),
+    ("WOOD", Theme::Wood),
+    ("PAN", Theme::Wood),
+];
+
+/// Flat name prefixes and their themes, matched like `TEXTURE_THEMES`.
+pub const FLAT_THEMES: &[(&str, Theme)] = &[
+    ("FLAT5_1", Theme::Wood),
+    ("FLAT5_2", Theme::Wood),
+    ("CEIL1_1", Theme::Wood),
+    ("FLOOR7_2", Theme::Marble),
+    ("DEM1_", Theme::Marble),
+    ("FLOOR6_1", Theme::Hell),
+    ("LAVA", Theme::Hell),
+    ("BLOOD", Theme::Hell),
+    ("SFLR", Theme::Hell),
+    ("RROCK", Theme::Hell),
+    ("FLAT5_7", Theme::Hell),
+    ("NUKAGE", Theme::Tech),
+    ("FLOOR0_", Theme::Tech),
+    ("FLOOR1_", Theme::Tech),
+    ("FLOOR3_3", Theme::Tech),
+    ("FLOOR4_8", Theme::Tech),
+    ("FLOOR5_", Theme::Tech),
+    ("CEIL3_", Theme::Tech),
+    ("CEIL5_", Theme::Tech),
+    ("TLITE", Theme::Tech),
+    ("GRNLITE", Theme::Tech),
+    ("COMP", Theme::Tech),
+    ("STEP", Theme::Tech),
+    ("FLOOR7_1", Theme::Urban),
+    ("MFLR8_1", Theme::Urban),
+    ("FLAT1_", Theme::Urban),
+];
+
+/// The share of a map's texture and flat references a theme needs for
+/// `guess_theme` to pick it.
+pub const DOMINANT_SHARE: f64 = 0.4;
+
+fn theme_of(name: &str, table: &[(&str, Theme)]) -> Option<Theme> {
+    table
+        .iter()
+        .find(|(prefix, _)| name.starts_with(prefix))
+        .map(|&(_, theme)| theme)
+}
+
+impl Map {
+    /// A guess at the map's theme, using `TEXTURE_THEMES` and `FLAT_THEMES`.
+    pub fn guess_theme(&self) -> Option<Theme> {
+        self.guess_theme_with(TEXTURE_THEMES, FLAT_THEMES)
+    }
+
+    /// A guess at the map's theme from prefix tables like `TEXTURE_THEMES`
+    /// and `FLAT_THEMES`. Every wall texture and flat reference counts
+    /// towards the theme of its name's family, and the theme with the most
+    /// is picked if it has at least `DOMINANT_SHARE` of all references,
+    /// those outside any family included. This is a heuristic: maps built
+    /// mostly from custom textures, or mixing themes evenly, give `None`, as
+    /// do maps whose sidedefs or sectors can't be read.
+    pub fn guess_theme_with(
+        &self,
+        textures: &[(&str, Theme)],
+        flats: &[(&str, Theme)],
+    ) -> Option<Theme> {
+        let mut votes = BTreeMap::<Theme, usize>::new();
+        let mut total = 0;
+        let references = [
+            (self.texture_counts().ok()?, textures),
+            (self.flat_counts().ok()?, flats),
+        ];
+        for (counts, table) in &references {
+            for (name, &n) in counts {
+                total += n;
+                if let Some(theme) = theme_of(name, table) {
+                    *votes.entry(theme).or_default() += n;
+                }
+            }
+        }
+        let (theme, n) = votes
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))?;
+        Some(theme).filter(|_| n as f64 >= total as f64 * DOMINANT_SHARE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::builder::square_room;
+    use crate::builder::WadBuilder;
+    use crate::map::Sector;
+    use crate::map::Sidedef;
+    use crate::wad::Wad;
+
+    // the guess for a square room with these wall textures, in turn round
+    // its four sides, and flats
+    fn guess(walls: &[&str], floor: &str, ceiling: &str) -> Option<Theme> {
+        let sidedefs = walls
+            .iter()
+            .cycle()
+            .take(4)
+            .flat_map(|&texture| {
+                Sidedef {
+                    x_offset: 0,
+                    y_offset: 0,
+                    upper_texture: "-".to_string(),
+                    lower_texture: "-".to_string(),
+                    middle_texture: texture.to_string(),
+                    sector: 0,
+                }
+                .to_bytes()
+            })
+            .collect();
+        let sector = Sector {
+            floor_height: 0,
+            ceiling_height: 128,
+            floor_flat: floor.to_string(),
+            ceiling_flat: ceiling.to_string(),
+            light_level: 160,
+            special: 0,
+            tag: 0,
+        };
+        let room = square_room("MAP01")
+            .lump("SIDEDEFS", sidedefs)
+            .lump("SECTORS", sector.to_bytes().to_vec());
+        let wad = Wad::from_reader(Cursor::new(WadBuilder::new().map(room).build())).unwrap();
+        wad.maps()[0].guess_theme()
+    }
+
+    #[test]
+    fn stock_looking_maps_get_their_theme() {
+        // a Hangar-style base
+        assert_eq!(
+            guess(&["STARTAN3", "COMPSPAN", "STARG3"], "FLOOR4_8", "CEIL3_5"),
+            Some(Theme::Tech)
+        );
+        // Inferno's flesh and fire
+        assert_eq!(
+            guess(&["SKINMET1", "SP_ROCK1", "FIREWALL"], "LAVA1", "FLOOR6_1"),
+            Some(Theme::Hell)
+        );
+        // Doom 2's cities
+        assert_eq!(
+            guess(&["BRICK7", "STUCCO", "BIGBRIK1"], "FLAT1_1", "CEIL5_1"),
+            Some(Theme::Urban)
+        );
+        // the marble library of a Doom 2 map
+        assert_eq!(
+            guess(&["MARBLE1", "MARBGRAY"], "DEM1_5", "FLOOR7_2"),
+            Some(Theme::Marble)
+        );
+    }
+
+    #[test]
+    fn custom_or_evenly_mixed_maps_have_no_theme() {
+        assert_eq!(guess(&["MYWALL1"], "MYFLOOR", "MYCEIL"), None);
+        assert_eq!(guess(&["MYWALL1"], "FLOOR4_8", "CEIL3_5"), None);
+        assert_eq!(
+            guess(
+                &["STARTAN3", "SKINMET1", "BRICK7", "WOOD1"],
+                "FLAT5_1",
+                "CEIL3_5"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn the_sky_and_missing_textures_are_not_counted() {
+        assert_eq!(guess(&["-"], "FLOOR4_8", "F_SKY1"), Some(Theme::Tech));
+    }
+}
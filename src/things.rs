@@ -1,124 +1,1030 @@
+use std::collections::BTreeMap;
+
+use crate::game::Game;
+use crate::wad::Wad;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Thing {
-    Player1Start,
-    Player2Start,
-    Player3Start,
-    Player4Start,
-	YellowKeycard,
-	Spiderdemon,
-	Backpack,
-	ShotgunGuy,
-BloodyMess,
-DeathmatchStart,
-BloodyMess2,
-	RedKeycard,
-TeleportLanding,
-DeadPlayer,
-	Cyberdemon,
-	EnergyCellPack,
-DeadFormerHuman,
-DeadFormerSergeant,
-DeadImp,
-DeadDemon,
-DeadCacodemon,
-DeadLostSoul,
-PoolOfBloodAndFlesh,
-	ImpaledHuman,
-	TwitchingImpaledHuman,
-	SkullOnAPole,
-	FiveSkullsShishKebab,
-	PileOfSkullsAndCandles,
-	Tall green pillar
-	Short green pillar
-	Tall red pillar
-	Short red pillar
-
-	Candelabra
-	Short green pillar with beating heart
-	Short red pillar with skull
-	Red skull key
-	Yellow skull key
-	Blue skull key
-	Evil eye
-	Floating skull
-	Burnt tree
-	Tall blue firestick
-	Tall green firestick
-	Tall red firestick
-	Brown stump
-	Tall techno column
-	Hanging victim, twitching
-	Hanging victim, arms out
-	Hanging victim, one-legged
-	Hanging pair of legs
-	Hanging leg
-	Large brown tree
-	Short blue firestick
-	Short green firestick
-	Short red firestick
-	Spectre
-	Hanging victim, arms out
-	Hanging pair of legs
-	Hanging victim, one-legged
-	Hanging leg
-	Hanging victim, twitching
-	Arch-vile
-	Heavy weapon dude
-	Revenant
-	Mancubus
-	Arachnotron
-	Hell knight
-	Burning barrel
-	Pain elemental
-	Commander Keen
-	Hanging victim, guts removed
-	Hanging victim, guts and brain removed
-	Hanging torso, looking down
-	Hanging torso, open skull
-	Hanging torso, looking up
-	Hanging torso, brain removed
-of blood
-of blood
-of brains
-	Super shotgun
-	Megasphere
-	Wolfenstein SS
-	Tall techno floor lamp
-	Short techno floor lamp
-spot
-	Romero's head
-spawner
-	Shotgun
-	Chaingun
-	Rocket launcher
-	Plasma gun
-	Chainsaw
-	BFG9000
-	Clip
-	4 shotgun shells
-	Rocket
-	Stimpack
-	Medikit
-	Supercharge
-	Health bonus
-	Armor bonus
-	Armor (green)
-	Megaarmor (blue)
-	Invulnerability
-	Berserk
-	Partial invisibility
-	Radiation shielding suit
-	Computer area map
-	Floor lamp
-	Exploding barrel
-	Light amplification visor
-	Box of rockets
-	Energy cell
-	Box of bullets
-	Box of shotgun shells
-	Imp
-	Demon
-	Baron of Hell
-	Zombieman
-	Cacodemon
-	Lost soul
-}
\ No newline at end of file
+    Player1Start = 1,
+    Player2Start = 2,
+    Player3Start = 3,
+    Player4Start = 4,
+    BlueKeycard = 5,
+    YellowKeycard = 6,
+    Spiderdemon = 7,
+    Backpack = 8,
+    ShotgunGuy = 9,
+    BloodyMess = 10,
+    DeathmatchStart = 11,
+    BloodyMess2 = 12,
+    RedKeycard = 13,
+    TeleportLanding = 14,
+    DeadPlayer = 15,
+    Cyberdemon = 16,
+    EnergyCellPack = 17,
+    DeadFormerHuman = 18,
+    DeadFormerSergeant = 19,
+    DeadImp = 20,
+    DeadDemon = 21,
+    DeadCacodemon = 22,
+    DeadLostSoul = 23,
+    PoolOfBloodAndFlesh = 24,
+    ImpaledHuman = 25,
+    TwitchingImpaledHuman = 26,
+    SkullOnAPole = 27,
+    FiveSkullsShishKebab = 28,
+    PileOfSkullsAndCandles = 29,
+    TallGreenPillar = 30,
+    ShortGreenPillar = 31,
+    TallRedPillar = 32,
+    ShortRedPillar = 33,
+    Candle = 34,
+    Candelabra = 35,
+    ShortGreenPillarWithBeatingHeart = 36,
+    ShortRedPillarWithSkull = 37,
+    RedSkullKey = 38,
+    YellowSkullKey = 39,
+    BlueSkullKey = 40,
+    EvilEye = 41,
+    FloatingSkull = 42,
+    BurntTree = 43,
+    TallBlueFirestick = 44,
+    TallGreenFirestick = 45,
+    TallRedFirestick = 46,
+    BrownStump = 47,
+    TallTechnoColumn = 48,
+    HangingVictimTwitching = 49,
+    HangingVictimArmsOut = 50,
+    HangingVictimOneLegged = 51,
+    HangingPairOfLegs = 52,
+    HangingLeg = 53,
+    LargeBrownTree = 54,
+    ShortBlueFirestick = 55,
+    ShortGreenFirestick = 56,
+    ShortRedFirestick = 57,
+    Spectre = 58,
+    HangingVictimArmsOut2 = 59,
+    HangingPairOfLegs2 = 60,
+    HangingVictimOneLegged2 = 61,
+    HangingLeg2 = 62,
+    HangingVictimTwitching2 = 63,
+    ArchVile = 64,
+    HeavyWeaponDude = 65,
+    Revenant = 66,
+    Mancubus = 67,
+    Arachnotron = 68,
+    HellKnight = 69,
+    BurningBarrel = 70,
+    PainElemental = 71,
+    CommanderKeen = 72,
+    HangingVictimGutsRemoved = 73,
+    HangingVictimGutsAndBrainRemoved = 74,
+    HangingTorsoLookingDown = 75,
+    HangingTorsoOpenSkull = 76,
+    HangingTorsoLookingUp = 77,
+    HangingTorsoBrainRemoved = 78,
+    PoolOfBlood = 79,
+    PoolOfBlood2 = 80,
+    PoolOfBrains = 81,
+    SuperShotgun = 82,
+    Megasphere = 83,
+    WolfensteinSs = 84,
+    TallTechnoFloorLamp = 85,
+    ShortTechnoFloorLamp = 86,
+    SpawnSpot = 87,
+    RomerosHead = 88,
+    MonsterSpawner = 89,
+    Shotgun = 2001,
+    Chaingun = 2002,
+    RocketLauncher = 2003,
+    PlasmaGun = 2004,
+    Chainsaw = 2005,
+    Bfg9000 = 2006,
+    Clip = 2007,
+    FourShotgunShells = 2008,
+    Rocket = 2010,
+    Stimpack = 2011,
+    Medikit = 2012,
+    Supercharge = 2013,
+    HealthBonus = 2014,
+    ArmorBonus = 2015,
+    Armor = 2018,
+    Megaarmor = 2019,
+    Invulnerability = 2022,
+    Berserk = 2023,
+    PartialInvisibility = 2024,
+    RadiationShieldingSuit = 2025,
+    ComputerAreaMap = 2026,
+    FloorLamp = 2028,
+    ExplodingBarrel = 2035,
+    LightAmplificationVisor = 2045,
+    BoxOfRockets = 2046,
+    EnergyCell = 2047,
+    BoxOfBullets = 2048,
+    BoxOfShotgunShells = 2049,
+    Imp = 3001,
+    Demon = 3002,
+    BaronOfHell = 3003,
+    Zombieman = 3004,
+    Cacodemon = 3005,
+    LostSoul = 3006,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ThingCategory {
+    PlayerStart,
+    Monster,
+    Weapon,
+    Ammo,
+    Health,
+    Armor,
+    Powerup,
+    Key,
+    Decoration,
+    Special,
+}
+
+/// The monsters counted as bosses: the Cyberdemon, the Spiderdemon and the
+/// boss brain behind Doom 2's Icon of Sin, Romero's head. Each is a monster
+/// too.
+pub const BOSSES: [Thing; 3] = [Thing::Cyberdemon, Thing::Spiderdemon, Thing::RomerosHead];
+
+impl Thing {
+    pub fn from_doomednum(n: u16) -> Option<Self> {
+        match n {
+            1 => Some(Thing::Player1Start),
+            2 => Some(Thing::Player2Start),
+            3 => Some(Thing::Player3Start),
+            4 => Some(Thing::Player4Start),
+            5 => Some(Thing::BlueKeycard),
+            6 => Some(Thing::YellowKeycard),
+            7 => Some(Thing::Spiderdemon),
+            8 => Some(Thing::Backpack),
+            9 => Some(Thing::ShotgunGuy),
+            10 => Some(Thing::BloodyMess),
+            11 => Some(Thing::DeathmatchStart),
+            12 => Some(Thing::BloodyMess2),
+            13 => Some(Thing::RedKeycard),
+            14 => Some(Thing::TeleportLanding),
+            15 => Some(Thing::DeadPlayer),
+            16 => Some(Thing::Cyberdemon),
+            17 => Some(Thing::EnergyCellPack),
+            18 => Some(Thing::DeadFormerHuman),
+            19 => Some(Thing::DeadFormerSergeant),
+            20 => Some(Thing::DeadImp),
+            21 => Some(Thing::DeadDemon),
+            22 => Some(Thing::DeadCacodemon),
+            23 => Some(Thing::DeadLostSoul),
+            24 => Some(Thing::PoolOfBloodAndFlesh),
+            25 => Some(Thing::ImpaledHuman),
+            26 => Some(Thing::TwitchingImpaledHuman),
+            27 => Some(Thing::SkullOnAPole),
+            28 => Some(Thing::FiveSkullsShishKebab),
+            29 => Some(Thing::PileOfSkullsAndCandles),
+            30 => Some(Thing::TallGreenPillar),
+            31 => Some(Thing::ShortGreenPillar),
+            32 => Some(Thing::TallRedPillar),
+            33 => Some(Thing::ShortRedPillar),
+            34 => Some(Thing::Candle),
+            35 => Some(Thing::Candelabra),
+            36 => Some(Thing::ShortGreenPillarWithBeatingHeart),
+            37 => Some(Thing::ShortRedPillarWithSkull),
+            38 => Some(Thing::RedSkullKey),
+            39 => Some(Thing::YellowSkullKey),
+            40 => Some(Thing::BlueSkullKey),
+            41 => Some(Thing::EvilEye),
+            42 => Some(Thing::FloatingSkull),
+            43 => Some(Thing::BurntTree),
+            44 => Some(Thing::TallBlueFirestick),
+            45 => Some(Thing::TallGreenFirestick),
+            46 => Some(Thing::TallRedFirestick),
+            47 => Some(Thing::BrownStump),
+            48 => Some(Thing::TallTechnoColumn),
+            49 => Some(Thing::HangingVictimTwitching),
+            50 => Some(Thing::HangingVictimArmsOut),
+            51 => Some(Thing::HangingVictimOneLegged),
+            52 => Some(Thing::HangingPairOfLegs),
+            53 => Some(Thing::HangingLeg),
+            54 => Some(Thing::LargeBrownTree),
+            55 => Some(Thing::ShortBlueFirestick),
+            56 => Some(Thing::ShortGreenFirestick),
+            57 => Some(Thing::ShortRedFirestick),
+            58 => Some(Thing::Spectre),
+            59 => Some(Thing::HangingVictimArmsOut2),
+            60 => Some(Thing::HangingPairOfLegs2),
+            61 => Some(Thing::HangingVictimOneLegged2),
+            62 => Some(Thing::HangingLeg2),
+            63 => Some(Thing::HangingVictimTwitching2),
+            64 => Some(Thing::ArchVile),
+            65 => Some(Thing::HeavyWeaponDude),
+            66 => Some(Thing::Revenant),
+            67 => Some(Thing::Mancubus),
+            68 => Some(Thing::Arachnotron),
+            69 => Some(Thing::HellKnight),
+            70 => Some(Thing::BurningBarrel),
+            71 => Some(Thing::PainElemental),
+            72 => Some(Thing::CommanderKeen),
+            73 => Some(Thing::HangingVictimGutsRemoved),
+            74 => Some(Thing::HangingVictimGutsAndBrainRemoved),
+            75 => Some(Thing::HangingTorsoLookingDown),
+            76 => Some(Thing::HangingTorsoOpenSkull),
+            77 => Some(Thing::HangingTorsoLookingUp),
+            78 => Some(Thing::HangingTorsoBrainRemoved),
+            79 => Some(Thing::PoolOfBlood),
+            80 => Some(Thing::PoolOfBlood2),
+            81 => Some(Thing::PoolOfBrains),
+            82 => Some(Thing::SuperShotgun),
+            83 => Some(Thing::Megasphere),
+            84 => Some(Thing::WolfensteinSs),
+            85 => Some(Thing::TallTechnoFloorLamp),
+            86 => Some(Thing::ShortTechnoFloorLamp),
+            87 => Some(Thing::SpawnSpot),
+            88 => Some(Thing::RomerosHead),
+            89 => Some(Thing::MonsterSpawner),
+            2001 => Some(Thing::Shotgun),
+            2002 => Some(Thing::Chaingun),
+            2003 => Some(Thing::RocketLauncher),
+            2004 => Some(Thing::PlasmaGun),
+            2005 => Some(Thing::Chainsaw),
+            2006 => Some(Thing::Bfg9000),
+            2007 => Some(Thing::Clip),
+            2008 => Some(Thing::FourShotgunShells),
+            2010 => Some(Thing::Rocket),
+            2011 => Some(Thing::Stimpack),
+            2012 => Some(Thing::Medikit),
+            2013 => Some(Thing::Supercharge),
+            2014 => Some(Thing::HealthBonus),
+            2015 => Some(Thing::ArmorBonus),
+            2018 => Some(Thing::Armor),
+            2019 => Some(Thing::Megaarmor),
+            2022 => Some(Thing::Invulnerability),
+            2023 => Some(Thing::Berserk),
+            2024 => Some(Thing::PartialInvisibility),
+            2025 => Some(Thing::RadiationShieldingSuit),
+            2026 => Some(Thing::ComputerAreaMap),
+            2028 => Some(Thing::FloorLamp),
+            2035 => Some(Thing::ExplodingBarrel),
+            2045 => Some(Thing::LightAmplificationVisor),
+            2046 => Some(Thing::BoxOfRockets),
+            2047 => Some(Thing::EnergyCell),
+            2048 => Some(Thing::BoxOfBullets),
+            2049 => Some(Thing::BoxOfShotgunShells),
+            3001 => Some(Thing::Imp),
+            3002 => Some(Thing::Demon),
+            3003 => Some(Thing::BaronOfHell),
+            3004 => Some(Thing::Zombieman),
+            3005 => Some(Thing::Cacodemon),
+            3006 => Some(Thing::LostSoul),
+            _ => None,
+        }
+    }
+
+    pub fn doomednum(self) -> u16 {
+        self as u16
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Thing::Player1Start => "Player 1 start",
+            Thing::Player2Start => "Player 2 start",
+            Thing::Player3Start => "Player 3 start",
+            Thing::Player4Start => "Player 4 start",
+            Thing::BlueKeycard => "Blue keycard",
+            Thing::YellowKeycard => "Yellow keycard",
+            Thing::Spiderdemon => "Spiderdemon",
+            Thing::Backpack => "Backpack",
+            Thing::ShotgunGuy => "Shotgun guy",
+            Thing::BloodyMess => "Bloody mess",
+            Thing::DeathmatchStart => "Deathmatch start",
+            Thing::BloodyMess2 => "Bloody mess",
+            Thing::RedKeycard => "Red keycard",
+            Thing::TeleportLanding => "Teleport landing",
+            Thing::DeadPlayer => "Dead player",
+            Thing::Cyberdemon => "Cyberdemon",
+            Thing::EnergyCellPack => "Energy cell pack",
+            Thing::DeadFormerHuman => "Dead former human",
+            Thing::DeadFormerSergeant => "Dead former sergeant",
+            Thing::DeadImp => "Dead imp",
+            Thing::DeadDemon => "Dead demon",
+            Thing::DeadCacodemon => "Dead cacodemon",
+            Thing::DeadLostSoul => "Dead lost soul",
+            Thing::PoolOfBloodAndFlesh => "Pool of blood and flesh",
+            Thing::ImpaledHuman => "Impaled human",
+            Thing::TwitchingImpaledHuman => "Twitching impaled human",
+            Thing::SkullOnAPole => "Skull on a pole",
+            Thing::FiveSkullsShishKebab => "Five skulls \"shish kebab\"",
+            Thing::PileOfSkullsAndCandles => "Pile of skulls and candles",
+            Thing::TallGreenPillar => "Tall green pillar",
+            Thing::ShortGreenPillar => "Short green pillar",
+            Thing::TallRedPillar => "Tall red pillar",
+            Thing::ShortRedPillar => "Short red pillar",
+            Thing::Candle => "Candle",
+            Thing::Candelabra => "Candelabra",
+            Thing::ShortGreenPillarWithBeatingHeart => "Short green pillar with beating heart",
+            Thing::ShortRedPillarWithSkull => "Short red pillar with skull",
+            Thing::RedSkullKey => "Red skull key",
+            Thing::YellowSkullKey => "Yellow skull key",
+            Thing::BlueSkullKey => "Blue skull key",
+            Thing::EvilEye => "Evil eye",
+            Thing::FloatingSkull => "Floating skull",
+            Thing::BurntTree => "Burnt tree",
+            Thing::TallBlueFirestick => "Tall blue firestick",
+            Thing::TallGreenFirestick => "Tall green firestick",
+            Thing::TallRedFirestick => "Tall red firestick",
+            Thing::BrownStump => "Brown stump",
+            Thing::TallTechnoColumn => "Tall techno column",
+            Thing::HangingVictimTwitching => "Hanging victim, twitching",
+            Thing::HangingVictimArmsOut => "Hanging victim, arms out",
+            Thing::HangingVictimOneLegged => "Hanging victim, one-legged",
+            Thing::HangingPairOfLegs => "Hanging pair of legs",
+            Thing::HangingLeg => "Hanging leg",
+            Thing::LargeBrownTree => "Large brown tree",
+            Thing::ShortBlueFirestick => "Short blue firestick",
+            Thing::ShortGreenFirestick => "Short green firestick",
+            Thing::ShortRedFirestick => "Short red firestick",
+            Thing::Spectre => "Spectre",
+            Thing::HangingVictimArmsOut2 => "Hanging victim, arms out",
+            Thing::HangingPairOfLegs2 => "Hanging pair of legs",
+            Thing::HangingVictimOneLegged2 => "Hanging victim, one-legged",
+            Thing::HangingLeg2 => "Hanging leg",
+            Thing::HangingVictimTwitching2 => "Hanging victim, twitching",
+            Thing::ArchVile => "Arch-vile",
+            Thing::HeavyWeaponDude => "Heavy weapon dude",
+            Thing::Revenant => "Revenant",
+            Thing::Mancubus => "Mancubus",
+            Thing::Arachnotron => "Arachnotron",
+            Thing::HellKnight => "Hell knight",
+            Thing::BurningBarrel => "Burning barrel",
+            Thing::PainElemental => "Pain elemental",
+            Thing::CommanderKeen => "Commander Keen",
+            Thing::HangingVictimGutsRemoved => "Hanging victim, guts removed",
+            Thing::HangingVictimGutsAndBrainRemoved => "Hanging victim, guts and brain removed",
+            Thing::HangingTorsoLookingDown => "Hanging torso, looking down",
+            Thing::HangingTorsoOpenSkull => "Hanging torso, open skull",
+            Thing::HangingTorsoLookingUp => "Hanging torso, looking up",
+            Thing::HangingTorsoBrainRemoved => "Hanging torso, brain removed",
+            Thing::PoolOfBlood => "Pool of blood",
+            Thing::PoolOfBlood2 => "Pool of blood",
+            Thing::PoolOfBrains => "Pool of brains",
+            Thing::SuperShotgun => "Super shotgun",
+            Thing::Megasphere => "Megasphere",
+            Thing::WolfensteinSs => "Wolfenstein SS",
+            Thing::TallTechnoFloorLamp => "Tall techno floor lamp",
+            Thing::ShortTechnoFloorLamp => "Short techno floor lamp",
+            Thing::SpawnSpot => "Spawn spot",
+            Thing::RomerosHead => "Romero's head",
+            Thing::MonsterSpawner => "Monster spawner",
+            Thing::Shotgun => "Shotgun",
+            Thing::Chaingun => "Chaingun",
+            Thing::RocketLauncher => "Rocket launcher",
+            Thing::PlasmaGun => "Plasma gun",
+            Thing::Chainsaw => "Chainsaw",
+            Thing::Bfg9000 => "BFG9000",
+            Thing::Clip => "Clip",
+            Thing::FourShotgunShells => "4 shotgun shells",
+            Thing::Rocket => "Rocket",
+            Thing::Stimpack => "Stimpack",
+            Thing::Medikit => "Medikit",
+            Thing::Supercharge => "Supercharge",
+            Thing::HealthBonus => "Health bonus",
+            Thing::ArmorBonus => "Armor bonus",
+            Thing::Armor => "Armor",
+            Thing::Megaarmor => "Megaarmor",
+            Thing::Invulnerability => "Invulnerability",
+            Thing::Berserk => "Berserk",
+            Thing::PartialInvisibility => "Partial invisibility",
+            Thing::RadiationShieldingSuit => "Radiation shielding suit",
+            Thing::ComputerAreaMap => "Computer area map",
+            Thing::FloorLamp => "Floor lamp",
+            Thing::ExplodingBarrel => "Exploding barrel",
+            Thing::LightAmplificationVisor => "Light amplification visor",
+            Thing::BoxOfRockets => "Box of rockets",
+            Thing::EnergyCell => "Energy cell",
+            Thing::BoxOfBullets => "Box of bullets",
+            Thing::BoxOfShotgunShells => "Box of shotgun shells",
+            Thing::Imp => "Imp",
+            Thing::Demon => "Demon",
+            Thing::BaronOfHell => "Baron of Hell",
+            Thing::Zombieman => "Zombieman",
+            Thing::Cacodemon => "Cacodemon",
+            Thing::LostSoul => "Lost soul",
+        }
+    }
+
+    /// The radius and height the engine gives a thing's actor, for players and
+    /// monsters. Other things don't block movement in ways worth checking.
+    pub fn size(self) -> Option<(i16, i16)> {
+        Some(match self {
+            Thing::Player1Start
+            | Thing::Player2Start
+            | Thing::Player3Start
+            | Thing::Player4Start
+            | Thing::DeathmatchStart => (16, 56),
+            Thing::Zombieman
+            | Thing::ShotgunGuy
+            | Thing::HeavyWeaponDude
+            | Thing::Imp
+            | Thing::Revenant
+            | Thing::ArchVile
+            | Thing::WolfensteinSs => (20, 56),
+            Thing::Demon | Thing::Spectre => (30, 56),
+            Thing::LostSoul => (16, 56),
+            Thing::Cacodemon | Thing::PainElemental => (31, 56),
+            Thing::BaronOfHell | Thing::HellKnight => (24, 64),
+            Thing::Arachnotron => (64, 64),
+            Thing::Mancubus => (48, 64),
+            Thing::Cyberdemon => (40, 110),
+            Thing::Spiderdemon => (128, 100),
+            Thing::CommanderKeen => (16, 72),
+            _ => return None,
+        })
+    }
+
+    /// The height of a decoration the engine hangs from the ceiling rather
+    /// than standing on the floor, from Doom's `mobjinfo` table; `None` for
+    /// everything else. Every hanging decoration is 16 units in radius.
+    pub fn hanging_height(self) -> Option<i16> {
+        Some(match self {
+            Thing::HangingLeg | Thing::HangingLeg2 | Thing::HangingVictimOneLegged2 => 52,
+            Thing::HangingVictimTwitching
+            | Thing::HangingVictimTwitching2
+            | Thing::HangingPairOfLegs
+            | Thing::HangingPairOfLegs2 => 68,
+            Thing::HangingVictimArmsOut
+            | Thing::HangingVictimArmsOut2
+            | Thing::HangingVictimOneLegged => 84,
+            Thing::HangingVictimGutsRemoved | Thing::HangingVictimGutsAndBrainRemoved => 88,
+            Thing::HangingTorsoLookingDown
+            | Thing::HangingTorsoOpenSkull
+            | Thing::HangingTorsoLookingUp
+            | Thing::HangingTorsoBrainRemoved => 64,
+            _ => return None,
+        })
+    }
+
+    /// The hit points a monster spawns with, from Doom's `mobjinfo` table.
+    pub fn health(self) -> Option<u32> {
+        Some(match self {
+            Thing::Zombieman => 20,
+            Thing::ShotgunGuy => 30,
+            Thing::WolfensteinSs => 50,
+            Thing::Imp => 60,
+            Thing::HeavyWeaponDude => 70,
+            Thing::LostSoul | Thing::CommanderKeen => 100,
+            Thing::Demon | Thing::Spectre => 150,
+            Thing::Revenant => 300,
+            Thing::Cacodemon | Thing::PainElemental => 400,
+            Thing::HellKnight | Thing::Arachnotron => 500,
+            Thing::Mancubus => 600,
+            Thing::ArchVile => 700,
+            Thing::BaronOfHell => 1000,
+            Thing::Spiderdemon => 3000,
+            Thing::Cyberdemon => 4000,
+            _ => return None,
+        })
+    }
+
+    /// Whether the thing is one of `BOSSES`.
+    pub fn is_boss(self) -> bool {
+        BOSSES.contains(&self)
+    }
+
+    pub fn category(self) -> ThingCategory {
+        use ThingCategory::*;
+        match self {
+            Thing::Player1Start
+            | Thing::Player2Start
+            | Thing::Player3Start
+            | Thing::Player4Start
+            | Thing::DeathmatchStart => PlayerStart,
+            Thing::BlueKeycard
+            | Thing::YellowKeycard
+            | Thing::RedKeycard
+            | Thing::RedSkullKey
+            | Thing::YellowSkullKey
+            | Thing::BlueSkullKey => Key,
+            Thing::Spiderdemon
+            | Thing::ShotgunGuy
+            | Thing::Cyberdemon
+            | Thing::Spectre
+            | Thing::ArchVile
+            | Thing::HeavyWeaponDude
+            | Thing::Revenant
+            | Thing::Mancubus
+            | Thing::Arachnotron
+            | Thing::HellKnight
+            | Thing::PainElemental
+            | Thing::CommanderKeen
+            | Thing::WolfensteinSs
+            | Thing::RomerosHead
+            | Thing::Imp
+            | Thing::Demon
+            | Thing::BaronOfHell
+            | Thing::Zombieman
+            | Thing::Cacodemon
+            | Thing::LostSoul => Monster,
+            Thing::Backpack
+            | Thing::EnergyCellPack
+            | Thing::Clip
+            | Thing::FourShotgunShells
+            | Thing::Rocket
+            | Thing::BoxOfRockets
+            | Thing::EnergyCell
+            | Thing::BoxOfBullets
+            | Thing::BoxOfShotgunShells => Ammo,
+            Thing::BloodyMess
+            | Thing::BloodyMess2
+            | Thing::DeadPlayer
+            | Thing::DeadFormerHuman
+            | Thing::DeadFormerSergeant
+            | Thing::DeadImp
+            | Thing::DeadDemon
+            | Thing::DeadCacodemon
+            | Thing::DeadLostSoul
+            | Thing::PoolOfBloodAndFlesh
+            | Thing::ImpaledHuman
+            | Thing::TwitchingImpaledHuman
+            | Thing::SkullOnAPole
+            | Thing::FiveSkullsShishKebab
+            | Thing::PileOfSkullsAndCandles
+            | Thing::TallGreenPillar
+            | Thing::ShortGreenPillar
+            | Thing::TallRedPillar
+            | Thing::ShortRedPillar
+            | Thing::Candle
+            | Thing::Candelabra
+            | Thing::ShortGreenPillarWithBeatingHeart
+            | Thing::ShortRedPillarWithSkull
+            | Thing::EvilEye
+            | Thing::FloatingSkull
+            | Thing::BurntTree
+            | Thing::TallBlueFirestick
+            | Thing::TallGreenFirestick
+            | Thing::TallRedFirestick
+            | Thing::BrownStump
+            | Thing::TallTechnoColumn
+            | Thing::HangingVictimTwitching
+            | Thing::HangingVictimArmsOut
+            | Thing::HangingVictimOneLegged
+            | Thing::HangingPairOfLegs
+            | Thing::HangingLeg
+            | Thing::LargeBrownTree
+            | Thing::ShortBlueFirestick
+            | Thing::ShortGreenFirestick
+            | Thing::ShortRedFirestick
+            | Thing::HangingVictimArmsOut2
+            | Thing::HangingPairOfLegs2
+            | Thing::HangingVictimOneLegged2
+            | Thing::HangingLeg2
+            | Thing::HangingVictimTwitching2
+            | Thing::BurningBarrel
+            | Thing::HangingVictimGutsRemoved
+            | Thing::HangingVictimGutsAndBrainRemoved
+            | Thing::HangingTorsoLookingDown
+            | Thing::HangingTorsoOpenSkull
+            | Thing::HangingTorsoLookingUp
+            | Thing::HangingTorsoBrainRemoved
+            | Thing::PoolOfBlood
+            | Thing::PoolOfBlood2
+            | Thing::PoolOfBrains
+            | Thing::TallTechnoFloorLamp
+            | Thing::ShortTechnoFloorLamp
+            | Thing::FloorLamp => Decoration,
+            Thing::TeleportLanding
+            | Thing::SpawnSpot
+            | Thing::MonsterSpawner
+            | Thing::ExplodingBarrel => Special,
+            Thing::SuperShotgun
+            | Thing::Shotgun
+            | Thing::Chaingun
+            | Thing::RocketLauncher
+            | Thing::PlasmaGun
+            | Thing::Chainsaw
+            | Thing::Bfg9000 => Weapon,
+            Thing::Megasphere
+            | Thing::Supercharge
+            | Thing::Invulnerability
+            | Thing::Berserk
+            | Thing::PartialInvisibility
+            | Thing::RadiationShieldingSuit
+            | Thing::ComputerAreaMap
+            | Thing::LightAmplificationVisor => Powerup,
+            Thing::Stimpack | Thing::Medikit | Thing::HealthBonus => Health,
+            Thing::ArmorBonus | Thing::Armor | Thing::Megaarmor => Armor,
+        }
+    }
+}
+
+/// The set of thing type numbers a game understands. Heretic, Hexen and Strife
+/// reuse many of Doom's numbers for unrelated things, so the table has to match
+/// the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThingTable {
+    Doom,
+    Heretic,
+    Hexen,
+    Strife,
+}
+
+impl ThingTable {
+    /// Doom's table is used for both Doom games and for WADs of unknown origin.
+    pub fn for_game(game: Game) -> Self {
+        match game {
+            Game::Heretic => ThingTable::Heretic,
+            Game::Hexen => ThingTable::Hexen,
+            Game::Strife => ThingTable::Strife,
+            Game::Doom | Game::Doom2 | Game::Unknown => ThingTable::Doom,
+        }
+    }
+
+    fn lookup(self, doomednum: u16) -> Option<(&'static str, ThingCategory)> {
+        let table = match self {
+            ThingTable::Doom => {
+                return Thing::from_doomednum(doomednum).map(|t| (t.name(), t.category()))
+            }
+            ThingTable::Heretic => HERETIC_THINGS,
+            ThingTable::Hexen => HEXEN_THINGS,
+            ThingTable::Strife => STRIFE_THINGS,
+        };
+        table
+            .iter()
+            .find(|(n, _, _)| *n == doomednum)
+            .map(|&(_, name, category)| (name, category))
+    }
+
+    pub fn name(self, doomednum: u16) -> Option<&'static str> {
+        self.lookup(doomednum).map(|(name, _)| name)
+    }
+
+    pub fn category(self, doomednum: u16) -> Option<ThingCategory> {
+        self.lookup(doomednum).map(|(_, category)| category)
+    }
+
+    /// The Doom thing a number stands for, in Doom's table only. Actor sizes,
+    /// heights and health are only known for Doom's things.
+    pub fn doom_thing(self, doomednum: u16) -> Option<Thing> {
+        match self {
+            ThingTable::Doom => Thing::from_doomednum(doomednum),
+            _ => None,
+        }
+    }
+
+    /// Whether the number is one of the game's bosses: `BOSSES` for Doom,
+    /// `HERETIC_BOSSES`, `HEXEN_BOSSES` or `STRIFE_BOSSES` for the others.
+    pub fn is_boss(self, doomednum: u16) -> bool {
+        let bosses: &[u16] = match self {
+            ThingTable::Doom => return self.doom_thing(doomednum).is_some_and(Thing::is_boss),
+            ThingTable::Heretic => &HERETIC_BOSSES,
+            ThingTable::Hexen => &HEXEN_BOSSES,
+            ThingTable::Strife => &STRIFE_BOSSES,
+        };
+        bosses.contains(&doomednum)
+    }
+
+    /// Names the types in `Map::thing_type_counts`, most common first and
+    /// then by name. Unknown types are kept, named `Unknown(<n>)`.
+    pub fn inventory(self, counts: &BTreeMap<u16, usize>) -> Vec<(String, usize)> {
+        let mut named = counts
+            .iter()
+            .map(|(&doomednum, &count)| (self.thing_name(doomednum), count))
+            .collect::<Vec<_>>();
+        named.sort_by(|(a, m), (b, n)| n.cmp(m).then_with(|| a.cmp(b)));
+        named
+    }
+
+    /// The display name for a thing type in this table, or `Unknown(<n>)`
+    /// for unrecognized numbers.
+    pub fn thing_name(self, doomednum: u16) -> String {
+        match self.name(doomednum) {
+            Some(name) => name.to_string(),
+            None => format!("Unknown({})", doomednum),
+        }
+    }
+}
+
+impl Wad {
+    /// The thing table for the game `detect_game` guesses.
+    pub fn thing_table(&self) -> ThingTable {
+        ThingTable::for_game(self.detect_game())
+    }
+}
+
+/// Heretic's episode bosses: the iron lich, the maulotaur and D'Sparil.
+pub const HERETIC_BOSSES: [u16; 3] = [6, 9, 7];
+/// Hexen's bosses: the death wyvern, the heresiarch, the three class bosses
+/// and Korax.
+pub const HEXEN_BOSSES: [u16; 6] = [254, 10080, 10100, 10101, 10102, 10200];
+/// Strife's bosses: the Order's leaders and the Entity.
+pub const STRIFE_BOSSES: [u16; 5] = [12, 64, 71, 199, 128];
+
+const HERETIC_THINGS: &[(u16, &str, ThingCategory)] = {
+    use ThingCategory::*;
+    &[
+        (1, "Player 1 start", PlayerStart),
+        (2, "Player 2 start", PlayerStart),
+        (3, "Player 3 start", PlayerStart),
+        (4, "Player 4 start", PlayerStart),
+        (11, "Deathmatch start", PlayerStart),
+        (14, "Teleport landing", Special),
+        (56, "D'Sparil teleport spot", Special),
+        (43, "Pod generator", Special),
+        (87, "Volcano", Special),
+        (2035, "Pod", Special),
+        (66, "Gargoyle", Monster),
+        (5, "Fire gargoyle", Monster),
+        (68, "Golem", Monster),
+        (69, "Golem ghost", Monster),
+        (45, "Nitrogolem", Monster),
+        (46, "Nitrogolem ghost", Monster),
+        (15, "Disciple of D'Sparil", Monster),
+        (64, "Undead warrior", Monster),
+        (65, "Undead warrior ghost", Monster),
+        (70, "Weredragon", Monster),
+        (90, "Sabreclaw", Monster),
+        (92, "Ophidian", Monster),
+        (6, "Iron lich", Monster),
+        (9, "Maulotaur", Monster),
+        (7, "D'Sparil", Monster),
+        (2005, "Gauntlets of the Necromancer", Weapon),
+        (2001, "Ethereal crossbow", Weapon),
+        (53, "Dragon claw", Weapon),
+        (2004, "Hellstaff", Weapon),
+        (2003, "Phoenix rod", Weapon),
+        (2002, "Firemace", Weapon),
+        (10, "Wand crystal", Ammo),
+        (12, "Crystal geode", Ammo),
+        (18, "Ethereal arrows", Ammo),
+        (19, "Quiver of ethereal arrows", Ammo),
+        (54, "Claw orb", Ammo),
+        (55, "Energy orb", Ammo),
+        (20, "Lesser runes", Ammo),
+        (21, "Greater runes", Ammo),
+        (22, "Flame orb", Ammo),
+        (23, "Inferno orb", Ammo),
+        (13, "Mace spheres", Ammo),
+        (16, "Pile of mace spheres", Ammo),
+        (8, "Bag of holding", Ammo),
+        (81, "Crystal vial", Health),
+        (82, "Quartz flask", Health),
+        (32, "Mystic urn", Health),
+        (85, "Silver shield", Armor),
+        (31, "Enchanted shield", Armor),
+        (36, "Chaos device", Powerup),
+        (30, "Morph ovum", Powerup),
+        (75, "Shadowsphere", Powerup),
+        (84, "Ring of invincibility", Powerup),
+        (83, "Wings of wrath", Powerup),
+        (86, "Tome of power", Powerup),
+        (33, "Torch", Powerup),
+        (34, "Time bomb of the ancients", Powerup),
+        (35, "Map scroll", Powerup),
+        (73, "Green key", Key),
+        (79, "Blue key", Key),
+        (80, "Yellow key", Key),
+        (17, "Hanging skull", Decoration),
+        (24, "Hanging skull", Decoration),
+        (25, "Hanging skull", Decoration),
+        (26, "Hanging skull", Decoration),
+        (27, "Serpent torch", Decoration),
+        (28, "Chandelier", Decoration),
+        (29, "Short grey pillar", Decoration),
+        (37, "Small stalagmite", Decoration),
+        (38, "Large stalagmite", Decoration),
+        (39, "Large stalactite", Decoration),
+        (40, "Small stalactite", Decoration),
+        (44, "Barrel", Decoration),
+        (47, "Brown pillar", Decoration),
+        (48, "Moss", Decoration),
+        (49, "Moss", Decoration),
+        (50, "Wall torch", Decoration),
+        (51, "Hanging corpse", Decoration),
+        (76, "Fire brazier", Decoration),
+        (94, "Blue key statue", Decoration),
+        (95, "Green key statue", Decoration),
+        (96, "Yellow key statue", Decoration),
+    ]
+};
+
+// Hexen decorations are not listed yet; they show up as unknown types
+const HEXEN_THINGS: &[(u16, &str, ThingCategory)] = {
+    use ThingCategory::*;
+    &[
+        (1, "Player 1 start", PlayerStart),
+        (2, "Player 2 start", PlayerStart),
+        (3, "Player 3 start", PlayerStart),
+        (4, "Player 4 start", PlayerStart),
+        (11, "Deathmatch start", PlayerStart),
+        (14, "Teleport landing", Special),
+        (3000, "Polyobject anchor", Special),
+        (3001, "Polyobject start spot", Special),
+        (3002, "Crushing polyobject start spot", Special),
+        (9001, "Map spot", Special),
+        (9013, "Map spot with gravity", Special),
+        (107, "Centaur", Monster),
+        (115, "Slaughtaur", Monster),
+        (10030, "Ettin", Monster),
+        (10060, "Afrit", Monster),
+        (31, "Chaos serpent", Monster),
+        (8080, "Brown chaos serpent", Monster),
+        (34, "Reiver", Monster),
+        (10011, "Buried reiver", Monster),
+        (114, "Dark bishop", Monster),
+        (121, "Stalker", Monster),
+        (120, "Stalker leader", Monster),
+        (8020, "Wendigo", Monster),
+        (254, "Death wyvern", Monster),
+        (10080, "Heresiarch", Monster),
+        (10100, "Zedek", Monster),
+        (10101, "Traductus", Monster),
+        (10102, "Menelkir", Monster),
+        (10200, "Korax", Monster),
+        (10, "Serpent staff", Weapon),
+        (8010, "Timon's axe", Weapon),
+        (53, "Frost shards", Weapon),
+        (123, "Hammer of retribution", Weapon),
+        (8009, "Firestorm", Weapon),
+        (8040, "Arc of death", Weapon),
+        (12, "Quietus piece", Weapon),
+        (13, "Quietus piece", Weapon),
+        (16, "Quietus piece", Weapon),
+        (18, "Wraithverge piece", Weapon),
+        (19, "Wraithverge piece", Weapon),
+        (20, "Wraithverge piece", Weapon),
+        (21, "Bloodscourge piece", Weapon),
+        (22, "Bloodscourge piece", Weapon),
+        (23, "Bloodscourge piece", Weapon),
+        (122, "Blue mana", Ammo),
+        (124, "Green mana", Ammo),
+        (8004, "Combined mana", Ammo),
+        (81, "Crystal vial", Health),
+        (82, "Quartz flask", Health),
+        (32, "Mystic urn", Health),
+        (8005, "Mesh armor", Armor),
+        (8006, "Falcon shield", Armor),
+        (8007, "Platinum helm", Armor),
+        (8008, "Amulet of warding", Armor),
+        (30, "Porkalator", Powerup),
+        (33, "Torch", Powerup),
+        (36, "Chaos device", Powerup),
+        (83, "Wings of wrath", Powerup),
+        (84, "Icon of the defender", Powerup),
+        (86, "Dark servant", Powerup),
+        (10040, "Banishment device", Powerup),
+        (10110, "Disc of repulsion", Powerup),
+        (10120, "Mystic ambit incant", Powerup),
+        (8000, "Flechette", Powerup),
+        (8002, "Boots of speed", Powerup),
+        (8003, "Krater of might", Powerup),
+        (8041, "Dragonskin bracers", Powerup),
+        (8030, "Steel key", Key),
+        (8031, "Cave key", Key),
+        (8032, "Axe key", Key),
+        (8033, "Fire key", Key),
+        (8034, "Emerald key", Key),
+        (8035, "Dungeon key", Key),
+        (8036, "Silver key", Key),
+        (8037, "Rusted key", Key),
+        (8038, "Horn key", Key),
+        (8039, "Swamp key", Key),
+        (8200, "Castle key", Key),
+    ]
+};
+
+// Type numbers from the Strife 1.31 executable's mobjinfo table, as listed by
+// the ZDoom wiki's Strife editor number page. Peasants, rebels and other
+// non-hostile characters are left out, as are decorations.
+const STRIFE_THINGS: &[(u16, &str, ThingCategory)] = {
+    use ThingCategory::*;
+    &[
+        (1, "Player 1 start", PlayerStart),
+        (2, "Player 2 start", PlayerStart),
+        (3, "Player 3 start", PlayerStart),
+        (4, "Player 4 start", PlayerStart),
+        (11, "Deathmatch start", PlayerStart),
+        (14, "Teleport landing", Special),
+        (3002, "Acolyte", Monster),
+        (142, "Acolyte (red)", Monster),
+        (143, "Acolyte (rust)", Monster),
+        (146, "Acolyte (gray)", Monster),
+        (147, "Acolyte (dark green)", Monster),
+        (148, "Acolyte (gold)", Monster),
+        (231, "Acolyte (blue)", Monster),
+        (232, "Acolyte (light green)", Monster),
+        (58, "Shadow acolyte", Monster),
+        (3001, "Reaver", Monster),
+        (3006, "Sentinel", Monster),
+        (186, "Stalker", Monster),
+        (3003, "Templar", Monster),
+        (3005, "Crusader", Monster),
+        (16, "Inquisitor", Monster),
+        (26, "Ceiling turret", Monster),
+        (12, "Loremaster", Monster),
+        (64, "Bishop", Monster),
+        (71, "Programmer", Monster),
+        (199, "Oracle", Monster),
+        (128, "Entity", Monster),
+        (129, "Spectre A", Monster),
+        (75, "Spectre B", Monster),
+        (76, "Spectre C", Monster),
+        (167, "Spectre D", Monster),
+        (168, "Spectre E", Monster),
+        (2001, "Crossbow", Weapon),
+        (2002, "Assault gun", Weapon),
+        (2003, "Mini-missile launcher", Weapon),
+        (2004, "Mauler", Weapon),
+        (2005, "Flamethrower", Weapon),
+        (154, "Grenade launcher", Weapon),
+        (2007, "Clip of bullets", Ammo),
+        (2048, "Ammo box", Ammo),
+        (2010, "Mini-missiles", Ammo),
+        (2046, "Crate of missiles", Ammo),
+        (2047, "Energy pod", Ammo),
+        (17, "Energy pack", Ammo),
+        (114, "Electric bolts", Ammo),
+        (115, "Poison bolts", Ammo),
+        (152, "HE-grenade rounds", Ammo),
+        (153, "Phosphorus-grenade rounds", Ammo),
+        (183, "Ammo satchel", Ammo),
+        (2011, "Med patch", Health),
+        (2012, "Medical kit", Health),
+        (83, "Surgery kit", Health),
+        (2018, "Leather armor", Armor),
+        (2019, "Metal armor", Armor),
+        (2024, "Shadow armor", Powerup),
+        (2025, "Environmental suit", Powerup),
+        (2026, "Map", Powerup),
+        (207, "Targeter", Powerup),
+        (10, "Teleporter beacon", Powerup),
+        (13, "ID badge", Key),
+        (91, "Severed hand", Key),
+        (230, "Base key", Key),
+        (233, "Governor's key", Key),
+        (234, "Passcard", Key),
+        (235, "Prison key", Key),
+        (236, "Power key 1", Key),
+        (237, "Power key 2", Key),
+        (238, "Power key 3", Key),
+        (239, "Gold key", Key),
+        (240, "ID card", Key),
+        (241, "Silver key", Key),
+        (242, "Oracle key", Key),
+        (243, "Military ID", Key),
+        (244, "Order key", Key),
+        (245, "Warehouse key", Key),
+        (246, "Brass key", Key),
+        (247, "Red crystal key", Key),
+        (248, "Blue crystal key", Key),
+        (249, "Chapel key", Key),
+        (250, "Catacomb key", Key),
+        (251, "Security key", Key),
+        (252, "Core key", Key),
+        (253, "Mauler key", Key),
+        (254, "Factory key", Key),
+        (255, "Mine key", Key),
+    ]
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_game_gets_its_own_table() {
+        assert_eq!(ThingTable::for_game(Game::Doom2), ThingTable::Doom);
+        assert_eq!(ThingTable::for_game(Game::Unknown), ThingTable::Doom);
+        assert_eq!(ThingTable::for_game(Game::Heretic), ThingTable::Heretic);
+        assert_eq!(ThingTable::for_game(Game::Hexen), ThingTable::Hexen);
+        assert_eq!(ThingTable::for_game(Game::Strife), ThingTable::Strife);
+    }
+
+    #[test]
+    fn iconic_things_are_named_per_game() {
+        use ThingCategory::*;
+        let cases = [
+            (ThingTable::Doom, 3001, "Imp", Monster),
+            (ThingTable::Doom, 2001, "Shotgun", Weapon),
+            (ThingTable::Heretic, 66, "Gargoyle", Monster),
+            (ThingTable::Heretic, 15, "Disciple of D'Sparil", Monster),
+            (ThingTable::Heretic, 10, "Wand crystal", Ammo),
+            (ThingTable::Hexen, 10030, "Ettin", Monster),
+            (ThingTable::Hexen, 10060, "Afrit", Monster),
+            (ThingTable::Hexen, 122, "Blue mana", Ammo),
+            (ThingTable::Hexen, 124, "Green mana", Ammo),
+            (ThingTable::Strife, 3002, "Acolyte", Monster),
+        ];
+        for (table, doomednum, name, category) in cases {
+            assert_eq!(table.name(doomednum), Some(name));
+            assert_eq!(table.category(doomednum), Some(category));
+        }
+    }
+
+    #[test]
+    fn shared_numbers_mean_different_things() {
+        assert_eq!(ThingTable::Doom.name(3001), Some("Imp"));
+        assert_eq!(ThingTable::Hexen.name(3001), Some("Polyobject start spot"));
+        assert_eq!(ThingTable::Strife.name(3001), Some("Reaver"));
+        assert_eq!(ThingTable::Heretic.name(10), Some("Wand crystal"));
+        assert_eq!(ThingTable::Hexen.name(10), Some("Serpent staff"));
+        assert_eq!(ThingTable::Heretic.thing_name(10030), "Unknown(10030)");
+    }
+}
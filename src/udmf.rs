@@ -0,0 +1,260 @@
+use crate::wad::Lump;
+use crate::wad::Wad;
+
+/// A map in the text-based UDMF format: the lumps from the `TEXTMAP` after
+/// its marker up to its `ENDMAP`, such as `ZNODES`, `REJECT`, `DIALOGUE`
+/// and `BEHAVIOR`.
+#[derive(Debug, Clone)]
+pub struct UdmfMap {
+    pub(crate) name: String,
+    /// `TEXTMAP` first, then the rest in directory order; `ENDMAP` is left
+    /// out.
+    pub(crate) lumps: Vec<Lump>,
+}
+
+impl UdmfMap {
+    /// The map's name, from the marker before its `TEXTMAP`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn textmap(&self) -> &Lump {
+        &self.lumps[0]
+    }
+
+    pub fn lumps(&self) -> &[Lump] {
+        &self.lumps
+    }
+
+    pub fn lump(&self, name: &str) -> Option<&Lump> {
+        self.lumps
+            .iter()
+            .find(|l| l.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A key in a UDMF `TEXTMAP` that the base UDMF specification doesn't
+/// define, with the kind of block it was set in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UdmfField {
+    /// `thing`, `linedef`, `sidedef`, `vertex` or `sector`, or whatever block
+    /// name a port made up.
+    pub block: String,
+    pub key: String,
+}
+
+impl UdmfField {
+    /// Whether the key is one of the `user_` properties that mods attach for
+    /// their own scripts.
+    pub fn is_user(&self) -> bool {
+        self.key.starts_with("user_")
+    }
+}
+
+const THING_KEYS: &[&str] = &[
+    "id",
+    "x",
+    "y",
+    "height",
+    "angle",
+    "type",
+    "skill1",
+    "skill2",
+    "skill3",
+    "skill4",
+    "skill5",
+    "ambush",
+    "single",
+    "dm",
+    "coop",
+    "friend",
+    "dormant",
+    "class1",
+    "class2",
+    "class3",
+    "standing",
+    "strifeally",
+    "translucent",
+    "invisible",
+    "special",
+    "arg0",
+    "arg1",
+    "arg2",
+    "arg3",
+    "arg4",
+    "comment",
+];
+const LINEDEF_KEYS: &[&str] = &[
+    "id",
+    "v1",
+    "v2",
+    "blocking",
+    "blockmonsters",
+    "twosided",
+    "dontpegtop",
+    "dontpegbottom",
+    "secret",
+    "blocksound",
+    "dontdraw",
+    "mapped",
+    "passuse",
+    "translucent",
+    "jumpover",
+    "blockfloaters",
+    "playercross",
+    "playeruse",
+    "monstercross",
+    "monsteruse",
+    "impact",
+    "playerpush",
+    "monsterpush",
+    "missilecross",
+    "repeatspecial",
+    "special",
+    "arg0",
+    "arg1",
+    "arg2",
+    "arg3",
+    "arg4",
+    "sidefront",
+    "sideback",
+    "comment",
+];
+const SIDEDEF_KEYS: &[&str] = &[
+    "offsetx",
+    "offsety",
+    "texturetop",
+    "texturebottom",
+    "texturemiddle",
+    "sector",
+    "comment",
+];
+const VERTEX_KEYS: &[&str] = &["x", "y"];
+const SECTOR_KEYS: &[&str] = &[
+    "heightfloor",
+    "heightceiling",
+    "texturefloor",
+    "textureceiling",
+    "lightlevel",
+    "special",
+    "id",
+    "comment",
+];
+
+fn standard_keys(block: &str) -> &'static [&'static str] {
+    match block {
+        "thing" => THING_KEYS,
+        "linedef" => LINEDEF_KEYS,
+        "sidedef" => SIDEDEF_KEYS,
+        "vertex" => VERTEX_KEYS,
+        "sector" => SECTOR_KEYS,
+        _ => &[],
+    }
+}
+
+// splits a TEXTMAP into identifiers, values and the punctuation `{ } = ;`,
+// dropping comments and whitespace
+fn tokens(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut tokens = vec![];
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        match bytes[pos] {
+            b if b.is_ascii_whitespace() => pos += 1,
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                pos = text[pos + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| pos + end + 4);
+            }
+            b'"' => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'"' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+                pos = (pos + 1).min(bytes.len());
+                tokens.push(&text[start..pos]);
+            }
+            b'{' | b'}' | b'=' | b';' => {
+                pos += 1;
+                tokens.push(&text[start..pos]);
+            }
+            _ => {
+                while pos < bytes.len()
+                    && !bytes[pos].is_ascii_whitespace()
+                    && !b"{}=;\"/".contains(&bytes[pos])
+                {
+                    pos += 1;
+                }
+                // a lone '/' that doesn't start a comment
+                pos = pos.max(start + 1);
+                tokens.push(&text[start..pos]);
+            }
+        }
+    }
+    tokens
+}
+
+/// Lists the keys in a `TEXTMAP` lump outside the base UDMF specification,
+/// such as a port's namespace extensions or `user_` properties, each
+/// distinct block and key once in the order they first appear. Keys are
+/// case-insensitive in UDMF, so they're given in lowercase.
+pub fn udmf_extensions(textmap: &[u8]) -> Vec<UdmfField> {
+    let text = String::from_utf8_lossy(textmap);
+    let tokens = tokens(&text);
+    let mut fields = vec![];
+    let mut block: Option<String> = None;
+    let mut i = 0;
+    while i < tokens.len() {
+        match (tokens[i], tokens.get(i + 1).copied()) {
+            ("}", _) => {
+                block = None;
+                i += 1;
+            }
+            (name, Some("{")) => {
+                block = Some(name.to_ascii_lowercase());
+                i += 2;
+            }
+            (key, Some("=")) => {
+                if let Some(block) = &block {
+                    let key = key.to_ascii_lowercase();
+                    if !standard_keys(block).contains(&key.as_str()) {
+                        let field = UdmfField {
+                            block: block.clone(),
+                            key,
+                        };
+                        if !fields.contains(&field) {
+                            fields.push(field);
+                        }
+                    }
+                }
+                // skip the value and its semicolon
+                while i < tokens.len() && tokens[i] != ";" {
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    fields
+}
+
+impl Wad {
+    /// The non-standard UDMF keys each text-format map sets, as found by
+    /// `udmf_extensions`, in the order of `Wad::udmf_maps`.
+    pub fn udmf_extensions(&self) -> Vec<(String, Vec<UdmfField>)> {
+        self.udmf_maps()
+            .iter()
+            .map(|map| {
+                let fields = udmf_extensions(map.textmap().as_bytes());
+                (map.name().to_string(), fields)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,38 @@
+use crate::geometry::sector_at;
+use crate::map::Map;
+use crate::things::Thing;
+use crate::Error;
+
+impl Map {
+    /// Positions of the voodoo dolls in the map. The engine spawns a player at
+    /// every player 1 start and the last one becomes the real player, so every
+    /// earlier start is a doll.
+    pub fn voodoo_dolls(&self) -> Result<Vec<(i16, i16)>, Error> {
+        let mut starts = self
+            .things()?
+            .into_iter()
+            .filter(|t| t.doomednum == Thing::Player1Start.doomednum())
+            .map(|t| (t.x, t.y))
+            .collect::<Vec<_>>();
+        starts.pop();
+        Ok(starts)
+    }
+
+    /// The voodoo dolls standing in damaging sectors, the usual setup for
+    /// conveyor-driven scripting tricks.
+    pub fn voodoo_dolls_on_damaging_floors(&self) -> Result<Vec<(i16, i16)>, Error> {
+        let vertexes = self.vertexes()?;
+        let linedefs = self.linedefs()?;
+        let sidedefs = self.sidedefs()?;
+        let sectors = self.sectors()?;
+        Ok(self
+            .voodoo_dolls()?
+            .into_iter()
+            .filter(|&(x, y)| {
+                sector_at(&vertexes, &linedefs, &sidedefs, x as f64, y as f64)
+                    .and_then(|s| sectors.get(s as usize))
+                    .is_some_and(|s| s.is_damaging())
+            })
+            .collect())
+    }
+}
@@ -1,14 +1,37 @@
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
 use std::fs::File;
+use std::io::Cursor;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
-use std::mem::MaybeUninit;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::ops::Range;
 use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
 
-use byteorder::ReadBytesExt;
+use byteorder::ByteOrder;
+use byteorder::BE;
 use byteorder::LE;
 
+use crate::animations::parse_animated;
+use crate::animations::parse_switches;
+use crate::animations::AnimDef;
+use crate::animations::SwitchDef;
+use crate::diagnostics::Diagnostic;
+use crate::diagnostics::Severity;
+use crate::gzip;
+use crate::lumps::MARKER_SECTIONS;
+use crate::map::Map;
+use crate::md5::Md5;
+use crate::pattern::glob_match;
+use crate::udmf::UdmfMap;
 use crate::Error;
 
 #[derive(Debug)]
@@ -16,24 +39,109 @@ pub struct Header {
     id: [u8; 4],
     dir_ct: i32,
     dir_ptr: i32,
+    endianness: Endianness,
 }
 
+/// The byte order of a WAD's header, directory and map lumps. PC WADs are
+/// little-endian; some console ports, such as Jaguar Doom, are big-endian.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub(crate) fn read_i16(self, buf: &[u8]) -> i16 {
+        match self {
+            Endianness::Little => LE::read_i16(buf),
+            Endianness::Big => BE::read_i16(buf),
+        }
+    }
+
+    pub(crate) fn read_u16(self, buf: &[u8]) -> u16 {
+        match self {
+            Endianness::Little => LE::read_u16(buf),
+            Endianness::Big => BE::read_u16(buf),
+        }
+    }
+
+    pub(crate) fn read_i32(self, buf: &[u8]) -> i32 {
+        match self {
+            Endianness::Little => LE::read_i32(buf),
+            Endianness::Big => BE::read_i32(buf),
+        }
+    }
+
+    pub(crate) fn write_i32(self, buf: &mut [u8], n: i32) {
+        match self {
+            Endianness::Little => LE::write_i32(buf, n),
+            Endianness::Big => BE::write_i32(buf, n),
+        }
+    }
+}
+
+/// A directory entry as stored in the file.
 #[derive(Debug)]
-struct RawLump {
+pub struct RawLump {
     ptr: i32,
     size: i32,
     name: [u8; 8],
 }
 
+impl RawLump {
+    pub fn name(&self) -> String {
+        name_from_bytes(&self.name)
+    }
+
+    /// The lump's position, relative to the start of the WAD.
+    pub fn offset(&self) -> i32 {
+        self.ptr
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+}
+
+/// A lump's name and location, as listed by `Wad::directory`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    /// The position of the lump's data from the start of the file.
+    pub offset: i32,
+    pub size: i32,
+}
+
 #[derive(Debug, Clone)]
 pub struct Lump {
-    name: String,
-    data: Vec<u8>,
-    kind: LumpKind,
+    pub(crate) name: String,
+    pub(crate) offset: i32,
+    pub(crate) data: Vec<u8>,
+    pub(crate) kind: LumpKind,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum LumpKind {
+impl Lump {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> LumpKind {
+        self.kind
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The lump's contents, exactly as stored in the WAD.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LumpKind {
     Things,
     Linedefs,
     Sidedefs,
@@ -50,22 +158,7 @@ enum LumpKind {
     Other,
 }
 
-#[derive(Debug)]
-pub struct Map {
-    name: String,
-    things: Lump,
-    linedefs: Lump,
-    sidedefs: Lump,
-    vertexes: Lump,
-    segs: Lump,
-    subsectors: Lump,
-    nodes: Lump,
-    sectors: Lump,
-    reject: Option<Lump>,
-    blockmap: Lump,
-    behavior: Option<Lump>,
-    scripts: Option<Lump>,
-}
+const DIRECTORY_ENTRY_SIZE: i64 = 16;
 
 const REQUIRED_MAP_COMPONENTS: [LumpKind; 9] = [
     LumpKind::Things,
@@ -79,19 +172,149 @@ const REQUIRED_MAP_COMPONENTS: [LumpKind; 9] = [
     LumpKind::Blockmap,
 ];
 
+// the order maps' lumps are stored in
+const MAP_LUMP_ORDER: [&str; 12] = [
+    "THINGS", "LINEDEFS", "SIDEDEFS", "VERTEXES", "SEGS", "SSECTORS", "NODES", "SECTORS", "REJECT",
+    "BLOCKMAP", "BEHAVIOR", "SCRIPTS",
+];
+
 const OPTIONAL_MAP_COMPONENTS: [LumpKind; 3] =
     [LumpKind::Reject, LumpKind::Behavior, LumpKind::Scripts];
 
+/// The components of `REQUIRED_MAP_COMPONENTS` that `ParseOptions::alpha`
+/// lets a map go without. The alpha and pre-release builds built the BSP and
+/// blockmap at load time, or in a later pass of the editor, so their maps
+/// often lack these lumps.
+pub const ALPHA_OPTIONAL_MAP_COMPONENTS: [LumpKind; 4] = [
+    LumpKind::Segs,
+    LumpKind::Subsectors,
+    LumpKind::Nodes,
+    LumpKind::Blockmap,
+];
+
+/// How `Wad::from_file_with_options` reads a WAD: how strictly it treats a
+/// malformed directory, which old map layouts it accepts, whether it keeps
+/// the case of lump names, and whether it hashes an IWAD along the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Fail with `Error::InvalidLumpOrder` on a repeated or orphaned map
+    /// component. When off, a repeated component is ignored and a map missing
+    /// components is left out of `Wad::maps`, kept in `Wad::partial_maps`
+    /// instead.
+    pub strict: bool,
+    /// Record what lenient parsing skipped, available from
+    /// `Wad::parse_diagnostics`.
+    pub collect_diagnostics: bool,
+    /// Accept maps from Doom's alpha and pre-release builds, which lack some
+    /// lumps later releases require: any of `ALPHA_OPTIONAL_MAP_COMPONENTS`
+    /// (SEGS, SSECTORS, NODES and BLOCKMAP) may be missing, and is read as
+    /// an empty lump. THINGS, LINEDEFS, SIDEDEFS, VERTEXES and SECTORS are
+    /// still required. Statistics needing a missing lump fail for that map.
+    pub alpha: bool,
+    /// Keep lump names as written instead of uppercasing them, for ports
+    /// such as ZDoom where case can matter. Lookups by name, such as
+    /// `Wad::lump_by_name`, then only match the exact case; map lumps are
+    /// still recognized in any case.
+    pub preserve_case: bool,
+    /// Take an IWAD's MD5 while it's read, for `Wad::md5` and
+    /// `Wad::identify_iwad`. Off by default, since it reads the whole file
+    /// rather than just the lumps.
+    pub hash_iwad: bool,
+}
+
+impl ParseOptions {
+    // whether a map can't be assembled without a component of this kind
+    fn requires(&self, kind: LumpKind) -> bool {
+        REQUIRED_MAP_COMPONENTS.contains(&kind)
+            && !(self.alpha && ALPHA_OPTIONAL_MAP_COMPONENTS.contains(&kind))
+    }
+
+    fn is_complete(&self, components: &HashMap<LumpKind, Lump>) -> bool {
+        self.missing(components).is_empty()
+    }
+
+    fn missing(&self, components: &HashMap<LumpKind, Lump>) -> Vec<LumpKind> {
+        REQUIRED_MAP_COMPONENTS
+            .iter()
+            .copied()
+            .filter(|&c| !components.contains_key(&c) && self.requires(c))
+            .collect()
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            collect_diagnostics: false,
+            alpha: false,
+            preserve_case: false,
+            hash_iwad: false,
+        }
+    }
+}
+
+// a WAD's offsets and sizes are i32s, so nothing past this can be part of one
+const MAX_WAD_SIZE: usize = i32::MAX as usize;
+
+/// A WAD file on disk, read directly or, if it's gzip-compressed, from a copy
+/// decompressed into memory.
+enum WadFile {
+    Plain(File),
+    Decompressed(Cursor<Vec<u8>>),
+}
+
+impl WadFile {
+    fn open(path: &Path) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let mut magic = [0u8; 2];
+        let is_gzip = file.read(&mut magic)? == 2 && gzip::is_gzip(&magic);
+        file.seek(SeekFrom::Start(0))?;
+        if !is_gzip {
+            return Ok(WadFile::Plain(file));
+        }
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+        let data = gzip::decompress(&data, MAX_WAD_SIZE)
+            .ok_or_else(|| Error::InvalidGzip(path.to_owned()))?;
+        Ok(WadFile::Decompressed(Cursor::new(data)))
+    }
+}
+
+impl Read for WadFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            WadFile::Plain(f) => f.read(buf),
+            WadFile::Decompressed(c) => c.read(buf),
+        }
+    }
+}
+
+impl Seek for WadFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            WadFile::Plain(f) => f.seek(pos),
+            WadFile::Decompressed(c) => c.seek(pos),
+        }
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Flat(RawLump);
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Sprite(RawLump);
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Wad {
     header: Header,
-    lumps: Vec<Lump>,
+    source: PathBuf,
+    pub(crate) lumps: Vec<Lump>,
     maps: Vec<Map>,
+    partial_maps: Vec<Map>,
+    udmf_maps: Vec<UdmfMap>,
     sounds: Vec<Lump>,
     music: Vec<Lump>,
     playpal: Option<Lump>,
@@ -99,14 +322,88 @@ pub struct Wad {
     endoom: Option<Lump>,
     textures: [Option<Lump>; 2],
     demos: [Option<Lump>; 3],
+    /// The size of the file in bytes, from the header on.
+    file_len: u64,
+    report: ParseReport,
+    /// Whether lump names kept their case, from `ParseOptions::preserve_case`.
+    preserve_case: bool,
+    /// The whole file's MD5, taken only for IWADs.
+    md5: Option<String>,
 }
 
+/// How long each phase of loading a WAD took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadTimings {
+    /// Reading and checking the header and directory.
+    pub directory: Duration,
+    /// Reading the lumps' data.
+    pub lump_data: Duration,
+    /// Sorting the lumps into maps, music and the rest.
+    pub map_assembly: Duration,
+}
+
+/// What a single parse of a WAD did, counted as it went.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseReport {
+    /// The header, directory and lump data read, in bytes. Lumps sharing data
+    /// are counted each time they're read.
+    pub bytes_read: u64,
+    /// How many lumps of each kind the directory lists.
+    pub lumps_by_kind: BTreeMap<LumpKind, usize>,
+    /// The maps assembled from the lumps, including any a later map of the
+    /// same name replaces.
+    pub maps: usize,
+    /// The text-format maps found, counted like `maps`.
+    pub udmf_maps: usize,
+    /// Repeated map lumps lenient parsing ignored.
+    pub skipped_lumps: usize,
+    /// Maps lenient parsing left out of `Wad::maps` for missing some of
+    /// their lumps, which are in `Wad::partial_maps`, or for a UDMF map, its
+    /// `ENDMAP`.
+    pub dropped_maps: usize,
+    /// What lenient parsing skipped, if `ParseOptions::collect_diagnostics`
+    /// was set.
+    pub diagnostics: Vec<Diagnostic>,
+    pub timings: LoadTimings,
+}
+
+/// What occupies a `LayoutSegment`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutKind {
+    Header,
+    /// A lump's data.
+    Lump(String),
+    Directory,
+    /// Bytes that nothing points at, which rewriting the WAD would reclaim.
+    Gap,
+    /// Bytes claimed by more than one of the other segments. It follows the
+    /// segment that starts the overlap.
+    Overlap,
+}
+
+/// A byte range of a WAD file, from `Wad::layout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutSegment {
+    pub kind: LayoutKind,
+    pub offset: u64,
+    pub size: u64,
+}
+
+const HEADER_SIZE: u64 = 12;
+
 impl Wad {
-    fn new(header: Header) -> Self {
+    fn new(header: Header, source: PathBuf) -> Self {
         Self {
             header,
+            source,
+            file_len: 0,
+            report: ParseReport::default(),
+            preserve_case: false,
+            md5: None,
             lumps: vec![],
             maps: vec![],
+            partial_maps: vec![],
+            udmf_maps: vec![],
             sounds: vec![],
             music: vec![],
             playpal: None,
@@ -117,117 +414,1623 @@ impl Wad {
         }
     }
 
-    pub(crate) fn from_file(file: impl AsRef<Path>) -> Result<Self, Error> {
-        let mut f = File::open(file.as_ref())?;
-        let mut header: Header = unsafe { MaybeUninit::uninit().assume_init() };
-        f.read_exact(&mut header.id)?;
-        if &header.id != b"IWAD" && &header.id != b"PWAD" {
-            return Err(Error::NotAWad(file.as_ref().to_owned()));
-        }
-        header.dir_ct = f.read_i32::<LE>()?;
-        header.dir_ptr = f.read_i32::<LE>()?;
+    pub fn from_file(file: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_file_with_progress(file, |_, _| {})
+    }
+
+    /// Like `from_file`, calling `progress(lumps_read, lump_count)` as lumps are read.
+    pub fn from_file_with_progress(
+        file: impl AsRef<Path>,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<Self, Error> {
+        let f = WadFile::open(file.as_ref())?;
+        Self::parse(
+            f,
+            0,
+            None,
+            file.as_ref(),
+            ParseOptions::default(),
+            &mut progress,
+        )
+    }
+
+    /// Like `from_file`, with control over how malformed directories are handled.
+    pub fn from_file_with_options(
+        file: impl AsRef<Path>,
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        let f = WadFile::open(file.as_ref())?;
+        Self::parse(f, 0, None, file.as_ref(), options, &mut |_, _| {})
+    }
+
+    /// Like `from_file`, but reading the header, directory and map lumps in the
+    /// given byte order instead of detecting it.
+    pub fn from_file_with_endianness(
+        file: impl AsRef<Path>,
+        endianness: Endianness,
+    ) -> Result<Self, Error> {
+        let f = WadFile::open(file.as_ref())?;
+        Self::parse(
+            f,
+            0,
+            Some(endianness),
+            file.as_ref(),
+            ParseOptions::default(),
+            &mut |_, _| {},
+        )
+    }
+
+    /// Parses a WAD held in memory. Malformed input of any kind gives an
+    /// error rather than a panic, so this is the entry point for fuzzing.
+    pub fn try_parse(bytes: &[u8]) -> Result<Self, Error> {
+        Self::parse(
+            Cursor::new(bytes),
+            0,
+            None,
+            Path::new("<memory>"),
+            ParseOptions::default(),
+            &mut |_, _| {},
+        )
+    }
+
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, Error> {
+        Self::from_reader_at(reader, 0)
+    }
+
+    /// Like `from_reader`, with control over how malformed directories are
+    /// handled.
+    pub fn from_reader_with_options<R: Read + Seek>(
+        reader: R,
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        Self::parse(
+            reader,
+            0,
+            None,
+            Path::new("<stream>"),
+            options,
+            &mut |_, _| {},
+        )
+    }
+
+    /// Parses a WAD whose header starts `offset` bytes into `reader`, such as one
+    /// appended to an executable. Directory and lump offsets are relative to the header.
+    pub fn from_reader_at<R: Read + Seek>(reader: R, offset: u64) -> Result<Self, Error> {
+        let source = PathBuf::from(format!("<stream at offset {}>", offset));
+        Self::parse(
+            reader,
+            offset,
+            None,
+            &source,
+            ParseOptions::default(),
+            &mut |_, _| {},
+        )
+    }
 
-        let mut wad = Wad::new(header);
-        f.seek(SeekFrom::Start(wad.header.dir_ptr as u64))?;
+    fn parse<R: Read + Seek>(
+        mut f: R,
+        base: u64,
+        endianness: Option<Endianness>,
+        source: &Path,
+        options: ParseOptions,
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Self, Error> {
+        let started = Instant::now();
+        let header = read_header(&mut f, base, endianness, source)?;
+        let directory = read_directory(&mut f, base, &header)?;
+        let file_len = f.seek(SeekFrom::End(0))?.saturating_sub(base);
+        let endianness = header.endianness;
+        let mut report = ParseReport {
+            bytes_read: HEADER_SIZE + DIRECTORY_ENTRY_SIZE as u64 * directory.len() as u64,
+            timings: LoadTimings {
+                directory: started.elapsed(),
+                ..LoadTimings::default()
+            },
+            ..ParseReport::default()
+        };
+        let lumps_started = Instant::now();
+
+        let mut wad = Wad::new(header, source.to_owned());
+        wad.file_len = file_len;
+        if wad.is_iwad() && options.hash_iwad {
+            wad.md5 = Some(hash_file(&mut f, base, file_len)?);
+        }
+        wad.preserve_case = options.preserve_case;
         let mut possible_map_name = String::new();
         let mut map_components = HashMap::<LumpKind, Lump>::new();
-        for _ in 0..wad.header.dir_ct {
-            let mut raw_lump: RawLump = unsafe { MaybeUninit::uninit().assume_init() };
-            raw_lump.ptr = f.read_i32::<LE>()?;
-            raw_lump.size = f.read_i32::<LE>()?;
-            f.read_exact(&mut raw_lump.name)?;
-
-            let lump_name = raw_lump
-                .name
-                .iter()
-                .cloned()
-                .take_while(|&b| b != b'\0')
-                .collect::<Vec<_>>();
-
+        let mut udmf_map: Option<UdmfMap> = None;
+        let skipped = |report: &mut ParseReport, map: &str, message: String| {
+            if options.collect_diagnostics {
+                report.diagnostics.push(Diagnostic::new(
+                    Severity::Warning,
+                    Some(map).filter(|m| !m.is_empty()),
+                    message,
+                ));
+            }
+        };
+        for (i, raw_lump) in directory.into_iter().enumerate() {
+            progress(i, wad.header.dir_ct as usize);
+            let extent = lump_extent(&raw_lump, file_len)?;
             let mut lump = Lump {
-                name: String::from_utf8(lump_name)?.to_uppercase(),
-                data: Vec::with_capacity(raw_lump.size as usize),
+                name: if options.preserve_case {
+                    name_as_written(&raw_lump.name)
+                } else {
+                    name_from_bytes(&raw_lump.name)
+                },
+                offset: raw_lump.ptr,
+                data: vec![],
                 kind: LumpKind::Other,
             };
 
-            let old_pos = f.stream_position()?;
-            f.seek(SeekFrom::Start(raw_lump.ptr as u64))?;
-
-            lump.data.resize(raw_lump.size as usize, 0u8);
-            f.read_exact(&mut lump.data)?;
-
-            f.seek(SeekFrom::Start(old_pos))?;
-
-            lump.kind = match lump.name.as_str() {
-                "THINGS" => LumpKind::Things,
-                "LINEDEFS" => LumpKind::Linedefs,
-                "SIDEDEFS" => LumpKind::Sidedefs,
-                "VERTEXES" => LumpKind::Vertexes,
-                "SEGS" => LumpKind::Segs,
-                "SSECTORS" => LumpKind::Subsectors,
-                "NODES" => LumpKind::Nodes,
-                "SECTORS" => LumpKind::Sectors,
-                "REJECT" => LumpKind::Reject,
-                "BLOCKMAP" => LumpKind::Blockmap,
-                "BEHAVIOR" => LumpKind::Behavior,
-                "SCRIPTS" => LumpKind::Scripts,
-                _ => LumpKind::Other,
-            };
+            let read_started = Instant::now();
+            extent.read_into(&mut f, base, &mut lump.data)?;
+            report.timings.lump_data += read_started.elapsed();
+            report.bytes_read += extent.len as u64;
 
-            wad.lumps.push(lump.clone());
+            lump.kind = lump_kind(&lump.name.to_ascii_uppercase());
+            *report.lumps_by_kind.entry(lump.kind).or_default() += 1;
 
-            if map_components.contains_key(&lump.kind) {
-                return Err(Error::InvalidLumpOrder(raw_lump.ptr, lump.name));
-            } else if REQUIRED_MAP_COMPONENTS.contains(&lump.kind)
-                || OPTIONAL_MAP_COMPONENTS.contains(&lump.kind)
-            {
-                map_components.insert(lump.kind, lump);
-            } else if REQUIRED_MAP_COMPONENTS
+            wad.lumps.push(lump.clone());
+            if let Some(n) = ["DEMO1", "DEMO2", "DEMO3"]
                 .iter()
-                .all(|c| map_components.contains_key(c))
+                .position(|d| wad.names_match(d, &lump.name))
             {
-                wad.maps.push(Map {
+                wad.demos[n] = Some(lump.clone());
+            }
+
+            // a text-format map runs from its TEXTMAP to ENDMAP, whatever
+            // lumps come between, so none of them are binary map components
+            if let Some(mut udmf) = udmf_map.take() {
+                if lump.name.eq_ignore_ascii_case("ENDMAP") {
+                    wad.udmf_maps.push(udmf);
+                } else {
+                    udmf.lumps.push(lump);
+                    udmf_map = Some(udmf);
+                }
+                continue;
+            }
+            if map_components.is_empty() && lump.name.eq_ignore_ascii_case("TEXTMAP") {
+                udmf_map = Some(UdmfMap {
                     name: std::mem::take(&mut possible_map_name),
-                    things: map_components.remove(&LumpKind::Things).unwrap(),
-                    linedefs: map_components.remove(&LumpKind::Linedefs).unwrap(),
-                    sidedefs: map_components.remove(&LumpKind::Sidedefs).unwrap(),
-                    vertexes: map_components.remove(&LumpKind::Vertexes).unwrap(),
-                    segs: map_components.remove(&LumpKind::Segs).unwrap(),
-                    subsectors: map_components.remove(&LumpKind::Subsectors).unwrap(),
-                    nodes: map_components.remove(&LumpKind::Nodes).unwrap(),
-                    sectors: map_components.remove(&LumpKind::Sectors).unwrap(),
-                    reject: map_components.remove(&LumpKind::Reject),
-                    blockmap: map_components.remove(&LumpKind::Blockmap).unwrap(),
-                    behavior: map_components.remove(&LumpKind::Behavior),
-                    scripts: map_components.remove(&LumpKind::Scripts),
+                    lumps: vec![lump],
                 });
-                debug_assert!(map_components.is_empty());
+                continue;
+            }
+
+            if REQUIRED_MAP_COMPONENTS.contains(&lump.kind)
+                || OPTIONAL_MAP_COMPONENTS.contains(&lump.kind)
+            {
+                match map_components.entry(lump.kind) {
+                    Entry::Occupied(_) if options.strict => {
+                        return Err(Error::InvalidLumpOrder(raw_lump.ptr, lump.name));
+                    }
+                    Entry::Occupied(_) => {
+                        report.skipped_lumps += 1;
+                        skipped(
+                            &mut report,
+                            &possible_map_name,
+                            format!(
+                                "ignored repeated map lump '{}' at offset {}",
+                                lump.name, raw_lump.ptr
+                            ),
+                        );
+                    }
+                    Entry::Vacant(v) => {
+                        v.insert(lump);
+                    }
+                }
+            } else if options.is_complete(&map_components) {
+                wad.maps.push(take_map(
+                    std::mem::take(&mut possible_map_name),
+                    endianness,
+                    &mut map_components,
+                ));
                 possible_map_name = lump.name;
             } else {
                 if !map_components.is_empty() {
-                    return Err(Error::InvalidLumpOrder(raw_lump.ptr, lump.name));
-                }
-                if lump.data.len() >= 4 {
-                    // check for music header
-                    let mut header = [0u8; 4];
-                    lump.data.as_slice().read_exact(&mut header).unwrap();
-                    //              MIDI                  MUS
-                    if &header == b"MThd" || &header == b"MUS\x1A" {
-                        wad.music.push(lump);
-                        continue;
+                    if options.strict {
+                        return Err(Error::InvalidLumpOrder(raw_lump.ptr, lump.name));
                     }
-
-                    // TODO check for sound header (doom format)
+                    let map = take_partial_map(
+                        std::mem::take(&mut possible_map_name),
+                        endianness,
+                        &mut map_components,
+                        options,
+                    );
+                    report.dropped_maps += 1;
+                    skipped(
+                        &mut report,
+                        &map.name,
+                        format!(
+                            "kept a map missing {} before '{}' at offset {} as a partial map",
+                            component_names(&map.missing),
+                            lump.name,
+                            raw_lump.ptr
+                        ),
+                    );
+                    wad.partial_maps.push(map);
                 }
+                // check for music header: MIDI or MUS
+                if lump.data.starts_with(b"MThd") || lump.data.starts_with(b"MUS\x1A") {
+                    wad.music.push(lump);
+                    continue;
+                }
+                // TODO check for sound header (doom format)
                 // 0-length lumps can be map names or markers
                 possible_map_name = lump.name;
             }
         }
 
+        progress(wad.header.dir_ct as usize, wad.header.dir_ct as usize);
+
+        if let Some(udmf) = udmf_map {
+            if options.strict {
+                return Err(Error::CorruptDirectory(format!(
+                    "the UDMF map '{}' has no ENDMAP",
+                    udmf.name
+                )));
+            }
+            report.dropped_maps += 1;
+            skipped(
+                &mut report,
+                &udmf.name,
+                "dropped a UDMF map with no ENDMAP".to_string(),
+            );
+        }
+
+        if options.is_complete(&map_components) {
+            // the directory ended right after a map's last component
+            wad.maps
+                .push(take_map(possible_map_name, endianness, &mut map_components));
+        } else if !map_components.is_empty() && !options.strict {
+            let map = take_partial_map(possible_map_name, endianness, &mut map_components, options);
+            report.dropped_maps += 1;
+            skipped(
+                &mut report,
+                &map.name,
+                format!(
+                    "kept a map missing {} at the end of the directory as a partial map",
+                    component_names(&map.missing)
+                ),
+            );
+            wad.partial_maps.push(map);
+        }
+
+        if options.collect_diagnostics {
+            for (i, map) in wad.maps.iter().enumerate() {
+                let later = |m: &Map| wad.names_match(&m.name, &map.name);
+                let first = !wad.maps[..i].iter().any(later);
+                let count = wad.maps.iter().filter(|m| later(m)).count();
+                if first && count > 1 {
+                    report.diagnostics.push(Diagnostic::new(
+                        Severity::Warning,
+                        Some(&map.name),
+                        format!("the map appears {} times; the last one is used", count),
+                    ));
+                }
+            }
+        }
+
+        attach_gl_nodes(&mut wad.maps, &wad.lumps);
+        report.maps = wad.maps.len();
+        report.udmf_maps = wad.udmf_maps.len();
+        report.timings.map_assembly = lumps_started
+            .elapsed()
+            .saturating_sub(report.timings.lump_data);
+        wad.report = report;
         Ok(wad)
     }
 
+    /// Writes the WAD back out: the header, every lump's data in directory
+    /// order, then the directory, all in the byte order it was read with.
+    /// Lumps that shared data in the original file are written separately, and
+    /// empty lumps such as map markers get the offset where they would start.
+    /// Reading the result gives back the same lumps and maps. Fails with
+    /// `Error::CorruptDirectory`, before writing anything, if the lumps are
+    /// too large or too many for a directory's 32-bit offsets and sizes.
+    pub fn write<W: Write + Seek>(&self, mut out: W) -> Result<(), Error> {
+        let too_large = || {
+            Error::CorruptDirectory(format!(
+                "{} lumps of {} bytes don't fit in a WAD's 32-bit offsets",
+                self.lumps.len(),
+                self.lumps.iter().map(|l| l.data.len() as u64).sum::<u64>()
+            ))
+        };
+        let count = i32::try_from(self.lumps.len()).map_err(|_| too_large())?;
+        let mut entries = Vec::with_capacity(self.lumps.len());
+        let mut offset = 12i32;
+        for lump in &self.lumps {
+            let size = i32::try_from(lump.data.len()).map_err(|_| too_large())?;
+            entries.push((offset, size));
+            offset = offset.checked_add(size).ok_or_else(too_large)?;
+        }
+        count
+            .checked_mul(DIRECTORY_ENTRY_SIZE as i32)
+            .and_then(|size| offset.checked_add(size))
+            .ok_or_else(too_large)?;
+
+        let e = self.header.endianness;
+        let start = out.stream_position()?;
+        out.write_all(&[0u8; 12])?;
+        let mut directory = Vec::with_capacity(self.lumps.len() * DIRECTORY_ENTRY_SIZE as usize);
+        for (lump, &(offset, size)) in self.lumps.iter().zip(&entries) {
+            out.write_all(&lump.data)?;
+            let mut entry = [0u8; DIRECTORY_ENTRY_SIZE as usize];
+            e.write_i32(&mut entry[0..], offset);
+            e.write_i32(&mut entry[4..], size);
+            entry[8..].copy_from_slice(&name_to_bytes(&lump.name));
+            directory.extend_from_slice(&entry);
+        }
+        out.write_all(&directory)?;
+
+        let mut header = [0u8; 12];
+        header[..4].copy_from_slice(&self.header.id);
+        e.write_i32(&mut header[4..], count);
+        e.write_i32(&mut header[8..], offset);
+        out.seek(SeekFrom::Start(start))?;
+        out.write_all(&header)?;
+        out.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    /// Combines several WADs the way the engine sees them when they're loaded
+    /// together, later WADs taking precedence over earlier ones:
+    ///
+    /// - A lump replaces an earlier lump with the same name, keeping the
+    ///   earlier one's place in the directory.
+    /// - Sprite, flat and patch sections are combined into one section each,
+    ///   with the same rule applied to the lumps inside them. The doubled
+    ///   `FF_START`-style markers join their single counterparts' sections.
+    /// - Every map from every input is kept. A map with the same name as an
+    ///   earlier one replaces it whole, so no lumps from two versions of a map
+    ///   are mixed. A map with no marker, at the very start of a directory,
+    ///   can only be read back if it ends up first in the result.
+    ///
+    /// The result is an IWAD if any input is, and uses the first input's byte
+    /// order. Lump data is copied as it is, so the inputs should share a byte
+    /// order. An error is returned if the combined directory can't be read
+    /// back, such as when a markerless map no longer comes first.
+    pub fn merge(wads: &[Wad]) -> Result<Wad, Error> {
+        let mut parts = Vec::<Part>::new();
+        for wad in wads {
+            for part in Part::split(&wad.lumps) {
+                match (parts.iter_mut().find(|p| p.overrides(&part)), part) {
+                    (Some(Part::Section(_, lumps)), Part::Section(_, added)) => {
+                        for lump in added {
+                            match lumps.iter_mut().find(|l| l.name == lump.name) {
+                                Some(old) => *old = lump,
+                                None => lumps.push(lump),
+                            }
+                        }
+                    }
+                    (Some(old), part) => *old = part,
+                    (None, part) => parts.push(part),
+                }
+            }
+        }
+
+        let endianness = wads
+            .first()
+            .map_or(Endianness::Little, |w| w.header.endianness);
+        let header = Header {
+            id: if wads.iter().any(Wad::is_iwad) {
+                *b"IWAD"
+            } else {
+                *b"PWAD"
+            },
+            dir_ct: 0,
+            dir_ptr: 0,
+            endianness,
+        };
+        let mut merged = Wad::new(header, PathBuf::from("<merged>"));
+        merged.lumps = parts.into_iter().flat_map(Part::into_lumps).collect();
+        merged.reparsed()
+    }
+
+    /// Reads a map exported as loose lump files into a WAD holding just that
+    /// map. The directory is named after the map, such as `MAP01`, and holds a
+    /// file for each lump named after it, such as `THINGS` or `THINGS.lmp`,
+    /// in any case. The lumps are put in the usual order: `THINGS`,
+    /// `LINEDEFS`, `SIDEDEFS`, `VERTEXES`, `SEGS`, `SSECTORS`, `NODES`,
+    /// `SECTORS`, `REJECT`, `BLOCKMAP`, `BEHAVIOR` and `SCRIPTS`. Every one
+    /// but `REJECT`, `BEHAVIOR` and `SCRIPTS` is required, giving
+    /// `Error::MissingLump` when it's absent. Other files are ignored. A
+    /// directory whose name isn't 1 to 8 ASCII characters, and so can't be a
+    /// map marker, gives `Error::CorruptDirectory`.
+    pub fn from_lump_directory(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let mut files = HashMap::<String, PathBuf>::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            // a stem that isn't ASCII can't name a lump, so it's one of the
+            // other files
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .filter(|s| s.is_ascii());
+            if let (true, Some(stem)) = (path.is_file(), stem) {
+                files.insert(stem.to_ascii_uppercase(), path);
+            }
+        }
+        let dir_name = dir.canonicalize()?.file_name().map(|n| n.to_owned());
+        let map_name = dir_name
+            .as_ref()
+            .and_then(|n| n.to_str())
+            .filter(|n| n.is_ascii() && (1..=8).contains(&n.len()))
+            .ok_or_else(|| {
+                Error::CorruptDirectory(format!(
+                    "the map directory {} isn't named with 1 to 8 ASCII characters",
+                    dir.to_string_lossy()
+                ))
+            })?
+            .to_ascii_uppercase();
+
+        let header = Header {
+            id: *b"PWAD",
+            dir_ct: 0,
+            dir_ptr: 0,
+            endianness: Endianness::Little,
+        };
+        let mut wad = Wad::new(header, dir.to_owned());
+        wad.lumps.push(Lump {
+            kind: lump_kind(&map_name),
+            name: map_name,
+            offset: 0,
+            data: vec![],
+        });
+        for name in MAP_LUMP_ORDER {
+            let kind = lump_kind(name);
+            match files.get(name) {
+                Some(path) => wad.lumps.push(Lump {
+                    name: name.to_string(),
+                    offset: 0,
+                    data: fs::read(path)?,
+                    kind,
+                }),
+                None if REQUIRED_MAP_COMPONENTS.contains(&kind) => {
+                    return Err(Error::MissingLump(dir.to_owned(), name.to_string()));
+                }
+                None => {}
+            }
+        }
+        wad.reparsed()
+    }
+
+    // writes the lumps out and reads them back, so maps, music, demos and the
+    // rest are picked out as usual
+    fn reparsed(&self) -> Result<Self, Error> {
+        let mut data = Cursor::new(vec![]);
+        self.write(&mut data)?;
+        let options = ParseOptions {
+            strict: false,
+            collect_diagnostics: false,
+            alpha: false,
+            preserve_case: self.preserve_case,
+            hash_iwad: false,
+        };
+        Self::parse(
+            data,
+            0,
+            Some(self.header.endianness),
+            &self.source,
+            options,
+            &mut |_, _| {},
+        )
+    }
+
+    /// Lists a WAD file's lumps by reading only its header and directory, so
+    /// the cost doesn't depend on how much lump data there is. Gzip-compressed
+    /// files still have to be decompressed first.
+    pub fn directory(path: impl AsRef<Path>) -> Result<Vec<DirEntry>, Error> {
+        let mut file = WadFile::open(path.as_ref())?;
+        let header = read_header(&mut file, 0, None, path.as_ref())?;
+        Ok(read_directory(&mut file, 0, &header)?
+            .iter()
+            .map(|raw| DirEntry {
+                name: raw.name(),
+                offset: raw.ptr,
+                size: raw.size,
+            })
+            .collect())
+    }
+
+    /// Reads the lumps of a WAD file one at a time, passing each directory entry
+    /// and its data to `f` without classifying them or building a `Wad`. Stops
+    /// early when `f` returns `ControlFlow::Break`.
+    pub fn for_each_lump<F>(path: impl AsRef<Path>, mut f: F) -> Result<(), Error>
+    where
+        F: FnMut(&RawLump, &[u8]) -> ControlFlow<()>,
+    {
+        let mut file = WadFile::open(path.as_ref())?;
+        let header = read_header(&mut file, 0, None, path.as_ref())?;
+
+        let file_len = file.seek(SeekFrom::End(0))?;
+        let mut data = vec![];
+        for raw_lump in read_directory(&mut file, 0, &header)? {
+            lump_extent(&raw_lump, file_len)?.read_into(&mut file, 0, &mut data)?;
+            if f(&raw_lump, &data).is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the data of the lump at `index` in a WAD file's directory, the
+    /// same index as in `directory`, without loading any other lump.
+    pub fn read_lump_data(path: impl AsRef<Path>, index: usize) -> Result<Vec<u8>, Error> {
+        let mut file = WadFile::open(path.as_ref())?;
+        let header = read_header(&mut file, 0, None, path.as_ref())?;
+        let directory = read_directory(&mut file, 0, &header)?;
+        let raw_lump = directory
+            .get(index)
+            .ok_or(Error::LumpIndexOutOfRange(index, directory.len()))?;
+
+        let file_len = file.seek(SeekFrom::End(0))?;
+        let mut data = vec![];
+        lump_extent(raw_lump, file_len)?.read_into(&mut file, 0, &mut data)?;
+        Ok(data)
+    }
+
+    /// Reads a single map from a WAD file, loading only that map's lumps. Map
+    /// names are matched case-insensitively; if several maps share the name, the
+    /// last one wins, as in the engine.
+    pub fn load_map(path: impl AsRef<Path>, name: &str) -> Result<Map, Error> {
+        let mut file = WadFile::open(path.as_ref())?;
+        let header = read_header(&mut file, 0, None, path.as_ref())?;
+        let directory = read_directory(&mut file, 0, &header)?;
+
+        let is_component = |raw: &RawLump| {
+            let kind = lump_kind(&raw.name());
+            REQUIRED_MAP_COMPONENTS.contains(&kind) || OPTIONAL_MAP_COMPONENTS.contains(&kind)
+        };
+        // a map marker is any lump directly followed by a map component
+        let markers = directory
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| !is_component(&pair[0]) && is_component(&pair[1]))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let marker = match markers
+            .iter()
+            .rev()
+            .find(|&&i| directory[i].name().eq_ignore_ascii_case(name))
+        {
+            Some(&i) => i,
+            None => {
+                return Err(Error::MapNotFound(
+                    name.to_string(),
+                    markers.iter().map(|&i| directory[i].name()).collect(),
+                ))
+            }
+        };
+
+        let file_len = file.seek(SeekFrom::End(0))?;
+        let mut components = HashMap::<LumpKind, Lump>::new();
+        for raw_lump in directory[marker + 1..]
+            .iter()
+            .take_while(|r| is_component(r))
+        {
+            let extent = lump_extent(raw_lump, file_len)?;
+            let mut lump = Lump {
+                name: raw_lump.name(),
+                offset: raw_lump.ptr,
+                data: vec![],
+                kind: LumpKind::Other,
+            };
+            lump.kind = lump_kind(&lump.name);
+            extent.read_into(&mut file, 0, &mut lump.data)?;
+            if components.contains_key(&lump.kind) {
+                return Err(Error::InvalidLumpOrder(raw_lump.ptr, lump.name));
+            }
+            components.insert(lump.kind, lump);
+        }
+        if let Some(missing) = REQUIRED_MAP_COMPONENTS
+            .iter()
+            .find(|c| !components.contains_key(c))
+        {
+            return Err(Error::CorruptDirectory(format!(
+                "map {} has no {:?} lump",
+                directory[marker].name(),
+                missing
+            )));
+        }
+        Ok(take_map(
+            directory[marker].name(),
+            header.endianness,
+            &mut components,
+        ))
+    }
+
+    /// Adds the GL nodes in a `.gwa` file, as written by glBSP for ports that
+    /// read them from beside the WAD, to the maps they're for. In a `.gwa`,
+    /// as in a WAD, each map's GL lumps follow a `GL_<map>` marker, such as
+    /// `GL_MAP01`; a map whose name is too long for that has a `GL_LEVEL`
+    /// marker holding `LEVEL=<map>`. Lumps go to the last map with the
+    /// marker's name, replacing any GL nodes it had, and a marker for a map
+    /// the WAD doesn't have is skipped.
+    pub fn with_gwa(mut self, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let gwa = Wad::from_file(path)?;
+        attach_gl_nodes(&mut self.maps, &gwa.lumps);
+        Ok(self)
+    }
+
+    /// The `.gwa` file next to a WAD, with the same stem, if there is one.
+    pub fn sibling_gwa(path: impl AsRef<Path>) -> Option<PathBuf> {
+        ["gwa", "GWA"]
+            .iter()
+            .map(|ext| path.as_ref().with_extension(ext))
+            .find(|p| p.is_file())
+    }
+
+    /// How long loading the WAD took, phase by phase.
+    pub fn load_timings(&self) -> LoadTimings {
+        self.report.timings
+    }
+
+    /// What lenient parsing skipped, if `ParseOptions::collect_diagnostics` was set.
+    pub fn parse_diagnostics(&self) -> &[Diagnostic] {
+        &self.report.diagnostics
+    }
+
+    /// What parsing the WAD read and found, gathered while it was loaded.
+    pub fn parse_report(&self) -> ParseReport {
+        self.report.clone()
+    }
+
+    /// The MD5 of the whole file as lowercase hex, for matching against
+    /// published checksums. Only taken for IWADs read with
+    /// `ParseOptions::hash_iwad`, so `None` for PWADs and otherwise.
+    pub fn md5(&self) -> Option<&str> {
+        self.md5.as_deref()
+    }
+
+    /// The file the WAD was read from, or a placeholder for readers.
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// The byte order the WAD was read with, detected from the header unless
+    /// given to `from_file_with_endianness`.
+    pub fn endianness(&self) -> Endianness {
+        self.header.endianness
+    }
+
+    pub fn is_iwad(&self) -> bool {
+        &self.header.id == b"IWAD"
+    }
+
+    /// The file as byte ranges in order of offset: the header, each lump's
+    /// data, the directory, and the gaps between them, with overlaps called
+    /// out as segments of their own. Zero-size lumps such as markers have no
+    /// data and are left out.
+    pub fn layout(&self) -> Vec<LayoutSegment> {
+        let directory = LayoutSegment {
+            kind: LayoutKind::Directory,
+            offset: self.header.dir_ptr as u64,
+            size: self.header.dir_ct as u64 * DIRECTORY_ENTRY_SIZE as u64,
+        };
+        let mut regions = self
+            .lumps
+            .iter()
+            .filter(|l| !l.data.is_empty())
+            .map(|l| LayoutSegment {
+                kind: LayoutKind::Lump(l.name.clone()),
+                offset: l.offset as u64,
+                size: l.data.len() as u64,
+            })
+            .chain(Some(directory).filter(|d| d.size > 0))
+            .collect::<Vec<_>>();
+        regions.sort_by_key(|r| (r.offset, r.size));
+
+        let mut layout = vec![LayoutSegment {
+            kind: LayoutKind::Header,
+            offset: 0,
+            size: HEADER_SIZE,
+        }];
+        let mut covered = HEADER_SIZE;
+        for region in regions {
+            let (start, end) = (region.offset, region.offset + region.size);
+            if start > covered {
+                layout.push(LayoutSegment {
+                    kind: LayoutKind::Gap,
+                    offset: covered,
+                    size: start - covered,
+                });
+            }
+            layout.push(region);
+            if start < covered {
+                layout.push(LayoutSegment {
+                    kind: LayoutKind::Overlap,
+                    offset: start,
+                    size: end.min(covered) - start,
+                });
+            }
+            covered = covered.max(end);
+        }
+        if self.file_len > covered {
+            layout.push(LayoutSegment {
+                kind: LayoutKind::Gap,
+                offset: covered,
+                size: self.file_len - covered,
+            });
+        }
+        layout
+    }
+
+    /// A single grep-able line describing the WAD, in the stable format
+    /// `<file> [<IWAD|PWAD>] <n> lumps, <n> maps, <n> textures, game=<game>`,
+    /// followed by ` titlemap` and ` intermission` when the WAD has a
+    /// `TITLEMAP` or its own intermission graphics. The map count leaves out
+    /// the title map.
+    pub fn summary_line(&self) -> String {
+        let name = self
+            .source
+            .file_name()
+            .unwrap_or(self.source.as_os_str())
+            .to_string_lossy();
+        format!(
+            "{} [{}] {} lumps, {} maps, {} textures, game={}{}{}",
+            name,
+            if self.is_iwad() { "IWAD" } else { "PWAD" },
+            self.length(),
+            self.playable_maps().count(),
+            self.texture_names().len(),
+            self.detect_game(),
+            if self.title_map().is_some() {
+                " titlemap"
+            } else {
+                ""
+            },
+            if self.intermission_graphics().is_empty() {
+                ""
+            } else {
+                " intermission"
+            }
+        )
+    }
+
+    pub fn maps(&self) -> &[Map] {
+        &self.maps
+    }
+
+    /// The maps lenient parsing found missing required lumps, in directory
+    /// order. They aren't in `maps`, so statistics over a WAD's maps never
+    /// meet one; `Map::missing_components` says what each lacks, and
+    /// decoding a missing lump fails with `Error::MissingMapLump`. Always
+    /// empty with `ParseOptions::strict`, which rejects such maps.
+    pub fn partial_maps(&self) -> &[Map] {
+        &self.partial_maps
+    }
+
+    /// The text-format maps, in directory order. They aren't in `maps`,
+    /// whose statistics need the binary lumps.
+    pub fn udmf_maps(&self) -> &[UdmfMap] {
+        &self.udmf_maps
+    }
+
+    /// Every map except the title map.
+    pub fn playable_maps(&self) -> impl Iterator<Item = &Map> {
+        self.maps.iter().filter(|m| !m.is_title_map())
+    }
+
+    pub fn title_map(&self) -> Option<&Map> {
+        self.maps.iter().rev().find(|m| m.is_title_map())
+    }
+
+    /// The map with the given name, matched case-insensitively. If several
+    /// maps share the name, the last one wins, as in the engine.
+    pub fn map_by_name(&self, name: &str) -> Option<&Map> {
+        self.maps
+            .iter()
+            .rev()
+            .find(|m| self.names_match(&m.name, name))
+    }
+
+    /// Every map with the given name, in directory order.
+    pub fn maps_by_name(&self, name: &str) -> Vec<&Map> {
+        self.maps
+            .iter()
+            .filter(|m| self.names_match(&m.name, name))
+            .collect()
+    }
+
+    /// Reads the lumps at directory indexes `range` as one map, whether or not
+    /// parsing found a map there. The range may start with a marker, which names
+    /// the map; otherwise the map is named after the range. Every other lump
+    /// must be a map component, with no repeats and none of the required
+    /// ones missing.
+    pub fn map_from_range(&self, range: Range<usize>) -> Result<Map, Error> {
+        let invalid = |reason: String| Error::InvalidLumpRange(range.start, range.end, reason);
+        let lumps = self
+            .lumps
+            .get(range.clone())
+            .ok_or_else(|| invalid(format!("the directory has {} lumps", self.lumps.len())))?;
+        let is_component = |l: &Lump| {
+            REQUIRED_MAP_COMPONENTS.contains(&l.kind) || OPTIONAL_MAP_COMPONENTS.contains(&l.kind)
+        };
+        let (name, lumps) = match lumps.split_first() {
+            Some((marker, rest)) if !is_component(marker) => (marker.name.clone(), rest),
+            _ => (format!("{}:{}", range.start, range.end), lumps),
+        };
+
+        let mut components = HashMap::<LumpKind, Lump>::new();
+        for lump in lumps {
+            if !is_component(lump) {
+                return Err(invalid(format!("'{}' isn't a map lump", lump.name)));
+            }
+            if components.insert(lump.kind, lump.clone()).is_some() {
+                return Err(invalid(format!("there are two {} lumps", lump.name)));
+            }
+        }
+        if let Some(missing) = REQUIRED_MAP_COMPONENTS
+            .iter()
+            .find(|c| !components.contains_key(c))
+        {
+            return Err(invalid(format!("there is no {:?} lump", missing)));
+        }
+        Ok(take_map(name, self.header.endianness, &mut components))
+    }
+
+    /// Maps whose names match a case-insensitive glob such as `MAP0*` or `E[12]M?`.
+    pub fn maps_matching(&self, pattern: &str) -> Vec<&Map> {
+        self.maps
+            .iter()
+            .filter(|m| glob_match(pattern, &m.name))
+            .collect()
+    }
+
+    /// The data of the lump at `index` in the directory. The WAD keeps every
+    /// directory entry in order, map lumps included, since maps hold copies
+    /// of theirs rather than taking them out; so `index` is the same as in
+    /// `Wad::directory` and `iter_lumps`.
+    pub fn lump_data(&self, index: usize) -> Option<&[u8]> {
+        self.lumps.get(index).map(|l| l.data.as_slice())
+    }
+
+    /// Finds the last lump with the given name, matching the engine's load-order rule.
+    pub fn lump_by_name(&self, name: &str) -> Option<&Lump> {
+        self.lumps
+            .iter()
+            .rev()
+            .find(|l| self.names_match(&l.name, name))
+    }
+
+    // lump and map names are compared ignoring case, unless it was kept
+    fn names_match(&self, a: &str, b: &str) -> bool {
+        if self.preserve_case {
+            a == b
+        } else {
+            a.eq_ignore_ascii_case(b)
+        }
+    }
+
+    pub fn animated(&self) -> Option<Vec<AnimDef>> {
+        self.lump_by_name("ANIMATED")
+            .map(|l| parse_animated(&l.data))
+    }
+
+    pub fn switches(&self) -> Option<Vec<SwitchDef>> {
+        self.lump_by_name("SWITCHES")
+            .map(|l| parse_switches(&l.data))
+    }
+
+    /// The built-in demo `DEMO<n>`, for `n` from 1 to 3.
+    pub fn demo(&self, n: usize) -> Option<&Lump> {
+        self.demos.get(n.checked_sub(1)?)?.as_ref()
+    }
+
     pub fn length(&self) -> usize {
         self.lumps.len()
     }
+
+    /// The number of lumps in the directory; the same as `length`.
+    pub fn len(&self) -> usize {
+        self.length()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lumps.is_empty()
+    }
+
+    /// Every lump in directory order, including map components and markers.
+    pub fn iter_lumps(&self) -> impl Iterator<Item = &Lump> {
+        self.lumps.iter()
+    }
+}
+
+fn lump_kind(name: &str) -> LumpKind {
+    match name {
+        "THINGS" => LumpKind::Things,
+        "LINEDEFS" => LumpKind::Linedefs,
+        "SIDEDEFS" => LumpKind::Sidedefs,
+        "VERTEXES" => LumpKind::Vertexes,
+        "SEGS" => LumpKind::Segs,
+        "SSECTORS" => LumpKind::Subsectors,
+        "NODES" => LumpKind::Nodes,
+        "SECTORS" => LumpKind::Sectors,
+        "REJECT" => LumpKind::Reject,
+        "BLOCKMAP" => LumpKind::Blockmap,
+        "BEHAVIOR" => LumpKind::Behavior,
+        "SCRIPTS" => LumpKind::Scripts,
+        _ => LumpKind::Other,
+    }
+}
+
+fn read_directory<R: Read + Seek>(
+    f: &mut R,
+    base: u64,
+    header: &Header,
+) -> Result<Vec<RawLump>, Error> {
+    let file_len = f.seek(SeekFrom::End(0))?.saturating_sub(base);
+    let mut directory = vec![];
+    check_directory(header, file_len)?.read_into(f, base, &mut directory)?;
+
+    let e = header.endianness;
+    Ok(directory
+        .chunks_exact(DIRECTORY_ENTRY_SIZE as usize)
+        .map(|entry| {
+            let mut name = [0u8; 8];
+            name.copy_from_slice(&entry[8..16]);
+            RawLump {
+                ptr: e.read_i32(&entry[0..]),
+                size: e.read_i32(&entry[4..]),
+                name,
+            }
+        })
+        .collect())
+}
+
+// the MD5 of `len` bytes from `base`, read in pieces rather than all at once
+fn hash_file<R: Read + Seek>(f: &mut R, base: u64, len: u64) -> Result<String, Error> {
+    f.seek(SeekFrom::Start(base))?;
+    let mut hash = Md5::new();
+    let mut buffer = vec![0; 1 << 16];
+    let mut reader = f.take(len);
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => return Ok(hash.finish_hex()),
+            n => hash.update(&buffer[..n]),
+        }
+    }
+}
+
+/// Reads and checks the header. Unless `endianness` is given, the header is read
+/// as little-endian and then as big-endian, taking the first reading whose
+/// directory fits in the file.
+fn read_header<R: Read + Seek>(
+    f: &mut R,
+    base: u64,
+    endianness: Option<Endianness>,
+    source: &Path,
+) -> Result<Header, Error> {
+    f.seek(SeekFrom::Start(base))?;
+    let mut raw = [0u8; 12];
+    f.read_exact(&mut raw)?;
+    // a few community tools write the id in lowercase or with spaces around
+    // it; it's compared trimmed and case-insensitively, and kept uppercased
+    let trimmed = raw[..4].trim_ascii();
+    if !trimmed.eq_ignore_ascii_case(b"IWAD") && !trimmed.eq_ignore_ascii_case(b"PWAD") {
+        return Err(Error::NotAWad(source.to_owned()));
+    }
+    let mut id = [0u8; 4];
+    id.copy_from_slice(trimmed);
+    id.make_ascii_uppercase();
+    let file_len = f.seek(SeekFrom::End(0))?.saturating_sub(base);
+
+    let read = |endianness: Endianness| Header {
+        id,
+        dir_ct: endianness.read_i32(&raw[4..]),
+        dir_ptr: endianness.read_i32(&raw[8..]),
+        endianness,
+    };
+    let header = match endianness {
+        Some(e) => read(e),
+        None => {
+            let little = read(Endianness::Little);
+            let big = read(Endianness::Big);
+            if check_directory(&little, file_len).is_err()
+                && check_directory(&big, file_len).is_ok()
+            {
+                big
+            } else {
+                little
+            }
+        }
+    };
+    check_directory(&header, file_len)?;
+    Ok(header)
+}
+
+/// Where some data lies in a WAD file, in the types seeking and allocating
+/// take. Only `check_directory` and `lump_extent` make one, from offsets and
+/// sizes they've checked.
+#[derive(Debug, Clone, Copy)]
+struct Extent {
+    start: u64,
+    len: usize,
+}
+
+impl Extent {
+    /// Reads the data into `buf`, replacing what it held, from a file whose
+    /// header is `base` bytes into `f`.
+    fn read_into<R: Read + Seek>(
+        self,
+        f: &mut R,
+        base: u64,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        buf.clear();
+        buf.resize(self.len, 0u8);
+        f.seek(SeekFrom::Start(base + self.start))?;
+        f.read_exact(buf)?;
+        Ok(())
+    }
+}
+
+// The WAD format stores counts, offsets and sizes as `i32`s. These two checks
+// are where they become a `u64` and a `usize`, once they're known to be
+// non-negative and to end inside the file, so that no later `as` cast can wrap
+// or reach past its end, even near `i32::MAX`.
+
+/// Checks the header's directory against the file, whose data is `file_len`
+/// bytes long, and gives where the directory is.
+fn check_directory(header: &Header, file_len: u64) -> Result<Extent, Error> {
+    let count = u64::try_from(header.dir_ct)
+        .map_err(|_| Error::CorruptDirectory(format!("negative lump count {}", header.dir_ct)))?;
+    let start = u64::try_from(header.dir_ptr)
+        .ok()
+        .filter(|&start| start >= HEADER_SIZE)
+        .ok_or_else(|| {
+            Error::CorruptDirectory(format!(
+                "directory offset {} points before the end of the header",
+                header.dir_ptr
+            ))
+        })?;
+    // both are below 2^31, so neither overflows
+    let len = count * DIRECTORY_ENTRY_SIZE as u64;
+    if start + len > file_len {
+        return Err(Error::CorruptDirectory(format!(
+            "{} entries at offset {} don't fit in a {}-byte file",
+            header.dir_ct, header.dir_ptr, file_len
+        )));
+    }
+    let len = usize::try_from(len).map_err(|_| {
+        Error::CorruptDirectory(format!("{} entries don't fit in memory", header.dir_ct))
+    })?;
+    Ok(Extent { start, len })
+}
+
+/// Checks a lump's directory entry against the file, whose data is
+/// `file_len` bytes long, and gives where its data is.
+fn lump_extent(raw: &RawLump, file_len: u64) -> Result<Extent, Error> {
+    let out_of_bounds = || Error::LumpOutOfBounds(raw.name(), raw.ptr, raw.size);
+    let start = u64::try_from(raw.ptr).map_err(|_| out_of_bounds())?;
+    let size = u64::try_from(raw.size).map_err(|_| out_of_bounds())?;
+    if start + size > file_len {
+        return Err(out_of_bounds());
+    }
+    let len = usize::try_from(size).map_err(|_| out_of_bounds())?;
+    Ok(Extent { start, len })
+}
+
+/// Decodes an 8-byte, NUL-padded name. Bytes are read as Latin-1 so that names
+/// that aren't valid UTF-8 still load; only ASCII letters are uppercased.
+pub(crate) fn name_from_bytes(name: &[u8]) -> String {
+    name_as_written(name).to_ascii_uppercase()
+}
+
+/// Lays a name out as it's stored in a directory entry or map lump: one byte
+/// per char, the reverse of how names are read, cut to 8 bytes and padded
+/// with zeroes. A char past U+00FF can't have come from a WAD and is written
+/// as `?`.
+pub(crate) fn name_to_bytes(name: &str) -> [u8; 8] {
+    let mut bytes = [0u8; 8];
+    for (b, c) in bytes.iter_mut().zip(name.chars()) {
+        *b = if u32::from(c) <= 0xFF { c as u8 } else { b'?' };
+    }
+    bytes
+}
+
+fn name_as_written(name: &[u8]) -> String {
+    name.iter()
+        .take(8)
+        .take_while(|&&b| b != b'\0')
+        .map(|&b| b as char)
+        .collect()
+}
+
+const GL_LUMPS: [&str; 5] = ["GL_VERT", "GL_SEGS", "GL_SSECT", "GL_NODES", "GL_PVS"];
+
+/// The map a `GL_` marker is for: the rest of its name, or for `GL_LEVEL` the
+/// name given by the `LEVEL=` line of its text.
+fn gl_marker_map(lump: &Lump) -> Option<String> {
+    let map = lump.name.strip_prefix("GL_")?;
+    if GL_LUMPS.contains(&lump.name.as_str()) {
+        return None;
+    }
+    if map != "LEVEL" {
+        return Some(map.to_string());
+    }
+    String::from_utf8_lossy(&lump.data)
+        .lines()
+        .find_map(|line| Some(line.trim().strip_prefix("LEVEL=")?.to_ascii_uppercase()))
+}
+
+fn attach_gl_nodes(maps: &mut [Map], lumps: &[Lump]) {
+    let mut i = 0;
+    while i < lumps.len() {
+        let map_name = match gl_marker_map(&lumps[i]) {
+            Some(name) => name,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+        let start = i + 1;
+        i = start;
+        while lumps
+            .get(i)
+            .is_some_and(|l| GL_LUMPS.contains(&l.name.as_str()))
+        {
+            i += 1;
+        }
+        if let Some(map) = maps.iter_mut().rev().find(|m| m.name == map_name) {
+            map.gl = lumps[start..i].to_vec();
+        }
+    }
+}
+
+// a partial map, with placeholders for the components it's missing
+fn take_partial_map(
+    name: String,
+    endianness: Endianness,
+    components: &mut HashMap<LumpKind, Lump>,
+    options: ParseOptions,
+) -> Map {
+    let missing = options.missing(components);
+    for &kind in &missing {
+        components.insert(
+            kind,
+            Lump {
+                name: component_name(kind).to_string(),
+                offset: 0,
+                data: vec![],
+                kind,
+            },
+        );
+    }
+    let mut map = take_map(name, endianness, components);
+    map.missing = missing;
+    map
+}
+
+fn component_name(kind: LumpKind) -> &'static str {
+    MAP_LUMP_ORDER
+        .iter()
+        .copied()
+        .find(|&name| lump_kind(name) == kind)
+        .unwrap_or("?")
+}
+
+fn component_names(kinds: &[LumpKind]) -> String {
+    kinds
+        .iter()
+        .map(|&k| component_name(k))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Every caller checks that all of REQUIRED_MAP_COMPONENTS are present first,
+// apart from those ParseOptions::alpha lets be missing, which come out empty.
+fn take_map(name: String, endianness: Endianness, components: &mut HashMap<LumpKind, Lump>) -> Map {
+    let mut optional = |kind: LumpKind, name: &str| {
+        components.remove(&kind).unwrap_or_else(|| Lump {
+            name: name.to_string(),
+            offset: 0,
+            data: vec![],
+            kind,
+        })
+    };
+    let segs = optional(LumpKind::Segs, "SEGS");
+    let subsectors = optional(LumpKind::Subsectors, "SSECTORS");
+    let nodes = optional(LumpKind::Nodes, "NODES");
+    let blockmap = optional(LumpKind::Blockmap, "BLOCKMAP");
+    let map = Map {
+        name,
+        endianness,
+        things: components.remove(&LumpKind::Things).unwrap(),
+        linedefs: components.remove(&LumpKind::Linedefs).unwrap(),
+        sidedefs: components.remove(&LumpKind::Sidedefs).unwrap(),
+        vertexes: components.remove(&LumpKind::Vertexes).unwrap(),
+        segs,
+        subsectors,
+        nodes,
+        sectors: components.remove(&LumpKind::Sectors).unwrap(),
+        reject: components.remove(&LumpKind::Reject),
+        blockmap,
+        behavior: components.remove(&LumpKind::Behavior),
+        scripts: components.remove(&LumpKind::Scripts),
+        gl: vec![],
+        missing: vec![],
+    };
+    debug_assert!(components.is_empty());
+    map
+}
+
+/// A unit of a WAD's directory that `Wad::merge` overrides as a whole.
+enum Part {
+    Lump(Lump),
+    /// A sprite, flat or patch section: its start marker and the lumps
+    /// between the markers.
+    Section(&'static str, Vec<Lump>),
+    /// A map's name, and its marker followed by its components.
+    Map(String, Vec<Lump>),
+}
+
+impl Part {
+    fn split(lumps: &[Lump]) -> Vec<Part> {
+        let is_component = |l: &Lump| {
+            REQUIRED_MAP_COMPONENTS.contains(&l.kind) || OPTIONAL_MAP_COMPONENTS.contains(&l.kind)
+        };
+        let mut parts = vec![];
+        let mut i = 0;
+        while i < lumps.len() {
+            let rest = &lumps[i + 1..];
+            if let Some(&(start, end)) = MARKER_SECTIONS.iter().find(|(s, _)| *s == lumps[i].name) {
+                // an unterminated section is left as ordinary lumps
+                if let Some(len) = rest.iter().position(|l| l.name == end) {
+                    parts.push(Part::Section(start, rest[..len].to_vec()));
+                    i += len + 2;
+                    continue;
+                }
+            }
+            let components = rest.iter().take_while(|l| is_component(l)).count();
+            if is_component(&lumps[i]) {
+                // a map at the very start of the directory, with no marker
+                let len = components + 1;
+                parts.push(Part::Map(String::new(), lumps[i..i + len].to_vec()));
+                i += len;
+            } else if components > 0 {
+                let map = lumps[i..=i + components].to_vec();
+                parts.push(Part::Map(lumps[i].name.clone(), map));
+                i += components + 1;
+            } else {
+                parts.push(Part::Lump(lumps[i].clone()));
+                i += 1;
+            }
+        }
+        parts
+    }
+
+    /// Whether `later` takes the place of this part when merging.
+    fn overrides(&self, later: &Part) -> bool {
+        // "FF_START" belongs with "F_START", and so on
+        let section = |start: &str| start.as_bytes()[0];
+        match (self, later) {
+            (Part::Lump(a), Part::Lump(b)) => a.name == b.name,
+            (Part::Section(a, _), Part::Section(b, _)) => section(a) == section(b),
+            (Part::Map(a, _), Part::Map(b, _)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn into_lumps(self) -> Vec<Lump> {
+        match self {
+            Part::Lump(lump) => vec![lump],
+            Part::Section(start, mut lumps) => {
+                let end = MARKER_SECTIONS.iter().find(|(s, _)| *s == start).unwrap().1;
+                let marker = |name: &str| Lump {
+                    name: name.to_string(),
+                    offset: 0,
+                    data: vec![],
+                    kind: LumpKind::Other,
+                };
+                lumps.insert(0, marker(start));
+                lumps.push(marker(end));
+                lumps
+            }
+            Part::Map(_, lumps) => lumps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::builder::square_room;
+    use crate::builder::WadBuilder;
+
+    fn parse(bytes: Vec<u8>) -> Result<Wad, Error> {
+        Wad::from_reader(Cursor::new(bytes))
+    }
+
+    fn with_id(id: &[u8; 4]) -> Vec<u8> {
+        let mut bytes = WadBuilder::new().map(square_room("MAP01")).build();
+        bytes[..4].copy_from_slice(id);
+        bytes
+    }
+
+    #[test]
+    fn header_ids_in_any_case_or_padding_are_accepted() {
+        for id in [b"IWAD", b"PWAD", b"iwad", b"pwad", b"PwAd"] {
+            let wad = parse(with_id(id)).unwrap();
+            assert_eq!(wad.is_iwad(), id.eq_ignore_ascii_case(b"IWAD"));
+            assert_eq!(wad.maps().len(), 1);
+        }
+    }
+
+    #[test]
+    fn other_header_ids_are_rejected() {
+        for id in [
+            b"WAD2",
+            b"PWA ",
+            b" WAD",
+            b"\0\0\0\0",
+            b"PK\x03\x04",
+            b"IWAX",
+        ] {
+            assert!(matches!(parse(with_id(id)), Err(Error::NotAWad(_))));
+        }
+    }
+
+    fn header_bytes(dir_ct: i32, dir_ptr: i32) -> Vec<u8> {
+        let mut bytes = b"PWAD".to_vec();
+        bytes.extend_from_slice(&dir_ct.to_le_bytes());
+        bytes.extend_from_slice(&dir_ptr.to_le_bytes());
+        bytes.extend_from_slice(&[0; 32]);
+        bytes
+    }
+
+    #[test]
+    fn negative_lump_count_is_a_corrupt_directory() {
+        for count in [-1, -16, i32::MIN] {
+            assert!(matches!(
+                parse(header_bytes(count, 12)),
+                Err(Error::CorruptDirectory(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn directory_offset_before_the_header_end_is_a_corrupt_directory() {
+        for ptr in [-1, 0, 11, i32::MIN] {
+            assert!(matches!(
+                parse(header_bytes(1, ptr)),
+                Err(Error::CorruptDirectory(_))
+            ));
+        }
+        // an empty directory right after the header is fine
+        assert!(parse(header_bytes(0, 12)).is_ok());
+    }
+
+    fn header(dir_ct: i32, dir_ptr: i32) -> Header {
+        Header {
+            id: *b"PWAD",
+            dir_ct,
+            dir_ptr,
+            endianness: Endianness::Little,
+        }
+    }
+
+    fn raw_lump(ptr: i32, size: i32) -> RawLump {
+        RawLump {
+            ptr,
+            size,
+            name: *b"DATA\0\0\0\0",
+        }
+    }
+
+    #[test]
+    fn directory_extents_near_i32_max() {
+        let max = i32::MAX as u64;
+        // an empty directory at the last offset a header can name
+        let extent = check_directory(&header(0, i32::MAX), max).unwrap();
+        assert_eq!((extent.start, extent.len), (max, 0));
+        assert!(check_directory(&header(1, i32::MAX), max).is_err());
+        assert!(check_directory(&header(i32::MAX, 12), max).is_err());
+        // the largest count and offset together don't overflow
+        let extent = check_directory(&header(i32::MAX, i32::MAX), u64::MAX).unwrap();
+        assert_eq!(extent.len as u64, max * DIRECTORY_ENTRY_SIZE as u64);
+        assert!(check_directory(&header(i32::MIN, 12), u64::MAX).is_err());
+    }
+
+    #[test]
+    fn lump_extents_near_i32_max() {
+        let max = i32::MAX as u64;
+        let extent = lump_extent(&raw_lump(i32::MAX, i32::MAX), 2 * max).unwrap();
+        assert_eq!((extent.start, extent.len as u64), (max, max));
+        assert!(lump_extent(&raw_lump(i32::MAX, i32::MAX), 2 * max - 1).is_err());
+        for (ptr, size) in [(-1, 0), (0, -1), (i32::MIN, 16), (16, i32::MIN)] {
+            assert!(matches!(
+                lump_extent(&raw_lump(ptr, size), u64::MAX),
+                Err(Error::LumpOutOfBounds(_, p, s)) if (p, s) == (ptr, size)
+            ));
+        }
+    }
+
+    #[test]
+    fn lump_ending_one_past_the_file_is_out_of_bounds() {
+        assert!(lump_extent(&raw_lump(100, 20), 120).is_ok());
+        assert!(matches!(
+            lump_extent(&raw_lump(100, 20), 119),
+            Err(Error::LumpOutOfBounds(..))
+        ));
+        assert!(lump_extent(&raw_lump(120, 0), 120).is_ok());
+        assert!(lump_extent(&raw_lump(121, 0), 120).is_err());
+    }
+
+    #[test]
+    fn merge_lets_later_lumps_and_maps_win() {
+        let base = WadBuilder::new()
+            .iwad()
+            .lump("DEHACKED", b"base".to_vec())
+            .map(square_room("MAP01"))
+            .map(square_room("MAP02"))
+            .build();
+        let patch = WadBuilder::new()
+            .lump("DEHACKED", b"patch".to_vec())
+            .map(square_room("MAP01").vertex(32, 32))
+            .build();
+        let merged = Wad::merge(&[parse(base).unwrap(), parse(patch).unwrap()]).unwrap();
+
+        assert!(merged.is_iwad());
+        let names: Vec<_> = merged.maps().iter().map(Map::name).collect();
+        assert_eq!(names, ["MAP01", "MAP02"]);
+        assert_eq!(merged.maps()[0].vertexes().unwrap().len(), 5);
+        assert_eq!(merged.maps()[1].vertexes().unwrap().len(), 4);
+        assert_eq!(
+            merged.lump_by_name("DEHACKED").unwrap().as_bytes(),
+            b"patch"
+        );
+    }
+
+    #[test]
+    fn wads_too_large_for_their_directory_are_not_written() {
+        let mut wad = parse(WadBuilder::new().lump("PLAYPAL", vec![0; 768]).build()).unwrap();
+        // zeroed allocations that are never touched, so they take no memory
+        for name in ["BIG1", "BIG2"] {
+            wad.lumps.push(Lump {
+                name: name.to_string(),
+                offset: 0,
+                data: vec![0; 1 << 30],
+                kind: LumpKind::Other,
+            });
+        }
+        let mut written = Cursor::new(vec![]);
+        assert!(matches!(
+            wad.write(&mut written),
+            Err(Error::CorruptDirectory(_))
+        ));
+        assert!(written.into_inner().is_empty());
+    }
+
+    #[test]
+    fn lump_directories_must_be_named_like_a_map() {
+        let root = std::env::temp_dir().join(format!("doom-map-stats-{}", std::process::id()));
+        for name in ["M\u{C4}P01", "MAP01_OLD", "map01"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+        }
+        for name in ["M\u{C4}P01", "MAP01_OLD"] {
+            assert!(matches!(
+                Wad::from_lump_directory(root.join(name)),
+                Err(Error::CorruptDirectory(_))
+            ));
+        }
+        // a good name gets as far as looking for the lumps
+        assert!(matches!(
+            Wad::from_lump_directory(root.join("map01")),
+            Err(Error::MissingLump(_, lump)) if lump == "THINGS"
+        ));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn written_wad_reads_back_the_same() {
+        let bytes = WadBuilder::new()
+            .iwad()
+            .lump("PLAYPAL", vec![0; 768])
+            .lump("\u{C9}T\u{C9}", b"high bytes in the name".to_vec())
+            .map(square_room("E1M1").lump("BLOCKMAP", vec![1; 8]))
+            .map(square_room("E1M2").vertex(-16, 300))
+            .lump("F_START", vec![])
+            .lump("FLOOR4_8", vec![7; 4096])
+            .lump("F_END", vec![])
+            .build();
+        let wad = parse(bytes).unwrap();
+        let mut written = Cursor::new(vec![]);
+        wad.write(&mut written).unwrap();
+        let reread = parse(written.into_inner()).unwrap();
+
+        assert_eq!(reread.is_iwad(), wad.is_iwad());
+        assert_eq!(reread.len(), wad.len());
+        for (a, b) in wad.iter_lumps().zip(reread.iter_lumps()) {
+            assert_eq!(a.name(), b.name());
+            assert_eq!(a.kind(), b.kind());
+            assert_eq!(a.as_bytes(), b.as_bytes());
+        }
+        assert!(reread.lump_by_name("\u{C9}T\u{C9}").is_some());
+
+        assert_eq!(reread.maps().len(), wad.maps().len());
+        for (a, b) in wad.maps().iter().zip(reread.maps()) {
+            assert_eq!(a.name(), b.name());
+            assert_eq!(a.vertexes().unwrap(), b.vertexes().unwrap());
+            assert_eq!(a.linedefs().unwrap(), b.linedefs().unwrap());
+            assert_eq!(a.sidedefs().unwrap(), b.sidedefs().unwrap());
+            assert_eq!(a.sectors().unwrap(), b.sectors().unwrap());
+            assert_eq!(a.things().unwrap(), b.things().unwrap());
+        }
+    }
+
+    const TEXTMAP: &[u8] = b"namespace = \"zdoom\";\nthing { x = 64.0; y = 64.0; type = 1; }\n";
+
+    fn udmf_wad(endmap: bool) -> Vec<u8> {
+        let builder = WadBuilder::new()
+            .map(square_room("MAP01"))
+            .lump("MAP02", vec![])
+            .lump("TEXTMAP", TEXTMAP.to_vec())
+            .lump("ZNODES", vec![0; 16])
+            .lump("BEHAVIOR", vec![0; 8]);
+        if endmap {
+            builder
+                .lump("ENDMAP", vec![])
+                .lump("DEMO1", vec![1; 4])
+                .build()
+        } else {
+            builder.build()
+        }
+    }
+
+    #[test]
+    fn udmf_map_runs_from_its_marker_to_endmap() {
+        let wad = parse(udmf_wad(true)).unwrap();
+
+        assert_eq!(wad.maps().len(), 1);
+        assert_eq!(wad.udmf_maps().len(), 1);
+        let udmf = &wad.udmf_maps()[0];
+        assert_eq!(udmf.name(), "MAP02");
+        assert_eq!(udmf.textmap().as_bytes(), TEXTMAP);
+        let names: Vec<_> = udmf.lumps().iter().map(Lump::name).collect();
+        assert_eq!(names, ["TEXTMAP", "ZNODES", "BEHAVIOR"]);
+        assert!(wad.misplaced_lumps().is_empty());
+        assert!(wad.lump_by_name("DEMO1").is_some());
+    }
+
+    #[test]
+    fn udmf_map_without_endmap_fails_strict_parsing() {
+        assert!(matches!(
+            parse(udmf_wad(false)),
+            Err(Error::CorruptDirectory(_))
+        ));
+
+        let options = ParseOptions {
+            strict: false,
+            ..ParseOptions::default()
+        };
+        let wad = Wad::from_reader_with_options(Cursor::new(udmf_wad(false)), options).unwrap();
+        assert!(wad.udmf_maps().is_empty());
+        assert_eq!(wad.maps().len(), 1);
+    }
+
+    #[test]
+    fn iwads_are_only_hashed_when_asked() {
+        let bytes = WadBuilder::new().iwad().map(square_room("E1M1")).build();
+        let mut hash = crate::md5::Md5::new();
+        hash.update(&bytes);
+        let md5 = hash.finish_hex();
+
+        assert_eq!(parse(bytes.clone()).unwrap().md5(), None);
+        let options = ParseOptions {
+            hash_iwad: true,
+            ..ParseOptions::default()
+        };
+        let wad = Wad::from_reader_with_options(Cursor::new(bytes), options).unwrap();
+        assert_eq!(wad.md5(), Some(md5.as_str()));
+        assert!(wad.identify_iwad().is_none());
+
+        let pwad = WadBuilder::new().map(square_room("E1M1")).build();
+        let wad = Wad::from_reader_with_options(Cursor::new(pwad), options).unwrap();
+        assert_eq!(wad.md5(), None);
+    }
 }